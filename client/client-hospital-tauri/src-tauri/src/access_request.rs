@@ -0,0 +1,221 @@
+use std::{
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Context};
+use iota_types::base_types::IotaAddress;
+use serde_json::json;
+use tauri::{async_runtime::Mutex, http::StatusCode, State};
+use tauri_plugin_http::reqwest;
+
+use crate::{
+    constants::{
+        ACCESS_REQUESTS_MAX, ACCESS_REQUEST_REASON_LEN_MAX, ACCESS_REQUEST_VALIDITY, PROXY_BASE_URL,
+    },
+    current_fn,
+    hospital_error::HospitalError,
+    types::{
+        AccessRequestStatus, AppState, CommandAccessRequestEntry, CommandGenerateAccessQrScopeArgs,
+        CommandGetAccessRequestsResponseData, CommandSubmitAccessRequestResponseData,
+        HospitalPersonnelQrPayload, HospitalPersonnelQrScope,
+        ProxyReencryptionCreateAccessRequestResponseData, ProxyReencryptionErrorResponse,
+        ProxyReencryptionGetAccessRequestStatusResponseData, ProxyReencryptionSuccessResponse,
+        ResponseStatus, SubmittedAccessRequest, SuccessResponse,
+    },
+    utils::{
+        do_http_post_request_json, encode_hospital_personnel_qr, get_iota_address_from_keys_entry,
+        get_iota_key_pair_from_keys_entry, get_pre_keys_from_keys_entry, parse_keys_entry,
+        serde_serialize_to_base64, validate_access_scope,
+    },
+};
+
+/**
+ * Asks a patient for access without meeting in person. The request is signed like the access
+ * QR, so the patient app verifies it the same way before granting access.
+ */
+#[tauri::command]
+pub async fn submit_access_request(
+    state: State<'_, Mutex<AppState>>,
+    patient_iota_address: String,
+    reason: String,
+    scope: CommandGenerateAccessQrScopeArgs,
+) -> Result<SuccessResponse<CommandSubmitAccessRequestResponseData>, HospitalError> {
+    let mut state = state.lock().await;
+    let mut keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+        .context(current_fn!())?;
+    let req_client = reqwest::Client::new();
+
+    let patient_iota_address = IotaAddress::from_str(patient_iota_address.trim())
+        .map_err(|_| anyhow!("Invalid args: patient IOTA address"))?;
+    let reason = reason.trim().to_string();
+    if reason.is_empty() || reason.chars().count() > ACCESS_REQUEST_REASON_LEN_MAX {
+        return Err(HospitalError::Anyhow(anyhow!(
+            "Invalid args: reason must be between 1 and {} characters",
+            ACCESS_REQUEST_REASON_LEN_MAX
+        )));
+    }
+    let scope = HospitalPersonnelQrScope::from(scope);
+    validate_access_scope(&scope)?;
+
+    let (
+        hospital_personnel_iota_address,
+        hospital_personnel_iota_key_pair,
+        hospital_personnel_pre_public_key,
+    ) = {
+        let pin = state.auth_state.session.pin().context(current_fn!())?;
+        let hospital_personnel_iota_address =
+            get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
+        let hospital_personnel_iota_key_pair =
+            get_iota_key_pair_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+        let (_, hospital_personnel_pre_public_key) =
+            get_pre_keys_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+
+        (
+            hospital_personnel_iota_address,
+            hospital_personnel_iota_key_pair,
+            hospital_personnel_pre_public_key,
+        )
+    };
+
+    let iat = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .context(current_fn!())?
+        .as_secs();
+    let code = encode_hospital_personnel_qr(
+        &hospital_personnel_iota_key_pair,
+        &HospitalPersonnelQrPayload {
+            exp: iat + ACCESS_REQUEST_VALIDITY,
+            iat,
+            iota_address: hospital_personnel_iota_address.to_string(),
+            patient_iota_address: Some(patient_iota_address.to_string()),
+            pre_public_key: serde_serialize_to_base64(&hospital_personnel_pre_public_key)
+                .context(current_fn!())?,
+            reason: Some(reason.clone()),
+            scope: Some(scope.clone()),
+        },
+    )?;
+
+    let access_request = do_http_post_request_json::<
+        _,
+        ProxyReencryptionSuccessResponse<ProxyReencryptionCreateAccessRequestResponseData>,
+        ProxyReencryptionErrorResponse,
+    >(
+        None,
+        &format!("{}/access-request", PROXY_BASE_URL),
+        &json!({ "code": code }),
+        &req_client,
+        StatusCode::CREATED,
+    )
+    .await
+    .context(current_fn!())?
+    .data;
+
+    keys_entry
+        .access_requests
+        .retain(|access_request| access_request.exp > iat);
+    if keys_entry.access_requests.len() >= ACCESS_REQUESTS_MAX {
+        keys_entry.access_requests.remove(0);
+    }
+    keys_entry.access_requests.push(SubmittedAccessRequest {
+        created_at: iat,
+        exp: access_request.exp,
+        id: access_request.id.clone(),
+        patient_iota_address: patient_iota_address.to_string(),
+        reason,
+        scope: Some(scope),
+    });
+    state
+        .keys_entry
+        .set_secret(&serde_json::to_vec(&keys_entry).context(current_fn!())?)
+        .context(current_fn!())?;
+
+    Ok(SuccessResponse {
+        status: ResponseStatus::Success,
+        data: CommandSubmitAccessRequestResponseData {
+            exp: access_request.exp,
+            id: access_request.id,
+        },
+    })
+}
+
+/**
+ * Submitted access requests with the patient's answer, newest first
+ */
+#[tauri::command]
+pub async fn get_access_requests(
+    state: State<'_, Mutex<AppState>>,
+) -> Result<SuccessResponse<CommandGetAccessRequestsResponseData>, HospitalError> {
+    let mut state = state.lock().await;
+    let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+        .context(current_fn!())?;
+    let req_client = reqwest::Client::new();
+
+    state.auth_state.session.pin().context(current_fn!())?;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .context(current_fn!())?
+        .as_secs();
+
+    let mut entries = vec![];
+    for access_request in keys_entry.access_requests.iter().rev() {
+        let status = if access_request.exp <= now {
+            AccessRequestStatus::Expired
+        } else {
+            get_access_request_status(&req_client, &access_request.id)
+                .await
+                .context(current_fn!())?
+        };
+
+        entries.push(CommandAccessRequestEntry {
+            created_at: access_request.created_at,
+            exp: access_request.exp,
+            id: access_request.id.clone(),
+            patient_iota_address: access_request.patient_iota_address.clone(),
+            reason: access_request.reason.clone(),
+            scope: access_request.scope.clone(),
+            status,
+        });
+    }
+
+    Ok(SuccessResponse {
+        status: ResponseStatus::Success,
+        data: CommandGetAccessRequestsResponseData { entries },
+    })
+}
+
+/**
+ * return: `AccessRequestStatus::Expired` once the proxy no longer knows the request
+ */
+async fn get_access_request_status(
+    req_client: &reqwest::Client,
+    id: &str,
+) -> Result<AccessRequestStatus, HospitalError> {
+    let res = req_client
+        .get(format!("{}/access-request/status", PROXY_BASE_URL))
+        .query(&[("id", id)])
+        .send()
+        .await
+        .context(current_fn!())?;
+
+    match res.status() {
+        StatusCode::OK => {
+            Ok(res
+                .json::<ProxyReencryptionSuccessResponse<
+                    ProxyReencryptionGetAccessRequestStatusResponseData,
+                >>()
+                .await
+                .context(current_fn!())?
+                .data
+                .status)
+        }
+        StatusCode::NOT_FOUND => Ok(AccessRequestStatus::Expired),
+        _ => {
+            let error: ProxyReencryptionErrorResponse = res.json().await.context(current_fn!())?;
+            Err(HospitalError::Anyhow(
+                anyhow!(format!("{:#?}", error)).context(current_fn!()),
+            ))
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use anyhow::{anyhow, Context};
 use tauri::{async_runtime::Mutex, State};
 use umbral_pre::encrypt;
+use zeroize::Zeroizing;
 
 use crate::{
     current_fn,
@@ -17,7 +18,7 @@ use crate::{
         get_global_admin_iota_address_from_keys_entry,
         get_global_admin_iota_key_pair_from_keys_entry, get_iota_address_from_keys_entry,
         get_iota_key_pair_from_keys_entry, get_pre_keys_from_keys_entry, parse_keys_entry,
-        serde_serialize_to_base64,
+        serde_serialize_to_base64, unlock_keys_entry,
     },
 };
 use base64::{engine::general_purpose::STANDARD, Engine as _};
@@ -95,10 +96,10 @@ pub async fn hospital_admin_add_activation_key(
     state: State<'_, Mutex<AppState>>,
     personnel_id_part: String,
     role: String,
-    pin: String,
+    pin: Zeroizing<String>,
 ) -> Result<SuccessResponse<CommandHospitalAdminAddActivationKeyResponse>, HospitalError> {
     let state = state.lock().await;
-    let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+    let keys_entry = unlock_keys_entry(&state.keys_entry, &state.pin_attempts_entry, &pin)
         .context(current_fn!())?;
 
     let (
@@ -112,13 +113,13 @@ pub async fn hospital_admin_add_activation_key(
             encode_activation_key_from_keys_entry(&keys_entry).context(current_fn!())?;
 
         let (_, hospital_admin_pre_public_key) =
-            get_pre_keys_from_keys_entry(&keys_entry, pin.clone()).context(current_fn!())?;
+            get_pre_keys_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
         let (_, hospital_admin_hospital_part) =
             decode_hospital_personnel_id(keys_entry.id.clone().unwrap()).context(current_fn!())?;
         let hospital_admin_iota_address =
             get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
         let hospital_admin_iota_key_pair =
-            get_iota_key_pair_from_keys_entry(&keys_entry, pin).context(current_fn!())?;
+            get_iota_key_pair_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
 
         (
             admin_activation_key,
@@ -221,7 +222,7 @@ pub async fn activate_app(
     ) = {
         let seed = generate_64_bytes_seed();
         let (random_iota_address, random_iota_key_pair) =
-            generate_iota_keys_ed(&seed).context(current_fn!())?;
+            generate_iota_keys_ed(seed.as_slice()).context(current_fn!())?;
         let (hospital_personnel_id_part_hash, hospital_personnel_hospital_part_hash) =
             decode_hospital_personnel_id_to_argon(id.clone()).context(current_fn!())?;
 
@@ -20,7 +20,7 @@ use base64::{engine::general_purpose::STANDARD, Engine as _};
 pub async fn get_hospital_personnels(
     state: State<'_, Mutex<AppState>>,
 ) -> Result<SuccessResponse<CommandGetHospitalPersonnelsResponseData>, HospitalError> {
-    let state = state.lock().await;
+    let mut state = state.lock().await;
     let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
         .context(current_fn!())?;
 
@@ -31,11 +31,7 @@ pub async fn get_hospital_personnels(
             get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
         let (hospital_admin_pre_secret_key, _) = get_pre_keys_from_keys_entry(
             &keys_entry,
-            state
-                .auth_state
-                .session_pin
-                .clone()
-                .ok_or(anyhow!("Session PIN not found").context(current_fn!()))?,
+            &state.auth_state.session.pin().context(current_fn!())?,
         )?;
 
         (
@@ -3,6 +3,7 @@ use serde_json::{json, Value};
 use tauri::{async_runtime::Mutex, http::StatusCode, State};
 use tauri_plugin_http::reqwest;
 use umbral_pre::{decrypt_original, decrypt_reencrypted, Capsule, CapsuleFrag, PublicKey};
+use zeroize::Zeroizing;
 
 use crate::{
     constants::PROXY_BASE_URL,
@@ -29,19 +30,15 @@ pub async fn get_administrative_data(
     access_token: String,
     patient_iota_address: String,
 ) -> Result<SuccessResponse<Value>, HospitalError> {
-    let state = state.lock().await;
+    let mut state = state.lock().await;
     let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
         .context(current_fn!())?;
     let req_client = reqwest::Client::new();
 
     let hospital_personnel_pre_secret_key = {
-        let pin = state
-            .auth_state
-            .session_pin
-            .clone()
-            .ok_or(anyhow!("Session PIN not found"))?;
+        let pin = state.auth_state.session.pin().context(current_fn!())?;
         let (hospital_personnel_pre_secret_key, _) =
-            get_pre_keys_from_keys_entry(&keys_entry, pin).context(current_fn!())?;
+            get_pre_keys_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
 
         hospital_personnel_pre_secret_key
     };
@@ -76,6 +73,7 @@ pub async fn get_administrative_data(
                 .decode(res.data.enc_data_pre_secret_key_seed)
                 .context(current_fn!())?,
         )
+        .map(Zeroizing::new)
         .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
         let (data_pre_secret_key, data_pre_public_key) =
             compute_pre_keys(&data_pre_secret_key_seed).context(current_fn!())?;
@@ -103,6 +101,7 @@ pub async fn get_administrative_data(
                 .decode(res.data.enc_patient_private_adm_data_key_nonce)
                 .context(current_fn!())?,
         )
+        .map(Zeroizing::new)
         .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
         let administrative_data_key_nonce: KeyNonce =
             serde_json::from_slice(&administrative_data_key_nonce).context(current_fn!())?;
@@ -110,11 +109,13 @@ pub async fn get_administrative_data(
             &STANDARD
                 .decode(res.data.enc_patient_private_adm_data)
                 .context(current_fn!())?,
+            &Zeroizing::new(
+                STANDARD
+                    .decode(&administrative_data_key_nonce.key)
+                    .context(current_fn!())?,
+            ),
             &STANDARD
-                .decode(administrative_data_key_nonce.key)
-                .context(current_fn!())?,
-            &STANDARD
-                .decode(administrative_data_key_nonce.nonce)
+                .decode(&administrative_data_key_nonce.nonce)
                 .context(current_fn!())?,
         )
         .context(current_fn!())?;
@@ -138,7 +139,7 @@ pub async fn get_administrative_data(
 pub async fn get_read_access_administrative_personnel(
     state: State<'_, Mutex<AppState>>,
 ) -> Result<SuccessResponse<Vec<AccessData>>, HospitalError> {
-    let state = state.lock().await;
+    let mut state = state.lock().await;
     let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
         .context(current_fn!())?;
 
@@ -148,19 +149,15 @@ pub async fn get_read_access_administrative_personnel(
         administrative_personnel_iota_key_pair,
         administrative_personnel_pre_secret_key,
     ) = {
-        let pin = state
-            .auth_state
-            .session_pin
-            .clone()
-            .ok_or(anyhow!("Session PIN not found on auth state").context(current_fn!()))?;
+        let pin = state.auth_state.session.pin().context(current_fn!())?;
         let activation_key =
             encode_activation_key_from_keys_entry(&keys_entry).context(current_fn!())?;
         let administrative_personnel_iota_address =
             get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
         let administrative_personnel_iota_key_pair =
-            get_iota_key_pair_from_keys_entry(&keys_entry, pin.clone()).context(current_fn!())?;
+            get_iota_key_pair_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
         let (administrative_personnel_pre_secret_key, _) =
-            get_pre_keys_from_keys_entry(&keys_entry, pin).context(current_fn!())?;
+            get_pre_keys_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
 
         (
             activation_key,
@@ -209,6 +206,7 @@ pub async fn get_read_access_administrative_personnel(
                 access_token: access_metadata.access_token,
                 exp: access.exp,
                 medical_metadata_index: access.medical_metadata_index,
+                medical_metadata_scope: access.medical_metadata_scope,
                 patient_iota_address: access_metadata.patient_iota_address,
                 patient_name: access_metadata.patient_name,
                 patient_pre_public_key: access_metadata.patient_pre_public_key,
@@ -2,9 +2,70 @@ pub const IOTA_URL: &str = "http://localhost:9000";
 pub const GAS_STATION_BASE_URL: &str = "http://localhost:9527/v1";
 pub const GAS_BUDGET: u64 = 10_000_000;
 pub const HASH_SALT: &str = "169224A2BE2B267684F93A9CE38080D359BD774741FD3AE738D09B657A1A8104";
+/// `KeysEntry` vault format, 0: SHA-256 of the PIN, 1: Argon2id with `KeysEntry.pin_kdf`
+pub const KEYS_ENTRY_VAULT_VERSION: u8 = 1;
+/// Argon2id memory cost in KiB
+pub const PIN_KDF_M_COST: u32 = 19 * 1024;
+pub const PIN_KDF_P_COST: u32 = 1;
+pub const PIN_KDF_SALT_LEN: usize = 16;
+pub const PIN_KDF_T_COST: u32 = 2;
+/// Failed PIN attempts allowed before the lockout kicks in
+pub const PIN_FREE_ATTEMPTS: u32 = 3;
+/// Duration: 30 seconds, doubled on every further failed attempt
+pub const PIN_LOCKOUT_BASE_DUR: u64 = 30;
+/// Duration: 1 day
+pub const PIN_LOCKOUT_MAX_DUR: u64 = 86400;
+/// Bounds of `Settings.pin_wipe_after_failed_attempts`, the lowest still leaves room for typos
+pub const PIN_WIPE_AFTER_FAILED_ATTEMPTS_MIN: u32 = 5;
+pub const PIN_WIPE_AFTER_FAILED_ATTEMPTS_MAX: u32 = 20;
+/// Duration: 5 minutes, default of `Settings.session_idle_timeout`
+pub const SESSION_IDLE_TIMEOUT: u64 = 300;
+/// Duration: 1 minute
+pub const SESSION_IDLE_TIMEOUT_MIN: u64 = 60;
+/// Duration: 1 hour
+pub const SESSION_IDLE_TIMEOUT_MAX: u64 = 3600;
+/// Duration: 15 seconds, how often idle sessions are locked in the background
+pub const SESSION_LOCK_CHECK_INTERVAL: u64 = 15;
 pub const _IPFS_BASE_URL: &str = "http://localhost:9094";
 pub const _IPFS_GATEWAY_BASE_URL: &str = "http://127.0.0.1:8080";
+/// Hospital personnel QR format, see `encode_hospital_personnel_qr`
+pub const HOSPITAL_PERSONNEL_QR_PREFIX: &str = "decmed-hp";
+pub const HOSPITAL_PERSONNEL_QR_VERSION: u8 = 1;
+/// Duration: 2 minutes, the displayed QR is rotated before so screenshots go stale quickly
+pub const HOSPITAL_PERSONNEL_QR_VALIDITY: u64 = 120;
+/// Duration: 1 minute
+pub const HOSPITAL_PERSONNEL_QR_ROTATION_INTERVAL: u64 = 60;
+/// Size: pixels, smallest side of a rendered QR image
+pub const HOSPITAL_PERSONNEL_QR_IMAGE_SIZE_MIN: u32 = 512;
+/// Access handoff without a camera, opened by the patient app: `decmed://grant?code={QR content}`
+pub const ACCESS_HANDOFF_URI: &str = "decmed://grant";
+pub const ACCESS_HANDOFF_URI_CODE_PARAM: &str = "code";
+/// Duration: 1 day, how long the proxy keeps an access request for the patient
+pub const ACCESS_REQUEST_VALIDITY: u64 = 24 * 60 * 60;
+/// Characters
+pub const ACCESS_REQUEST_REASON_LEN_MAX: usize = 500;
+/// Submitted access requests tracked per account, the oldest are dropped first
+pub const ACCESS_REQUESTS_MAX: usize = 50;
+/// Access duration bounds in minutes a QR can ask for, the patient app applies the role policy
+pub const ACCESS_DUR_MIN: u64 = 1;
+pub const ACCESS_DUR_MAX: u64 = 24 * 60;
+/// Bytes, the same bounds are enforced by the proxy and on chain
+pub const EMERGENCY_JUSTIFICATION_LEN_MIN: usize = 20;
+pub const EMERGENCY_JUSTIFICATION_LEN_MAX: usize = 1000;
+/// Codes the patient app exports anamnesis, physical and psychological check with
+pub const FHIR_LOINC_SYSTEM: &str = "http://loinc.org";
+pub const FHIR_LOINC_ANAMNESIS: &str = "10164-2";
+pub const FHIR_LOINC_PHYSICAL_CHECK: &str = "29545-1";
+pub const FHIR_LOINC_PSYCHOLOGICAL_CHECK: &str = "10190-7";
+/// Identifier system of the DecMed patient id in exported bundles
+pub const FHIR_PATIENT_ID_SYSTEM: &str = "urn:decmed:patient-id";
 pub const PROXY_BASE_URL: &str = "http://localhost:4000/api/v1";
+pub const AES_STREAM_MAGIC: &[u8; 4] = b"DMED";
+pub const AES_STREAM_VERSION: u8 = 1;
+/// Size: 64 KiB
+pub const AES_STREAM_CHUNK_SIZE: u32 = 64 * 1024;
+/// magic (4) | version (1) | chunk size (4) | nonce prefix (7)
+pub const AES_STREAM_HEADER_LEN: usize = 16;
 
 pub const DECMED_MODULE_HOSPITAL_PERSONNEL: &str = "hospital_personnel";
 
@@ -0,0 +1,244 @@
+use anyhow::anyhow;
+
+use crate::{
+    constants::{
+        FHIR_LOINC_ANAMNESIS, FHIR_LOINC_PHYSICAL_CHECK, FHIR_LOINC_PSYCHOLOGICAL_CHECK,
+        FHIR_LOINC_SYSTEM, FHIR_PATIENT_ID_SYSTEM,
+    },
+    current_fn,
+    hospital_error::HospitalError,
+    types::{
+        CommandFhirBundleRecord, CommandImportFhirBundleResponseData,
+        CommandNewMedicalRecordPayload, FhirBundle, FhirCodeableConcept, FhirReference,
+        FhirResource, ResponseStatus, SuccessResponse,
+    },
+};
+
+/**
+ * Maps a FHIR R4 `Bundle` to medical records, one per `Encounter`. Resources without an encounter
+ * are collected into one more record. Nothing is created here, the records prefill
+ * `new_medical_record` so the medical personnel reviews them first.
+ * - `Observation`: anamnesis, physical and psychological check by LOINC code, other observations
+ *   are added to the physical check
+ * - `Condition`: diagnose
+ * - `CarePlan`, `MedicationRequest`: therapy
+ */
+#[tauri::command]
+pub async fn import_fhir_bundle(
+    bundle: String,
+) -> Result<SuccessResponse<CommandImportFhirBundleResponseData>, HospitalError> {
+    let bundle: FhirBundle = serde_json::from_str(&bundle)
+        .map_err(|err| anyhow!("Invalid FHIR bundle: {}", err).context(current_fn!()))?;
+
+    if bundle.resource_type != "Bundle" {
+        return Err(HospitalError::Anyhow(anyhow!(
+            "Invalid FHIR bundle: expected a Bundle, found {}",
+            bundle.resource_type
+        )));
+    }
+
+    let mut patient_id = None;
+    // References an encounter is known by, with the record mapped from it
+    let mut records: Vec<(Vec<String>, CommandFhirBundleRecord)> = vec![];
+
+    for entry in bundle.entry.iter() {
+        match &entry.resource {
+            Some(FhirResource::Encounter(encounter)) => {
+                let references = entry
+                    .full_url
+                    .iter()
+                    .cloned()
+                    .chain(encounter.id.iter().map(|id| format!("Encounter/{}", id)))
+                    .collect();
+
+                records.push((
+                    references,
+                    CommandFhirBundleRecord {
+                        created_at: encounter
+                            .period
+                            .as_ref()
+                            .and_then(|period| period.start.clone()),
+                        data: CommandNewMedicalRecordPayload::default(),
+                    },
+                ));
+            }
+            Some(FhirResource::Patient(patient)) if patient_id.is_none() => {
+                patient_id = patient
+                    .identifier
+                    .iter()
+                    .find(|identifier| identifier.system.as_deref() == Some(FHIR_PATIENT_ID_SYSTEM))
+                    .and_then(|identifier| identifier.value.clone());
+            }
+            _ => {}
+        }
+    }
+
+    for entry in bundle.entry {
+        let Some(resource) = entry.resource else {
+            continue;
+        };
+
+        match resource {
+            FhirResource::CarePlan(care_plan) => {
+                if let Some(therapy) = care_plan.description.or(care_plan.title) {
+                    let record = encounter_record(&mut records, care_plan.encounter.as_ref());
+                    append_line(&mut record.therapy, &therapy);
+                }
+            }
+            FhirResource::Condition(condition) => {
+                if let Some(diagnose) = condition.code.as_ref().and_then(codeable_concept_text) {
+                    let record = encounter_record(&mut records, condition.encounter.as_ref());
+                    append_line(&mut record.diagnose, &diagnose);
+                }
+            }
+            FhirResource::MedicationRequest(medication_request) => {
+                let Some(medication) = medication_request
+                    .medication_codeable_concept
+                    .as_ref()
+                    .and_then(codeable_concept_text)
+                else {
+                    continue;
+                };
+                let dosage = medication_request
+                    .dosage_instruction
+                    .iter()
+                    .filter_map(|dosage_instruction| dosage_instruction.text.clone())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+
+                let record = encounter_record(&mut records, medication_request.encounter.as_ref());
+                if dosage.is_empty() {
+                    append_line(&mut record.therapy, &medication);
+                } else {
+                    append_line(&mut record.therapy, &format!("{} ({})", medication, dosage));
+                }
+            }
+            FhirResource::Observation(observation) => {
+                let Some(value) = observation.value_string else {
+                    continue;
+                };
+                let loinc_code = observation.code.as_ref().and_then(|code| {
+                    code.coding
+                        .iter()
+                        .find(|coding| coding.system.as_deref() == Some(FHIR_LOINC_SYSTEM))
+                        .and_then(|coding| coding.code.clone())
+                });
+
+                let record = encounter_record(&mut records, observation.encounter.as_ref());
+                match loinc_code.as_deref() {
+                    Some(FHIR_LOINC_ANAMNESIS) => append_line(&mut record.anamnesis, &value),
+                    Some(FHIR_LOINC_PHYSICAL_CHECK) => {
+                        append_line(&mut record.physical_check, &value)
+                    }
+                    Some(FHIR_LOINC_PSYCHOLOGICAL_CHECK) => {
+                        append_line(&mut record.psychological_check, &value)
+                    }
+                    _ => match observation.code.as_ref().and_then(codeable_concept_text) {
+                        Some(label) => append_line(
+                            &mut record.physical_check,
+                            &format!("{}: {}", label, value),
+                        ),
+                        None => append_line(&mut record.physical_check, &value),
+                    },
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let records = records
+        .into_iter()
+        .map(|(_, record)| record)
+        .filter(|record| {
+            let data = &record.data;
+
+            !(data.anamnesis.is_empty()
+                && data.diagnose.is_empty()
+                && data.physical_check.is_empty()
+                && data.psychological_check.is_empty()
+                && data.therapy.is_empty())
+        })
+        .collect::<Vec<CommandFhirBundleRecord>>();
+
+    if records.is_empty() {
+        return Err(anyhow!("FHIR bundle contains no medical record")
+            .context(current_fn!())
+            .into());
+    }
+
+    Ok(SuccessResponse {
+        data: CommandImportFhirBundleResponseData {
+            patient_id,
+            records,
+        },
+        status: ResponseStatus::Success,
+    })
+}
+
+/**
+ * return: data of the record mapped from the referenced encounter, of the record collecting
+ * resources without a known encounter otherwise
+ */
+fn encounter_record<'a>(
+    records: &'a mut Vec<(Vec<String>, CommandFhirBundleRecord)>,
+    encounter: Option<&FhirReference>,
+) -> &'a mut CommandNewMedicalRecordPayload {
+    let reference = encounter.and_then(|encounter| encounter.reference.as_deref());
+    let position = records.iter().position(|(references, _)| match reference {
+        Some(reference) => references.iter().any(|r| r == reference),
+        None => references.is_empty(),
+    });
+
+    let position = match position {
+        Some(position) => position,
+        None => {
+            // Unknown encounters are collected together with resources without one
+            let position = records
+                .iter()
+                .position(|(references, _)| references.is_empty());
+
+            position.unwrap_or_else(|| {
+                records.push((
+                    vec![],
+                    CommandFhirBundleRecord {
+                        created_at: None,
+                        data: CommandNewMedicalRecordPayload::default(),
+                    },
+                ));
+
+                records.len() - 1
+            })
+        }
+    };
+
+    &mut records[position].1.data
+}
+
+/**
+ * return: `text`, the displays of the codings otherwise
+ */
+fn codeable_concept_text(codeable_concept: &FhirCodeableConcept) -> Option<String> {
+    if let Some(text) = &codeable_concept.text {
+        return Some(text.clone());
+    }
+
+    let displays = codeable_concept
+        .coding
+        .iter()
+        .filter_map(|coding| coding.display.clone())
+        .collect::<Vec<String>>();
+
+    (!displays.is_empty()).then(|| displays.join(", "))
+}
+
+fn append_line(field: &mut String, value: &str) {
+    let value = value.trim();
+    if value.is_empty() {
+        return;
+    }
+
+    if !field.is_empty() {
+        field.push('\n');
+    }
+    field.push_str(value);
+}
@@ -4,6 +4,8 @@ use thiserror::Error;
 pub enum HospitalError {
     #[error(transparent)]
     Anyhow(#[from] anyhow::Error),
+    #[error("Integrity mismatch: expected sha256 {expected}, got {actual}")]
+    IntegrityMismatch { actual: String, expected: String },
 }
 
 impl serde::Serialize for HospitalError {
@@ -1,11 +1,14 @@
+mod access_request;
 mod activation;
 mod admin;
 mod administrative_personnel;
 mod constants;
+mod fhir;
 mod hospital_error;
 mod macros;
 mod medical_personnel;
 mod move_call;
+mod session;
 mod shared_cmds;
 mod signin;
 mod signout;
@@ -19,17 +22,20 @@ use constants::{
     DECMED_HOSPITAL_PERSONNEL_ID_ACCOUNT_OBJECT_ID,
     DECMED_HOSPITAL_PERSONNEL_ID_ACCOUNT_OBJECT_VERSION, DECMED_MODULE_ADMIN,
     DECMED_MODULE_HOSPITAL_PERSONNEL, DECMED_PACKAGE_ID, DECMED_PATIENT_ID_ACCOUNT_OBJECT_ID,
-    DECMED_PATIENT_ID_ACCOUNT_OBJECT_VERSION,
+    DECMED_PATIENT_ID_ACCOUNT_OBJECT_VERSION, KEYS_ENTRY_VAULT_VERSION,
+    SESSION_LOCK_CHECK_INTERVAL,
 };
 use iota_types::{base_types::ObjectID, Identifier};
 use keyring::Entry;
 use move_call::MoveCall;
-use std::str::FromStr;
+use session::Session;
+use std::{str::FromStr, thread, time::Duration};
 use tauri::{async_runtime::Mutex, Manager};
-use types::{AppState, AuthState, DecmedPackage, KeysEntry, SignInState, SignUpState};
+use types::{AppState, AuthState, DecmedPackage, KeysEntry, Settings, SignInState, SignUpState};
 
 fn setup(app: &mut tauri::App) -> std::result::Result<(), Box<dyn std::error::Error>> {
     let keys_entry = Entry::new("decmed_service_keys", "decmed_user")?;
+    let pin_attempts_entry = Entry::new("decmed_service_pin_attempts", "decmed_user")?;
     let decmed_package = DecmedPackage {
         package_id: ObjectID::from_str(DECMED_PACKAGE_ID)?,
         module_hospital_personnel: Identifier::from_str(DECMED_MODULE_HOSPITAL_PERSONNEL)?,
@@ -50,6 +56,7 @@ fn setup(app: &mut tauri::App) -> std::result::Result<(), Box<dyn std::error::Er
         global_admin_cap_id: ObjectID::from_str(DECMED_GLOBAL_ADMIN_CAP_ID)?,
     };
     let new_keys_entry = KeysEntry {
+        access_requests: vec![],
         id: None,
         admin_address: Some(String::from(
             "0x52a65ae806223e49aaff1cf7f670fee87c1767de1d200a661c1fee44a61fc37f",
@@ -63,7 +70,10 @@ fn setup(app: &mut tauri::App) -> std::result::Result<(), Box<dyn std::error::Er
         pre_secret_key: None,
         pre_public_key: None,
         iota_nonce: None,
+        pin_kdf: None,
         pre_nonce: None,
+        settings: Settings::default(),
+        vault_version: KEYS_ENTRY_VAULT_VERSION,
     };
     let signin_state = SignInState { pin: None };
     let signup_state = SignUpState {
@@ -73,7 +83,7 @@ fn setup(app: &mut tauri::App) -> std::result::Result<(), Box<dyn std::error::Er
     let auth_state = AuthState {
         is_signed_up: false,
         role: None,
-        session_pin: Some("123456".to_string()),
+        session: Session::default(),
     };
     let move_call = MoveCall {
         decmed_package: decmed_package.clone(),
@@ -100,10 +110,22 @@ fn setup(app: &mut tauri::App) -> std::result::Result<(), Box<dyn std::error::Er
         auth_state,
         keys_entry,
         move_call,
+        pin_attempts_entry,
         signin_state,
         signup_state,
     }));
 
+    // Idle sessions are locked even when no command comes in
+    let app_handle = app.handle().clone();
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(SESSION_LOCK_CHECK_INTERVAL));
+
+        let state = app_handle.state::<Mutex<AppState>>();
+        tauri::async_runtime::block_on(async {
+            state.lock().await.auth_state.session.lock_if_idle();
+        });
+    });
+
     Ok(())
 }
 
@@ -127,14 +149,26 @@ pub fn run() {
             shared_cmds::validate_pin,
             shared_cmds::validate_confirm_pin,
             shared_cmds::get_profile,
+            shared_cmds::generate_access_qr,
             shared_cmds::update_profile,
             shared_cmds::auth_status,
+            shared_cmds::lock_session,
+            shared_cmds::unlock_session,
+            shared_cmds::get_settings,
+            shared_cmds::update_settings,
+            shared_cmds::update_pin_wipe_settings,
             admin::get_hospital_personnels,
+            access_request::submit_access_request,
+            access_request::get_access_requests,
+            fhir::import_fhir_bundle,
             medical_personnel::new_medical_record,
             medical_personnel::get_medical_record,
             medical_personnel::get_medical_record_update,
+            medical_personnel::get_medical_record_versions,
             medical_personnel::get_read_access_medical_personnel,
             medical_personnel::get_update_access_medical_personnel,
+            medical_personnel::register_emergency_recipient,
+            medical_personnel::request_emergency_access,
             medical_personnel::update_medical_record,
             administrative_personnel::get_administrative_data,
             administrative_personnel::get_read_access_administrative_personnel,
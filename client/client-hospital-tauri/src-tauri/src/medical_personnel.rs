@@ -1,30 +1,35 @@
-use std::str::FromStr;
+use std::{io::Cursor, str::FromStr};
 
 use anyhow::{anyhow, Context};
+use futures_util::stream;
 use iota_types::base_types::IotaAddress;
 use serde_json::{json, Value};
 use tauri::{async_runtime::Mutex, http::StatusCode, State};
 use tauri_plugin_http::reqwest;
 use umbral_pre::{decrypt_original, decrypt_reencrypted, encrypt, Capsule, CapsuleFrag, PublicKey};
+use zeroize::Zeroizing;
 
 use crate::{
-    constants::PROXY_BASE_URL,
+    constants::{EMERGENCY_JUSTIFICATION_LEN_MAX, EMERGENCY_JUSTIFICATION_LEN_MIN, PROXY_BASE_URL},
     current_fn,
     hospital_error::HospitalError,
     types::{
         AccessData, AccessMetadata, AccessMetadataEncrypted, AppState,
-        CommandNewMedicalRecordPayload, CommandUpdateMedicalRecordPayload, KeyNonce, MedicalData,
-        MedicalMetadata, PatientPrivateAdministrativeData, ProxyReencryptionErrorResponse,
-        ProxyReencryptionGetMedicalRecordResponseData,
-        ProxyReencryptionGetMedicalRecordUpdateResponseData, ProxyReencryptionSuccessResponse,
-        ResponseStatus, SuccessResponse,
+        CommandNewMedicalRecordPayload, CommandRequestEmergencyAccessResponseData,
+        CommandUpdateMedicalRecordPayload, KeyNonce, MedicalData, MedicalMetadata,
+        PatientPrivateAdministrativeData, ProxyReencryptionCreateEmergencyAccessResponseData,
+        ProxyReencryptionErrorResponse, ProxyReencryptionGetMedicalRecordResponseData,
+        ProxyReencryptionGetMedicalRecordUpdateResponseData,
+        ProxyReencryptionGetMedicalRecordVersionsResponseData, ProxyReencryptionSuccessResponse,
+        ProxyReencryptionUploadMedicalRecordContentResponseData, ResponseStatus, SuccessResponse,
     },
     utils::{
-        aes_decrypt, aes_encrypt, compute_pre_keys, do_http_get_request_json,
+        aes_decrypt, aes_stream_decrypt_response, aes_stream_encrypt, compute_pre_keys,
+        do_http_get_request_json, do_http_get_request_stream, do_http_post_request_bytes,
         do_http_post_request_json, do_http_put_request_json, encode_activation_key_from_keys_entry,
         get_iota_address_from_keys_entry, get_iota_key_pair_from_keys_entry,
         get_pre_keys_from_keys_entry, parse_keys_entry, serde_deserialize_from_base64,
-        serde_serialize_to_base64,
+        serde_serialize_to_base64, sign_proxy_nonce,
     },
 };
 use base64::{engine::general_purpose::STANDARD, Engine as _};
@@ -39,7 +44,12 @@ pub async fn new_medical_record(
 ) -> Result<SuccessResponse<()>, HospitalError> {
     let req_client = reqwest::Client::new();
 
-    let (medical_metadata, patient_iota_address) = {
+    let (
+        enc_medical_data,
+        enc_medical_data_key_nonce,
+        medical_data_key_nonce_capsule,
+        patient_iota_address,
+    ) = {
         let patient_iota_address =
             IotaAddress::from_str(&patient_iota_address).context(current_fn!())?;
         let patient_pre_public_key: PublicKey =
@@ -52,9 +62,11 @@ pub async fn new_medical_record(
             psychological_check: data.psychological_check,
             therapy: data.therapy,
         };
-        let (enc_medical_data, medical_data_key, medical_data_nonce) =
-            aes_encrypt(&serde_json::to_vec(&medical_data).context(current_fn!())?)
-                .context(current_fn!())?;
+        // Encrypted while the upload is sent, the ciphertext is never held whole
+        let (medical_data_key, medical_data_nonce, enc_medical_data) =
+            aes_stream_encrypt(Cursor::new(Zeroizing::new(
+                serde_json::to_vec(&medical_data).context(current_fn!())?,
+            )));
 
         let medical_data_key_nonce = KeyNonce {
             key: STANDARD.encode(medical_data_key),
@@ -62,18 +74,36 @@ pub async fn new_medical_record(
         };
         let (medical_data_key_nonce_capsule, enc_medical_data_key_nonce) = encrypt(
             &patient_pre_public_key,
-            &serde_json::to_vec(&medical_data_key_nonce).context(current_fn!())?,
+            &Zeroizing::new(serde_json::to_vec(&medical_data_key_nonce).context(current_fn!())?),
         )
         .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
 
-        let medical_metadata = MedicalMetadata {
-            capsule: serde_serialize_to_base64(&medical_data_key_nonce_capsule)
-                .context(current_fn!())?,
-            enc_data: STANDARD.encode(enc_medical_data),
-            enc_key_and_nonce: STANDARD.encode(enc_medical_data_key_nonce),
-        };
+        (
+            enc_medical_data,
+            enc_medical_data_key_nonce,
+            medical_data_key_nonce_capsule,
+            patient_iota_address,
+        )
+    };
+
+    let res = do_http_post_request_bytes::<
+        ProxyReencryptionSuccessResponse<ProxyReencryptionUploadMedicalRecordContentResponseData>,
+        ProxyReencryptionErrorResponse,
+    >(
+        Some(access_token.clone()),
+        &format!("{}/medical-record/content", PROXY_BASE_URL),
+        reqwest::Body::wrap_stream(stream::iter(enc_medical_data)),
+        &req_client,
+        StatusCode::OK,
+    )
+    .await
+    .context(current_fn!())?;
 
-        (medical_metadata, patient_iota_address)
+    let medical_metadata = MedicalMetadata {
+        capsule: serde_serialize_to_base64(&medical_data_key_nonce_capsule)
+            .context(current_fn!())?,
+        enc_key_and_nonce: STANDARD.encode(enc_medical_data_key_nonce),
+        locator: res.data.locator,
     };
 
     let _ = do_http_post_request_json::<
@@ -105,20 +135,17 @@ pub async fn get_medical_record(
     access_token: String,
     index: Option<u64>,
     patient_iota_address: String,
+    version: Option<u64>,
 ) -> Result<SuccessResponse<Value>, HospitalError> {
-    let state = state.lock().await;
+    let mut state = state.lock().await;
     let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
         .context(current_fn!())?;
     let req_client = reqwest::Client::new();
 
     let hospital_personnel_pre_secret_key = {
-        let pin = state
-            .auth_state
-            .session_pin
-            .clone()
-            .ok_or(anyhow!("Session PIN not found"))?;
+        let pin = state.auth_state.session.pin().context(current_fn!())?;
         let (hospital_personnel_pre_secret_key, _) =
-            get_pre_keys_from_keys_entry(&keys_entry, pin).context(current_fn!())?;
+            get_pre_keys_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
 
         hospital_personnel_pre_secret_key
     };
@@ -128,14 +155,17 @@ pub async fn get_medical_record(
         ProxyReencryptionErrorResponse,
         _,
     >(
-        Some(access_token),
+        Some(access_token.clone()),
         &req_client,
         StatusCode::OK,
         format!(
-            "{}/medical-record?index={}&patient_iota_address={}",
+            "{}/medical-record?index={}&patient_iota_address={}&version={}",
             PROXY_BASE_URL,
             index.unwrap_or(0),
-            patient_iota_address
+            patient_iota_address,
+            version
+                .map(|version| version.to_string())
+                .unwrap_or_default()
         ),
     )
     .await
@@ -155,6 +185,7 @@ pub async fn get_medical_record(
                 .decode(res.data.enc_data_pre_secret_key_seed)
                 .context(current_fn!())?,
         )
+        .map(Zeroizing::new)
         .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
         let (medical_record_pre_secret_key, medical_record_pre_public_key) =
             compute_pre_keys(&medical_record_pre_secret_key_seed).context(current_fn!())?;
@@ -181,20 +212,41 @@ pub async fn get_medical_record(
                 .decode(res.data.enc_medical_data_key_nonce)
                 .context(current_fn!())?,
         )
+        .map(Zeroizing::new)
         .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
         let medical_data_key_nonce: KeyNonce =
             serde_json::from_slice(&medical_data_key_nonce).context(current_fn!())?;
-        let medical_data = aes_decrypt(
-            &STANDARD
-                .decode(res.data.enc_medical_data)
-                .context(current_fn!())?,
-            &STANDARD
-                .decode(medical_data_key_nonce.key)
-                .context(current_fn!())?,
+        let medical_data_content = do_http_get_request_stream::<ProxyReencryptionErrorResponse, _>(
+            Some(access_token),
+            &req_client,
+            StatusCode::OK,
+            format!(
+                "{}/medical-record/content?index={}&patient_iota_address={}&version={}",
+                PROXY_BASE_URL,
+                index.unwrap_or(0),
+                patient_iota_address,
+                version
+                    .map(|version| version.to_string())
+                    .unwrap_or_default()
+            ),
+        )
+        .await
+        .context(current_fn!())?;
+        let mut medical_data = Zeroizing::new(Vec::new());
+        aes_stream_decrypt_response(
+            medical_data_content,
+            &Zeroizing::new(
+                STANDARD
+                    .decode(&medical_data_key_nonce.key)
+                    .context(current_fn!())?,
+            ),
             &STANDARD
-                .decode(medical_data_key_nonce.nonce)
+                .decode(&medical_data_key_nonce.nonce)
                 .context(current_fn!())?,
+            res.data.medical_data_sha256.as_deref(),
+            &mut *medical_data,
         )
+        .await
         .context(current_fn!())?;
         let medical_data: MedicalData =
             serde_json::from_slice(&medical_data).context(current_fn!())?;
@@ -221,6 +273,7 @@ pub async fn get_medical_record(
                 .decode(res.data.enc_administrative_data_key_nonce)
                 .context(current_fn!())?,
         )
+        .map(Zeroizing::new)
         .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
         let administrative_data_key_nonce: KeyNonce =
             serde_json::from_slice(&administrative_data_key_nonce).context(current_fn!())?;
@@ -228,11 +281,13 @@ pub async fn get_medical_record(
             &STANDARD
                 .decode(res.data.enc_administrative_data)
                 .context(current_fn!())?,
+            &Zeroizing::new(
+                STANDARD
+                    .decode(&administrative_data_key_nonce.key)
+                    .context(current_fn!())?,
+            ),
             &STANDARD
-                .decode(administrative_data_key_nonce.key)
-                .context(current_fn!())?,
-            &STANDARD
-                .decode(administrative_data_key_nonce.nonce)
+                .decode(&administrative_data_key_nonce.nonce)
                 .context(current_fn!())?,
         )
         .context(current_fn!())?;
@@ -245,10 +300,13 @@ pub async fn get_medical_record(
     let res_data = json!({
         "administrativeData": administrative_data,
         "createdAt": res.data.medical_data_created_at,
+        "createdBy": res.data.medical_data_created_by,
         "currentIndex": res.data.current_index,
         "medicalData": medical_data,
         "nextIndex": res.data.next_index,
         "prevIndex": res.data.prev_index,
+        "version": res.data.medical_data_version,
+        "versionCount": res.data.medical_data_versions.len(),
     });
 
     Ok(SuccessResponse {
@@ -264,19 +322,15 @@ pub async fn get_medical_record_update(
     index: u64,
     patient_iota_address: String,
 ) -> Result<SuccessResponse<Value>, HospitalError> {
-    let state = state.lock().await;
+    let mut state = state.lock().await;
     let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
         .context(current_fn!())?;
     let req_client = reqwest::Client::new();
 
     let hospital_personnel_pre_secret_key = {
-        let pin = state
-            .auth_state
-            .session_pin
-            .clone()
-            .ok_or(anyhow!("Session PIN not found"))?;
+        let pin = state.auth_state.session.pin().context(current_fn!())?;
         let (hospital_personnel_pre_secret_key, _) =
-            get_pre_keys_from_keys_entry(&keys_entry, pin).context(current_fn!())?;
+            get_pre_keys_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
 
         hospital_personnel_pre_secret_key
     };
@@ -286,7 +340,7 @@ pub async fn get_medical_record_update(
         ProxyReencryptionErrorResponse,
         _,
     >(
-        Some(access_token),
+        Some(access_token.clone()),
         &req_client,
         StatusCode::OK,
         format!(
@@ -311,6 +365,7 @@ pub async fn get_medical_record_update(
                 .decode(res.data.enc_data_pre_secret_key_seed)
                 .context(current_fn!())?,
         )
+        .map(Zeroizing::new)
         .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
         let (medical_record_pre_secret_key, medical_record_pre_public_key) =
             compute_pre_keys(&medical_record_pre_secret_key_seed).context(current_fn!())?;
@@ -337,20 +392,36 @@ pub async fn get_medical_record_update(
                 .decode(res.data.enc_medical_data_key_nonce)
                 .context(current_fn!())?,
         )
+        .map(Zeroizing::new)
         .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
         let medical_data_key_nonce: KeyNonce =
             serde_json::from_slice(&medical_data_key_nonce).context(current_fn!())?;
-        let medical_data = aes_decrypt(
-            &STANDARD
-                .decode(res.data.enc_medical_data)
-                .context(current_fn!())?,
-            &STANDARD
-                .decode(medical_data_key_nonce.key)
-                .context(current_fn!())?,
+        let medical_data_content = do_http_get_request_stream::<ProxyReencryptionErrorResponse, _>(
+            Some(access_token),
+            &req_client,
+            StatusCode::OK,
+            format!(
+                "{}/medical-record/content?index={}&patient_iota_address={}",
+                PROXY_BASE_URL, index, patient_iota_address
+            ),
+        )
+        .await
+        .context(current_fn!())?;
+        let mut medical_data = Zeroizing::new(Vec::new());
+        aes_stream_decrypt_response(
+            medical_data_content,
+            &Zeroizing::new(
+                STANDARD
+                    .decode(&medical_data_key_nonce.key)
+                    .context(current_fn!())?,
+            ),
             &STANDARD
-                .decode(medical_data_key_nonce.nonce)
+                .decode(&medical_data_key_nonce.nonce)
                 .context(current_fn!())?,
+            res.data.medical_data_sha256.as_deref(),
+            &mut *medical_data,
         )
+        .await
         .context(current_fn!())?;
         let medical_data: MedicalData =
             serde_json::from_slice(&medical_data).context(current_fn!())?;
@@ -377,6 +448,7 @@ pub async fn get_medical_record_update(
                 .decode(res.data.enc_administrative_data_key_nonce)
                 .context(current_fn!())?,
         )
+        .map(Zeroizing::new)
         .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
         let administrative_data_key_nonce: KeyNonce =
             serde_json::from_slice(&administrative_data_key_nonce).context(current_fn!())?;
@@ -384,11 +456,13 @@ pub async fn get_medical_record_update(
             &STANDARD
                 .decode(res.data.enc_administrative_data)
                 .context(current_fn!())?,
+            &Zeroizing::new(
+                STANDARD
+                    .decode(&administrative_data_key_nonce.key)
+                    .context(current_fn!())?,
+            ),
             &STANDARD
-                .decode(administrative_data_key_nonce.key)
-                .context(current_fn!())?,
-            &STANDARD
-                .decode(administrative_data_key_nonce.nonce)
+                .decode(&administrative_data_key_nonce.nonce)
                 .context(current_fn!())?,
         )
         .context(current_fn!())?;
@@ -410,11 +484,66 @@ pub async fn get_medical_record_update(
     })
 }
 
+/**
+ * Revision timeline of a record, oldest first and the current version last.
+ * A version is opened with `get_medical_record`.
+ */
+#[tauri::command]
+pub async fn get_medical_record_versions(
+    _state: State<'_, Mutex<AppState>>,
+    access_token: String,
+    index: Option<u64>,
+    patient_iota_address: String,
+) -> Result<SuccessResponse<Value>, HospitalError> {
+    let req_client = reqwest::Client::new();
+
+    let res = do_http_get_request_json::<
+        ProxyReencryptionSuccessResponse<ProxyReencryptionGetMedicalRecordVersionsResponseData>,
+        ProxyReencryptionErrorResponse,
+        _,
+    >(
+        Some(access_token),
+        &req_client,
+        StatusCode::OK,
+        format!(
+            "{}/medical-record/versions?index={}&patient_iota_address={}",
+            PROXY_BASE_URL,
+            index.unwrap_or(0),
+            patient_iota_address
+        ),
+    )
+    .await
+    .context(current_fn!())?;
+
+    let versions = res
+        .data
+        .versions
+        .into_iter()
+        .map(|version| {
+            json!({
+                "createdAt": version.created_at,
+                "createdBy": version.created_by,
+                "version": version.version,
+            })
+        })
+        .collect::<Vec<Value>>();
+
+    let res_data = json!({
+        "currentIndex": res.data.current_index,
+        "versions": versions,
+    });
+
+    Ok(SuccessResponse {
+        data: res_data,
+        status: ResponseStatus::Success,
+    })
+}
+
 #[tauri::command]
 pub async fn get_read_access_medical_personnel(
     state: State<'_, Mutex<AppState>>,
 ) -> Result<SuccessResponse<Vec<AccessData>>, HospitalError> {
-    let state = state.lock().await;
+    let mut state = state.lock().await;
     let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
         .context(current_fn!())?;
 
@@ -424,19 +553,15 @@ pub async fn get_read_access_medical_personnel(
         medical_personnel_iota_key_pair,
         medical_personnel_pre_secret_key,
     ) = {
-        let pin = state
-            .auth_state
-            .session_pin
-            .clone()
-            .ok_or(anyhow!("Session PIN not found on auth state").context(current_fn!()))?;
+        let pin = state.auth_state.session.pin().context(current_fn!())?;
         let activation_key =
             encode_activation_key_from_keys_entry(&keys_entry).context(current_fn!())?;
         let medical_personnel_iota_address =
             get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
         let medical_personnel_iota_key_pair =
-            get_iota_key_pair_from_keys_entry(&keys_entry, pin.clone()).context(current_fn!())?;
+            get_iota_key_pair_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
         let (medical_personnel_pre_secret_key, _) =
-            get_pre_keys_from_keys_entry(&keys_entry, pin).context(current_fn!())?;
+            get_pre_keys_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
 
         (
             activation_key,
@@ -479,14 +604,31 @@ pub async fn get_read_access_medical_personnel(
             .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
             let access_metadata: AccessMetadata =
                 serde_json::from_slice(&access_metadata).context(current_fn!())?;
+            let patient_name = match access_metadata.enc_patient_name {
+                Some(enc_patient_name) => {
+                    let patient_name = decrypt_original(
+                        &medical_personnel_pre_secret_key,
+                        &serde_deserialize_from_base64(enc_patient_name.capsule)
+                            .context(current_fn!())?,
+                        &STANDARD
+                            .decode(enc_patient_name.enc_data)
+                            .context(current_fn!())?,
+                    )
+                    .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
+
+                    String::from_utf8(patient_name.to_vec()).context(current_fn!())?
+                }
+                None => access_metadata.patient_name,
+            };
 
             let access = AccessData {
                 access_data_types: access.access_data_types,
                 access_token: access_metadata.access_token,
                 exp: access.exp,
                 medical_metadata_index: access.medical_metadata_index,
+                medical_metadata_scope: access.medical_metadata_scope,
                 patient_iota_address: access_metadata.patient_iota_address,
-                patient_name: access_metadata.patient_name,
+                patient_name,
                 patient_pre_public_key: access_metadata.patient_pre_public_key,
             };
 
@@ -500,11 +642,152 @@ pub async fn get_read_access_medical_personnel(
     })
 }
 
+/**
+ * Registers the PRE public key patients with emergency access enabled build break-glass keys for.
+ * Sent again on every dashboard visit, patients rebuild the keys when it changed.
+ */
+#[tauri::command]
+pub async fn register_emergency_recipient(
+    state: State<'_, Mutex<AppState>>,
+) -> Result<SuccessResponse<()>, HospitalError> {
+    let mut state = state.lock().await;
+    let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+        .context(current_fn!())?;
+    let req_client = reqwest::Client::new();
+
+    let (
+        medical_personnel_iota_address,
+        medical_personnel_iota_key_pair,
+        medical_personnel_pre_public_key,
+    ) = {
+        let pin = state.auth_state.session.pin().context(current_fn!())?;
+        let medical_personnel_iota_address =
+            get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
+        let medical_personnel_iota_key_pair =
+            get_iota_key_pair_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+        let (_, medical_personnel_pre_public_key) =
+            get_pre_keys_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+
+        (
+            medical_personnel_iota_address,
+            medical_personnel_iota_key_pair,
+            medical_personnel_pre_public_key,
+        )
+    };
+
+    let signature = sign_proxy_nonce(
+        &req_client,
+        medical_personnel_iota_address,
+        &medical_personnel_iota_key_pair,
+    )
+    .await
+    .context(current_fn!())?;
+
+    do_http_post_request_json::<
+        _,
+        ProxyReencryptionSuccessResponse<()>,
+        ProxyReencryptionErrorResponse,
+    >(
+        None,
+        &format!("{}/emergency/recipients", PROXY_BASE_URL),
+        &json!({
+            "hospital_personnel_iota_address": medical_personnel_iota_address.to_string(),
+            "hospital_personnel_pre_public_key": serde_serialize_to_base64(&medical_personnel_pre_public_key)
+                .context(current_fn!())?,
+            "signature": signature,
+        }),
+        &req_client,
+        StatusCode::OK,
+    )
+    .await
+    .context(current_fn!())?;
+
+    Ok(SuccessResponse {
+        data: (),
+        status: ResponseStatus::Success,
+    })
+}
+
+/**
+ * Break-glass read access without the patient, only granted when the patient enabled emergency
+ * access. The justification is logged on chain and shown to the patient, the access is listed by
+ * `get_read_access_medical_personnel` like any other.
+ */
+#[tauri::command]
+pub async fn request_emergency_access(
+    state: State<'_, Mutex<AppState>>,
+    justification: String,
+    patient_iota_address: String,
+) -> Result<SuccessResponse<CommandRequestEmergencyAccessResponseData>, HospitalError> {
+    let mut state = state.lock().await;
+    let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+        .context(current_fn!())?;
+    let req_client = reqwest::Client::new();
+
+    let patient_iota_address = IotaAddress::from_str(patient_iota_address.trim())
+        .map_err(|_| anyhow!("Invalid args: patient IOTA address"))?;
+    let justification = justification.trim().to_string();
+    if !(EMERGENCY_JUSTIFICATION_LEN_MIN..=EMERGENCY_JUSTIFICATION_LEN_MAX)
+        .contains(&justification.len())
+    {
+        return Err(HospitalError::Anyhow(anyhow!(
+            "Invalid args: justification must be between {} and {} characters",
+            EMERGENCY_JUSTIFICATION_LEN_MIN,
+            EMERGENCY_JUSTIFICATION_LEN_MAX
+        )));
+    }
+
+    let (medical_personnel_iota_address, medical_personnel_iota_key_pair) = {
+        let pin = state.auth_state.session.pin().context(current_fn!())?;
+        let medical_personnel_iota_address =
+            get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
+        let medical_personnel_iota_key_pair =
+            get_iota_key_pair_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+
+        (
+            medical_personnel_iota_address,
+            medical_personnel_iota_key_pair,
+        )
+    };
+
+    let signature = sign_proxy_nonce(
+        &req_client,
+        medical_personnel_iota_address,
+        &medical_personnel_iota_key_pair,
+    )
+    .await
+    .context(current_fn!())?;
+
+    let res = do_http_post_request_json::<
+        _,
+        ProxyReencryptionSuccessResponse<ProxyReencryptionCreateEmergencyAccessResponseData>,
+        ProxyReencryptionErrorResponse,
+    >(
+        None,
+        &format!("{}/emergency/access", PROXY_BASE_URL),
+        &json!({
+            "hospital_personnel_iota_address": medical_personnel_iota_address.to_string(),
+            "justification": justification,
+            "patient_iota_address": patient_iota_address.to_string(),
+            "signature": signature,
+        }),
+        &req_client,
+        StatusCode::OK,
+    )
+    .await
+    .context(current_fn!())?;
+
+    Ok(SuccessResponse {
+        data: CommandRequestEmergencyAccessResponseData { exp: res.data.exp },
+        status: ResponseStatus::Success,
+    })
+}
+
 #[tauri::command]
 pub async fn get_update_access_medical_personnel(
     state: State<'_, Mutex<AppState>>,
 ) -> Result<SuccessResponse<Vec<AccessData>>, HospitalError> {
-    let state = state.lock().await;
+    let mut state = state.lock().await;
     let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
         .context(current_fn!())?;
 
@@ -514,19 +797,15 @@ pub async fn get_update_access_medical_personnel(
         medical_personnel_iota_key_pair,
         medical_personnel_pre_secret_key,
     ) = {
-        let pin = state
-            .auth_state
-            .session_pin
-            .clone()
-            .ok_or(anyhow!("Session PIN not found on auth state").context(current_fn!()))?;
+        let pin = state.auth_state.session.pin().context(current_fn!())?;
         let activation_key =
             encode_activation_key_from_keys_entry(&keys_entry).context(current_fn!())?;
         let medical_personnel_iota_address =
             get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
         let medical_personnel_iota_key_pair =
-            get_iota_key_pair_from_keys_entry(&keys_entry, pin.clone()).context(current_fn!())?;
+            get_iota_key_pair_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
         let (medical_personnel_pre_secret_key, _) =
-            get_pre_keys_from_keys_entry(&keys_entry, pin).context(current_fn!())?;
+            get_pre_keys_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
 
         (
             activation_key,
@@ -575,6 +854,7 @@ pub async fn get_update_access_medical_personnel(
                 access_token: access_metadata.access_token,
                 exp: access.exp,
                 medical_metadata_index: access.medical_metadata_index,
+                medical_metadata_scope: access.medical_metadata_scope,
                 patient_iota_address: access_metadata.patient_iota_address,
                 patient_name: access_metadata.patient_name,
                 patient_pre_public_key: access_metadata.patient_pre_public_key,
@@ -595,12 +875,18 @@ pub async fn update_medical_record(
     _state: State<'_, Mutex<AppState>>,
     access_token: String,
     data: CommandUpdateMedicalRecordPayload,
+    index: u64,
     patient_iota_address: String,
     patient_pre_public_key: String,
 ) -> Result<SuccessResponse<()>, HospitalError> {
     let req_client = reqwest::Client::new();
 
-    let (medical_metadata, patient_iota_address) = {
+    let (
+        enc_medical_data,
+        enc_medical_data_key_nonce,
+        medical_data_key_nonce_capsule,
+        patient_iota_address,
+    ) = {
         let patient_iota_address =
             IotaAddress::from_str(&patient_iota_address).context(current_fn!())?;
         let patient_pre_public_key: PublicKey =
@@ -613,9 +899,11 @@ pub async fn update_medical_record(
             psychological_check: data.psychological_check,
             therapy: data.therapy,
         };
-        let (enc_medical_data, medical_data_key, medical_data_nonce) =
-            aes_encrypt(&serde_json::to_vec(&medical_data).context(current_fn!())?)
-                .context(current_fn!())?;
+        // Encrypted while the upload is sent, the ciphertext is never held whole
+        let (medical_data_key, medical_data_nonce, enc_medical_data) =
+            aes_stream_encrypt(Cursor::new(Zeroizing::new(
+                serde_json::to_vec(&medical_data).context(current_fn!())?,
+            )));
 
         let medical_data_key_nonce = KeyNonce {
             key: STANDARD.encode(medical_data_key),
@@ -623,18 +911,36 @@ pub async fn update_medical_record(
         };
         let (medical_data_key_nonce_capsule, enc_medical_data_key_nonce) = encrypt(
             &patient_pre_public_key,
-            &serde_json::to_vec(&medical_data_key_nonce).context(current_fn!())?,
+            &Zeroizing::new(serde_json::to_vec(&medical_data_key_nonce).context(current_fn!())?),
         )
         .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
 
-        let medical_metadata = MedicalMetadata {
-            capsule: serde_serialize_to_base64(&medical_data_key_nonce_capsule)
-                .context(current_fn!())?,
-            enc_data: STANDARD.encode(enc_medical_data),
-            enc_key_and_nonce: STANDARD.encode(enc_medical_data_key_nonce),
-        };
+        (
+            enc_medical_data,
+            enc_medical_data_key_nonce,
+            medical_data_key_nonce_capsule,
+            patient_iota_address,
+        )
+    };
+
+    let res = do_http_post_request_bytes::<
+        ProxyReencryptionSuccessResponse<ProxyReencryptionUploadMedicalRecordContentResponseData>,
+        ProxyReencryptionErrorResponse,
+    >(
+        Some(access_token.clone()),
+        &format!("{}/medical-record/content", PROXY_BASE_URL),
+        reqwest::Body::wrap_stream(stream::iter(enc_medical_data)),
+        &req_client,
+        StatusCode::OK,
+    )
+    .await
+    .context(current_fn!())?;
 
-        (medical_metadata, patient_iota_address)
+    let medical_metadata = MedicalMetadata {
+        capsule: serde_serialize_to_base64(&medical_data_key_nonce_capsule)
+            .context(current_fn!())?,
+        enc_key_and_nonce: STANDARD.encode(enc_medical_data_key_nonce),
+        locator: res.data.locator,
     };
 
     let _ = do_http_put_request_json::<
@@ -645,6 +951,7 @@ pub async fn update_medical_record(
         Some(access_token),
         &format!("{}/medical-record", PROXY_BASE_URL),
         &json!({
+            "index": index,
             "medical_metadata": serde_serialize_to_base64(&medical_metadata).context(current_fn!())?,
             "patient_iota_address": patient_iota_address.to_string(),
         }),
@@ -1,21 +1,33 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use anyhow::{anyhow, Context};
 use tauri::{async_runtime::Mutex, State};
 use umbral_pre::{decrypt_original, encrypt, Capsule};
+use zeroize::Zeroizing;
 
 use crate::{
+    constants::{
+        HOSPITAL_PERSONNEL_QR_ROTATION_INTERVAL, HOSPITAL_PERSONNEL_QR_VALIDITY,
+        PIN_WIPE_AFTER_FAILED_ATTEMPTS_MAX, PIN_WIPE_AFTER_FAILED_ATTEMPTS_MIN,
+        SESSION_IDLE_TIMEOUT_MAX, SESSION_IDLE_TIMEOUT_MIN,
+    },
     current_fn,
     hospital_error::HospitalError,
     types::{
-        AdministrativeData, AppState, CommandGetProfileResponseData, CommandUpdateProfileArgs,
-        HospitalPersonnelRole, KeyNonce, PrivateAdministrativeData, PrivateAdministrativeMetadata,
-        PublicAdministrativeData, ResponseStatus, SuccessResponse,
+        AdministrativeData, AppState, CommandGenerateAccessQrResponseData,
+        CommandGenerateAccessQrScopeArgs, CommandGetProfileResponseData,
+        CommandGetSettingsResponseData, CommandUpdateProfileArgs, HospitalPersonnelQrPayload,
+        HospitalPersonnelQrScope, HospitalPersonnelRole, KeyNonce, PrivateAdministrativeData,
+        PrivateAdministrativeMetadata, PublicAdministrativeData, QrImageFormat, ResponseStatus,
+        SuccessResponse,
     },
     utils::{
-        aes_decrypt, aes_encrypt, decode_hospital_personnel_id_to_argon,
-        encode_activation_key_from_keys_entry, generate_64_bytes_seed, generate_iota_keys_ed,
-        get_iota_address_from_keys_entry, get_iota_key_pair_from_keys_entry,
-        get_pre_keys_from_keys_entry, parse_keys_entry, serde_deserialize_from_base64,
-        serde_serialize_to_base64, validate_by_regex,
+        aes_decrypt, aes_encrypt, decode_hospital_personnel_id_to_argon, encode_access_handoff_uri,
+        encode_activation_key_from_keys_entry, encode_hospital_personnel_qr,
+        generate_64_bytes_seed, generate_iota_keys_ed, get_iota_address_from_keys_entry,
+        get_iota_key_pair_from_keys_entry, get_pre_keys_from_keys_entry, parse_keys_entry,
+        render_qr, serde_deserialize_from_base64, serde_serialize_to_base64, unlock_keys_entry,
+        validate_access_scope, validate_by_regex,
     },
 };
 
@@ -24,7 +36,7 @@ use base64::{engine::general_purpose::STANDARD, Engine as _};
 #[tauri::command]
 pub async fn validate_pin(
     state: State<'_, Mutex<AppState>>,
-    pin: String,
+    pin: Zeroizing<String>,
     auth_type: String,
 ) -> Result<SuccessResponse<()>, HospitalError> {
     let mut state = state.lock().await;
@@ -56,7 +68,7 @@ pub async fn validate_pin(
 #[tauri::command]
 pub async fn validate_confirm_pin(
     state: State<'_, Mutex<AppState>>,
-    confirm_pin: String,
+    confirm_pin: Zeroizing<String>,
     auth_type: String,
 ) -> Result<SuccessResponse<()>, HospitalError> {
     let state = state.lock().await;
@@ -93,6 +105,78 @@ pub async fn validate_confirm_pin(
     })
 }
 
+/**
+ * QR a patient scans to grant access, optionally asking for `scope`. It expires shortly after
+ * `rotate_at`, so the UI has to generate a new one by then.
+ */
+#[tauri::command]
+pub async fn generate_access_qr(
+    state: State<'_, Mutex<AppState>>,
+    format: QrImageFormat,
+    scope: Option<CommandGenerateAccessQrScopeArgs>,
+) -> Result<SuccessResponse<CommandGenerateAccessQrResponseData>, HospitalError> {
+    let mut state = state.lock().await;
+    let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+        .context(current_fn!())?;
+
+    let (
+        hospital_personnel_iota_address,
+        hospital_personnel_iota_key_pair,
+        hospital_personnel_pre_public_key,
+    ) = {
+        let pin = state.auth_state.session.pin().context(current_fn!())?;
+        let hospital_personnel_iota_address =
+            get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
+        let hospital_personnel_iota_key_pair =
+            get_iota_key_pair_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+        let (_, hospital_personnel_pre_public_key) =
+            get_pre_keys_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+
+        (
+            hospital_personnel_iota_address,
+            hospital_personnel_iota_key_pair,
+            hospital_personnel_pre_public_key,
+        )
+    };
+
+    let scope = scope.map(HospitalPersonnelQrScope::from);
+    if let Some(scope) = &scope {
+        validate_access_scope(scope)?;
+    }
+
+    let iat = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .context(current_fn!())?
+        .as_secs();
+    let content = encode_hospital_personnel_qr(
+        &hospital_personnel_iota_key_pair,
+        &HospitalPersonnelQrPayload {
+            exp: iat + HOSPITAL_PERSONNEL_QR_VALIDITY,
+            iat,
+            iota_address: hospital_personnel_iota_address.to_string(),
+            patient_iota_address: None,
+            pre_public_key: serde_serialize_to_base64(&hospital_personnel_pre_public_key)
+                .context(current_fn!())?,
+            reason: None,
+            scope,
+        },
+    )?;
+    let (image, mime_type) = render_qr(&content, format)?;
+    let deep_link = encode_access_handoff_uri(&content)?;
+
+    Ok(SuccessResponse {
+        status: ResponseStatus::Success,
+        data: CommandGenerateAccessQrResponseData {
+            content,
+            deep_link,
+            expires_at: iat + HOSPITAL_PERSONNEL_QR_VALIDITY,
+            image: STANDARD.encode(image),
+            mime_type: mime_type.to_string(),
+            rotate_at: iat + HOSPITAL_PERSONNEL_QR_ROTATION_INTERVAL,
+        },
+    })
+}
+
 #[tauri::command]
 pub async fn get_profile(
     state: State<'_, Mutex<AppState>>,
@@ -111,17 +195,13 @@ pub async fn get_profile(
         let activation_key =
             encode_activation_key_from_keys_entry(&keys_entry).context(current_fn!())?;
 
-        let pin = state
-            .auth_state
-            .session_pin
-            .clone()
-            .ok_or(anyhow!("Session PIN not found").context(current_fn!()))?;
+        let pin = state.auth_state.session.pin().context(current_fn!())?;
         let hospital_personnel_iota_address =
             get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
         let hospital_personnel_iota_key_pair =
-            get_iota_key_pair_from_keys_entry(&keys_entry, pin.clone()).context(current_fn!())?;
+            get_iota_key_pair_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
         let (hospital_personnel_pre_secret_key, hospital_personnel_pre_public_key) =
-            get_pre_keys_from_keys_entry(&keys_entry, pin.clone()).context(current_fn!())?;
+            get_pre_keys_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
 
         (
             activation_key,
@@ -158,6 +238,7 @@ pub async fn get_profile(
             .decode(private_administrative_metadata.enc_key_nonce)
             .context(current_fn!())?,
     )
+    .map(Zeroizing::new)
     .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
     let private_administrative_data_key_nonce: KeyNonce =
         serde_json::from_slice(&private_administrative_data_key_nonce).context(current_fn!())?;
@@ -165,11 +246,13 @@ pub async fn get_profile(
         &STANDARD
             .decode(private_administrative_metadata.enc_data)
             .context(current_fn!())?,
+        &Zeroizing::new(
+            STANDARD
+                .decode(&private_administrative_data_key_nonce.key)
+                .context(current_fn!())?,
+        ),
         &STANDARD
-            .decode(private_administrative_data_key_nonce.key)
-            .context(current_fn!())?,
-        &STANDARD
-            .decode(private_administrative_data_key_nonce.nonce)
+            .decode(&private_administrative_data_key_nonce.nonce)
             .context(current_fn!())?,
     )?;
     let private_administrative_data: PrivateAdministrativeData =
@@ -236,17 +319,13 @@ pub async fn update_profile(
         let activation_key =
             encode_activation_key_from_keys_entry(&keys_entry).context(current_fn!())?;
 
-        let pin = state
-            .auth_state
-            .session_pin
-            .clone()
-            .ok_or(anyhow!("Session PIN not found").context(current_fn!()))?;
+        let pin = state.auth_state.session.pin().context(current_fn!())?;
         let hospital_personnel_iota_key_pair =
-            get_iota_key_pair_from_keys_entry(&keys_entry, pin.clone()).context(current_fn!())?;
+            get_iota_key_pair_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
         let hospital_personnel_iota_address =
             get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
         let (_, hospital_personnel_pre_public_key) =
-            get_pre_keys_from_keys_entry(&keys_entry, pin).context(current_fn!())?;
+            get_pre_keys_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
 
         (
             activation_key,
@@ -280,7 +359,10 @@ pub async fn update_profile(
             enc_private_administrative_data_key_nonce,
         ) = encrypt(
             &hospital_personnel_pre_public_key,
-            &serde_json::to_vec(&private_administrative_data_key_nonce).context(current_fn!())?,
+            &Zeroizing::new(
+                serde_json::to_vec(&private_administrative_data_key_nonce)
+                    .context(current_fn!())?,
+            ),
         )
         .unwrap();
 
@@ -342,7 +424,7 @@ pub async fn update_profile(
 pub async fn auth_status(
     state: State<'_, Mutex<AppState>>,
 ) -> Result<SuccessResponse<Option<HospitalPersonnelRole>>, HospitalError> {
-    let state = state.lock().await;
+    let mut state = state.lock().await;
     let keys_entry = parse_keys_entry(
         &state
             .keys_entry
@@ -375,7 +457,7 @@ pub async fn auth_status(
         let activation_key =
             encode_activation_key_from_keys_entry(&keys_entry).context(current_fn!())?;
         let seed = generate_64_bytes_seed();
-        let (random_iota_address, _) = generate_iota_keys_ed(&seed)
+        let (random_iota_address, _) = generate_iota_keys_ed(seed.as_slice())
             .context("$<0>$")
             .context(current_fn!())?;
         let (hospital_personnel_id_part_hash, hospital_personnel_hospital_part_hash) =
@@ -429,14 +511,143 @@ pub async fn auth_status(
         ));
     }
 
-    if state.auth_state.session_pin.is_none() {
+    if state.auth_state.session.is_locked() {
         return Err(HospitalError::Anyhow(
-            anyhow!("Session PIN not found").context("$<4>$"),
+            anyhow!("Session locked").context("$<4>$"),
         ));
     }
 
+    state.auth_state.session.touch();
+
     Ok(SuccessResponse {
         data: role,
         status: ResponseStatus::Success,
     })
 }
+
+#[tauri::command]
+pub async fn lock_session(
+    state: State<'_, Mutex<AppState>>,
+) -> Result<SuccessResponse<()>, HospitalError> {
+    let mut state = state.lock().await;
+
+    state.auth_state.session.lock();
+
+    Ok(SuccessResponse {
+        status: ResponseStatus::Success,
+        data: (),
+    })
+}
+
+#[tauri::command]
+pub async fn unlock_session(
+    state: State<'_, Mutex<AppState>>,
+    pin: Zeroizing<String>,
+) -> Result<SuccessResponse<()>, HospitalError> {
+    let mut state = state.lock().await;
+
+    let keys_entry = unlock_keys_entry(&state.keys_entry, &state.pin_attempts_entry, &pin)
+        .context(current_fn!())?;
+
+    state
+        .auth_state
+        .session
+        .unlock(pin, keys_entry.settings.session_idle_timeout);
+
+    Ok(SuccessResponse {
+        status: ResponseStatus::Success,
+        data: (),
+    })
+}
+
+#[tauri::command]
+pub async fn get_settings(
+    state: State<'_, Mutex<AppState>>,
+) -> Result<SuccessResponse<CommandGetSettingsResponseData>, HospitalError> {
+    let state = state.lock().await;
+    let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+        .context(current_fn!())?;
+
+    Ok(SuccessResponse {
+        status: ResponseStatus::Success,
+        data: CommandGetSettingsResponseData {
+            pin_wipe_after_failed_attempts: keys_entry.settings.pin_wipe_after_failed_attempts,
+            session_idle_timeout: keys_entry.settings.session_idle_timeout,
+        },
+    })
+}
+
+#[tauri::command]
+pub async fn update_settings(
+    state: State<'_, Mutex<AppState>>,
+    session_idle_timeout: u64,
+) -> Result<SuccessResponse<()>, HospitalError> {
+    let mut state = state.lock().await;
+    let mut keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+        .context(current_fn!())?;
+
+    // Only an unlocked session can change its own lock policy
+    state.auth_state.session.pin().context(current_fn!())?;
+
+    if !(SESSION_IDLE_TIMEOUT_MIN..=SESSION_IDLE_TIMEOUT_MAX).contains(&session_idle_timeout) {
+        return Err(HospitalError::Anyhow(anyhow!(
+            "Invalid args: session_idle_timeout must be between {} and {} seconds",
+            SESSION_IDLE_TIMEOUT_MIN,
+            SESSION_IDLE_TIMEOUT_MAX
+        )));
+    }
+
+    keys_entry.settings.session_idle_timeout = session_idle_timeout;
+    state
+        .keys_entry
+        .set_secret(&serde_json::to_vec(&keys_entry).context(current_fn!())?)
+        .context(current_fn!())?;
+
+    state
+        .auth_state
+        .session
+        .set_idle_timeout(session_idle_timeout);
+
+    Ok(SuccessResponse {
+        status: ResponseStatus::Success,
+        data: (),
+    })
+}
+
+/**
+ * `pin_wipe_after_failed_attempts`: none turns the wipe off
+ */
+#[tauri::command]
+pub async fn update_pin_wipe_settings(
+    state: State<'_, Mutex<AppState>>,
+    pin_wipe_after_failed_attempts: Option<u32>,
+) -> Result<SuccessResponse<()>, HospitalError> {
+    let state = state.lock().await;
+    let mut keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+        .context(current_fn!())?;
+
+    state.auth_state.session.pin().context(current_fn!())?;
+
+    let is_in_range = pin_wipe_after_failed_attempts.is_none_or(|max_attempts| {
+        (PIN_WIPE_AFTER_FAILED_ATTEMPTS_MIN..=PIN_WIPE_AFTER_FAILED_ATTEMPTS_MAX)
+            .contains(&max_attempts)
+    });
+    if !is_in_range {
+        return Err(HospitalError::Anyhow(anyhow!(
+            "Invalid args: pin_wipe_after_failed_attempts must be between {} and {}",
+            PIN_WIPE_AFTER_FAILED_ATTEMPTS_MIN,
+            PIN_WIPE_AFTER_FAILED_ATTEMPTS_MAX
+        )));
+    }
+
+    keys_entry.settings.pin_wipe_after_failed_attempts = pin_wipe_after_failed_attempts;
+    state
+        .keys_entry
+        .set_secret(&serde_json::to_vec(&keys_entry).context(current_fn!())?)
+        .context(current_fn!())?;
+
+    Ok(SuccessResponse {
+        status: ResponseStatus::Success,
+        data: (),
+    })
+}
@@ -12,7 +12,7 @@ use crate::{
 pub async fn signout(
     state: State<'_, Mutex<AppState>>,
 ) -> Result<SuccessResponse<()>, HospitalError> {
-    let state = state.lock().await;
+    let mut state = state.lock().await;
 
     let mut keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
         .context(current_fn!())?;
@@ -27,6 +27,8 @@ pub async fn signout(
         .set_secret(&keys_entry)
         .context(current_fn!())?;
 
+    state.auth_state.session.lock();
+
     Ok(SuccessResponse {
         status: ResponseStatus::Success,
         data: (),
@@ -55,6 +57,7 @@ pub async fn reset(
         .context(current_fn!())?;
     state.auth_state.role = None;
     state.auth_state.is_signed_up = false;
+    state.auth_state.session.lock();
 
     Ok(SuccessResponse {
         status: ResponseStatus::Success,
@@ -2,18 +2,20 @@ use anyhow::{anyhow, Context};
 use bip39::Mnemonic;
 use tauri::{async_runtime::Mutex, State};
 use umbral_pre::encrypt;
+use zeroize::Zeroizing;
 
 use crate::{
     current_fn,
     hospital_error::HospitalError,
     types::{
-        AdministrativeData, AppState, KeyNonce, PrivateAdministrativeData,
+        AdministrativeData, AppState, KeyNonce, PinAttempts, PrivateAdministrativeData,
         PrivateAdministrativeMetadata, PublicAdministrativeData, ResponseStatus, SuccessResponse,
     },
     utils::{
-        aes_encrypt, aes_encrypt_custom_key, compute_pre_keys, compute_seed_from_seed_words,
+        aes_encrypt, compute_pre_keys, compute_seed_from_seed_words,
         decode_hospital_personnel_id_to_argon, encode_activation_key_from_keys_entry,
-        generate_iota_keys_ed, parse_keys_entry, serde_serialize_to_base64, sha_hash,
+        generate_iota_keys_ed, parse_keys_entry, save_pin_attempts, seal_keys_entry,
+        serde_serialize_to_base64,
     },
 };
 use base64::{engine::general_purpose::STANDARD, Engine as _};
@@ -27,7 +29,7 @@ pub async fn generate_mnemonic(
     let mnemonic = Mnemonic::generate(12).context(current_fn!())?;
     let words = mnemonic.words().collect::<Vec<&str>>().join(" ");
 
-    state.signup_state.seed_words = Some(words.clone());
+    state.signup_state.seed_words = Some(Zeroizing::new(words.clone()));
 
     Ok(SuccessResponse {
         data: words,
@@ -55,7 +57,7 @@ pub async fn is_signed_up(
 #[tauri::command]
 pub async fn signup(
     state: State<'_, Mutex<AppState>>,
-    seed_words: String,
+    seed_words: Zeroizing<String>,
 ) -> Result<SuccessResponse<()>, HospitalError> {
     let mut state = state.lock().await;
     let mut keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
@@ -99,7 +101,7 @@ pub async fn signup(
             compute_seed_from_seed_words(&state.signup_state.seed_words.as_ref().unwrap(), &id)
                 .context(current_fn!())?;
         let (hospital_personnel_iota_address, hospital_personnel_iota_key_pair) =
-            generate_iota_keys_ed(&seed).context(current_fn!())?;
+            generate_iota_keys_ed(seed.as_slice()).context(current_fn!())?;
         let (_, hospital_personnel_pre_public_key) =
             compute_pre_keys(&seed[0..32]).context(current_fn!())?;
 
@@ -116,15 +118,7 @@ pub async fn signup(
         )
     };
 
-    let (
-        private_administrative_data,
-        private_administrative_metadata,
-        public_administrative_data,
-        enc_hospital_personnel_pre_secret_key,
-        hospital_personnel_pre_secret_key_nonce,
-        enc_hospital_personnel_iota_key_pair,
-        hospital_personnel_iota_keypair_nonce,
-    ) = {
+    let (private_administrative_data, private_administrative_metadata, public_administrative_data) = {
         // Construct private administrative data
         let private_administrative_data = PrivateAdministrativeData { id: id.clone() };
         let (
@@ -143,7 +137,10 @@ pub async fn signup(
             enc_private_administrative_data_key_nonce,
         ) = encrypt(
             &hospital_personnel_pre_public_key,
-            &serde_json::to_vec(&private_administrative_data_key_nonce).context(current_fn!())?,
+            &Zeroizing::new(
+                serde_json::to_vec(&private_administrative_data_key_nonce)
+                    .context(current_fn!())?,
+            ),
         )
         .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
 
@@ -157,33 +154,22 @@ pub async fn signup(
         // Construct public administrative data
         let public_administrative_data = PublicAdministrativeData { name: None };
 
-        // Encrypt PRE secret key
-        let (enc_hospital_personnel_pre_secret_key, hospital_personnel_pre_secret_key_nonce) =
-            aes_encrypt_custom_key(sha_hash(pin.as_bytes()).as_slice(), &seed[0..32])
-                .context(current_fn!())?;
-
-        // Encrypt IOTA keypair
-        let (enc_hospital_personnel_iota_key_pair, hospital_personnel_iota_key_pair_nonce) =
-            aes_encrypt_custom_key(
-                sha_hash(pin.as_bytes()).as_slice(),
-                hospital_personnel_iota_key_pair
-                    .encode()
-                    .unwrap()
-                    .as_bytes(),
-            )
-            .context(current_fn!())?;
-
         (
             private_administrative_data,
             private_administrative_metadata,
             public_administrative_data,
-            enc_hospital_personnel_pre_secret_key,
-            hospital_personnel_pre_secret_key_nonce,
-            enc_hospital_personnel_iota_key_pair,
-            hospital_personnel_iota_key_pair_nonce,
         )
     };
 
+    // Encrypt IOTA keypair and PRE secret key
+    seal_keys_entry(
+        &mut keys_entry,
+        &pin,
+        &hospital_personnel_iota_key_pair,
+        &seed[0..32],
+    )
+    .context(current_fn!())?;
+
     let _ = state
         .move_call
         .signup(
@@ -199,17 +185,18 @@ pub async fn signup(
         .context(current_fn!())?;
 
     keys_entry.iota_address = Some(hospital_personnel_iota_address.to_string());
-    keys_entry.iota_key_pair = Some(STANDARD.encode(enc_hospital_personnel_iota_key_pair));
-    keys_entry.pre_secret_key = Some(STANDARD.encode(enc_hospital_personnel_pre_secret_key));
     keys_entry.pre_public_key =
         Some(serde_serialize_to_base64(&hospital_personnel_pre_public_key).context(current_fn!())?);
-    keys_entry.pre_nonce = Some(STANDARD.encode(hospital_personnel_pre_secret_key_nonce));
-    keys_entry.iota_nonce = Some(STANDARD.encode(hospital_personnel_iota_keypair_nonce));
-    let keys_entry = serde_json::to_vec(&keys_entry).context(current_fn!())?;
     state
         .keys_entry
-        .set_secret(&keys_entry)
+        .set_secret(&serde_json::to_vec(&keys_entry).context(current_fn!())?)
         .context(current_fn!())?;
+    save_pin_attempts(&state.pin_attempts_entry, &PinAttempts::default()).context(current_fn!())?;
+
+    state
+        .auth_state
+        .session
+        .unlock(pin, keys_entry.settings.session_idle_timeout);
 
     state.administrative_data = Some(AdministrativeData {
         private: private_administrative_data,
@@ -7,10 +7,21 @@ use keyring::Entry;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::move_call::MoveCall;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+use crate::{constants::SESSION_IDLE_TIMEOUT, move_call::MoveCall, session::Session};
 
 // Enum
 
+/// `Expired` is local, the proxy forgets requests once they expire
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum AccessRequestStatus {
+    Approved,
+    Expired,
+    Pending,
+    Rejected,
+}
+
 #[derive(Clone, Copy, Debug, Deserialize, Serialize)]
 pub enum HospitalPersonnelRole {
     Admin,
@@ -36,12 +47,32 @@ pub enum MoveHospitalPersonnelAccessDataType {
     Medical,
 }
 
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub enum QrImageFormat {
+    Png,
+    Svg,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub enum ResponseStatus {
     Error,
     Success,
 }
 
+/// FHIR R4 resources `import_fhir_bundle` maps to medical records, others are skipped
+#[derive(Debug, Deserialize)]
+#[serde(tag = "resourceType")]
+pub enum FhirResource {
+    CarePlan(FhirCarePlan),
+    Condition(FhirCondition),
+    Encounter(FhirEncounter),
+    MedicationRequest(FhirMedicationRequest),
+    Observation(FhirObservation),
+    Patient(FhirPatient),
+    #[serde(other)]
+    Other,
+}
+
 // Struct
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -53,6 +84,8 @@ pub struct AccessData {
     pub exp: u64,
     #[serde(rename = "medicalMetadataIndex")]
     pub medical_metadata_index: Option<u64>,
+    #[serde(rename = "medicalMetadataScope")]
+    pub medical_metadata_scope: Option<Vec<u64>>,
     #[serde(rename = "patientIotaAddress")]
     pub patient_iota_address: String,
     #[serde(rename = "patientName")]
@@ -64,6 +97,10 @@ pub struct AccessData {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct AccessMetadata {
     pub access_token: String,
+    /// Set on break-glass accesses, encrypted for the personnel by the patient app since the
+    /// proxy can't read the name, `patient_name` is empty then
+    #[serde(default)]
+    pub enc_patient_name: Option<AccessMetadataEncrypted>,
     pub patient_iota_address: String,
     pub patient_name: String,
     pub patient_pre_public_key: Option<String>,
@@ -104,6 +141,8 @@ pub struct AppState {
     pub auth_state: AuthState,
     pub keys_entry: Entry,
     pub move_call: MoveCall,
+    /// `PinAttempts` of the keys entry
+    pub pin_attempts_entry: Entry,
     pub signin_state: SignInState,
     pub signup_state: SignUpState,
 }
@@ -112,7 +151,57 @@ pub struct AppState {
 pub struct AuthState {
     pub is_signed_up: bool,
     pub role: Option<HospitalPersonnelRole>,
-    pub session_pin: Option<String>,
+    #[serde(skip)]
+    pub session: Session,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CommandAccessRequestEntry {
+    /// Unix timestamp in seconds
+    #[serde(rename = "createdAt")]
+    pub created_at: u64,
+    /// Unix timestamp in seconds
+    pub exp: u64,
+    pub id: String,
+    #[serde(rename = "patientIotaAddress")]
+    pub patient_iota_address: String,
+    pub reason: String,
+    pub scope: Option<HospitalPersonnelQrScope>,
+    pub status: AccessRequestStatus,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CommandGenerateAccessQrResponseData {
+    /// Pasteable as is when no camera is available
+    pub content: String,
+    /// `decmed://grant` deep link carrying `content`
+    #[serde(rename = "deepLink")]
+    pub deep_link: String,
+    /// Unix timestamp in seconds
+    #[serde(rename = "expiresAt")]
+    pub expires_at: u64,
+    /// Base64 encoded
+    pub image: String,
+    #[serde(rename = "mimeType")]
+    pub mime_type: String,
+    /// Unix timestamp in seconds, a new QR should be generated by then
+    #[serde(rename = "rotateAt")]
+    pub rotate_at: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CommandGenerateAccessQrScopeArgs {
+    #[serde(rename = "expDurRead")]
+    pub exp_dur_read: u64,
+    #[serde(rename = "expDurUpdate")]
+    pub exp_dur_update: Option<u64>,
+    #[serde(rename = "isMedicalRead")]
+    pub is_medical_read: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CommandGetAccessRequestsResponseData {
+    pub entries: Vec<CommandAccessRequestEntry>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -136,6 +225,14 @@ pub struct CommandGetProfileResponseData {
     pub role: HospitalPersonnelRole,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CommandGetSettingsResponseData {
+    #[serde(rename = "pinWipeAfterFailedAttempts")]
+    pub pin_wipe_after_failed_attempts: Option<u32>,
+    #[serde(rename = "sessionIdleTimeout")]
+    pub session_idle_timeout: u64,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct CommandGlobalAdminAddActivationKeyResponse {
     #[serde(rename = "activationKey")]
@@ -150,7 +247,24 @@ pub struct CommandHospitalAdminAddActivationKeyResponse {
     pub id: String,
 }
 
+/// Medical record mapped from the resources of one FHIR `Encounter`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CommandFhirBundleRecord {
+    /// Start of the encounter
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<String>,
+    pub data: CommandNewMedicalRecordPayload,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
+pub struct CommandImportFhirBundleResponseData {
+    /// DecMed patient id the bundle was exported for, `None` on bundles from outside DecMed
+    #[serde(rename = "patientId")]
+    pub patient_id: Option<String>,
+    pub records: Vec<CommandFhirBundleRecord>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct CommandNewMedicalRecordPayload {
     pub anamnesis: String,
     #[serde(rename = "physicalCheck")]
@@ -161,6 +275,19 @@ pub struct CommandNewMedicalRecordPayload {
     pub therapy: String,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CommandRequestEmergencyAccessResponseData {
+    /// Unix timestamp in seconds
+    pub exp: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CommandSubmitAccessRequestResponseData {
+    /// Unix timestamp in seconds
+    pub exp: u64,
+    pub id: String,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct CommandUpdateMedicalRecordPayload {
     pub anamnesis: String,
@@ -183,6 +310,100 @@ pub struct ExecuteTxResponse {
     pub error: Option<String>,
 }
 
+/// Only the elements `import_fhir_bundle` reads, bundles from other systems carry more
+#[derive(Debug, Deserialize)]
+pub struct FhirBundle {
+    #[serde(default)]
+    pub entry: Vec<FhirBundleEntry>,
+    #[serde(rename = "resourceType")]
+    pub resource_type: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FhirBundleEntry {
+    #[serde(rename = "fullUrl")]
+    pub full_url: Option<String>,
+    pub resource: Option<FhirResource>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FhirCarePlan {
+    pub description: Option<String>,
+    pub encounter: Option<FhirReference>,
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FhirCodeableConcept {
+    #[serde(default)]
+    pub coding: Vec<FhirCoding>,
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FhirCoding {
+    pub code: Option<String>,
+    pub display: Option<String>,
+    pub system: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FhirCondition {
+    pub code: Option<FhirCodeableConcept>,
+    pub encounter: Option<FhirReference>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FhirDosage {
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FhirEncounter {
+    pub id: Option<String>,
+    pub period: Option<FhirPeriod>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FhirIdentifier {
+    pub system: Option<String>,
+    pub value: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FhirMedicationRequest {
+    #[serde(default, rename = "dosageInstruction")]
+    pub dosage_instruction: Vec<FhirDosage>,
+    pub encounter: Option<FhirReference>,
+    #[serde(rename = "medicationCodeableConcept")]
+    pub medication_codeable_concept: Option<FhirCodeableConcept>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FhirObservation {
+    pub code: Option<FhirCodeableConcept>,
+    pub encounter: Option<FhirReference>,
+    #[serde(rename = "valueString")]
+    pub value_string: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FhirPatient {
+    #[serde(default)]
+    pub identifier: Vec<FhirIdentifier>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FhirPeriod {
+    pub start: Option<String>,
+}
+
+/// A bundle `fullUrl` or `{resourceType}/{id}`
+#[derive(Debug, Deserialize)]
+pub struct FhirReference {
+    pub reference: Option<String>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct HospitalPersonnelMetadata {
     pub activation_key: String,
@@ -190,7 +411,47 @@ pub struct HospitalPersonnelMetadata {
     pub role: HospitalPersonnelRole,
 }
 
+/// Signed by the hospital personnel IOTA key
 #[derive(Debug, Deserialize, Serialize)]
+pub struct HospitalPersonnelQrPayload {
+    /// Unix timestamp in seconds
+    pub exp: u64,
+    /// Unix timestamp in seconds
+    pub iat: u64,
+    pub iota_address: String,
+    /// Set on access requests, which only that patient can approve
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub patient_iota_address: Option<String>,
+    /// Base64 encoded
+    pub pre_public_key: String,
+    /// Set on access requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<HospitalPersonnelQrScope>,
+}
+
+/// Access the personnel asks for, the patient can still change it before granting
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HospitalPersonnelQrScope {
+    /// Minutes
+    pub exp_dur_read: u64,
+    /// Minutes, `None` asks for no update access
+    pub exp_dur_update: Option<u64>,
+    pub is_medical_read: bool,
+}
+
+impl From<CommandGenerateAccessQrScopeArgs> for HospitalPersonnelQrScope {
+    fn from(scope: CommandGenerateAccessQrScopeArgs) -> Self {
+        Self {
+            exp_dur_read: scope.exp_dur_read,
+            exp_dur_update: scope.exp_dur_update,
+            is_medical_read: scope.is_medical_read,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Zeroize, ZeroizeOnDrop)]
 pub struct KeyNonce {
     pub key: String,
     pub nonce: String,
@@ -198,6 +459,9 @@ pub struct KeyNonce {
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct KeysEntry {
+    /// Access requests submitted to the proxy, polled for the patient's answer
+    #[serde(default)]
+    pub access_requests: Vec<SubmittedAccessRequest>,
     pub activation_key: Option<String>,
     pub admin_address: Option<String>,
     pub admin_secret_key: Option<String>,
@@ -205,9 +469,15 @@ pub struct KeysEntry {
     pub iota_address: Option<String>,
     pub iota_key_pair: Option<String>,
     pub iota_nonce: Option<String>,
+    pub pin_kdf: Option<PinKdf>,
     pub pre_nonce: Option<String>,
     pub pre_public_key: Option<String>,
     pub pre_secret_key: Option<String>,
+    #[serde(default)]
+    pub settings: Settings,
+    /// See `KEYS_ENTRY_VAULT_VERSION`, entries written before versioning are `0`
+    #[serde(default)]
+    pub vault_version: u8,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -222,8 +492,8 @@ pub struct MedicalData {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct MedicalMetadata {
     pub capsule: String,
-    pub enc_data: String,
     pub enc_key_and_nonce: String,
+    pub locator: String,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -237,6 +507,7 @@ pub struct MoveHospitalPersonnelAccessData {
     pub exp: u64,
     pub metadata: String,
     pub medical_metadata_index: Option<u64>,
+    pub medical_metadata_scope: Option<Vec<u64>>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -269,6 +540,26 @@ pub struct PatientPrivateAdministrativeData {
     pub marital_status: Option<String>,
 }
 
+/// Failed PIN attempts on this device. Kept on its own keyring entry, so restoring an older copy
+/// of the `KeysEntry` doesn't reset them.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct PinAttempts {
+    pub failed_attempts: u32,
+    /// Unix timestamp in seconds
+    pub locked_until: Option<u64>,
+}
+
+/// Argon2id parameters the PIN key of a `KeysEntry` is derived with
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PinKdf {
+    /// Memory cost in KiB
+    pub m_cost: u32,
+    pub p_cost: u32,
+    /// Random per install, regenerated each time the keys are sealed
+    pub salt: String,
+    pub t_cost: u32,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct PrivateAdministrativeData {
     pub id: String,
@@ -281,12 +572,33 @@ pub struct PrivateAdministrativeMetadata {
     pub enc_key_nonce: String,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProxyReencryptionCreateAccessRequestResponseData {
+    /// Unix timestamp in seconds
+    pub exp: u64,
+    pub id: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProxyReencryptionCreateEmergencyAccessResponseData {
+    pub access_token_read: String,
+    /// Unix timestamp in seconds
+    pub exp: u64,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ProxyReencryptionErrorResponse {
     pub error: String,
     pub status_code: u16,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProxyReencryptionGetAccessRequestStatusResponseData {
+    /// Unix timestamp in seconds
+    pub exp: u64,
+    pub status: AccessRequestStatus,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ProxyReencryptionGetPatientAdministrativeDataResponseData {
     pub c_frag: String,
@@ -311,10 +623,13 @@ pub struct ProxyReencryptionGetMedicalRecordResponseData {
     pub enc_administrative_data: String,
     pub enc_administrative_data_key_nonce: String,
     pub enc_data_pre_secret_key_seed: String,
-    pub enc_medical_data: String,
     pub enc_medical_data_key_nonce: String,
     pub medical_data_capsule: String,
     pub medical_data_created_at: String,
+    pub medical_data_created_by: Option<String>,
+    pub medical_data_sha256: Option<String>,
+    pub medical_data_version: u64,
+    pub medical_data_versions: Vec<ProxyReencryptionMedicalRecordVersion>,
     pub next_index: Option<u64>,
     pub patient_pre_public_key: String,
     pub prev_index: Option<u64>,
@@ -331,20 +646,38 @@ pub struct ProxyReencryptionGetMedicalRecordUpdateResponseData {
     pub enc_administrative_data: String,
     pub enc_administrative_data_key_nonce: String,
     pub enc_data_pre_secret_key_seed: String,
-    pub enc_medical_data: String,
     pub enc_medical_data_key_nonce: String,
     pub medical_data_capsule: String,
     pub medical_data_created_at: String,
+    pub medical_data_sha256: Option<String>,
     pub patient_pre_public_key: String,
     pub signer_pre_public_key: String,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProxyReencryptionGetMedicalRecordVersionsResponseData {
+    pub current_index: u64,
+    pub versions: Vec<ProxyReencryptionMedicalRecordVersion>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProxyReencryptionMedicalRecordVersion {
+    pub created_at: String,
+    pub created_by: Option<String>,
+    pub version: u64,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ProxyReencryptionSuccessResponse<T> {
     pub data: T,
     pub status_code: u16,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProxyReencryptionUploadMedicalRecordContentResponseData {
+    pub locator: String,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct PublicAdministrativeData {
     pub name: Option<String>,
@@ -363,13 +696,45 @@ pub struct ReserveGasResult {
     pub sponsor_address: IotaAddress,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Settings {
+    /// Key material is wiped from this device after this many failed PIN attempts, `None` disables
+    /// it. The account can be restored from its seed words.
+    #[serde(default)]
+    pub pin_wipe_after_failed_attempts: Option<u32>,
+    /// Seconds without activity before the session locks
+    pub session_idle_timeout: u64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            pin_wipe_after_failed_attempts: None,
+            session_idle_timeout: SESSION_IDLE_TIMEOUT,
+        }
+    }
+}
+
 pub struct SignInState {
-    pub pin: Option<String>,
+    pub pin: Option<Zeroizing<String>>,
 }
 
 pub struct SignUpState {
-    pub pin: Option<String>,
-    pub seed_words: Option<String>,
+    pub pin: Option<Zeroizing<String>>,
+    pub seed_words: Option<Zeroizing<String>>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SubmittedAccessRequest {
+    /// Unix timestamp in seconds
+    pub created_at: u64,
+    /// Unix timestamp in seconds
+    pub exp: u64,
+    /// SHA-256 of the signed request code, assigned by the proxy
+    pub id: String,
+    pub patient_iota_address: String,
+    pub reason: String,
+    pub scope: Option<HospitalPersonnelQrScope>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
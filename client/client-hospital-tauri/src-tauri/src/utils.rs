@@ -1,22 +1,34 @@
 use std::{
     fmt::{Debug, Display},
+    io::{Cursor, Read, Write},
     str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
 };
 
-use aes_gcm::{aead::Aead, AeadCore, Aes256Gcm, KeyInit, Nonce};
+use aes_gcm::{
+    aead::{
+        generic_array::GenericArray,
+        stream::{DecryptorBE32, EncryptorBE32},
+        Aead,
+    },
+    AeadCore, Aes256Gcm, KeyInit, Nonce,
+};
 use anyhow::{anyhow, Context};
 use argon2::{
     password_hash::{PasswordHasher, SaltString},
     Algorithm, Argon2, Params, PasswordHash, PasswordVerifier, Version,
 };
 use bip39::Mnemonic;
+use image::{ImageFormat, Luma};
 use iota_json_rpc_types::{
     DevInspectResults, IotaObjectDataOptions, IotaTransactionBlockEffectsAPI,
 };
 use iota_keys::key_derive::derive_key_pair_from_path;
 use iota_sdk::{IotaClient, IotaClientBuilder};
 use iota_types::base_types::{IotaAddress, ObjectID, ObjectRef};
-use iota_types::crypto::{EmptySignInfo, IotaKeyPair, SignatureScheme};
+use iota_types::crypto::{
+    EmptySignInfo, EncodeDecodeBase64, IotaKeyPair, Signature, SignatureScheme,
+};
 use iota_types::message_envelope::Envelope;
 use iota_types::programmable_transaction_builder::ProgrammableTransactionBuilder;
 use iota_types::transaction::{
@@ -24,21 +36,36 @@ use iota_types::transaction::{
     TransactionDataAPI,
 };
 use iota_types::{Identifier, TypeTag};
+use keyring::Entry;
+use qrcode::{render::svg, QrCode};
 use rand::Rng;
 use regex::Regex;
 use serde::{de::DeserializeOwned, Serialize};
 use serde_json::json;
 use sha2::{Digest, Sha256};
+use shared_crypto::intent::{Intent, IntentMessage};
 use tauri::http::StatusCode;
 use tauri_plugin_http::reqwest::{self, Client, IntoUrl};
 use umbral_pre::{PublicKey, SecretKey, SecretKeyFactory};
-
-use crate::constants::{GAS_STATION_BASE_URL, HASH_SALT};
+use zeroize::Zeroizing;
+
+use crate::constants::{
+    ACCESS_DUR_MAX, ACCESS_DUR_MIN, ACCESS_HANDOFF_URI, ACCESS_HANDOFF_URI_CODE_PARAM,
+    AES_STREAM_CHUNK_SIZE, AES_STREAM_HEADER_LEN, AES_STREAM_MAGIC, AES_STREAM_VERSION,
+    GAS_STATION_BASE_URL, HASH_SALT, HOSPITAL_PERSONNEL_QR_IMAGE_SIZE_MIN,
+    HOSPITAL_PERSONNEL_QR_PREFIX, HOSPITAL_PERSONNEL_QR_VERSION, KEYS_ENTRY_VAULT_VERSION,
+    PIN_FREE_ATTEMPTS, PIN_KDF_M_COST, PIN_KDF_P_COST, PIN_KDF_SALT_LEN, PIN_KDF_T_COST,
+    PIN_LOCKOUT_BASE_DUR, PIN_LOCKOUT_MAX_DUR, PROXY_BASE_URL,
+};
 use crate::{
-    constants::{IOTA_URL, _IPFS_GATEWAY_BASE_URL},
+    constants::{_IPFS_GATEWAY_BASE_URL, IOTA_URL},
     current_fn,
     hospital_error::HospitalError,
-    types::{ExecuteTxResponse, KeysEntry, ReserveGasResponse},
+    types::{
+        ExecuteTxResponse, HospitalPersonnelQrPayload, HospitalPersonnelQrScope, KeysEntry,
+        PinAttempts, PinKdf, ProxyReencryptionErrorResponse, ProxyReencryptionSuccessResponse,
+        QrImageFormat, ReserveGasResponse,
+    },
 };
 use base64::{engine::general_purpose::STANDARD, Engine as _};
 
@@ -107,10 +134,10 @@ pub fn parse_keys_entry(keys_entry: &Vec<u8>) -> Result<KeysEntry, HospitalError
     Ok(serde_json::from_slice(keys_entry).context(current_fn!())?)
 }
 
-pub fn generate_64_bytes_seed() -> [u8; 64] {
+pub fn generate_64_bytes_seed() -> Zeroizing<[u8; 64]> {
     let mut rng = rand::rng();
-    let mut random_seed = [0u8; 64];
-    rng.fill(&mut random_seed);
+    let mut random_seed = Zeroizing::new([0u8; 64]);
+    rng.fill(&mut *random_seed);
 
     random_seed
 }
@@ -261,7 +288,7 @@ pub fn _argon_verify(hash: String, password: String) -> Result<bool, HospitalErr
 * nonce: 12 bytes
 * return: (ciphertext, key, nonce)
 */
-pub fn aes_encrypt(data: &[u8]) -> Result<(Vec<u8>, Vec<u8>, Vec<u8>), HospitalError> {
+pub fn aes_encrypt(data: &[u8]) -> Result<(Vec<u8>, Zeroizing<Vec<u8>>, Vec<u8>), HospitalError> {
     let key = Aes256Gcm::generate_key(aes_gcm::aead::OsRng);
     let cipher = Aes256Gcm::new(&key);
     let nonce = Aes256Gcm::generate_nonce(&mut aes_gcm::aead::OsRng);
@@ -270,7 +297,7 @@ pub fn aes_encrypt(data: &[u8]) -> Result<(Vec<u8>, Vec<u8>, Vec<u8>), HospitalE
         .encrypt(&nonce, data)
         .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
 
-    Ok((ciphertext, key.to_vec(), nonce.to_vec()))
+    Ok((ciphertext, Zeroizing::new(key.to_vec()), nonce.to_vec()))
 }
 
 pub fn aes_encrypt_custom_key(
@@ -287,7 +314,11 @@ pub fn aes_encrypt_custom_key(
     Ok((ciphertext, nonce.to_vec()))
 }
 
-pub fn aes_decrypt(ciphertext: &[u8], key: &[u8], nonce: &[u8]) -> Result<Vec<u8>, HospitalError> {
+pub fn aes_decrypt(
+    ciphertext: &[u8],
+    key: &[u8],
+    nonce: &[u8],
+) -> Result<Zeroizing<Vec<u8>>, HospitalError> {
     let cipher = Aes256Gcm::new_from_slice(key).unwrap();
     let nonce = Nonce::from_slice(nonce);
 
@@ -295,7 +326,208 @@ pub fn aes_decrypt(ciphertext: &[u8], key: &[u8], nonce: &[u8]) -> Result<Vec<u8
         .decrypt(nonce, ciphertext)
         .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
 
-    Ok(original)
+    Ok(Zeroizing::new(original))
+}
+
+/**
+* Chunked AES-256-GCM (STREAM construction, 32-bit big endian counter).
+* layout: `magic | version | chunk_size | nonce_prefix | chunk_0 | ... | chunk_n`,
+* every chunk is `chunk_size + 16` bytes except the last one.
+* Nothing is encrypted until the returned encryptor is iterated, one chunk at a time.
+* return: `(key, nonce_prefix, encryptor)`
+*/
+pub fn aes_stream_encrypt<R>(reader: R) -> (Zeroizing<Vec<u8>>, Vec<u8>, AesStreamEncryptor<R>)
+where
+    R: Read,
+{
+    let key = Aes256Gcm::generate_key(aes_gcm::aead::OsRng);
+    let mut nonce_prefix = [0u8; 7];
+    rand::rng().fill(&mut nonce_prefix);
+
+    let encryptor = EncryptorBE32::from_aead(
+        Aes256Gcm::new(&key),
+        GenericArray::from_slice(&nonce_prefix),
+    );
+
+    let mut header = Vec::with_capacity(AES_STREAM_HEADER_LEN);
+    header.extend_from_slice(AES_STREAM_MAGIC);
+    header.push(AES_STREAM_VERSION);
+    header.extend_from_slice(&AES_STREAM_CHUNK_SIZE.to_be_bytes());
+    header.extend_from_slice(&nonce_prefix);
+
+    (
+        Zeroizing::new(key.to_vec()),
+        nonce_prefix.to_vec(),
+        AesStreamEncryptor {
+            chunk: None,
+            encryptor: Some(encryptor),
+            header: Some(header),
+            reader,
+        },
+    )
+}
+
+/**
+* Yields the header, then every encrypted chunk of `aes_stream_encrypt`. The next plaintext chunk
+* is read ahead, the last chunk is only known once the reader ends.
+*/
+pub struct AesStreamEncryptor<R> {
+    chunk: Option<Zeroizing<Vec<u8>>>,
+    /// `None` once the last chunk is encrypted
+    encryptor: Option<EncryptorBE32<Aes256Gcm>>,
+    header: Option<Vec<u8>>,
+    reader: R,
+}
+
+impl<R: Read> AesStreamEncryptor<R> {
+    fn read_chunk(&mut self) -> std::io::Result<Zeroizing<Vec<u8>>> {
+        let mut chunk = Zeroizing::new(Vec::with_capacity(AES_STREAM_CHUNK_SIZE as usize));
+        self.reader
+            .by_ref()
+            .take(AES_STREAM_CHUNK_SIZE as u64)
+            .read_to_end(&mut chunk)?;
+
+        Ok(chunk)
+    }
+
+    fn encrypt_chunk(&mut self) -> std::io::Result<Option<Vec<u8>>> {
+        if self.encryptor.is_none() {
+            return Ok(None);
+        }
+
+        let chunk = match self.chunk.take() {
+            Some(chunk) => chunk,
+            None => self.read_chunk()?,
+        };
+        let next_chunk = self.read_chunk()?;
+
+        let enc_chunk = if next_chunk.is_empty() {
+            self.encryptor
+                .take()
+                .map(|encryptor| encryptor.encrypt_last(chunk.as_slice()))
+        } else {
+            self.chunk = Some(next_chunk);
+            self.encryptor
+                .as_mut()
+                .map(|encryptor| encryptor.encrypt_next(chunk.as_slice()))
+        };
+
+        enc_chunk
+            .transpose()
+            .map_err(|e| std::io::Error::other(e.to_string()))
+    }
+}
+
+impl<R: Read> Iterator for AesStreamEncryptor<R> {
+    type Item = std::io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(header) = self.header.take() {
+            return Some(Ok(header));
+        }
+
+        self.encrypt_chunk().transpose()
+    }
+}
+
+/**
+* Decrypts the body of `res` while it is downloaded, only one encrypted chunk is buffered and
+* every decrypted chunk is written to `writer` right away.
+* Bodies without the stream header are the older single-shot base64 ciphertext,
+* those are decrypted with `nonce`.
+* When `sha256` is given, the received bytes are checked against it before decrypting the last chunk.
+* A truncated or tampered body is only detected at the end, discard `writer` on error.
+*/
+pub async fn aes_stream_decrypt_response<W>(
+    mut res: reqwest::Response,
+    key: &[u8],
+    nonce: &[u8],
+    sha256: Option<&str>,
+    mut writer: W,
+) -> Result<(), HospitalError>
+where
+    W: Write,
+{
+    let mut hasher = Sha256::new();
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut decryptor: Option<(DecryptorBE32<Aes256Gcm>, usize)> = None;
+    let mut is_stream = true;
+
+    while let Some(bytes) = res.chunk().await.context(current_fn!())? {
+        hasher.update(&bytes);
+        buffer.extend_from_slice(&bytes);
+
+        if is_stream && decryptor.is_none() {
+            if buffer.len() < AES_STREAM_HEADER_LEN {
+                continue;
+            }
+
+            if &buffer[0..4] != AES_STREAM_MAGIC {
+                is_stream = false;
+                continue;
+            }
+
+            if buffer[4] != AES_STREAM_VERSION {
+                return Err(HospitalError::Anyhow(
+                    anyhow!(format!("Unsupported stream version: {}", buffer[4]))
+                        .context(current_fn!()),
+                ));
+            }
+
+            let chunk_size: [u8; 4] = buffer[5..9].try_into().context(current_fn!())?;
+            let cipher = Aes256Gcm::new_from_slice(key)
+                .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
+
+            decryptor = Some((
+                DecryptorBE32::from_aead(
+                    cipher,
+                    GenericArray::from_slice(&buffer[9..AES_STREAM_HEADER_LEN]),
+                ),
+                u32::from_be_bytes(chunk_size) as usize + 16,
+            ));
+            buffer.drain(..AES_STREAM_HEADER_LEN);
+        }
+
+        // Keep the remainder, the last chunk is only known once the body ends
+        if let Some((decryptor, enc_chunk_size)) = decryptor.as_mut() {
+            while buffer.len() > *enc_chunk_size {
+                let enc_chunk: Vec<u8> = buffer.drain(..*enc_chunk_size).collect();
+                let chunk = decryptor
+                    .decrypt_next(enc_chunk.as_slice())
+                    .map(Zeroizing::new)
+                    .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
+                writer.write_all(&chunk).context(current_fn!())?;
+            }
+        }
+    }
+
+    if let Some(expected) = sha256 {
+        let actual = hex::encode(hasher.finalize());
+
+        if actual != expected {
+            return Err(HospitalError::IntegrityMismatch {
+                actual,
+                expected: expected.to_string(),
+            });
+        }
+    }
+
+    let data = match decryptor {
+        Some((decryptor, _)) => decryptor
+            .decrypt_last(buffer.as_slice())
+            .map(Zeroizing::new)
+            .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?,
+        None => aes_decrypt(
+            &STANDARD.decode(&buffer).context(current_fn!())?,
+            key,
+            nonce,
+        )
+        .context(current_fn!())?,
+    };
+    writer.write_all(&data).context(current_fn!())?;
+    writer.flush().context(current_fn!())?;
+
+    Ok(())
 }
 
 pub fn sha_hash(data: &[u8]) -> Vec<u8> {
@@ -387,23 +619,126 @@ pub fn handle_error_execute_tx(response: ExecuteTxResponse) -> Result<(), Hospit
     Ok(())
 }
 
-pub fn _decode_hospital_personnel_qr(
-    content: String,
-) -> Result<(IotaAddress, PublicKey), HospitalError> {
-    let content: Vec<&str> = content.split("@").collect();
+/**
+ * The patient app verifies the signature against the on-chain personnel account
+ * return: `{HOSPITAL_PERSONNEL_QR_PREFIX}:{version}:{base64 payload}:{base64 signature}`
+ */
+pub fn encode_hospital_personnel_qr(
+    iota_key_pair: &IotaKeyPair,
+    payload: &HospitalPersonnelQrPayload,
+) -> Result<String, HospitalError> {
+    let payload = STANDARD.encode(serde_json::to_vec(payload).context(current_fn!())?);
+
+    let signature = Signature::new_secure(
+        &IntentMessage::new(Intent::personal_message(), payload.clone()),
+        iota_key_pair,
+    );
+
+    Ok(format!(
+        "{}:{}:{}:{}",
+        HOSPITAL_PERSONNEL_QR_PREFIX,
+        HOSPITAL_PERSONNEL_QR_VERSION,
+        payload,
+        signature.encode_base64()
+    ))
+}
 
-    if content.len() != 2 {
-        return Err(HospitalError::Anyhow(
-            anyhow!("Invalid content length, expected 2 found {}", content.len())
-                .context(current_fn!()),
-        ));
+/**
+ * Only the duration bounds, the patient app applies the policy of the personnel role
+ */
+pub fn validate_access_scope(scope: &HospitalPersonnelQrScope) -> Result<(), HospitalError> {
+    if !(ACCESS_DUR_MIN..=ACCESS_DUR_MAX).contains(&scope.exp_dur_read)
+        || scope.exp_dur_update.is_some_and(|exp_dur_update| {
+            !(ACCESS_DUR_MIN..=ACCESS_DUR_MAX).contains(&exp_dur_update)
+        })
+    {
+        return Err(HospitalError::Anyhow(anyhow!(
+            "Invalid args: access duration must be between {} and {} minutes",
+            ACCESS_DUR_MIN,
+            ACCESS_DUR_MAX
+        )));
+    }
+
+    Ok(())
+}
+
+/**
+ * return: `decmed://grant?code={content}`
+ */
+pub fn encode_access_handoff_uri(content: &str) -> Result<String, HospitalError> {
+    Ok(reqwest::Url::parse_with_params(
+        ACCESS_HANDOFF_URI,
+        &[(ACCESS_HANDOFF_URI_CODE_PARAM, content)],
+    )
+    .context(current_fn!())?
+    .to_string())
+}
+
+/**
+ * return: encoded image and its MIME type
+ */
+pub fn render_qr(
+    content: &str,
+    format: QrImageFormat,
+) -> Result<(Vec<u8>, &'static str), HospitalError> {
+    let qr_code = QrCode::new(content.as_bytes()).context(current_fn!())?;
+
+    match format {
+        QrImageFormat::Png => {
+            let image = qr_code
+                .render::<Luma<u8>>()
+                .min_dimensions(
+                    HOSPITAL_PERSONNEL_QR_IMAGE_SIZE_MIN,
+                    HOSPITAL_PERSONNEL_QR_IMAGE_SIZE_MIN,
+                )
+                .build();
+            let mut png = Cursor::new(vec![]);
+            image
+                .write_to(&mut png, ImageFormat::Png)
+                .context(current_fn!())?;
+
+            Ok((png.into_inner(), "image/png"))
+        }
+        QrImageFormat::Svg => {
+            let svg = qr_code
+                .render::<svg::Color>()
+                .min_dimensions(
+                    HOSPITAL_PERSONNEL_QR_IMAGE_SIZE_MIN,
+                    HOSPITAL_PERSONNEL_QR_IMAGE_SIZE_MIN,
+                )
+                .build();
+
+            Ok((svg.into_bytes(), "image/svg+xml"))
+        }
     }
+}
+
+/**
+ * return: base64 encoded signature over a fresh proxy nonce, consumed by the next signed request
+ */
+pub async fn sign_proxy_nonce(
+    req_client: &Client,
+    iota_address: IotaAddress,
+    iota_key_pair: &IotaKeyPair,
+) -> Result<String, HospitalError> {
+    let nonce = do_http_post_request_json::<
+        _,
+        ProxyReencryptionSuccessResponse<String>,
+        ProxyReencryptionErrorResponse,
+    >(
+        None,
+        &format!("{}/nonce", PROXY_BASE_URL),
+        &json!({ "iota_address": iota_address.to_string() }),
+        req_client,
+        StatusCode::OK,
+    )
+    .await
+    .context(current_fn!())?
+    .data;
 
-    let iota_address = IotaAddress::from_str(content[0]).context(current_fn!())?;
-    let pre_public_key =
-        serde_deserialize_from_base64(content[1].to_string()).context(current_fn!())?;
+    let intent_message = IntentMessage::new(Intent::personal_message(), nonce);
 
-    Ok((iota_address, pre_public_key))
+    Ok(Signature::new_secure(&intent_message, iota_key_pair).encode_base64())
 }
 
 pub async fn do_http_post_request_json<P, T, E>(
@@ -439,6 +774,44 @@ where
     Ok(res_body)
 }
 
+/**
+* `payload` is sent as `application/octet-stream`, a streamed body is sent chunked.
+*/
+pub async fn do_http_post_request_bytes<T, E>(
+    access_token: Option<String>,
+    endpoint: &str,
+    payload: reqwest::Body,
+    req_client: &Client,
+    success_status_code: StatusCode,
+) -> Result<T, HospitalError>
+where
+    E: DeserializeOwned + Debug,
+    T: DeserializeOwned,
+{
+    let mut res = req_client
+        .post(endpoint)
+        .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+        .body(payload);
+    if access_token.is_some() {
+        res = res.bearer_auth(access_token.unwrap());
+    }
+    let res = res.send().await.context(current_fn!())?;
+
+    let res_status = res.status();
+    let res_body = res.bytes().await.context(current_fn!())?;
+
+    if res_status != success_status_code {
+        let error: E = serde_json::from_slice(&res_body.to_vec()).context(current_fn!())?;
+        return Err(HospitalError::Anyhow(
+            anyhow!(format!("{:#?}", error)).context(current_fn!()),
+        ));
+    }
+
+    let res_body: T = serde_json::from_slice(&res_body.to_vec()).context(current_fn!())?;
+
+    Ok(res_body)
+}
+
 pub async fn do_http_put_request_json<P, T, E>(
     access_token: Option<String>,
     endpoint: &str,
@@ -483,9 +856,12 @@ pub fn get_iota_address_from_keys_entry(
     Ok(IotaAddress::from_str(&iota_address).context(current_fn!())?)
 }
 
+/**
+* The decrypted key pair encoding is wiped once decoded, `IotaKeyPair` wipes itself on drop
+*/
 pub fn get_iota_key_pair_from_keys_entry(
     keys_entry: &KeysEntry,
-    pin: String,
+    pin: &str,
 ) -> Result<IotaKeyPair, HospitalError> {
     let iota_key_pair = STANDARD
         .decode(
@@ -503,10 +879,11 @@ pub fn get_iota_key_pair_from_keys_entry(
             .context(current_fn!())?;
     let iota_key_pair = aes_decrypt(
         &iota_key_pair,
-        &sha_hash(pin.as_bytes()),
+        &derive_pin_key(keys_entry, pin).context(current_fn!())?,
         &iota_key_pair_nonce,
     )?;
-    let iota_key_pair = String::from_utf8(iota_key_pair).context(current_fn!())?;
+    let iota_key_pair =
+        Zeroizing::new(String::from_utf8(iota_key_pair.to_vec()).context(current_fn!())?);
     let iota_key_pair = IotaKeyPair::decode(&iota_key_pair)
         .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
 
@@ -547,9 +924,12 @@ pub fn get_global_admin_iota_key_pair_from_keys_entry(
     )
 }
 
+/**
+* The decrypted PRE seed is wiped once the keys are derived, `SecretKey` wipes itself on drop
+*/
 pub fn get_pre_keys_from_keys_entry(
     keys_entry: &KeysEntry,
-    pin: String,
+    pin: &str,
 ) -> Result<(SecretKey, PublicKey), HospitalError> {
     let pre_seed = STANDARD
         .decode(
@@ -569,7 +949,11 @@ pub fn get_pre_keys_from_keys_entry(
         )
         .context(current_fn!())?;
 
-    let pre_seed = aes_decrypt(&pre_seed, &sha_hash(pin.as_bytes()), &pre_seed_nonce)?;
+    let pre_seed = aes_decrypt(
+        &pre_seed,
+        &derive_pin_key(keys_entry, pin).context(current_fn!())?,
+        &pre_seed_nonce,
+    )?;
 
     let pre_secret_key = SecretKeyFactory::from_secure_randomness(&pre_seed)
         .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?
@@ -579,6 +963,203 @@ pub fn get_pre_keys_from_keys_entry(
     Ok((pre_secret_key, pre_public_key))
 }
 
+/**
+ * Derives the key the IOTA key pair and PRE seed are encrypted with, according to the vault
+ * version of `keys_entry`
+ * return: 32 bytes key
+ */
+pub fn derive_pin_key(
+    keys_entry: &KeysEntry,
+    pin: &str,
+) -> Result<Zeroizing<Vec<u8>>, HospitalError> {
+    match keys_entry.vault_version {
+        0 => Ok(Zeroizing::new(sha_hash(pin.as_bytes()))),
+        1 => {
+            let pin_kdf = keys_entry
+                .pin_kdf
+                .as_ref()
+                .ok_or(anyhow!("PIN KDF not found on keys entry").context(current_fn!()))?;
+            let params = Params::new(pin_kdf.m_cost, pin_kdf.t_cost, pin_kdf.p_cost, Some(32))
+                .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
+            let salt = STANDARD.decode(&pin_kdf.salt).context(current_fn!())?;
+
+            let mut pin_key = Zeroizing::new(vec![0u8; 32]);
+            Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                .hash_password_into(pin.as_bytes(), &salt, &mut pin_key)
+                .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
+
+            Ok(pin_key)
+        }
+        vault_version => Err(
+            anyhow!("Unsupported keys entry vault version: {}", vault_version)
+                .context(current_fn!())
+                .into(),
+        ),
+    }
+}
+
+/**
+ * Encrypts the IOTA key pair and PRE seed with a key derived from `pin` and a fresh salt, the
+ * entry is upgraded to `KEYS_ENTRY_VAULT_VERSION`.
+ */
+pub fn seal_keys_entry(
+    keys_entry: &mut KeysEntry,
+    pin: &str,
+    iota_key_pair: &IotaKeyPair,
+    pre_seed: &[u8],
+) -> Result<(), HospitalError> {
+    let mut salt = [0u8; PIN_KDF_SALT_LEN];
+    rand::rng().fill(&mut salt);
+
+    keys_entry.pin_kdf = Some(PinKdf {
+        m_cost: PIN_KDF_M_COST,
+        p_cost: PIN_KDF_P_COST,
+        salt: STANDARD.encode(salt),
+        t_cost: PIN_KDF_T_COST,
+    });
+    keys_entry.vault_version = KEYS_ENTRY_VAULT_VERSION;
+
+    let pin_key = derive_pin_key(keys_entry, pin).context(current_fn!())?;
+    let (enc_pre_seed, pre_seed_nonce) =
+        aes_encrypt_custom_key(&pin_key, pre_seed).context(current_fn!())?;
+    let iota_key_pair = Zeroizing::new(
+        iota_key_pair
+            .encode()
+            .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?,
+    );
+    let (enc_iota_key_pair, iota_key_pair_nonce) =
+        aes_encrypt_custom_key(&pin_key, iota_key_pair.as_bytes()).context(current_fn!())?;
+
+    keys_entry.iota_key_pair = Some(STANDARD.encode(enc_iota_key_pair));
+    keys_entry.iota_nonce = Some(STANDARD.encode(iota_key_pair_nonce));
+    keys_entry.pre_nonce = Some(STANDARD.encode(pre_seed_nonce));
+    keys_entry.pre_secret_key = Some(STANDARD.encode(enc_pre_seed));
+
+    Ok(())
+}
+
+/**
+ * return: the `PinAttempts` on `entry`, none failed if it wasn't written yet
+ */
+pub fn load_pin_attempts(entry: &Entry) -> Result<PinAttempts, HospitalError> {
+    match entry.get_secret() {
+        Ok(pin_attempts) => Ok(serde_json::from_slice(&pin_attempts).context(current_fn!())?),
+        Err(keyring::Error::NoEntry) => Ok(PinAttempts::default()),
+        Err(err) => Err(anyhow!(err).context(current_fn!()).into()),
+    }
+}
+
+pub fn save_pin_attempts(entry: &Entry, pin_attempts: &PinAttempts) -> Result<(), HospitalError> {
+    entry
+        .set_secret(&serde_json::to_vec(pin_attempts).context(current_fn!())?)
+        .context(current_fn!())?;
+
+    Ok(())
+}
+
+/**
+ * Checks `pin` against the keys entry stored on `entry`, attempts are counted on
+ * `pin_attempts_entry`:
+ * - a failed attempt is counted, past `PIN_FREE_ATTEMPTS` the PIN is locked for an escalating
+ *   duration and the key material is wiped once `Settings.pin_wipe_after_failed_attempts` is
+ *   reached
+ * - a successful attempt clears the counter and migrates entries on an older vault version
+ * return: the unlocked keys entry
+ */
+pub fn unlock_keys_entry(
+    entry: &Entry,
+    pin_attempts_entry: &Entry,
+    pin: &str,
+) -> Result<KeysEntry, HospitalError> {
+    let mut keys_entry =
+        parse_keys_entry(&entry.get_secret().context(current_fn!())?).context(current_fn!())?;
+    let mut pin_attempts = load_pin_attempts(pin_attempts_entry).context(current_fn!())?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .context(current_fn!())?
+        .as_secs();
+
+    if let Some(locked_until) = pin_attempts.locked_until {
+        if locked_until > now {
+            return Err(anyhow!(
+                "Too many failed PIN attempts, try again in {} seconds",
+                locked_until - now
+            )
+            .into());
+        }
+    }
+
+    let enc_pre_seed = STANDARD
+        .decode(
+            keys_entry
+                .pre_secret_key
+                .as_ref()
+                .ok_or(anyhow!("PRE Seed not found on keys entry").context(current_fn!()))?,
+        )
+        .context(current_fn!())?;
+    let pre_seed_nonce = STANDARD
+        .decode(
+            keys_entry
+                .pre_nonce
+                .as_ref()
+                .ok_or(anyhow!("PRE Seed nonce not found on keys entry").context(current_fn!()))?,
+        )
+        .context(current_fn!())?;
+    let pin_key = derive_pin_key(&keys_entry, pin).context(current_fn!())?;
+
+    let Ok(pre_seed) = aes_decrypt(&enc_pre_seed, &pin_key, &pre_seed_nonce) else {
+        pin_attempts.failed_attempts += 1;
+
+        let wipe = keys_entry
+            .settings
+            .pin_wipe_after_failed_attempts
+            .is_some_and(|max_attempts| pin_attempts.failed_attempts >= max_attempts);
+
+        if wipe {
+            keys_entry.iota_key_pair = None;
+            keys_entry.iota_nonce = None;
+            keys_entry.pin_kdf = None;
+            keys_entry.pre_nonce = None;
+            keys_entry.pre_secret_key = None;
+            entry
+                .set_secret(&serde_json::to_vec(&keys_entry).context(current_fn!())?)
+                .context(current_fn!())?;
+
+            pin_attempts = PinAttempts::default();
+        } else if pin_attempts.failed_attempts > PIN_FREE_ATTEMPTS {
+            let exp = (pin_attempts.failed_attempts - PIN_FREE_ATTEMPTS - 1).min(16);
+            pin_attempts.locked_until =
+                Some(now + (PIN_LOCKOUT_BASE_DUR << exp).min(PIN_LOCKOUT_MAX_DUR));
+        }
+
+        save_pin_attempts(pin_attempts_entry, &pin_attempts).context(current_fn!())?;
+
+        if wipe {
+            return Err(
+                anyhow!("Too many failed PIN attempts, keys removed from this device").into(),
+            );
+        }
+
+        return Err(anyhow!("Invalid PIN").into());
+    };
+
+    if keys_entry.vault_version < KEYS_ENTRY_VAULT_VERSION {
+        let iota_key_pair =
+            get_iota_key_pair_from_keys_entry(&keys_entry, pin).context(current_fn!())?;
+        seal_keys_entry(&mut keys_entry, pin, &iota_key_pair, &pre_seed).context(current_fn!())?;
+
+        entry
+            .set_secret(&serde_json::to_vec(&keys_entry).context(current_fn!())?)
+            .context(current_fn!())?;
+    }
+
+    if pin_attempts.failed_attempts > 0 || pin_attempts.locked_until.is_some() {
+        save_pin_attempts(pin_attempts_entry, &PinAttempts::default()).context(current_fn!())?;
+    }
+
+    Ok(keys_entry)
+}
+
 pub fn serde_serialize_to_base64<T>(val: &T) -> Result<String, HospitalError>
 where
     T: Serialize,
@@ -600,9 +1181,9 @@ where
 pub fn compute_seed_from_seed_words(
     seed_words: &str,
     passphrase: &str,
-) -> Result<[u8; 64], HospitalError> {
+) -> Result<Zeroizing<[u8; 64]>, HospitalError> {
     let mnemonic = Mnemonic::from_str(seed_words).context(current_fn!())?;
-    Ok(mnemonic.to_seed_normalized(passphrase))
+    Ok(Zeroizing::new(mnemonic.to_seed_normalized(passphrase)))
 }
 
 pub async fn do_http_get_request_json<T, E, U>(
@@ -651,6 +1232,37 @@ where
     }
 }
 
+/**
+* return: response with the body not consumed yet
+*/
+pub async fn do_http_get_request_stream<E, U>(
+    access_token: Option<String>,
+    req_client: &Client,
+    success_status_code: StatusCode,
+    url: U,
+) -> Result<reqwest::Response, HospitalError>
+where
+    E: DeserializeOwned + Debug,
+    U: IntoUrl,
+{
+    let mut res = req_client.get(url);
+    if access_token.is_some() {
+        res = res.bearer_auth(access_token.unwrap());
+    }
+    let res = res.send().await.context(current_fn!())?;
+
+    if res.status() != success_status_code {
+        let res_body = res.bytes().await.context(current_fn!())?;
+        let error: E = serde_json::from_slice(&res_body.to_vec()).context(current_fn!())?;
+
+        return Err(HospitalError::Anyhow(
+            anyhow!(format!("{:#?}", error)).context(current_fn!()),
+        ));
+    }
+
+    Ok(res)
+}
+
 pub async fn _do_http_get_request_text<T, E, U>(
     access_token: Option<String>,
     req_client: &Client,
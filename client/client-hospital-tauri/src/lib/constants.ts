@@ -4,3 +4,8 @@ export const SIGNIN_TOTAL_STEP = 3;
 export const ADMIN_ROLE = 'Admin';
 export const MEDICAL_PERSONNEL_ROLE = 'MedicalPersonnel';
 export const ADMINISTRATIVE_PERSONNEL_ROLE = 'AdministrativePersonnel';
+export const ACCESS_DUR_MIN = 1;
+export const ACCESS_DUR_MAX = 24 * 60;
+export const ACCESS_REQUEST_REASON_LEN_MAX = 500;
+export const EMERGENCY_JUSTIFICATION_LEN_MIN = 20;
+export const EMERGENCY_JUSTIFICATION_LEN_MAX = 1000;
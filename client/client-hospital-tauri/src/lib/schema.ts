@@ -200,6 +200,7 @@ export const createMedicalRecordSchema = z
 	.extend(psychologicalCheckSchema)
 	.extend(diagnoseSchema)
 	.extend(therapySchema);
+export const enterPinSchema = z.object(pinSchema);
 export const updateMedicalRecordSchema = z
 	.object(anamnesisSchema)
 	.extend(physicalCheckSchema)
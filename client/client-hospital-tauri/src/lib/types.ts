@@ -35,6 +35,11 @@ export type HospitalPersonnel = {
 	role: Role;
 };
 
+export type SubmitAccessRequestData = {
+	exp: number;
+	id: string;
+};
+
 export type SuccessResponse<T> = {
 	status: string;
 	data: T;
@@ -45,6 +50,7 @@ export type TauriAccessData = {
 	accessToken: string;
 	exp: number;
 	medicalMetadataIndex: number | null;
+	medicalMetadataScope: number[] | null;
 	patientIotaAddress: string;
 	patientName: string;
 	patientPrePublicKey: string | null;
@@ -79,6 +85,41 @@ export type AdministrativeData = {
 	hospital?: string;
 };
 
+export type AccessRequestStatus = 'Approved' | 'Expired' | 'Pending' | 'Rejected';
+
+export type AccessRequestEntry = {
+	createdAt: number;
+	exp: number;
+	id: string;
+	patientIotaAddress: string;
+	reason: string;
+	scope: {
+		exp_dur_read: number;
+		exp_dur_update: number | null;
+		is_medical_read: boolean;
+	} | null;
+	status: AccessRequestStatus;
+};
+
+export type GenerateAccessQrData = {
+	content: string;
+	deepLink: string;
+	expiresAt: number;
+	image: string;
+	mimeType: string;
+	rotateAt: number;
+};
+
+export type GenerateAccessQrScope = {
+	expDurRead: number;
+	expDurUpdate: number | null;
+	isMedicalRead: boolean;
+};
+
+export type GetAccessRequestsData = {
+	entries: AccessRequestEntry[];
+};
+
 export type GetProfileData = {
 	hospital: string | null;
 	id: string;
@@ -93,16 +134,42 @@ export type GetProfileData = {
 export type InvokeGetMedicalRecordResponseData = {
 	administrativeData: TauriPatientPrivateAdministrativeData;
 	createdAt: string;
+	createdBy?: string | null;
 	medicalData: TauriMedicalData;
 	currentIndex: number;
 	nextIndex?: number | null;
 	prevIndex?: number | null;
+	version?: number;
+	versionCount?: number;
+};
+
+export type InvokeGetMedicalRecordVersionsResponseData = {
+	currentIndex: number;
+	versions: {
+		createdAt: string;
+		createdBy: string | null;
+		version: number;
+	}[];
 };
 
 export type InvokeGetPatientAdministrativeDataResponseData = {
 	administrativeData: TauriPatientPrivateAdministrativeData;
 };
 
+export type InvokeImportFhirBundleResponseData = {
+	patientId: string | null;
+	records: {
+		createdAt: string | null;
+		data: {
+			anamnesis: string;
+			diagnose: string;
+			physicalCheck: string;
+			psychologicalCheck: string;
+			therapy: string;
+		};
+	}[];
+};
+
 export type InvokeGlobalAdminAddActivationKeyData = {
 	activationKey: string;
 	id: string;
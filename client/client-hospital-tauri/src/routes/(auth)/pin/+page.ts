@@ -1,5 +1,8 @@
 import { redirect } from '@sveltejs/kit';
 import type { PageLoad } from './$types';
+import { superValidate } from 'sveltekit-superforms';
+import { enterPinSchema } from '$lib/schema';
+import { zod } from 'sveltekit-superforms/adapters';
 
 export const load: PageLoad = async ({ parent, url }) => {
 	const { redirect_to } = await parent();
@@ -7,4 +10,10 @@ export const load: PageLoad = async ({ parent, url }) => {
 	if (redirect_to != null && redirect_to != url.pathname) {
 		return redirect(301, redirect_to);
 	}
+
+	const enterPinForm = await superValidate(zod(enterPinSchema));
+
+	return {
+		enterPinForm
+	};
 };
@@ -49,6 +49,10 @@ export const load: LayoutLoad = async ({ url }) => {
 					defaultData.redirect_to = '/complete-profile';
 					break;
 				}
+				case 4: {
+					defaultData.redirect_to = '/pin';
+					break;
+				}
 				default: {
 					defaultData.redirect_to = '/activation';
 					break;
@@ -3,6 +3,7 @@ import { createMedicalRecordSchema } from '$lib/schema';
 import type {
 	CreateMedicalRecordSchema,
 	InvokeGetPatientAdministrativeDataResponseData,
+	InvokeImportFhirBundleResponseData,
 	SuccessResponse
 } from '$lib/types';
 import { tryCatchAsVal } from '$lib/utils';
@@ -22,6 +23,8 @@ export class EmrCreateState {
 	accessToken = $state<string>('');
 	patientIotaAddress = $state('');
 	patientPrePublicKey = $state<string>('');
+	// Records of an imported FHIR bundle, one of them prefills the form
+	fhirRecords = $state<InvokeImportFhirBundleResponseData['records']>([]);
 	createMedicalRecordFormMeta: SuperForm<Infer<CreateMedicalRecordSchema>>;
 
 	constructor({
@@ -87,6 +90,40 @@ export class EmrCreateState {
 		return resInvokeGetPatientAdministrativeData.data.data;
 	};
 
+	importFhirBundle = async (file: File) => {
+		const bundle = await file.text();
+		const resInvokeImportFhirBundle = await tryCatchAsVal(async () => {
+			return (await invoke('import_fhir_bundle', {
+				bundle
+			})) as SuccessResponse<InvokeImportFhirBundleResponseData>;
+		});
+
+		if (!resInvokeImportFhirBundle.success) {
+			toast.error(resInvokeImportFhirBundle.error);
+			return;
+		}
+
+		const { patientId, records } = resInvokeImportFhirBundle.data.data;
+		const administrativeData = await this.fetchPatientAdministrativeData.catch(() => null);
+		if (
+			patientId &&
+			administrativeData &&
+			patientId !== administrativeData.administrativeData.id
+		) {
+			toast.warning('The FHIR bundle was exported for another patient.');
+		}
+
+		this.fhirRecords = records;
+		if (records.length === 1) {
+			this.applyFhirRecord(records[0]);
+		}
+	};
+
+	applyFhirRecord = (record: InvokeImportFhirBundleResponseData['records'][number]) => {
+		this.createMedicalRecordFormMeta.form.update(($form) => ({ ...$form, ...record.data }));
+		toast.success('Form filled from the FHIR bundle, review it before creating the record.');
+	};
+
 	fetchPatientAdministrativeData = $derived(
 		this.getPatientAdministrativeData(this.accessToken, this.patientIotaAddress)
 	);
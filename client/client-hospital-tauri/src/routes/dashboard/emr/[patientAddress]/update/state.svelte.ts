@@ -84,6 +84,7 @@ export class EmrUpdateState {
 								psychologicalCheck: form.data.psychologicalCheck,
 								therapy: form.data.therapy
 							},
+							index,
 							patientIotaAddress,
 							patientPrePublicKey
 						})) as SuccessResponse<null>;
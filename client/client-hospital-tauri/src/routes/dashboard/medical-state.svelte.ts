@@ -6,6 +6,15 @@ import { toast } from 'svelte-sonner';
 export class MedicalHomeState {
 	tabs = ['read', 'update'];
 	currentTab = $state(this.tabs[0]);
+	// Bumped to reload the read access after an emergency access is granted
+	readAccessKey = $state(0);
+
+	isEmergencyAccessDialogOpen = $state(false);
+	isRequestingEmergencyAccess = $state(false);
+	emergencyAccessForm = $state({
+		justification: '',
+		patientIotaAddress: ''
+	});
 
 	constructor() {}
 
@@ -26,6 +35,44 @@ export class MedicalHomeState {
 		return resInvokeGetReadAccess.data.data;
 	};
 
+	// Patients build break-glass keys only for registered personnel, failing is not blocking
+	registerEmergencyRecipient = async () => {
+		const resInvokeRegisterEmergencyRecipient = await tryCatchAsVal(async () => {
+			return (await invoke('register_emergency_recipient')) as SuccessResponse<null>;
+		});
+
+		if (!resInvokeRegisterEmergencyRecipient.success) {
+			console.log(resInvokeRegisterEmergencyRecipient.error);
+		}
+	};
+
+	requestEmergencyAccess = async () => {
+		this.isRequestingEmergencyAccess = true;
+		const resInvokeRequestEmergencyAccess = await tryCatchAsVal(async () => {
+			return (await invoke('request_emergency_access', {
+				justification: this.emergencyAccessForm.justification,
+				patientIotaAddress: this.emergencyAccessForm.patientIotaAddress
+			})) as SuccessResponse<{ exp: number }>;
+		});
+		this.isRequestingEmergencyAccess = false;
+
+		if (!resInvokeRequestEmergencyAccess.success) {
+			toast.error(resInvokeRequestEmergencyAccess.error);
+			return;
+		}
+
+		toast.success(
+			`Emergency access granted until ${new Date(
+				resInvokeRequestEmergencyAccess.data.data.exp * 1000
+			).toLocaleString()}`
+		);
+		this.emergencyAccessForm.justification = '';
+		this.emergencyAccessForm.patientIotaAddress = '';
+		this.isEmergencyAccessDialogOpen = false;
+		this.currentTab = this.tabs[0];
+		this.readAccessKey++;
+	};
+
 	get_update_access = async () => {
 		const resInvokeGetUpdateAccess = await tryCatchAsVal(async () => {
 			return (await invoke('get_update_access_medical_personnel')) as SuccessResponse<
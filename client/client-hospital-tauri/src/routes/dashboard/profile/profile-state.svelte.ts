@@ -1,17 +1,40 @@
-import type { GetProfileData, SuccessResponse } from '$lib/types';
+import type {
+	AccessRequestEntry,
+	GenerateAccessQrData,
+	GenerateAccessQrScope,
+	GetAccessRequestsData,
+	GetProfileData,
+	SubmitAccessRequestData,
+	SuccessResponse
+} from '$lib/types';
 import { tryCatchAsVal } from '$lib/utils';
 import { invoke } from '@tauri-apps/api/core';
 import { toast } from 'svelte-sonner';
 import { getAuthContext } from '../../(context)/auth-context.svelte';
 import { invalidateAll } from '$app/navigation';
 import { BaseDirectory, create, exists, mkdir } from '@tauri-apps/plugin-fs';
-import QRCode from 'qrcode';
 
 export class ProfileState {
 	authContext = getAuthContext();
 	profile = $state<GetProfileData>();
-	qr = $state('');
+	qr = $state<GenerateAccessQrData>();
+	qrScope = $state({
+		isRequested: false,
+		expDurRead: 60,
+		expDurUpdate: 0,
+		isMedicalRead: false
+	});
 	qrFileName = $derived(`qr-${this.profile?.id ?? 'unknown'}`);
+	qrRefreshTimeout: ReturnType<typeof setTimeout> | undefined;
+	accessRequests = $state<AccessRequestEntry[]>([]);
+	accessRequestForm = $state({
+		patientIotaAddress: '',
+		reason: '',
+		expDurRead: 60,
+		expDurUpdate: 0,
+		isMedicalRead: false
+	});
+	accessRequestPollInterval: ReturnType<typeof setInterval> | undefined;
 
 	signout = async () => {
 		const resInvokeSignout = await tryCatchAsVal(async () => {
@@ -39,20 +62,114 @@ export class ProfileState {
 
 		this.profile = resInvokeGetProfile.data.data;
 
-		QRCode.toString(
-			`${this.profile.iotaAddress ?? ''}@${this.profile.prePublicKey ?? ''}`,
-			{
-				type: 'svg'
-			},
-			(err, string) => {
-				if (err) throw err;
-				this.qr = string;
-			}
-		);
+		if (this.profile.role !== 'Admin') {
+			await this.refreshQr();
+			await this.getAccessRequests();
+			this.accessRequestPollInterval = setInterval(this.getAccessRequests, 30_000);
+		}
 
 		return resInvokeGetProfile.data.data;
 	};
 
+	getQrScope = (): GenerateAccessQrScope | null => {
+		if (!this.qrScope.isRequested) {
+			return null;
+		}
+
+		const isMedicalPersonnel = this.profile?.role === 'MedicalPersonnel';
+
+		return {
+			expDurRead: this.qrScope.expDurRead,
+			expDurUpdate:
+				isMedicalPersonnel && this.qrScope.expDurUpdate > 0 ? this.qrScope.expDurUpdate : null,
+			isMedicalRead: isMedicalPersonnel && this.qrScope.isMedicalRead
+		};
+	};
+
+	generateQr = async (format: 'Png' | 'Svg') => {
+		const resInvokeGenerateAccessQr = await tryCatchAsVal(async () => {
+			return (await invoke('generate_access_qr', {
+				format,
+				scope: this.getQrScope()
+			})) as SuccessResponse<GenerateAccessQrData>;
+		});
+
+		if (!resInvokeGenerateAccessQr.success) {
+			toast.error(resInvokeGenerateAccessQr.error);
+			return;
+		}
+
+		return resInvokeGenerateAccessQr.data.data;
+	};
+
+	// The signed QR expires, a new one is generated at its rotation time
+	refreshQr = async () => {
+		clearTimeout(this.qrRefreshTimeout);
+
+		const qr = await this.generateQr('Svg');
+
+		if (!qr) {
+			return;
+		}
+
+		this.qr = qr;
+		this.qrRefreshTimeout = setTimeout(
+			this.refreshQr,
+			Math.max(qr.rotateAt * 1000 - Date.now(), 10_000)
+		);
+	};
+
+	stopQrRefresh = () => {
+		clearTimeout(this.qrRefreshTimeout);
+		clearInterval(this.accessRequestPollInterval);
+	};
+
+	getAccessRequests = async () => {
+		const resInvokeGetAccessRequests = await tryCatchAsVal(async () => {
+			return (await invoke('get_access_requests')) as SuccessResponse<GetAccessRequestsData>;
+		});
+
+		if (!resInvokeGetAccessRequests.success) {
+			toast.error(resInvokeGetAccessRequests.error);
+			return;
+		}
+
+		this.accessRequests = resInvokeGetAccessRequests.data.data.entries;
+	};
+
+	submitAccessRequest = async () => {
+		const isMedicalPersonnel = this.profile?.role === 'MedicalPersonnel';
+
+		const resInvokeSubmitAccessRequest = await tryCatchAsVal(async () => {
+			return (await invoke('submit_access_request', {
+				patientIotaAddress: this.accessRequestForm.patientIotaAddress,
+				reason: this.accessRequestForm.reason,
+				scope: {
+					expDurRead: this.accessRequestForm.expDurRead,
+					expDurUpdate:
+						isMedicalPersonnel && this.accessRequestForm.expDurUpdate > 0
+							? this.accessRequestForm.expDurUpdate
+							: null,
+					isMedicalRead: isMedicalPersonnel && this.accessRequestForm.isMedicalRead
+				}
+			})) as SuccessResponse<SubmitAccessRequestData>;
+		});
+
+		if (!resInvokeSubmitAccessRequest.success) {
+			toast.error(resInvokeSubmitAccessRequest.error);
+			return;
+		}
+
+		toast.success(
+			`Access request sent, valid until ${new Date(
+				resInvokeSubmitAccessRequest.data.data.exp * 1000
+			).toLocaleString()}`
+		);
+		this.accessRequestForm.patientIotaAddress = '';
+		this.accessRequestForm.reason = '';
+		await this.getAccessRequests();
+	};
+
 	downloadQr = async () => {
 		if (
 			!(await exists('decmed-hospital/', {
@@ -64,15 +181,13 @@ export class ProfileState {
 			});
 		}
 
-		const res = await QRCode.toDataURL(
-			`${this.profile?.iotaAddress ?? ''}@${this.profile?.prePublicKey ?? ''}`,
-			{
-				type: 'image/png'
-			}
-		);
-		const response = await fetch(res);
-		const arrayBuffer = await response.arrayBuffer();
-		const buff = new Uint8Array(arrayBuffer);
+		const qr = await this.generateQr('Png');
+
+		if (!qr) {
+			return;
+		}
+
+		const buff = Uint8Array.from(atob(qr.image), (c) => c.charCodeAt(0));
 
 		const file = await create(`decmed-hospital/${this.qrFileName}.png`, {
 			baseDir: BaseDirectory.Home
@@ -80,6 +195,10 @@ export class ProfileState {
 		await file.write(buff);
 		await file.close();
 
-		toast.success(`QR Code downloaded to ~/decmed-hospital/${this.qrFileName}.png`);
+		toast.success(
+			`QR Code downloaded to ~/decmed-hospital/${this.qrFileName}.png, valid until ${new Date(
+				qr.expiresAt * 1000
+			).toLocaleTimeString()}`
+		);
 	};
 }
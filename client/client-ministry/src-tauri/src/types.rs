@@ -6,6 +6,7 @@ use iota_types::{
 use keyring::Entry;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
 use crate::move_call::MoveCall;
 
@@ -86,13 +87,13 @@ pub struct HospitalAdminMetadataEncrypted {
     pub enc_metadata: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Zeroize, ZeroizeOnDrop)]
 pub struct KeyNonce {
     pub key: String,
     pub nonce: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Zeroize, ZeroizeOnDrop)]
 pub struct KeysEntry {
     pub admin_address: Option<String>,
     pub admin_secret_key: Option<String>,
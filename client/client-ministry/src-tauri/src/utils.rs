@@ -28,6 +28,7 @@ use sha2::{Digest, Sha256};
 use tauri::http::StatusCode;
 use tauri_plugin_http::reqwest::{self, Client, IntoUrl};
 use umbral_pre::{PublicKey, SecretKey, SecretKeyFactory};
+use zeroize::Zeroizing;
 
 use crate::{
     client_error::ClientError,
@@ -105,10 +106,10 @@ pub fn parse_keys_entry(keys_entry: &Vec<u8>) -> Result<KeysEntry, ClientError>
     Ok(serde_json::from_slice(keys_entry).context(current_fn!())?)
 }
 
-pub fn generate_64_bytes_seed() -> [u8; 64] {
+pub fn generate_64_bytes_seed() -> Zeroizing<[u8; 64]> {
     let mut rng = rand::rng();
-    let mut random_seed = [0u8; 64];
-    rng.fill(&mut random_seed);
+    let mut random_seed = Zeroizing::new([0u8; 64]);
+    rng.fill(&mut *random_seed);
 
     random_seed
 }
@@ -367,9 +368,11 @@ pub fn get_global_admin_iota_key_pair_from_keys_entry(
 pub fn get_global_admin_pre_keys_from_keys_entry(
     keys_entry: &KeysEntry,
 ) -> Result<(SecretKey, PublicKey), ClientError> {
-    let pre_seed = STANDARD
-        .decode(keys_entry.admin_pre_seed.clone())
-        .context(current_fn!())?;
+    let pre_seed = Zeroizing::new(
+        STANDARD
+            .decode(&keys_entry.admin_pre_seed)
+            .context(current_fn!())?,
+    );
     let (pre_secret_key, pre_public_key) = compute_pre_keys(&pre_seed).context(current_fn!())?;
 
     Ok((pre_secret_key, pre_public_key))
@@ -1,27 +1,53 @@
 use std::str::FromStr;
 
 use anyhow::{anyhow, Context};
-use iota_types::base_types::IotaAddress;
+use chrono::{DateTime, TimeDelta, Utc};
+use iota_types::{
+    base_types::IotaAddress,
+    crypto::{EncodeDecodeBase64, IotaKeyPair, Signature},
+};
 use serde_json::{json, Value};
-use tauri::{async_runtime::Mutex, State};
+use shared_crypto::intent::{Intent, IntentMessage};
+use tauri::{async_runtime::Mutex, http::StatusCode, AppHandle, State};
+use tauri_plugin_http::reqwest;
+use zeroize::Zeroizing;
 
 use crate::{
+    constants::{
+        ACCESS_LOG_PAGE_SIZE_DEFAULT, ACCESS_LOG_PAGE_SIZE_MAX, MOVE_PAGE_SIZE_MAX, PROXY_BASE_URL,
+        REVOKE_ACCESS_BATCH_MAX, SCHEDULED_REVOCATIONS_MAX,
+    },
     current_fn,
+    move_call::MoveCall,
+    notification::show_notification,
     patient_error::PatientError,
     types::{
-        AppState, HospitalPersonnelPublicAdministrativeData, MovePatientAccessLog, ResponseStatus,
-        SuccessResponse,
+        AccessLogStatus, AppState, CommandAccessLogFilterInput,
+        HospitalPersonnelPublicAdministrativeData, KeysEntry, MovePatientAccessLog,
+        ProxyReencryptionErrorResponse, ProxyReencryptionNoncePayload,
+        ProxyReencryptionRevokeKeysPayload, ProxyReencryptionSuccessResponse, ResponseStatus,
+        ScheduledRevocation, SuccessResponse,
     },
     utils::{
-        get_iota_address_from_keys_entry, get_iota_key_pair_from_keys_entry, parse_keys_entry,
-        serde_deserialize_from_base64,
+        do_http_post_json_request, get_iota_address_from_keys_entry,
+        get_iota_key_pair_from_keys_entry, parse_keys_entry, serde_deserialize_from_base64,
+        unlock_keys_entry,
     },
 };
 
+/**
+ * Newest first. `cursor` counts log entries from the newest one, filtered out entries included.
+ * With `ward_iota_address` the log of a ward the signed in patient is guardian of is read.
+ * return: `{ entries, next_cursor }`, `next_cursor` is null on the last page
+ */
 #[tauri::command]
 pub async fn get_access_log(
     state: State<'_, Mutex<AppState>>,
-) -> Result<SuccessResponse<Vec<Value>>, PatientError> {
+    cursor: Option<u64>,
+    filter: Option<CommandAccessLogFilterInput>,
+    size: Option<u64>,
+    ward_iota_address: Option<String>,
+) -> Result<SuccessResponse<Value>, PatientError> {
     let state = state.lock().await;
     let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
         .context(current_fn!())?;
@@ -32,21 +58,67 @@ pub async fn get_access_log(
 
         patient_iota_address
     };
+    let ward_iota_address = ward_iota_address
+        .as_deref()
+        .map(IotaAddress::from_str)
+        .transpose()
+        .context(current_fn!())?;
 
-    let access_log: Vec<MovePatientAccessLog> = state
-        .move_call
-        .get_access_log(0, 10, patient_iota_address)
-        .await
+    let size = size
+        .unwrap_or(ACCESS_LOG_PAGE_SIZE_DEFAULT)
+        .clamp(1, ACCESS_LOG_PAGE_SIZE_MAX);
+    let mut cursor = cursor.unwrap_or(0);
+    let now = Utc::now();
+
+    let mut access_log = vec![];
+    let mut next_cursor = None;
+
+    // Filters are applied here, keep paging through the chain until the page is full
+    'paging: loop {
+        let access_log_page: Vec<MovePatientAccessLog> = match &ward_iota_address {
+            Some(ward_iota_address) => {
+                state
+                    .move_call
+                    .get_access_log_as_guardian(
+                        cursor,
+                        ward_iota_address,
+                        MOVE_PAGE_SIZE_MAX,
+                        patient_iota_address,
+                    )
+                    .await
+            }
+            None => {
+                state
+                    .move_call
+                    .get_access_log(cursor, MOVE_PAGE_SIZE_MAX, patient_iota_address)
+                    .await
+            }
+        }
         .context(current_fn!())?;
 
-    let access_log = access_log
-        .into_iter()
-        .map(|metadata| {
+        if access_log_page.is_empty() {
+            break;
+        }
+        let access_log_page_len = access_log_page.len() as u64;
+
+        for (i, metadata) in access_log_page.into_iter().enumerate() {
+            if access_log.len() as u64 == size {
+                next_cursor = Some(cursor + i as u64);
+                break 'paging;
+            }
+
             let hospital_personnel_metadata: HospitalPersonnelPublicAdministrativeData =
-                serde_deserialize_from_base64(metadata.hospital_personnel_metadata)
+                serde_deserialize_from_base64(metadata.hospital_personnel_metadata.clone())
                     .context(current_fn!())?;
+            let status = access_log_status(&metadata, now).context(current_fn!())?;
 
-            Ok(json!({
+            if let Some(filter) = &filter {
+                if !access_log_matches(filter, &metadata, &hospital_personnel_metadata, status) {
+                    continue;
+                }
+            }
+
+            access_log.push(json!({
                 "access_data_type": metadata.access_data_type,
                 "access_type": metadata.access_type,
                 "date": metadata.date,
@@ -55,38 +127,52 @@ pub async fn get_access_log(
                 "hospital_personnel_address": metadata.hospital_personnel_address,
                 "hospital_personnel_metadata": hospital_personnel_metadata,
                 "index": metadata.index,
+                "is_active": status == AccessLogStatus::Active,
                 "is_revoked": metadata.is_revoked,
-            }))
-        })
-        .collect::<Result<Vec<Value>, PatientError>>()?;
+                // Revocations are only scheduled for the own access log
+                "scheduled_revoke_at": keys_entry
+                    .scheduled_revocations
+                    .iter()
+                    .filter(|_| ward_iota_address.is_none())
+                    .find(|scheduled_revocation| scheduled_revocation.index == metadata.index)
+                    .map(|scheduled_revocation| scheduled_revocation.revoke_at),
+            }));
+        }
+
+        cursor += access_log_page_len;
+    }
 
     Ok(SuccessResponse {
-        data: access_log,
+        data: json!({
+            "entries": access_log,
+            "next_cursor": next_cursor,
+        }),
         status: ResponseStatus::Success,
     })
 }
 
+/**
+ * With `ward_iota_address` the access is revoked on behalf of a ward the signed in patient is
+ * guardian of
+ */
 #[tauri::command]
 pub async fn revoke_access(
     state: State<'_, Mutex<AppState>>,
     hospital_personnel_address: String,
     index: u64,
+    pin: Zeroizing<String>,
+    ward_iota_address: Option<String>,
 ) -> Result<SuccessResponse<()>, PatientError> {
     let state = state.lock().await;
-    let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+    // Revoking is sensitive, the PIN is entered again instead of using the session PIN
+    let keys_entry = unlock_keys_entry(&state.keys_entry, &state.pin_attempts_entry, &pin)
         .context(current_fn!())?;
 
     let (patient_iota_address, patient_iota_key_pair, hospital_personnel_address) = {
-        let pin = state
-            .auth_state
-            .session_pin
-            .clone()
-            .ok_or(anyhow!("Session PIN Not found"))
-            .context(current_fn!())?;
         let patient_iota_address =
             get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
         let patient_iota_key_pair =
-            get_iota_key_pair_from_keys_entry(&keys_entry, pin.clone()).context(current_fn!())?;
+            get_iota_key_pair_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
         let hospital_personnel_address =
             IotaAddress::from_str(&hospital_personnel_address).context(current_fn!())?;
 
@@ -97,19 +183,564 @@ pub async fn revoke_access(
         )
     };
 
-    let _ = state
-        .move_call
-        .revoke_access(
-            hospital_personnel_address,
-            index,
+    match ward_iota_address {
+        Some(ward_iota_address) => {
+            let ward_iota_address =
+                IotaAddress::from_str(&ward_iota_address).context(current_fn!())?;
+
+            state
+                .move_call
+                .revoke_access_as_guardian(
+                    hospital_personnel_address,
+                    index,
+                    &ward_iota_address,
+                    patient_iota_address,
+                    patient_iota_key_pair,
+                )
+                .await
+        }
+        None => {
+            state
+                .move_call
+                .revoke_access(
+                    hospital_personnel_address,
+                    index,
+                    patient_iota_address,
+                    patient_iota_key_pair,
+                )
+                .await
+        }
+    }
+    .context(current_fn!())?;
+
+    Ok(SuccessResponse {
+        data: (),
+        status: ResponseStatus::Success,
+    })
+}
+
+/**
+ * Revokes every active access and drops their keys on the proxy
+ * return: number of revoked access log entries
+ */
+#[tauri::command]
+pub async fn revoke_all_access(
+    state: State<'_, Mutex<AppState>>,
+    pin: Zeroizing<String>,
+) -> Result<SuccessResponse<u64>, PatientError> {
+    let state = state.lock().await;
+    // Revoking is sensitive, the PIN is entered again instead of using the session PIN
+    let mut keys_entry = unlock_keys_entry(&state.keys_entry, &state.pin_attempts_entry, &pin)
+        .context(current_fn!())?;
+
+    let (patient_iota_address, patient_iota_key_pair) = {
+        let patient_iota_address =
+            get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
+        let patient_iota_key_pair =
+            get_iota_key_pair_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+
+        (patient_iota_address, patient_iota_key_pair)
+    };
+
+    let now = Utc::now();
+    let access_log = get_full_access_log(&state.move_call, patient_iota_address)
+        .await
+        .context(current_fn!())?;
+    let accesses = access_log
+        .iter()
+        .filter(|metadata| {
+            access_log_status(metadata, now).is_ok_and(|status| status == AccessLogStatus::Active)
+        })
+        .map(|metadata| (metadata.hospital_personnel_address, metadata.index))
+        .collect::<Vec<(IotaAddress, u64)>>();
+
+    if !accesses.is_empty() {
+        revoke_accesses(
+            &state.move_call,
+            &access_log,
+            &accesses,
             patient_iota_address,
-            patient_iota_key_pair,
+            &patient_iota_key_pair,
+            now,
         )
         .await
         .context(current_fn!())?;
+    }
+
+    // Every scheduled revocation targets an access that is not active anymore
+    if !keys_entry.scheduled_revocations.is_empty() {
+        keys_entry.scheduled_revocations.clear();
+        state
+            .keys_entry
+            .set_secret(&serde_json::to_vec(&keys_entry).context(current_fn!())?)
+            .context(current_fn!())?;
+    }
+
+    Ok(SuccessResponse {
+        data: accesses.len() as u64,
+        status: ResponseStatus::Success,
+    })
+}
+
+/**
+ * The revocation is executed in the background once `revoke_at` has passed, while the session is
+ * locked it waits until the session is unlocked again and the patient is notified that it is
+ * overdue.
+ * `revoke_at` is a unix timestamp in seconds
+ */
+#[tauri::command]
+pub async fn schedule_revocation(
+    state: State<'_, Mutex<AppState>>,
+    hospital_personnel_address: String,
+    index: u64,
+    revoke_at: u64,
+) -> Result<SuccessResponse<()>, PatientError> {
+    let mut state = state.lock().await;
+    let mut keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+        .context(current_fn!())?;
+
+    let (patient_iota_address, hospital_personnel_address) = {
+        // Only an unlocked session can schedule, executing it needs the session PIN anyway
+        state.auth_state.session.pin().context(current_fn!())?;
+        let patient_iota_address =
+            get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
+        let hospital_personnel_address =
+            IotaAddress::from_str(&hospital_personnel_address).context(current_fn!())?;
+
+        (patient_iota_address, hospital_personnel_address)
+    };
+
+    let now = Utc::now();
+    if revoke_at <= now.timestamp() as u64 {
+        return Err(PatientError::Anyhow(anyhow!(
+            "Invalid args: revoke_at must be in the future"
+        )));
+    }
+
+    let access_log = get_full_access_log(&state.move_call, patient_iota_address)
+        .await
+        .context(current_fn!())?;
+    let metadata = access_log
+        .iter()
+        .find(|metadata| {
+            metadata.index == index
+                && metadata.hospital_personnel_address == hospital_personnel_address
+        })
+        .ok_or(anyhow!("Access not found"))?;
+
+    if access_log_status(metadata, now).context(current_fn!())? != AccessLogStatus::Active {
+        return Err(PatientError::Anyhow(anyhow!("Access is not active")));
+    }
+
+    let exp = access_log_exp(metadata).context(current_fn!())?;
+    if revoke_at >= exp.timestamp() as u64 {
+        return Err(PatientError::Anyhow(anyhow!(
+            "Access expires before the scheduled revocation"
+        )));
+    }
+
+    keys_entry
+        .scheduled_revocations
+        .retain(|scheduled_revocation| scheduled_revocation.index != index);
+
+    if keys_entry.scheduled_revocations.len() >= SCHEDULED_REVOCATIONS_MAX {
+        return Err(PatientError::Anyhow(anyhow!(
+            "Too many scheduled revocations, at most {}",
+            SCHEDULED_REVOCATIONS_MAX
+        )));
+    }
+
+    keys_entry.scheduled_revocations.push(ScheduledRevocation {
+        hospital_personnel_address,
+        index,
+        is_overdue_notified: false,
+        revoke_at,
+    });
+    state
+        .keys_entry
+        .set_secret(&serde_json::to_vec(&keys_entry).context(current_fn!())?)
+        .context(current_fn!())?;
 
     Ok(SuccessResponse {
         data: (),
         status: ResponseStatus::Success,
     })
 }
+
+#[tauri::command]
+pub async fn cancel_scheduled_revocation(
+    state: State<'_, Mutex<AppState>>,
+    index: u64,
+) -> Result<SuccessResponse<()>, PatientError> {
+    let mut state = state.lock().await;
+    let mut keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+        .context(current_fn!())?;
+
+    state.auth_state.session.pin().context(current_fn!())?;
+
+    let scheduled_revocations_len = keys_entry.scheduled_revocations.len();
+    keys_entry
+        .scheduled_revocations
+        .retain(|scheduled_revocation| scheduled_revocation.index != index);
+
+    if keys_entry.scheduled_revocations.len() == scheduled_revocations_len {
+        return Err(PatientError::Anyhow(anyhow!(
+            "Scheduled revocation not found"
+        )));
+    }
+
+    state
+        .keys_entry
+        .set_secret(&serde_json::to_vec(&keys_entry).context(current_fn!())?)
+        .context(current_fn!())?;
+
+    Ok(SuccessResponse {
+        data: (),
+        status: ResponseStatus::Success,
+    })
+}
+
+/**
+ * Executes the due scheduled revocations, called periodically in the background. Signing needs the
+ * PIN, while the session is locked the patient is notified once per overdue revocation instead. The
+ * state is not locked while revoking, commands are not held up by the chain or the proxy.
+ */
+pub async fn execute_scheduled_revocations(
+    app_handle: &AppHandle,
+    state: &Mutex<AppState>,
+) -> anyhow::Result<()> {
+    let now = Utc::now();
+
+    let (move_call, keys_entry, pin, due) = {
+        let mut state = state.lock().await;
+        let mut keys_entry =
+            parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+                .context(current_fn!())?;
+
+        let due = keys_entry
+            .scheduled_revocations
+            .iter()
+            .filter(|scheduled_revocation| scheduled_revocation.revoke_at <= now.timestamp() as u64)
+            .cloned()
+            .collect::<Vec<ScheduledRevocation>>();
+
+        if due.is_empty() {
+            return Ok(());
+        }
+
+        let Some(pin) = state.auth_state.session.peek_pin() else {
+            let overdue_len = due
+                .iter()
+                .filter(|scheduled_revocation| !scheduled_revocation.is_overdue_notified)
+                .count();
+
+            if overdue_len > 0 {
+                for scheduled_revocation in keys_entry.scheduled_revocations.iter_mut() {
+                    if scheduled_revocation.revoke_at <= now.timestamp() as u64 {
+                        scheduled_revocation.is_overdue_notified = true;
+                    }
+                }
+                state
+                    .keys_entry
+                    .set_secret(&serde_json::to_vec(&keys_entry).context(current_fn!())?)
+                    .context(current_fn!())?;
+
+                show_notification(
+                    app_handle,
+                    "Scheduled revocation overdue",
+                    &format!(
+                        "{} scheduled revocation(s) can't run while DecMed is locked, unlock it to \
+                         revoke the access",
+                        overdue_len
+                    ),
+                );
+            }
+
+            return Ok(());
+        };
+
+        (state.move_call.clone(), keys_entry, pin, due)
+    };
+
+    let (patient_iota_address, patient_iota_key_pair) = {
+        let patient_iota_address =
+            get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
+        let patient_iota_key_pair =
+            get_iota_key_pair_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+
+        (patient_iota_address, patient_iota_key_pair)
+    };
+
+    let access_log = get_full_access_log(&move_call, patient_iota_address)
+        .await
+        .context(current_fn!())?;
+    // Accesses that expired or were revoked in the meantime only leave the schedule
+    let accesses = due
+        .iter()
+        .filter(|scheduled_revocation| {
+            access_log.iter().any(|metadata| {
+                metadata.index == scheduled_revocation.index
+                    && access_log_status(metadata, now)
+                        .is_ok_and(|status| status == AccessLogStatus::Active)
+            })
+        })
+        .map(|scheduled_revocation| {
+            (
+                scheduled_revocation.hospital_personnel_address,
+                scheduled_revocation.index,
+            )
+        })
+        .collect::<Vec<(IotaAddress, u64)>>();
+
+    if !accesses.is_empty() {
+        revoke_accesses(
+            &move_call,
+            &access_log,
+            &accesses,
+            patient_iota_address,
+            &patient_iota_key_pair,
+            now,
+        )
+        .await
+        .context(current_fn!())?;
+    }
+
+    // Locked again only to drop the executed revocations, others may have been scheduled or
+    // cancelled and the patient may have signed out in the meantime
+    let state = state.lock().await;
+    let mut keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+        .context(current_fn!())?;
+
+    if keys_entry.iota_address.is_none()
+        || get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?
+            != patient_iota_address
+    {
+        return Ok(());
+    }
+
+    keys_entry
+        .scheduled_revocations
+        .retain(|scheduled_revocation| {
+            !due.iter().any(|due| {
+                due.index == scheduled_revocation.index
+                    && due.revoke_at == scheduled_revocation.revoke_at
+            })
+        });
+    state
+        .keys_entry
+        .set_secret(&serde_json::to_vec(&keys_entry).context(current_fn!())?)
+        .context(current_fn!())?;
+
+    Ok(())
+}
+
+/**
+ * return: number of scheduled revocations past their `revoke_at` that didn't run yet
+ */
+pub fn overdue_scheduled_revocations_len(keys_entry: &KeysEntry) -> u64 {
+    let now = Utc::now().timestamp() as u64;
+
+    keys_entry
+        .scheduled_revocations
+        .iter()
+        .filter(|scheduled_revocation| scheduled_revocation.revoke_at <= now)
+        .count() as u64
+}
+
+/**
+ * return: every access log entry, newest first
+ */
+pub async fn get_full_access_log(
+    move_call: &MoveCall,
+    patient_iota_address: IotaAddress,
+) -> anyhow::Result<Vec<MovePatientAccessLog>> {
+    let mut access_log = vec![];
+
+    loop {
+        let access_log_page = move_call
+            .get_access_log(
+                access_log.len() as u64,
+                MOVE_PAGE_SIZE_MAX,
+                patient_iota_address,
+            )
+            .await
+            .context(current_fn!())?;
+
+        if access_log_page.is_empty() {
+            break;
+        }
+        access_log.extend(access_log_page);
+    }
+
+    Ok(access_log)
+}
+
+/**
+ * Revokes `accesses` on chain, then drops the proxy keys of every personnel left without an active
+ * access. `access_log` is the full access log from before revoking.
+ */
+async fn revoke_accesses(
+    move_call: &MoveCall,
+    access_log: &[MovePatientAccessLog],
+    accesses: &[(IotaAddress, u64)],
+    patient_iota_address: IotaAddress,
+    patient_iota_key_pair: &IotaKeyPair,
+    now: DateTime<Utc>,
+) -> anyhow::Result<()> {
+    for accesses in accesses.chunks(REVOKE_ACCESS_BATCH_MAX) {
+        move_call
+            .revoke_access_batch(accesses, patient_iota_address, patient_iota_key_pair)
+            .await
+            .context(current_fn!())?;
+    }
+
+    // The proxy stores keys per personnel, they are kept while another access of it is active
+    let mut hospital_personnel_addresses = accesses
+        .iter()
+        .map(|(hospital_personnel_address, _)| *hospital_personnel_address)
+        .collect::<Vec<IotaAddress>>();
+    hospital_personnel_addresses.sort_unstable();
+    hospital_personnel_addresses.dedup();
+    hospital_personnel_addresses.retain(|hospital_personnel_address| {
+        !access_log.iter().any(|metadata| {
+            metadata.hospital_personnel_address == *hospital_personnel_address
+                && !accesses.contains(&(metadata.hospital_personnel_address, metadata.index))
+                && access_log_status(metadata, now)
+                    .is_ok_and(|status| status == AccessLogStatus::Active)
+        })
+    });
+
+    if hospital_personnel_addresses.is_empty() {
+        return Ok(());
+    }
+
+    revoke_proxy_keys(
+        &hospital_personnel_addresses,
+        patient_iota_address,
+        patient_iota_key_pair,
+    )
+    .await
+    .context(current_fn!())
+}
+
+async fn revoke_proxy_keys(
+    hospital_personnel_addresses: &[IotaAddress],
+    patient_iota_address: IotaAddress,
+    patient_iota_key_pair: &IotaKeyPair,
+) -> anyhow::Result<()> {
+    let req_client = reqwest::Client::new();
+
+    let nonce = {
+        let payload = ProxyReencryptionNoncePayload {
+            iota_address: patient_iota_address.to_string(),
+        };
+        do_http_post_json_request::<
+            _,
+            ProxyReencryptionSuccessResponse<String>,
+            ProxyReencryptionErrorResponse,
+        >(
+            None,
+            &format!("{}/nonce", PROXY_BASE_URL),
+            &payload,
+            &req_client,
+            StatusCode::OK,
+        )
+        .await
+        .context(current_fn!())?
+        .data
+    };
+
+    let signature = {
+        let intent_message = IntentMessage::new(Intent::personal_message(), nonce);
+        Signature::new_secure(&intent_message, patient_iota_key_pair)
+    };
+
+    let payload = ProxyReencryptionRevokeKeysPayload {
+        guardian_iota_address: None,
+        hospital_personnel_iota_addresses: hospital_personnel_addresses
+            .iter()
+            .map(|hospital_personnel_address| hospital_personnel_address.to_string())
+            .collect(),
+        patient_iota_address: patient_iota_address.to_string(),
+        signature: signature.encode_base64(),
+    };
+    let _ = do_http_post_json_request::<
+        _,
+        ProxyReencryptionSuccessResponse<()>,
+        ProxyReencryptionErrorResponse,
+    >(
+        None,
+        &format!("{}/keys/revoke", PROXY_BASE_URL),
+        &payload,
+        &req_client,
+        StatusCode::OK,
+    )
+    .await
+    .context(current_fn!())?;
+
+    Ok(())
+}
+
+/**
+ * `exp_dur` is in minutes from `date`
+ */
+pub fn access_log_status(
+    access_log: &MovePatientAccessLog,
+    now: DateTime<Utc>,
+) -> anyhow::Result<AccessLogStatus> {
+    if access_log.is_revoked {
+        return Ok(AccessLogStatus::Revoked);
+    }
+
+    let exp = access_log_exp(access_log).context(current_fn!())?;
+
+    if exp > now {
+        Ok(AccessLogStatus::Active)
+    } else {
+        Ok(AccessLogStatus::Expired)
+    }
+}
+
+pub fn access_log_exp(access_log: &MovePatientAccessLog) -> anyhow::Result<DateTime<Utc>> {
+    Ok(
+        (DateTime::parse_from_rfc3339(&access_log.date).context(current_fn!())?
+            + TimeDelta::minutes(access_log.exp_dur as i64))
+        .with_timezone(&Utc),
+    )
+}
+
+fn access_log_matches(
+    filter: &CommandAccessLogFilterInput,
+    access_log: &MovePatientAccessLog,
+    hospital_personnel_metadata: &HospitalPersonnelPublicAdministrativeData,
+    status: AccessLogStatus,
+) -> bool {
+    let contains_ignore_case =
+        |val: &str, part: &str| val.to_lowercase().contains(&part.trim().to_lowercase());
+
+    filter
+        .access_type
+        .is_none_or(|access_type| access_type == access_log.access_type)
+        && filter.hospital_name.as_deref().is_none_or(|hospital_name| {
+            contains_ignore_case(&access_log.hospital_metadata.name, hospital_name)
+        })
+        && filter
+            .hospital_personnel_address
+            .as_deref()
+            .is_none_or(|hospital_personnel_address| {
+                IotaAddress::from_str(hospital_personnel_address)
+                    .is_ok_and(|address| address == access_log.hospital_personnel_address)
+            })
+        && filter
+            .hospital_personnel_name
+            .as_deref()
+            .is_none_or(|hospital_personnel_name| {
+                hospital_personnel_metadata
+                    .name
+                    .as_deref()
+                    .is_some_and(|name| contains_ignore_case(name, hospital_personnel_name))
+            })
+        && filter
+            .status
+            .is_none_or(|filter_status| filter_status == status)
+}
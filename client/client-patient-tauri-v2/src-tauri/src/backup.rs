@@ -0,0 +1,173 @@
+use anyhow::{anyhow, Context};
+use tauri::{async_runtime::Mutex, State};
+use zeroize::Zeroizing;
+
+use crate::{
+    constants::{
+        BACKUP_PASSPHRASE_MIN_LEN, BACKUP_VERSION, PIN_KDF_M_COST_MAX, PIN_KDF_M_COST_MIN,
+        PIN_KDF_P_COST_MAX, PIN_KDF_P_COST_MIN, PIN_KDF_SALT_LEN, PIN_KDF_T_COST_MAX,
+        PIN_KDF_T_COST_MIN,
+    },
+    current_fn,
+    patient_error::PatientError,
+    types::{
+        AdministrativeData, AppState, Backup, BackupPayload, PinAttempts, ResponseStatus,
+        SuccessResponse,
+    },
+    utils::{
+        aes_decrypt, aes_encrypt_custom_key, derive_argon2id_key, generate_pin_kdf,
+        get_iota_address_from_keys_entry, parse_keys_entry, save_pin_attempts, unlock_keys_entry,
+    },
+};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+/**
+ * return: serialized `Backup`, the content of the backup file
+ */
+#[tauri::command]
+pub async fn export_backup(
+    state: State<'_, Mutex<AppState>>,
+    passphrase: Zeroizing<String>,
+    pin: Zeroizing<String>,
+) -> Result<SuccessResponse<String>, PatientError> {
+    let state = state.lock().await;
+    // Exporting is sensitive, the PIN is entered again instead of using the session PIN
+    let mut keys_entry = unlock_keys_entry(&state.keys_entry, &state.pin_attempts_entry, &pin)
+        .context(current_fn!())?;
+
+    if passphrase.chars().count() < BACKUP_PASSPHRASE_MIN_LEN {
+        return Err(PatientError::Anyhow(anyhow!(
+            "Invalid args: passphrase must be at least {} characters",
+            BACKUP_PASSPHRASE_MIN_LEN
+        )));
+    }
+
+    // The proxy access token belongs to this device
+    keys_entry.proxy_jwt = None;
+
+    let payload = BackupPayload {
+        administrative_data: state
+            .administrative_data
+            .as_ref()
+            .map(|administrative_data| administrative_data.private.clone()),
+        settings: keys_entry.settings.clone(),
+        keys_entry,
+    };
+
+    let pin_kdf = generate_pin_kdf();
+    let backup_key = derive_argon2id_key(passphrase.as_bytes(), &pin_kdf).context(current_fn!())?;
+    let (enc_payload, nonce) = aes_encrypt_custom_key(
+        &backup_key,
+        &Zeroizing::new(serde_json::to_vec(&payload).context(current_fn!())?),
+    )
+    .context(current_fn!())?;
+
+    let backup = Backup {
+        enc_payload: STANDARD.encode(enc_payload),
+        kdf: pin_kdf,
+        nonce: STANDARD.encode(nonce),
+        version: BACKUP_VERSION,
+    };
+
+    Ok(SuccessResponse {
+        data: serde_json::to_string(&backup).context(current_fn!())?,
+        status: ResponseStatus::Success,
+    })
+}
+
+/**
+ * Installs the keys entry of `backup` once its account is found on chain, the session stays
+ * locked until the PIN of the backed up account is entered.
+ */
+#[tauri::command]
+pub async fn import_backup(
+    state: State<'_, Mutex<AppState>>,
+    backup: String,
+    passphrase: Zeroizing<String>,
+) -> Result<SuccessResponse<()>, PatientError> {
+    let mut state = state.lock().await;
+    let current_keys_entry =
+        parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+            .context(current_fn!())?;
+
+    if current_keys_entry.iota_key_pair.is_some() {
+        return Err(PatientError::Anyhow(anyhow!(
+            "Sign out before importing a backup"
+        )));
+    }
+
+    let mut backup: Backup = serde_json::from_str(&backup)
+        .map_err(|_| anyhow!("Invalid backup file").context(current_fn!()))?;
+
+    if backup.version != BACKUP_VERSION {
+        return Err(PatientError::Anyhow(anyhow!(
+            "Unsupported backup version: {}",
+            backup.version
+        )));
+    }
+
+    // A crafted file must not make the derivation too cheap, or exhaust memory and CPU
+    let kdf = &mut backup.kdf;
+    kdf.m_cost = kdf.m_cost.clamp(PIN_KDF_M_COST_MIN, PIN_KDF_M_COST_MAX);
+    kdf.p_cost = kdf.p_cost.clamp(PIN_KDF_P_COST_MIN, PIN_KDF_P_COST_MAX);
+    kdf.t_cost = kdf.t_cost.clamp(PIN_KDF_T_COST_MIN, PIN_KDF_T_COST_MAX);
+    if STANDARD
+        .decode(&kdf.salt)
+        .map_or(true, |salt| salt.len() != PIN_KDF_SALT_LEN)
+    {
+        return Err(PatientError::Anyhow(anyhow!("Invalid backup file")));
+    }
+
+    let backup_key =
+        derive_argon2id_key(passphrase.as_bytes(), &backup.kdf).context(current_fn!())?;
+    let payload = aes_decrypt(
+        &STANDARD
+            .decode(&backup.enc_payload)
+            .context(current_fn!())?,
+        &backup_key,
+        &STANDARD.decode(&backup.nonce).context(current_fn!())?,
+    )
+    .map_err(|_| anyhow!("Invalid passphrase"))?;
+    let payload: BackupPayload = serde_json::from_slice(&payload).context(current_fn!())?;
+
+    let mut keys_entry = payload.keys_entry;
+
+    if keys_entry.id.is_none()
+        || keys_entry.iota_key_pair.is_none()
+        || keys_entry.pre_secret_key.is_none()
+    {
+        return Err(PatientError::Anyhow(anyhow!(
+            "Backup does not contain a signed in account"
+        )));
+    }
+
+    let patient_iota_address =
+        get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
+    let is_registered = state
+        .move_call
+        .is_account_registered(patient_iota_address)
+        .await
+        .context(current_fn!())?;
+
+    if !is_registered {
+        return Err(PatientError::Anyhow(anyhow!("Account not found")));
+    }
+
+    keys_entry.settings = payload.settings;
+    state
+        .keys_entry
+        .set_secret(&serde_json::to_vec(&keys_entry).context(current_fn!())?)
+        .context(current_fn!())?;
+    // Failed PIN attempts counted against the replaced keys entry don't carry over
+    save_pin_attempts(&state.pin_attempts_entry, &PinAttempts::default()).context(current_fn!())?;
+
+    state.administrative_data = payload
+        .administrative_data
+        .map(|private| AdministrativeData { private });
+    state.auth_state.session.lock();
+
+    Ok(SuccessResponse {
+        data: (),
+        status: ResponseStatus::Success,
+    })
+}
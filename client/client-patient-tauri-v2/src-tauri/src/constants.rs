@@ -3,9 +3,128 @@
 pub const IOTA_URL: &str = "http://localhost:9000";
 pub const GAS_STATION_BASE_URL: &str = "http://localhost:9527/v1";
 pub const GAS_BUDGET: u64 = 10_000_000;
+/// Access duration bounds in minutes, mirrors the policy of `decmed::patient::create_access`
+pub const ACCESS_DUR_MIN: u64 = 1;
+pub const ADMINISTRATIVE_READ_DUR_MAX: u64 = 60;
+pub const MEDICAL_READ_DUR_MAX: u64 = 24 * 60;
+pub const MEDICAL_UPDATE_DUR_MAX: u64 = 24 * 60;
+/// Hospital personnel QR format, see `decode_hospital_personnel_qr`
+pub const HOSPITAL_PERSONNEL_QR_PREFIX: &str = "decmed-hp";
+pub const HOSPITAL_PERSONNEL_QR_VERSION: u8 = 1;
+/// Duration: 1 minute, tolerated clock difference between the hospital and patient devices
+pub const HOSPITAL_PERSONNEL_QR_CLOCK_SKEW: u64 = 60;
+/// Duration: 1 day, longest QR validity accepted
+pub const HOSPITAL_PERSONNEL_QR_VALIDITY_MAX: u64 = 86400;
+/// Guardian code format, see `decode_guardian_code`
+pub const GUARDIAN_CODE_PREFIX: &str = "decmed-guardian";
+pub const GUARDIAN_CODE_VERSION: u8 = 1;
+/// Guardians per patient, mirrors `decmed::patient::GUARDIANS_MAX`
+pub const GUARDIANS_MAX: usize = 5;
+/// Access handoff without a camera: `decmed://grant?code={hospital personnel QR content}`
+pub const ACCESS_HANDOFF_URI_SCHEME: &str = "decmed";
+pub const ACCESS_HANDOFF_URI_HOST: &str = "grant";
+pub const ACCESS_HANDOFF_URI_CODE_PARAM: &str = "code";
+/// Camera photos are downscaled to this width/height before QR detection
+pub const QR_IMAGE_DIMENSION_MAX: u32 = 1600;
+/// Entries per `get_access_log` page
+pub const ACCESS_LOG_PAGE_SIZE_DEFAULT: u64 = 10;
+pub const ACCESS_LOG_PAGE_SIZE_MAX: u64 = 50;
+/// Entries `decmed::patient` returns per read-only call at most
+pub const MOVE_PAGE_SIZE_MAX: u64 = 10;
+/// Revocations per `revoke_access_batch` transaction, bounded by the gas reserved for it
+pub const REVOKE_ACCESS_BATCH_MAX: usize = 50;
+/// Pending `ScheduledRevocation`s per profile
+pub const SCHEDULED_REVOCATIONS_MAX: usize = 50;
+/// Entries per `get_medical_records` page
+pub const MEDICAL_RECORDS_PAGE_SIZE_DEFAULT: u64 = 10;
+pub const MEDICAL_RECORDS_PAGE_SIZE_MAX: u64 = 50;
 pub const HASH_SALT: &str = "169224A2BE2B267684F93A9CE38080D359BD774741FD3AE738D09B657A1A8104";
+/// Records per `export_fhir_bundle`, each one is fetched from IPFS
+pub const FHIR_EXPORT_RECORDS_MAX: usize = 50;
+/// Code systems and codes of the FHIR export
+pub const FHIR_ENCOUNTER_CLASS_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/v3-ActCode";
+pub const FHIR_ENCOUNTER_CLASS_AMBULATORY: &str = "AMB";
+pub const FHIR_MARITAL_STATUS_SYSTEM: &str =
+    "http://terminology.hl7.org/CodeSystem/v3-MaritalStatus";
+pub const FHIR_LOINC_SYSTEM: &str = "http://loinc.org";
+pub const FHIR_LOINC_ANAMNESIS: &str = "10164-2";
+pub const FHIR_LOINC_PHYSICAL_CHECK: &str = "29545-1";
+pub const FHIR_LOINC_PSYCHOLOGICAL_CHECK: &str = "10190-7";
+pub const FHIR_LOINC_EDUCATION: &str = "82589-3";
+pub const FHIR_LOINC_OCCUPATION: &str = "11341-5";
+pub const FHIR_EXTENSION_BIRTH_PLACE: &str =
+    "http://hl7.org/fhir/StructureDefinition/patient-birthPlace";
+pub const FHIR_EXTENSION_RELIGION: &str =
+    "http://hl7.org/fhir/StructureDefinition/patient-religion";
+/// Identifier systems of DecMed values, not registered outside DecMed
+pub const FHIR_IOTA_ADDRESS_SYSTEM: &str = "urn:decmed:iota-address";
+pub const FHIR_MEDICAL_RECORD_INDEX_SYSTEM: &str = "urn:decmed:medical-record-index";
+pub const FHIR_PATIENT_ID_SYSTEM: &str = "urn:decmed:patient-id";
+/// `Backup` file format
+pub const BACKUP_VERSION: u8 = 1;
+pub const BACKUP_PASSPHRASE_MIN_LEN: usize = 12;
+/// Directory under the app local data dir holding one `EncRecordIndex` file per profile
+pub const RECORD_INDEX_DIR: &str = "record_index";
+/// `EncRecordIndex` file format
+pub const RECORD_INDEX_VERSION: u8 = 1;
+/// Separates the record index key from other keys derived from the PRE seed
+pub const RECORD_INDEX_KEY_DOMAIN: &[u8] = b"decmed_patient_record_index";
+/// Keyring service every profile's `KeysEntry` is stored under, the profile id is the user
+pub const KEYS_ENTRY_SERVICE: &str = "decmed_patient_service_keys";
+/// Keyring service every profile's `PinAttempts` is stored under, the profile id is the user
+pub const PIN_ATTEMPTS_SERVICE: &str = "decmed_patient_service_pin_attempts";
+/// Profile id of the keys entry used before profiles existed
+pub const DEFAULT_PROFILE_ID: &str = "decmed_patient";
+pub const DEFAULT_PROFILE_LABEL: &str = "Default";
+pub const PROFILE_REGISTRY_SERVICE: &str = "decmed_patient_service_profiles";
+pub const PROFILE_REGISTRY_USER: &str = "decmed_patient";
+/// `KeysEntry` vault format, 0: SHA-256 of the PIN, 1: Argon2id with `KeysEntry.pin_kdf`
+pub const KEYS_ENTRY_VAULT_VERSION: u8 = 1;
+/// Argon2id memory cost in KiB
+pub const PIN_KDF_M_COST: u32 = 19 * 1024;
+pub const PIN_KDF_P_COST: u32 = 1;
+pub const PIN_KDF_SALT_LEN: usize = 16;
+pub const PIN_KDF_T_COST: u32 = 2;
+/// Bounds the KDF parameters of an imported backup are clamped to, the file is untrusted
+pub const PIN_KDF_M_COST_MIN: u32 = PIN_KDF_M_COST;
+pub const PIN_KDF_M_COST_MAX: u32 = 256 * 1024;
+pub const PIN_KDF_P_COST_MIN: u32 = PIN_KDF_P_COST;
+pub const PIN_KDF_P_COST_MAX: u32 = 4;
+pub const PIN_KDF_T_COST_MIN: u32 = PIN_KDF_T_COST;
+pub const PIN_KDF_T_COST_MAX: u32 = 10;
+/// Failed PIN attempts allowed before the lockout kicks in
+pub const PIN_FREE_ATTEMPTS: u32 = 3;
+/// Duration: 30 seconds, doubled on every further failed attempt
+pub const PIN_LOCKOUT_BASE_DUR: u64 = 30;
+/// Duration: 1 day
+pub const PIN_LOCKOUT_MAX_DUR: u64 = 86400;
+/// Bounds of `Settings.pin_wipe_after_failed_attempts`, the lowest still leaves room for typos
+pub const PIN_WIPE_AFTER_FAILED_ATTEMPTS_MIN: u32 = 5;
+pub const PIN_WIPE_AFTER_FAILED_ATTEMPTS_MAX: u32 = 20;
+/// Duration: 5 minutes, default of `Settings.session_idle_timeout`
+pub const SESSION_IDLE_TIMEOUT: u64 = 300;
+/// Duration: 1 minute
+pub const SESSION_IDLE_TIMEOUT_MIN: u64 = 60;
+/// Duration: 1 hour
+pub const SESSION_IDLE_TIMEOUT_MAX: u64 = 3600;
+/// Duration: 15 seconds, how often idle sessions are locked in the background
+pub const SESSION_LOCK_CHECK_INTERVAL: u64 = 15;
+/// Duration: 1 minute, how often due `ScheduledRevocation`s are executed in the background
+pub const SCHEDULED_REVOCATION_CHECK_INTERVAL: u64 = 60;
+/// Duration: 1 minute, how often new access events are looked for in the background
+pub const ACCESS_EVENT_CHECK_INTERVAL: u64 = 60;
+/// Duration: 10 minutes, active accesses expiring within it are notified once
+pub const ACCESS_EXPIRING_WINDOW: i64 = 10;
+/// Tauri event name the frontend listens to for `AccessEvent`s
+pub const ACCESS_EVENT: &str = "access-event";
 pub const PROXY_BASE_URL: &str = "http://localhost:4000/api/v1";
 pub const IPFS_GATEWAY_BASE_URL: &str = "http://127.0.0.1:8080";
+/// Upper bound of `Settings::ipfs_gateway_fallback_base_urls`, every one is tried on a miss
+pub const IPFS_GATEWAY_FALLBACK_BASE_URLS_MAX: usize = 5;
+pub const AES_STREAM_MAGIC: &[u8; 4] = b"DMED";
+pub const AES_STREAM_VERSION: u8 = 1;
+/// magic (4) | version (1) | chunk size (4) | nonce prefix (7)
+pub const AES_STREAM_HEADER_LEN: usize = 16;
 
 pub const DECMED_MODULE_PATIENT: &str = "patient";
 
@@ -0,0 +1,365 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Context};
+use iota_types::{base_types::IotaAddress, crypto::IotaKeyPair};
+use tauri::{async_runtime::Mutex, http::StatusCode, State};
+use tauri_plugin_http::reqwest;
+use umbral_pre::{encrypt, generate_kfrags, PublicKey, SecretKey, Signer};
+use zeroize::Zeroizing;
+
+use crate::{
+    constants::PROXY_BASE_URL,
+    current_fn,
+    patient_error::PatientError,
+    scan::sign_proxy_nonce,
+    types::{
+        AppState, CommandEmergencyAccessEntry, CommandGetEmergencyAccessResponseData,
+        HospitalPersonnelPublicAdministrativeData, MoveCreateAccessMetadata,
+        ProxyReencryptionEmergencyKeys, ProxyReencryptionEmergencyKeysPayload,
+        ProxyReencryptionEmergencyRecipient, ProxyReencryptionEmergencyRecipientsPayload,
+        ProxyReencryptionErrorResponse, ProxyReencryptionRevokeEmergencyKeysPayload,
+        ProxyReencryptionSuccessResponse, ResponseStatus, SuccessResponse,
+    },
+    utils::{
+        compute_pre_keys, do_http_post_json_request, generate_64_bytes_seed,
+        get_iota_address_from_keys_entry, get_iota_key_pair_from_keys_entry,
+        get_pre_keys_from_keys_entry, parse_keys_entry, serde_deserialize_from_base64,
+        serde_serialize_to_base64, unlock_keys_entry,
+    },
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+/**
+ * return: whether emergency access is enabled and the break-glass accesses not acknowledged yet
+ */
+#[tauri::command]
+pub async fn get_emergency_access(
+    state: State<'_, Mutex<AppState>>,
+) -> Result<SuccessResponse<CommandGetEmergencyAccessResponseData>, PatientError> {
+    let mut state = state.lock().await;
+    let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+        .context(current_fn!())?;
+    let patient_iota_address =
+        get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
+
+    // Reading the flag and the logs only needs an unlocked session
+    let pin = state.auth_state.session.pin().context(current_fn!())?;
+
+    let (is_enabled, emergency_access_logs) = state
+        .move_call
+        .get_emergency_access(patient_iota_address)
+        .await
+        .context(current_fn!())?;
+
+    // Medical personnel registered since the last visit get their keys now, best effort as the
+    // flag and the logs don't depend on it
+    let patient_name = state
+        .administrative_data
+        .as_ref()
+        .and_then(|administrative_data| administrative_data.private.name.clone());
+    if let (true, Some(patient_name)) = (is_enabled, patient_name) {
+        let res = async {
+            let patient_iota_key_pair =
+                get_iota_key_pair_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+            let (patient_pre_secret_key, patient_pre_public_key) =
+                get_pre_keys_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+
+            store_emergency_keys(
+                &reqwest::Client::new(),
+                patient_iota_address,
+                &patient_iota_key_pair,
+                patient_name,
+                &patient_pre_secret_key,
+                &patient_pre_public_key,
+            )
+            .await
+        }
+        .await;
+
+        if let Err(e) = res {
+            println!("{:#?}", e);
+        }
+    }
+
+    let mut entries = vec![];
+    for emergency_access_log in emergency_access_logs {
+        let hospital_personnel_metadata: HospitalPersonnelPublicAdministrativeData =
+            serde_deserialize_from_base64(emergency_access_log.hospital_personnel_metadata.clone())
+                .context(current_fn!())?;
+
+        entries.push(CommandEmergencyAccessEntry {
+            access_log_index: emergency_access_log.access_log_index,
+            date: emergency_access_log.date.clone(),
+            exp_dur: emergency_access_log.exp_dur,
+            hospital_name: emergency_access_log.hospital_metadata.name.clone(),
+            hospital_personnel_iota_address: emergency_access_log
+                .hospital_personnel_address
+                .to_string(),
+            hospital_personnel_name: hospital_personnel_metadata.name.clone(),
+            index: emergency_access_log.index,
+            justification: emergency_access_log.justification.clone(),
+        });
+    }
+
+    Ok(SuccessResponse {
+        data: CommandGetEmergencyAccessResponseData {
+            entries,
+            is_enabled,
+        },
+        status: ResponseStatus::Success,
+    })
+}
+
+/**
+ * Enabling deposits a kfrag with the proxy before consenting on chain, disabling withdraws the
+ * consent on chain before revoking the kfrag
+ */
+#[tauri::command]
+pub async fn set_emergency_access(
+    state: State<'_, Mutex<AppState>>,
+    is_enabled: bool,
+    pin: Zeroizing<String>,
+) -> Result<SuccessResponse<()>, PatientError> {
+    let state = state.lock().await;
+    // Sharing keys is sensitive, the PIN is entered again instead of using the session PIN
+    let keys_entry = unlock_keys_entry(&state.keys_entry, &state.pin_attempts_entry, &pin)
+        .context(current_fn!())?;
+    let req_client = reqwest::Client::new();
+
+    let (patient_iota_address, patient_iota_key_pair) = {
+        let patient_iota_address =
+            get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
+        let patient_iota_key_pair =
+            get_iota_key_pair_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+
+        (patient_iota_address, patient_iota_key_pair)
+    };
+
+    if is_enabled {
+        let (patient_pre_secret_key, patient_pre_public_key) =
+            get_pre_keys_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+        let patient_name = state
+            .administrative_data
+            .as_ref()
+            .ok_or(anyhow!("Administrative data not found on state").context(current_fn!()))?
+            .private
+            .name
+            .clone()
+            .context(current_fn!())?;
+
+        store_emergency_keys(
+            &req_client,
+            patient_iota_address,
+            &patient_iota_key_pair,
+            patient_name,
+            &patient_pre_secret_key,
+            &patient_pre_public_key,
+        )
+        .await
+        .context(current_fn!())?;
+
+        state
+            .move_call
+            .set_emergency_access(true, patient_iota_address, patient_iota_key_pair)
+            .await
+            .context(current_fn!())?;
+    } else {
+        state
+            .move_call
+            .set_emergency_access(false, patient_iota_address, patient_iota_key_pair.copy())
+            .await
+            .context(current_fn!())?;
+
+        let payload = ProxyReencryptionRevokeEmergencyKeysPayload {
+            patient_iota_address: patient_iota_address.to_string(),
+            signature: sign_proxy_nonce(&req_client, patient_iota_address, &patient_iota_key_pair)
+                .await
+                .context(current_fn!())?,
+        };
+        do_http_post_json_request::<
+            _,
+            ProxyReencryptionSuccessResponse<()>,
+            ProxyReencryptionErrorResponse,
+        >(
+            None,
+            &format!("{}/emergency/keys/revoke", PROXY_BASE_URL),
+            &payload,
+            &req_client,
+            StatusCode::OK,
+        )
+        .await
+        .context(current_fn!())?;
+    }
+
+    Ok(SuccessResponse {
+        data: (),
+        status: ResponseStatus::Success,
+    })
+}
+
+/**
+ * Acknowledged accesses are no longer returned by `get_emergency_access`, revoking the access
+ * itself is done from the access log like any other access
+ */
+#[tauri::command]
+pub async fn acknowledge_emergency_access(
+    state: State<'_, Mutex<AppState>>,
+    index: u64,
+) -> Result<SuccessResponse<()>, PatientError> {
+    let mut state = state.lock().await;
+    let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+        .context(current_fn!())?;
+
+    let (patient_iota_address, patient_iota_key_pair) = {
+        let pin = state.auth_state.session.pin().context(current_fn!())?;
+        let patient_iota_address =
+            get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
+        let patient_iota_key_pair =
+            get_iota_key_pair_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+
+        (patient_iota_address, patient_iota_key_pair)
+    };
+
+    state
+        .move_call
+        .acknowledge_emergency_access(index, patient_iota_address, patient_iota_key_pair)
+        .await
+        .context(current_fn!())?;
+
+    Ok(SuccessResponse {
+        data: (),
+        status: ResponseStatus::Success,
+    })
+}
+
+/**
+ * Builds keys for every registered medical personnel the proxy has none for yet: a kfrag to a
+ * fresh data key whose seed and the patient name are encrypted for the personnel only. The proxy
+ * hands them out on break-glass but can't decrypt any of them.
+ */
+async fn store_emergency_keys(
+    req_client: &reqwest::Client,
+    patient_iota_address: IotaAddress,
+    patient_iota_key_pair: &IotaKeyPair,
+    patient_name: String,
+    patient_pre_secret_key: &SecretKey,
+    patient_pre_public_key: &PublicKey,
+) -> Result<(), PatientError> {
+    let recipients = {
+        let payload = ProxyReencryptionEmergencyRecipientsPayload {
+            patient_iota_address: patient_iota_address.to_string(),
+            signature: sign_proxy_nonce(req_client, patient_iota_address, patient_iota_key_pair)
+                .await
+                .context(current_fn!())?,
+        };
+        do_http_post_json_request::<
+            _,
+            ProxyReencryptionSuccessResponse<Vec<ProxyReencryptionEmergencyRecipient>>,
+            ProxyReencryptionErrorResponse,
+        >(
+            None,
+            &format!("{}/emergency/recipients/pending", PROXY_BASE_URL),
+            &payload,
+            req_client,
+            StatusCode::OK,
+        )
+        .await
+        .context(current_fn!())?
+        .data
+    };
+
+    if recipients.is_empty() {
+        return Ok(());
+    }
+
+    let mut keys = HashMap::new();
+    for recipient in recipients {
+        let hospital_personnel_pre_public_key: PublicKey =
+            serde_deserialize_from_base64(recipient.pre_public_key.clone())
+                .context(current_fn!())?;
+
+        let data_pre_secret_key_seed = generate_64_bytes_seed();
+        let (_, data_pre_public_key) =
+            compute_pre_keys(&data_pre_secret_key_seed[0..32]).context(current_fn!())?;
+        let (data_pre_secret_key_seed_capsule, enc_data_pre_secret_key_seed) = encrypt(
+            &hospital_personnel_pre_public_key,
+            &data_pre_secret_key_seed[0..32],
+        )
+        .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
+
+        let enc_patient_name = {
+            let (capsule, enc_data) =
+                encrypt(&hospital_personnel_pre_public_key, patient_name.as_bytes())
+                    .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
+
+            MoveCreateAccessMetadata {
+                capsule: serde_serialize_to_base64(&capsule).context(current_fn!())?,
+                enc_data: STANDARD.encode(enc_data),
+            }
+        };
+
+        let (signer_secret_key, signer_public_key) = {
+            let signer_secret_key = Signer::new(SecretKey::random());
+            let signer_public_key = signer_secret_key.verifying_key();
+
+            (signer_secret_key, signer_public_key)
+        };
+
+        let k_frag = {
+            let k_frags = generate_kfrags(
+                patient_pre_secret_key,
+                &data_pre_public_key,
+                &signer_secret_key,
+                1,
+                1,
+                true,
+                true,
+            );
+            k_frags[0].clone().unverify()
+        };
+
+        keys.insert(
+            recipient.hospital_personnel_iota_address,
+            ProxyReencryptionEmergencyKeys {
+                data_pre_public_key: serde_serialize_to_base64(&data_pre_public_key)
+                    .context(current_fn!())?,
+                data_pre_secret_key_seed_capsule: serde_serialize_to_base64(
+                    &data_pre_secret_key_seed_capsule,
+                )
+                .context(current_fn!())?,
+                enc_data_pre_secret_key_seed: STANDARD.encode(enc_data_pre_secret_key_seed),
+                enc_patient_name,
+                hospital_personnel_pre_public_key: recipient.pre_public_key,
+                k_frag: serde_serialize_to_base64(&k_frag).context(current_fn!())?,
+                patient_pre_public_key: serde_serialize_to_base64(patient_pre_public_key)
+                    .context(current_fn!())?,
+                signer_pre_public_key: serde_serialize_to_base64(&signer_public_key)
+                    .context(current_fn!())?,
+            },
+        );
+    }
+
+    let payload = ProxyReencryptionEmergencyKeysPayload {
+        keys,
+        patient_iota_address: patient_iota_address.to_string(),
+        signature: sign_proxy_nonce(req_client, patient_iota_address, patient_iota_key_pair)
+            .await
+            .context(current_fn!())?,
+    };
+    do_http_post_json_request::<
+        _,
+        ProxyReencryptionSuccessResponse<()>,
+        ProxyReencryptionErrorResponse,
+    >(
+        None,
+        &format!("{}/emergency/keys", PROXY_BASE_URL),
+        &payload,
+        req_client,
+        StatusCode::OK,
+    )
+    .await
+    .context(current_fn!())?;
+
+    Ok(())
+}
@@ -0,0 +1,408 @@
+use std::io::Write;
+
+use anyhow::{anyhow, Context};
+use chrono::Utc;
+use tauri::{async_runtime::Mutex, AppHandle, State};
+use tauri_plugin_dialog::DialogExt;
+use tauri_plugin_fs::{FsExt, OpenOptions};
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+use crate::{
+    constants::{
+        FHIR_ENCOUNTER_CLASS_AMBULATORY, FHIR_ENCOUNTER_CLASS_SYSTEM, FHIR_EXPORT_RECORDS_MAX,
+        FHIR_EXTENSION_BIRTH_PLACE, FHIR_EXTENSION_RELIGION, FHIR_IOTA_ADDRESS_SYSTEM,
+        FHIR_LOINC_ANAMNESIS, FHIR_LOINC_EDUCATION, FHIR_LOINC_OCCUPATION,
+        FHIR_LOINC_PHYSICAL_CHECK, FHIR_LOINC_PSYCHOLOGICAL_CHECK, FHIR_LOINC_SYSTEM,
+        FHIR_MARITAL_STATUS_SYSTEM, FHIR_MEDICAL_RECORD_INDEX_SYSTEM, FHIR_PATIENT_ID_SYSTEM,
+    },
+    current_fn,
+    home::{decrypt_medical_record_key_nonce, get_medical_data},
+    patient_error::PatientError,
+    types::{
+        AppState, FhirBundle, FhirBundleEntry, FhirCarePlan, FhirCodeableConcept, FhirCoding,
+        FhirCondition, FhirEncounter, FhirEncounterParticipant, FhirExtension, FhirIdentifier,
+        FhirObservation, FhirPatient, FhirPeriod, FhirReference, FhirResource, FhirText,
+        MedicalData, MedicalDataSource, MedicalMetadata, PrivateAdministrativeData, ResponseStatus,
+        SuccessResponse,
+    },
+    utils::{
+        get_iota_address_from_keys_entry, get_iota_key_pair_from_keys_entry,
+        get_pre_keys_from_keys_entry, medical_metadata_versions, serde_deserialize_from_base64,
+        unlock_keys_entry,
+    },
+};
+
+/**
+ * Exports the current version of the selected own records and the administrative data as a
+ * FHIR R4 `Bundle`, for hospitals outside DecMed. The bundle is written to the file picked in
+ * the save dialog, it doesn't go through the webview.
+ * return: whether the file was saved, false when the dialog was cancelled
+ */
+#[tauri::command]
+pub async fn export_fhir_bundle(
+    app_handle: AppHandle,
+    state: State<'_, Mutex<AppState>>,
+    indexes: Vec<u64>,
+    pin: Zeroizing<String>,
+) -> Result<SuccessResponse<bool>, PatientError> {
+    let state = state.lock().await;
+    // The export is not encrypted, the PIN is entered again instead of using the session PIN
+    let keys_entry = unlock_keys_entry(&state.keys_entry, &state.pin_attempts_entry, &pin)
+        .context(current_fn!())?;
+
+    if indexes.is_empty() || indexes.len() > FHIR_EXPORT_RECORDS_MAX {
+        return Err(PatientError::Anyhow(anyhow!(
+            "Invalid args: select between 1 and {} records",
+            FHIR_EXPORT_RECORDS_MAX
+        )));
+    }
+
+    let (patient_iota_address, patient_iota_key_pair, patient_pre_secret_key) = {
+        let patient_iota_address =
+            get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
+        let patient_iota_key_pair =
+            get_iota_key_pair_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+        let (patient_pre_secret_key, _) =
+            get_pre_keys_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+
+        (
+            patient_iota_address,
+            patient_iota_key_pair,
+            patient_pre_secret_key,
+        )
+    };
+    let administrative_data = &state
+        .administrative_data
+        .as_ref()
+        .ok_or(anyhow!("Administrative data not found on state").context(current_fn!()))?
+        .private;
+
+    let patient_full_url = fhir_full_url();
+    let patient_reference = FhirReference {
+        identifier: None,
+        reference: Some(patient_full_url.clone()),
+    };
+    let mut entry = vec![FhirBundleEntry {
+        full_url: patient_full_url,
+        resource: FhirResource::Patient(fhir_patient(administrative_data)),
+    }];
+
+    for (code, display, value) in [
+        (
+            FHIR_LOINC_EDUCATION,
+            "Highest level of education",
+            &administrative_data.education,
+        ),
+        (
+            FHIR_LOINC_OCCUPATION,
+            "History of Occupation",
+            &administrative_data.occupation,
+        ),
+    ] {
+        if let Some(value) = value.as_ref().filter(|value| !value.trim().is_empty()) {
+            entry.push(FhirBundleEntry {
+                full_url: fhir_full_url(),
+                resource: FhirResource::Observation(fhir_observation(
+                    code,
+                    display,
+                    None,
+                    None,
+                    patient_reference.clone(),
+                    value.clone(),
+                )),
+            });
+        }
+    }
+
+    for index in indexes {
+        let medical_metadata = state
+            .move_call
+            .get_medical_record(index, patient_iota_address)
+            .await
+            .context(current_fn!())?;
+        let medical_metadata: MedicalMetadata =
+            serde_deserialize_from_base64(medical_metadata.metadata).context(current_fn!())?;
+        let medical_metadata = medical_metadata_versions(medical_metadata)
+            .pop()
+            .ok_or(anyhow!("Medical record version not found").context(current_fn!()))?;
+        let source = MedicalDataSource {
+            guardian_iota_address: None,
+            index,
+            iota_key_pair: &patient_iota_key_pair,
+            ipfs_gateway_fallback_base_urls: &keys_entry.settings.ipfs_gateway_fallback_base_urls,
+            patient_iota_address,
+            version: None,
+        };
+        let medical_record_key_nonce =
+            decrypt_medical_record_key_nonce(&medical_metadata, &patient_pre_secret_key)
+                .context(current_fn!())?;
+        let medical_data = get_medical_data(&medical_metadata, &medical_record_key_nonce, &source)
+            .await
+            .context(current_fn!())?;
+
+        entry.extend(medical_record_entries(
+            medical_metadata.created_at,
+            medical_metadata.created_by,
+            index,
+            medical_data,
+            &patient_reference,
+        ));
+    }
+
+    let now = Utc::now();
+    let bundle = FhirBundle {
+        bundle_type: "collection".to_string(),
+        entry,
+        id: Uuid::new_v4().to_string(),
+        resource_type: "Bundle".to_string(),
+        timestamp: now.to_rfc3339(),
+    };
+    let bundle = serde_json::to_vec_pretty(&bundle).context(current_fn!())?;
+    // The dialog waits for the patient, the state is not kept locked meanwhile
+    drop(state);
+
+    let file_dialog = app_handle
+        .dialog()
+        .file()
+        .add_filter("FHIR bundle", &["json"])
+        .set_file_name(format!("decmed-fhir-{}.json", now.format("%Y-%m-%d")));
+    let file_path = tauri::async_runtime::spawn_blocking(move || file_dialog.blocking_save_file())
+        .await
+        .context(current_fn!())?;
+
+    let Some(file_path) = file_path else {
+        return Ok(SuccessResponse {
+            data: false,
+            status: ResponseStatus::Success,
+        });
+    };
+
+    // Goes through the fs plugin, on Android the picked path is a content URI
+    let mut open_options = OpenOptions::new();
+    open_options.write(true).create(true).truncate(true);
+    let mut file = app_handle
+        .fs()
+        .open(file_path, open_options)
+        .context(current_fn!())?;
+    file.write_all(&bundle).context(current_fn!())?;
+
+    Ok(SuccessResponse {
+        data: true,
+        status: ResponseStatus::Success,
+    })
+}
+
+/**
+ * An `Encounter` with a `Condition` (diagnose), `Observation`s (anamnesis, physical and
+ * psychological check) and a `CarePlan` (therapy) referencing it. Empty fields get no resource.
+ */
+fn medical_record_entries(
+    created_at: String,
+    created_by: Option<String>,
+    index: u64,
+    medical_data: MedicalData,
+    patient_reference: &FhirReference,
+) -> Vec<FhirBundleEntry> {
+    let encounter_full_url = fhir_full_url();
+    let encounter_reference = FhirReference {
+        identifier: None,
+        reference: Some(encounter_full_url.clone()),
+    };
+    // The author is only known by its IOTA address
+    let author_reference = created_by.map(|created_by| FhirReference {
+        identifier: Some(FhirIdentifier {
+            system: FHIR_IOTA_ADDRESS_SYSTEM.to_string(),
+            value: created_by,
+        }),
+        reference: None,
+    });
+
+    let mut entry = vec![FhirBundleEntry {
+        full_url: encounter_full_url,
+        resource: FhirResource::Encounter(FhirEncounter {
+            class: FhirCoding {
+                code: FHIR_ENCOUNTER_CLASS_AMBULATORY.to_string(),
+                display: None,
+                system: FHIR_ENCOUNTER_CLASS_SYSTEM.to_string(),
+            },
+            identifier: vec![FhirIdentifier {
+                system: FHIR_MEDICAL_RECORD_INDEX_SYSTEM.to_string(),
+                value: index.to_string(),
+            }],
+            participant: author_reference
+                .as_ref()
+                .map(|author_reference| FhirEncounterParticipant {
+                    individual: author_reference.clone(),
+                })
+                .into_iter()
+                .collect(),
+            period: FhirPeriod {
+                start: created_at.clone(),
+            },
+            status: "finished".to_string(),
+            subject: patient_reference.clone(),
+        }),
+    }];
+
+    for (code, display, value) in [
+        (
+            FHIR_LOINC_ANAMNESIS,
+            "History of Present illness Narrative",
+            medical_data.anamnesis,
+        ),
+        (
+            FHIR_LOINC_PHYSICAL_CHECK,
+            "Physical findings Narrative",
+            medical_data.physical_check,
+        ),
+        (
+            FHIR_LOINC_PSYCHOLOGICAL_CHECK,
+            "Mental status Narrative",
+            medical_data.psychological_check,
+        ),
+    ] {
+        if value.trim().is_empty() {
+            continue;
+        }
+
+        entry.push(FhirBundleEntry {
+            full_url: fhir_full_url(),
+            resource: FhirResource::Observation(fhir_observation(
+                code,
+                display,
+                Some(created_at.clone()),
+                Some(encounter_reference.clone()),
+                patient_reference.clone(),
+                value,
+            )),
+        });
+    }
+
+    if !medical_data.diagnose.trim().is_empty() {
+        entry.push(FhirBundleEntry {
+            full_url: fhir_full_url(),
+            resource: FhirResource::Condition(FhirCondition {
+                code: FhirCodeableConcept {
+                    coding: vec![],
+                    text: Some(medical_data.diagnose),
+                },
+                encounter: encounter_reference.clone(),
+                recorded_date: created_at.clone(),
+                recorder: author_reference,
+                subject: patient_reference.clone(),
+            }),
+        });
+    }
+    if !medical_data.therapy.trim().is_empty() {
+        entry.push(FhirBundleEntry {
+            full_url: fhir_full_url(),
+            resource: FhirResource::CarePlan(FhirCarePlan {
+                created: created_at,
+                description: medical_data.therapy,
+                encounter: encounter_reference,
+                intent: "plan".to_string(),
+                status: "active".to_string(),
+                subject: patient_reference.clone(),
+            }),
+        });
+    }
+
+    entry
+}
+
+fn fhir_patient(administrative_data: &PrivateAdministrativeData) -> FhirPatient {
+    let mut extension = vec![];
+    if let Some(birth_place) = &administrative_data.birth_place {
+        extension.push(FhirExtension {
+            url: FHIR_EXTENSION_BIRTH_PLACE.to_string(),
+            value_address: Some(FhirText {
+                text: birth_place.clone(),
+            }),
+            value_codeable_concept: None,
+        });
+    }
+    if let Some(religion) = &administrative_data.religion {
+        extension.push(FhirExtension {
+            url: FHIR_EXTENSION_RELIGION.to_string(),
+            value_address: None,
+            value_codeable_concept: Some(FhirCodeableConcept {
+                coding: vec![],
+                text: Some(religion.clone()),
+            }),
+        });
+    }
+
+    FhirPatient {
+        birth_date: administrative_data.date_of_birth.clone(),
+        extension,
+        gender: administrative_data
+            .gender
+            .as_deref()
+            .map(|gender| match gender {
+                "Male" => "male",
+                "Female" => "female",
+                _ => "unknown",
+            })
+            .map(str::to_string),
+        identifier: vec![FhirIdentifier {
+            system: FHIR_PATIENT_ID_SYSTEM.to_string(),
+            value: administrative_data.id.clone(),
+        }],
+        marital_status: administrative_data
+            .marital_status
+            .as_ref()
+            .map(|marital_status| FhirCodeableConcept {
+                coding: match marital_status.as_str() {
+                    "Single" => Some("S"),
+                    "Married" => Some("M"),
+                    "Widowed" => Some("W"),
+                    "Divorced" => Some("D"),
+                    _ => None,
+                }
+                .map(|code| FhirCoding {
+                    code: code.to_string(),
+                    display: None,
+                    system: FHIR_MARITAL_STATUS_SYSTEM.to_string(),
+                })
+                .into_iter()
+                .collect(),
+                text: Some(marital_status.clone()),
+            }),
+        name: administrative_data
+            .name
+            .as_ref()
+            .map(|name| FhirText { text: name.clone() })
+            .into_iter()
+            .collect(),
+    }
+}
+
+fn fhir_observation(
+    code: &str,
+    display: &str,
+    effective_date_time: Option<String>,
+    encounter: Option<FhirReference>,
+    subject: FhirReference,
+    value: String,
+) -> FhirObservation {
+    FhirObservation {
+        code: FhirCodeableConcept {
+            coding: vec![FhirCoding {
+                code: code.to_string(),
+                display: Some(display.to_string()),
+                system: FHIR_LOINC_SYSTEM.to_string(),
+            }],
+            text: Some(display.to_string()),
+        },
+        effective_date_time,
+        encounter,
+        status: "final".to_string(),
+        subject,
+        value_string: value,
+    }
+}
+
+fn fhir_full_url() -> String {
+    format!("urn:uuid:{}", Uuid::new_v4())
+}
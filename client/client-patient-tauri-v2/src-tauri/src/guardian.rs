@@ -0,0 +1,563 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context};
+use iota_types::{base_types::IotaAddress, crypto::IotaKeyPair};
+use tauri::{async_runtime::Mutex, http::StatusCode, State};
+use tauri_plugin_http::reqwest;
+use umbral_pre::{
+    decrypt_original, decrypt_reencrypted, encrypt, generate_kfrags, Capsule, CapsuleFrag,
+    PublicKey, SecretKey, Signer,
+};
+use zeroize::Zeroizing;
+
+use crate::{
+    constants::{GUARDIANS_MAX, PROXY_BASE_URL},
+    current_fn,
+    move_call::MoveCall,
+    patient_error::PatientError,
+    scan::sign_proxy_nonce,
+    types::{
+        AppState, CommandGuardianEntry, CommandGuardianRightsInput, CommandWardEntry, KeyNonce,
+        MedicalMetadataVersion, MoveCreateAccessMetadata, MovePatientGuardian,
+        ProxyReencryptionErrorResponse, ProxyReencryptionGuardianKeys,
+        ProxyReencryptionGuardianMedicalRecordCFragPayload,
+        ProxyReencryptionGuardianMedicalRecordCFragResponseData,
+        ProxyReencryptionRevokeGuardianKeysPayload, ProxyReencryptionStoreGuardianKeysPayload,
+        ProxyReencryptionSuccessResponse, ResponseStatus, SuccessResponse, WardKeyMaterial,
+    },
+    utils::{
+        compute_pre_keys, decode_guardian_code, do_http_post_json_request, encode_guardian_code,
+        generate_64_bytes_seed, get_iota_address_from_keys_entry,
+        get_iota_key_pair_from_keys_entry, get_pre_keys_from_keys_entry, parse_keys_entry,
+        serde_deserialize_from_base64, serde_serialize_to_base64, unlock_keys_entry,
+    },
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+/**
+ * Code a patient enters in `add_guardian` to make the signed in patient its guardian
+ */
+#[tauri::command]
+pub async fn get_guardian_code(
+    state: State<'_, Mutex<AppState>>,
+) -> Result<SuccessResponse<String>, PatientError> {
+    let mut state = state.lock().await;
+    let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+        .context(current_fn!())?;
+
+    let (iota_key_pair, pre_public_key) = {
+        let pin = state.auth_state.session.pin().context(current_fn!())?;
+        let iota_key_pair =
+            get_iota_key_pair_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+        let (_, pre_public_key) =
+            get_pre_keys_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+
+        (iota_key_pair, pre_public_key)
+    };
+
+    Ok(SuccessResponse {
+        data: encode_guardian_code(&iota_key_pair, &pre_public_key).context(current_fn!())?,
+        status: ResponseStatus::Success,
+    })
+}
+
+/**
+ * Each right gets its own key material and the patient PRE seed is never shared:
+ * - read: a kfrag to the guardian PRE key, kept by the proxy
+ * - grant: the seed of a delegated data key, the proxy keeps the kfrag to it
+ * - revoke: nothing, revoking needs no keys
+ * The name, and the delegated seed, are encrypted for the guardian PRE key from the code.
+ */
+#[tauri::command]
+pub async fn add_guardian(
+    state: State<'_, Mutex<AppState>>,
+    guardian_code: String,
+    pin: Zeroizing<String>,
+    rights: CommandGuardianRightsInput,
+) -> Result<SuccessResponse<()>, PatientError> {
+    let state = state.lock().await;
+    // Sharing keys is sensitive, the PIN is entered again instead of using the session PIN
+    let keys_entry = unlock_keys_entry(&state.keys_entry, &state.pin_attempts_entry, &pin)
+        .context(current_fn!())?;
+    let guardian_code = decode_guardian_code(&guardian_code).context(current_fn!())?;
+
+    if !(rights.can_grant || rights.can_read || rights.can_revoke) {
+        return Err(PatientError::Anyhow(anyhow!(
+            "Invalid args: a guardian needs at least one right"
+        )));
+    }
+
+    let (patient_iota_address, patient_iota_key_pair) = {
+        let patient_iota_address =
+            get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
+        let patient_iota_key_pair =
+            get_iota_key_pair_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+
+        (patient_iota_address, patient_iota_key_pair)
+    };
+
+    if guardian_code.iota_address == patient_iota_address {
+        return Err(PatientError::Anyhow(anyhow!(
+            "Invalid args: a patient can't be its own guardian"
+        )));
+    }
+
+    // The same checks are enforced on chain, done here to fail before sharing keys
+    let guardians = state
+        .move_call
+        .get_guardians(patient_iota_address)
+        .await
+        .context(current_fn!())?;
+    if guardians
+        .iter()
+        .any(|guardian| guardian.address == guardian_code.iota_address)
+    {
+        return Err(PatientError::Anyhow(anyhow!("Guardian already added")));
+    }
+    if guardians.len() >= GUARDIANS_MAX {
+        return Err(PatientError::Anyhow(anyhow!(
+            "A patient can have at most {} guardians",
+            GUARDIANS_MAX
+        )));
+    }
+
+    let metadata = if rights.can_grant || rights.can_read {
+        let patient_name = state
+            .administrative_data
+            .as_ref()
+            .ok_or(anyhow!("Administrative data not found on state").context(current_fn!()))?
+            .private
+            .name
+            .clone()
+            .context(current_fn!())?;
+        let (patient_pre_secret_key, patient_pre_public_key) =
+            get_pre_keys_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+
+        let grant_data_pre_secret_key_seed = store_guardian_keys(
+            &guardian_code.iota_address,
+            &guardian_code.pre_public_key,
+            patient_iota_address,
+            &patient_iota_key_pair,
+            &patient_pre_secret_key,
+            &patient_pre_public_key,
+            &rights,
+        )
+        .await
+        .context(current_fn!())?;
+
+        let ward_key_material = WardKeyMaterial {
+            grant_data_pre_secret_key_seed: grant_data_pre_secret_key_seed.map(
+                |grant_data_pre_secret_key_seed| STANDARD.encode(&grant_data_pre_secret_key_seed),
+            ),
+            name: patient_name,
+            pre_public_key: serde_serialize_to_base64(&patient_pre_public_key)
+                .context(current_fn!())?,
+        };
+        let (capsule, enc_data) = encrypt(
+            &guardian_code.pre_public_key,
+            &Zeroizing::new(serde_json::to_vec(&ward_key_material).context(current_fn!())?),
+        )
+        .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
+
+        Some(
+            serde_serialize_to_base64(&MoveCreateAccessMetadata {
+                capsule: serde_serialize_to_base64(&capsule).context(current_fn!())?,
+                enc_data: STANDARD.encode(enc_data),
+            })
+            .context(current_fn!())?,
+        )
+    } else {
+        None
+    };
+
+    let _ = state
+        .move_call
+        .add_guardian(
+            rights.can_grant,
+            rights.can_read,
+            rights.can_revoke,
+            &guardian_code.iota_address,
+            metadata,
+            patient_iota_address,
+            patient_iota_key_pair,
+        )
+        .await
+        .context(current_fn!())?;
+
+    Ok(SuccessResponse {
+        data: (),
+        status: ResponseStatus::Success,
+    })
+}
+
+/**
+ * Without `ward_iota_address` the signed in patient removes one of its guardians, otherwise the
+ * signed in guardian steps down as guardian of the ward. The kfrags of the guardian are revoked
+ * with the proxy so the shared key material is useless from then on, no key rotation is needed
+ * since the patient PRE seed is never shared. Guardians added before that still hold the seed,
+ * the patient has to treat its keys as compromised and recreate the account to rotate them.
+ */
+#[tauri::command]
+pub async fn remove_guardian(
+    state: State<'_, Mutex<AppState>>,
+    guardian_iota_address: Option<String>,
+    pin: Zeroizing<String>,
+    ward_iota_address: Option<String>,
+) -> Result<SuccessResponse<()>, PatientError> {
+    let state = state.lock().await;
+    let keys_entry = unlock_keys_entry(&state.keys_entry, &state.pin_attempts_entry, &pin)
+        .context(current_fn!())?;
+
+    let (iota_address, iota_key_pair) = {
+        let iota_address = get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
+        let iota_key_pair =
+            get_iota_key_pair_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+
+        (iota_address, iota_key_pair)
+    };
+
+    let (guardian_iota_address, patient_iota_address) =
+        match (guardian_iota_address, ward_iota_address) {
+            (Some(guardian_iota_address), None) => (
+                IotaAddress::from_str(&guardian_iota_address).context(current_fn!())?,
+                iota_address,
+            ),
+            (None, Some(ward_iota_address)) => (
+                iota_address,
+                IotaAddress::from_str(&ward_iota_address).context(current_fn!())?,
+            ),
+            _ => {
+                return Err(PatientError::Anyhow(anyhow!(
+                    "Invalid args: either a guardian or a ward IOTA address is needed"
+                )))
+            }
+        };
+
+    let _ = state
+        .move_call
+        .remove_guardian(
+            &guardian_iota_address,
+            &patient_iota_address,
+            iota_address,
+            iota_key_pair.copy(),
+        )
+        .await
+        .context(current_fn!())?;
+
+    let req_client = reqwest::Client::new();
+    let payload = ProxyReencryptionRevokeGuardianKeysPayload {
+        guardian_iota_address: guardian_iota_address.to_string(),
+        patient_iota_address: patient_iota_address.to_string(),
+        signature: sign_proxy_nonce(&req_client, iota_address, &iota_key_pair)
+            .await
+            .context(current_fn!())?,
+        signer_iota_address: iota_address.to_string(),
+    };
+    do_http_post_json_request::<
+        _,
+        ProxyReencryptionSuccessResponse<()>,
+        ProxyReencryptionErrorResponse,
+    >(
+        None,
+        &format!("{}/guardian/keys/revoke", PROXY_BASE_URL),
+        &payload,
+        &req_client,
+        StatusCode::OK,
+    )
+    .await
+    .context(current_fn!())?;
+
+    Ok(SuccessResponse {
+        data: (),
+        status: ResponseStatus::Success,
+    })
+}
+
+#[tauri::command]
+pub async fn get_guardians(
+    state: State<'_, Mutex<AppState>>,
+) -> Result<SuccessResponse<Vec<CommandGuardianEntry>>, PatientError> {
+    let state = state.lock().await;
+    let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+        .context(current_fn!())?;
+    let patient_iota_address =
+        get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
+
+    let guardians = state
+        .move_call
+        .get_guardians(patient_iota_address)
+        .await
+        .context(current_fn!())?
+        .iter()
+        .map(|guardian| CommandGuardianEntry {
+            iota_address: guardian.address.to_string(),
+            rights: guardian_rights(guardian),
+        })
+        .collect();
+
+    Ok(SuccessResponse {
+        data: guardians,
+        status: ResponseStatus::Success,
+    })
+}
+
+/**
+ * Ward names are decrypted with the session PIN, a ward whose keys can't be decrypted is listed
+ * without a name
+ */
+#[tauri::command]
+pub async fn get_wards(
+    state: State<'_, Mutex<AppState>>,
+) -> Result<SuccessResponse<Vec<CommandWardEntry>>, PatientError> {
+    let mut state = state.lock().await;
+    let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+        .context(current_fn!())?;
+
+    let (guardian_iota_address, guardian_pre_secret_key) = {
+        let pin = state.auth_state.session.pin().context(current_fn!())?;
+        let guardian_iota_address =
+            get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
+        let (guardian_pre_secret_key, _) =
+            get_pre_keys_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+
+        (guardian_iota_address, guardian_pre_secret_key)
+    };
+
+    let wards = state
+        .move_call
+        .get_wards(guardian_iota_address)
+        .await
+        .context(current_fn!())?
+        .into_iter()
+        .map(|(ward_iota_address, guardian)| {
+            let name = guardian.metadata.clone().and_then(|metadata| {
+                decrypt_ward_key_material(&guardian_pre_secret_key, metadata)
+                    .map_err(|err| println!("{:#?}", err))
+                    .ok()
+                    .map(|ward_key_material| ward_key_material.name.clone())
+            });
+
+            CommandWardEntry {
+                iota_address: ward_iota_address.to_string(),
+                name,
+                rights: guardian_rights(&guardian),
+            }
+        })
+        .collect();
+
+    Ok(SuccessResponse {
+        data: wards,
+        status: ResponseStatus::Success,
+    })
+}
+
+/**
+ * Fails when the signed in patient is not a guardian of the ward or was shared no keys
+ * return: ward key material
+ */
+pub async fn get_ward_key_material(
+    move_call: &MoveCall,
+    guardian_iota_address: IotaAddress,
+    guardian_pre_secret_key: &SecretKey,
+    ward_iota_address: IotaAddress,
+) -> anyhow::Result<WardKeyMaterial> {
+    let metadata = move_call
+        .get_wards(guardian_iota_address)
+        .await
+        .context(current_fn!())?
+        .into_iter()
+        .find(|(iota_address, _)| *iota_address == ward_iota_address)
+        .ok_or(anyhow!("Not a guardian of the patient").context(current_fn!()))?
+        .1
+        .metadata
+        .ok_or(anyhow!("The patient shared no keys with its guardian").context(current_fn!()))?;
+
+    decrypt_ward_key_material(guardian_pre_secret_key, metadata).context(current_fn!())
+}
+
+/**
+ * The proxy re-encrypts the record key for the guardian PRE key, the ward PRE key is never known
+ * to the guardian
+ */
+pub async fn get_ward_medical_record_key_nonce(
+    guardian_iota_address: IotaAddress,
+    guardian_iota_key_pair: &IotaKeyPair,
+    guardian_pre_secret_key: &SecretKey,
+    index: u64,
+    medical_metadata: &MedicalMetadataVersion,
+    version: Option<u64>,
+    ward_iota_address: IotaAddress,
+    ward_key_material: &WardKeyMaterial,
+) -> anyhow::Result<KeyNonce> {
+    let req_client = reqwest::Client::new();
+    let payload = ProxyReencryptionGuardianMedicalRecordCFragPayload {
+        guardian_iota_address: guardian_iota_address.to_string(),
+        index,
+        patient_iota_address: ward_iota_address.to_string(),
+        signature: sign_proxy_nonce(&req_client, guardian_iota_address, guardian_iota_key_pair)
+            .await
+            .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?,
+        version,
+    };
+    let res = do_http_post_json_request::<
+        _,
+        ProxyReencryptionSuccessResponse<ProxyReencryptionGuardianMedicalRecordCFragResponseData>,
+        ProxyReencryptionErrorResponse,
+    >(
+        None,
+        &format!("{}/medical-record/c-frag/guardian", PROXY_BASE_URL),
+        &payload,
+        &req_client,
+        StatusCode::OK,
+    )
+    .await
+    .context(current_fn!())?
+    .data;
+
+    let ward_pre_public_key: PublicKey =
+        serde_deserialize_from_base64(ward_key_material.pre_public_key.clone())
+            .context(current_fn!())?;
+    let signer_pre_public_key: PublicKey =
+        serde_deserialize_from_base64(res.signer_pre_public_key).context(current_fn!())?;
+    let capsule: Capsule =
+        serde_deserialize_from_base64(medical_metadata.capsule.clone()).context(current_fn!())?;
+    let c_frag: CapsuleFrag = serde_deserialize_from_base64(res.c_frag).context(current_fn!())?;
+    let verified_cfrag = c_frag
+        .verify(
+            &capsule,
+            &signer_pre_public_key,
+            &ward_pre_public_key,
+            &guardian_pre_secret_key.public_key(),
+        )
+        .map_err(|e| anyhow!(e.0.to_string()).context(current_fn!()))?;
+
+    let medical_record_key_nonce = decrypt_reencrypted(
+        guardian_pre_secret_key,
+        &ward_pre_public_key,
+        &capsule,
+        [verified_cfrag],
+        STANDARD
+            .decode(&medical_metadata.enc_key_and_nonce)
+            .context(current_fn!())?,
+    )
+    .map(Zeroizing::new)
+    .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
+
+    serde_json::from_slice(&medical_record_key_nonce).context(current_fn!())
+}
+
+/**
+ * Deposits the kfrags of the rights with the proxy
+ * return: seed of the delegated data key when the guardian can grant access
+ */
+async fn store_guardian_keys(
+    guardian_iota_address: &IotaAddress,
+    guardian_pre_public_key: &PublicKey,
+    patient_iota_address: IotaAddress,
+    patient_iota_key_pair: &IotaKeyPair,
+    patient_pre_secret_key: &SecretKey,
+    patient_pre_public_key: &PublicKey,
+    rights: &CommandGuardianRightsInput,
+) -> Result<Option<Zeroizing<Vec<u8>>>, PatientError> {
+    let (signer_secret_key, signer_public_key) = {
+        let signer_secret_key = Signer::new(SecretKey::random());
+        let signer_public_key = signer_secret_key.verifying_key();
+
+        (signer_secret_key, signer_public_key)
+    };
+    let generate_k_frag = |receiving_pre_public_key: &PublicKey| {
+        let k_frags = generate_kfrags(
+            patient_pre_secret_key,
+            receiving_pre_public_key,
+            &signer_secret_key,
+            1,
+            1,
+            true,
+            true,
+        );
+        serde_serialize_to_base64(&k_frags[0].clone().unverify())
+    };
+
+    let (grant_data_pre_secret_key_seed, data_pre_public_key, grant_k_frag) = if rights.can_grant {
+        let data_pre_secret_key_seed = generate_64_bytes_seed();
+        let grant_data_pre_secret_key_seed =
+            Zeroizing::new(data_pre_secret_key_seed[0..32].to_vec());
+        let (_, data_pre_public_key) =
+            compute_pre_keys(&grant_data_pre_secret_key_seed).context(current_fn!())?;
+
+        (
+            Some(grant_data_pre_secret_key_seed),
+            Some(serde_serialize_to_base64(&data_pre_public_key).context(current_fn!())?),
+            Some(generate_k_frag(&data_pre_public_key).context(current_fn!())?),
+        )
+    } else {
+        (None, None, None)
+    };
+    let read_k_frag = if rights.can_read {
+        Some(generate_k_frag(guardian_pre_public_key).context(current_fn!())?)
+    } else {
+        None
+    };
+
+    let req_client = reqwest::Client::new();
+    let payload = ProxyReencryptionStoreGuardianKeysPayload {
+        guardian_iota_address: guardian_iota_address.to_string(),
+        keys: ProxyReencryptionGuardianKeys {
+            data_pre_public_key,
+            grant_k_frag,
+            guardian_pre_public_key: serde_serialize_to_base64(guardian_pre_public_key)
+                .context(current_fn!())?,
+            patient_pre_public_key: serde_serialize_to_base64(patient_pre_public_key)
+                .context(current_fn!())?,
+            read_k_frag,
+            signer_pre_public_key: serde_serialize_to_base64(&signer_public_key)
+                .context(current_fn!())?,
+        },
+        patient_iota_address: patient_iota_address.to_string(),
+        signature: sign_proxy_nonce(&req_client, patient_iota_address, patient_iota_key_pair)
+            .await
+            .context(current_fn!())?,
+    };
+    do_http_post_json_request::<
+        _,
+        ProxyReencryptionSuccessResponse<()>,
+        ProxyReencryptionErrorResponse,
+    >(
+        None,
+        &format!("{}/guardian/keys", PROXY_BASE_URL),
+        &payload,
+        &req_client,
+        StatusCode::OK,
+    )
+    .await
+    .context(current_fn!())?;
+
+    Ok(grant_data_pre_secret_key_seed)
+}
+
+fn decrypt_ward_key_material(
+    guardian_pre_secret_key: &SecretKey,
+    metadata: String,
+) -> anyhow::Result<WardKeyMaterial> {
+    let metadata: MoveCreateAccessMetadata =
+        serde_deserialize_from_base64(metadata).context(current_fn!())?;
+
+    let ward_key_material = decrypt_original(
+        guardian_pre_secret_key,
+        &serde_deserialize_from_base64(metadata.capsule).context(current_fn!())?,
+        &STANDARD.decode(metadata.enc_data).context(current_fn!())?,
+    )
+    .map(Zeroizing::new)
+    .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
+
+    serde_json::from_slice(&ward_key_material).context(current_fn!())
+}
+
+fn guardian_rights(guardian: &MovePatientGuardian) -> CommandGuardianRightsInput {
+    CommandGuardianRightsInput {
+        can_grant: guardian.can_grant,
+        can_read: guardian.can_read,
+        can_revoke: guardian.can_revoke,
+    }
+}
@@ -1,28 +1,59 @@
+use std::{path::PathBuf, str::FromStr};
+
 use anyhow::{anyhow, Context};
+use chrono::Utc;
+use iota_types::base_types::IotaAddress;
 use serde_json::{json, Value};
 use tauri::{async_runtime::Mutex, State};
-use umbral_pre::decrypt_original;
+use tauri_plugin_http::reqwest::{self, StatusCode};
+use umbral_pre::{decrypt_original, SecretKey};
+use zeroize::Zeroizing;
 
 use crate::{
+    constants::{
+        MEDICAL_RECORDS_PAGE_SIZE_DEFAULT, MEDICAL_RECORDS_PAGE_SIZE_MAX, MOVE_PAGE_SIZE_MAX,
+        PROXY_BASE_URL,
+    },
     current_fn,
+    guardian::{get_ward_key_material, get_ward_medical_record_key_nonce},
+    move_call::MoveCall,
     patient_error::PatientError,
+    scan::sign_proxy_nonce,
     types::{
-        AppState, CommandGetMedicalRecordsResponseData, KeyNonce, MedicalData, MedicalMetadata,
-        MovePatientMedicalMetadata, ResponseStatus, SuccessResponse,
+        AppState, BlobStoreBackend, CommandGetMedicalRecordVersionsResponseData,
+        CommandGetMedicalRecordsResponseData, CommandSearchMedicalRecordsInput,
+        CommandSyncMedicalRecordsResponseData, KeyNonce, MedicalData, MedicalDataSource,
+        MedicalMetadata, MedicalMetadataVersion, MovePatientMedicalMetadata,
+        ProxyReencryptionErrorResponse, ProxyReencryptionMedicalRecordContentPayload, RecordIndex,
+        RecordIndexEntry, ResponseStatus, SuccessResponse,
     },
     utils::{
-        aes_decrypt, get_data_ipfs, get_iota_address_from_keys_entry, get_pre_keys_from_keys_entry,
-        parse_keys_entry, serde_deserialize_from_base64,
+        aes_stream_decrypt_response, compute_pre_keys, derive_record_index_key,
+        do_http_post_json_request_stream, get_data_ipfs, get_iota_address_from_keys_entry,
+        get_iota_key_pair_from_keys_entry, get_pre_keys_from_keys_entry,
+        get_pre_seed_from_keys_entry, iso_to_local_date, load_profile_registry, load_record_index,
+        medical_metadata_versions, parse_date, parse_keys_entry, record_index_path,
+        save_record_index, serde_deserialize_from_base64,
     },
 };
 
 use base64::{engine::general_purpose::STANDARD, Engine as _};
 
+/**
+ * Newest first, `cursor` counts records from the newest one. With `ward_iota_address` the records
+ * of a ward the signed in patient is guardian of are listed. Own records are listed from the
+ * record index when the chain is unreachable, `offline` is set on the response then.
+ * return: `{ entries, last_synced_at, next_cursor, offline }`, `next_cursor` is null on the last
+ * page
+ */
 #[tauri::command]
 pub async fn get_medical_records(
     state: State<'_, Mutex<AppState>>,
-) -> Result<SuccessResponse<Vec<CommandGetMedicalRecordsResponseData>>, PatientError> {
-    let state = state.lock().await;
+    cursor: Option<u64>,
+    size: Option<u64>,
+    ward_iota_address: Option<String>,
+) -> Result<SuccessResponse<Value>, PatientError> {
+    let mut state = state.lock().await;
     let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
         .context(current_fn!())?;
 
@@ -32,13 +63,51 @@ pub async fn get_medical_records(
 
         patient_iota_address
     };
-
-    let medical_records: Vec<MovePatientMedicalMetadata> = state
-        .move_call
-        .get_medical_records(0, 10, patient_iota_address)
-        .await
+    let ward_iota_address = ward_iota_address
+        .as_deref()
+        .map(IotaAddress::from_str)
+        .transpose()
         .context(current_fn!())?;
 
+    let size = size
+        .unwrap_or(MEDICAL_RECORDS_PAGE_SIZE_DEFAULT)
+        .clamp(1, MEDICAL_RECORDS_PAGE_SIZE_MAX);
+    let cursor = cursor.unwrap_or(0);
+
+    let medical_records = get_medical_records_range(
+        &state.move_call,
+        cursor,
+        patient_iota_address,
+        size,
+        ward_iota_address.as_ref(),
+    )
+    .await;
+    let (medical_records, offline, last_synced_at) = match medical_records {
+        Ok(medical_records) => (medical_records, false, None),
+        // Own records are listed from the offline cache when the chain is unreachable
+        Err(err) if ward_iota_address.is_none() && err.is_unreachable() => {
+            let record_index = match load_active_record_index(&mut state) {
+                Ok(record_index) if !record_index.medical_metadata.is_empty() => record_index,
+                _ => return Err(err),
+            };
+            let medical_records = record_index
+                .medical_metadata
+                .into_iter()
+                .skip(cursor as usize)
+                .take(size as usize)
+                .collect::<Vec<MovePatientMedicalMetadata>>();
+
+            (medical_records, true, record_index.last_synced_at)
+        }
+        Err(err) => return Err(err),
+    };
+
+    // The oldest record has index 0
+    let next_cursor = medical_records
+        .last()
+        .filter(|metadata| metadata.index > 0 && medical_records.len() as u64 == size)
+        .map(|_| cursor + size);
+
     let medical_records = medical_records
         .into_iter()
         .map(|metadata| {
@@ -46,88 +115,695 @@ pub async fn get_medical_records(
                 serde_deserialize_from_base64(metadata.metadata).context(current_fn!())?;
 
             Ok(CommandGetMedicalRecordsResponseData {
-                cid: medical_metadata.cid,
-                index: metadata.index,
                 created_at: medical_metadata.created_at,
+                created_by: medical_metadata.created_by,
+                index: metadata.index,
+                locator: medical_metadata.locator,
             })
         })
         .collect::<Result<Vec<CommandGetMedicalRecordsResponseData>, PatientError>>()?;
 
     Ok(SuccessResponse {
         status: ResponseStatus::Success,
-        data: medical_records,
+        data: json!({
+            "entries": medical_records,
+            "last_synced_at": last_synced_at,
+            "next_cursor": next_cursor,
+            "offline": offline,
+        }),
     })
 }
 
+/**
+ * With `ward_iota_address` a record of a ward the signed in patient is guardian of is read, its
+ * key is re-encrypted for the guardian by the proxy. Ward records are not added to the record
+ * index.
+ * Own records opened before are read from the record index when the chain or IPFS is unreachable,
+ * `offline` is set on the response then.
+ */
 #[tauri::command]
 pub async fn get_medical_record(
     state: State<'_, Mutex<AppState>>,
     index: u64,
+    version: Option<u64>,
+    ward_iota_address: Option<String>,
 ) -> Result<SuccessResponse<Value>, PatientError> {
-    let state = state.lock().await;
+    let mut state = state.lock().await;
     let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
         .context(current_fn!())?;
 
-    let (patient_iota_address, patient_pre_secret_key) = {
-        let pin = state
-            .auth_state
-            .session_pin
-            .clone()
-            .ok_or(anyhow!("Session PIN not found"))
-            .context(current_fn!())?;
+    let (patient_iota_address, patient_iota_key_pair, patient_pre_secret_key, record_index_key) = {
+        let pin = state.auth_state.session.pin().context(current_fn!())?;
         let patient_iota_address =
             get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
-        let (patient_pre_secret_key, _) =
-            get_pre_keys_from_keys_entry(&keys_entry, pin).context(current_fn!())?;
+        let patient_iota_key_pair =
+            get_iota_key_pair_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+        let pre_seed = get_pre_seed_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+        let (patient_pre_secret_key, _) = compute_pre_keys(&pre_seed).context(current_fn!())?;
 
-        (patient_iota_address, patient_pre_secret_key)
+        (
+            patient_iota_address,
+            patient_iota_key_pair,
+            patient_pre_secret_key,
+            derive_record_index_key(&pre_seed),
+        )
     };
 
+    let ward_iota_address = ward_iota_address
+        .as_deref()
+        .map(IotaAddress::from_str)
+        .transpose()
+        .context(current_fn!())?;
+
+    let (medical_metadata, ward_keys) = match &ward_iota_address {
+        Some(ward_iota_address) => {
+            let medical_metadata = state
+                .move_call
+                .get_medical_record_as_guardian(index, ward_iota_address, patient_iota_address)
+                .await
+                .context(current_fn!())?;
+
+            let pin = state.auth_state.session.pin().context(current_fn!())?;
+            let (guardian_pre_secret_key, _) =
+                get_pre_keys_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+            let ward_key_material = get_ward_key_material(
+                &state.move_call,
+                patient_iota_address,
+                &guardian_pre_secret_key,
+                *ward_iota_address,
+            )
+            .await
+            .context(current_fn!())?;
+
+            (
+                medical_metadata,
+                Some((guardian_pre_secret_key, ward_key_material)),
+            )
+        }
+        None => match state
+            .move_call
+            .get_medical_record(index, patient_iota_address)
+            .await
+            .context(current_fn!())
+            .map_err(PatientError::from)
+        {
+            Ok(medical_metadata) => (medical_metadata, None),
+            Err(err) if err.is_unreachable() => {
+                return get_cached_medical_record(&state, &record_index_key, index, version, err)
+            }
+            Err(err) => return Err(err),
+        },
+    };
+    let move_medical_metadata = medical_metadata.clone();
+
+    let medical_metadata: MedicalMetadata =
+        serde_deserialize_from_base64(medical_metadata.metadata)?;
+
+    // `None` selects the current version
+    let mut medical_metadata_versions = medical_metadata_versions(medical_metadata);
+    let version_count = medical_metadata_versions.len() as u64;
+    let version = version.unwrap_or(version_count - 1);
+
+    if version >= version_count {
+        return Err(anyhow!("Medical record version not found")
+            .context(current_fn!())
+            .into());
+    }
+
+    let medical_metadata = medical_metadata_versions.swap_remove(version as usize);
+    let is_current_version = version == version_count - 1;
+
+    // A guardian reads the record of its ward, `patient_iota_address` is the signed in patient
+    let source = MedicalDataSource {
+        guardian_iota_address: ward_iota_address.map(|_| patient_iota_address),
+        index,
+        iota_key_pair: &patient_iota_key_pair,
+        ipfs_gateway_fallback_base_urls: &keys_entry.settings.ipfs_gateway_fallback_base_urls,
+        patient_iota_address: ward_iota_address.unwrap_or(patient_iota_address),
+        version: Some(version),
+    };
+    let medical_record_key_nonce = match (&ward_keys, ward_iota_address) {
+        (Some((guardian_pre_secret_key, ward_key_material)), Some(ward_iota_address)) => {
+            get_ward_medical_record_key_nonce(
+                patient_iota_address,
+                &patient_iota_key_pair,
+                guardian_pre_secret_key,
+                index,
+                &medical_metadata,
+                Some(version),
+                ward_iota_address,
+                ward_key_material,
+            )
+            .await
+            .context(current_fn!())?
+        }
+        _ => decrypt_medical_record_key_nonce(&medical_metadata, &patient_pre_secret_key)
+            .context(current_fn!())?,
+    };
+    let medical_record_content =
+        match get_medical_data(&medical_metadata, &medical_record_key_nonce, &source).await {
+            Ok(medical_record_content) => medical_record_content,
+            Err(err)
+                if is_current_version && ward_iota_address.is_none() && err.is_unreachable() =>
+            {
+                return get_cached_medical_record(
+                    &state,
+                    &record_index_key,
+                    index,
+                    Some(version),
+                    err,
+                )
+            }
+            Err(err) => return Err(err),
+        };
+
+    // Replaced versions are not searchable, a failing index never blocks opening the record
+    if is_current_version && ward_iota_address.is_none() {
+        let record_index_entry = RecordIndexEntry {
+            created_at: medical_metadata.created_at.clone(),
+            created_by: medical_metadata.created_by.clone(),
+            index,
+            locator: Some(medical_metadata.locator.clone()),
+            medical_data: medical_record_content.clone(),
+            version,
+        };
+
+        if let Err(err) = index_medical_record(
+            &state,
+            &record_index_key,
+            record_index_entry,
+            move_medical_metadata,
+        ) {
+            println!("{:#?}", err);
+        }
+    }
+
+    let res_data = json!({
+        "createdAt": medical_metadata.created_at,
+        "createdBy": medical_metadata.created_by,
+        "lastSyncedAt": None::<String>,
+        "medicalData": medical_record_content,
+        "offline": false,
+        "version": version,
+        "versionCount": version_count,
+    });
+
+    Ok(SuccessResponse {
+        data: res_data,
+        status: ResponseStatus::Success,
+    })
+}
+
+/**
+* Revision timeline of a record, oldest first and the current version last.
+* A version is opened with `get_medical_record`.
+*/
+#[tauri::command]
+pub async fn get_medical_record_versions(
+    state: State<'_, Mutex<AppState>>,
+    index: u64,
+) -> Result<SuccessResponse<Vec<CommandGetMedicalRecordVersionsResponseData>>, PatientError> {
+    let state = state.lock().await;
+    let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+        .context(current_fn!())?;
+
+    let patient_iota_address =
+        get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
+
     let medical_metadata = state
         .move_call
         .get_medical_record(index, patient_iota_address)
         .await
         .context(current_fn!())?;
-
     let medical_metadata: MedicalMetadata =
-        serde_deserialize_from_base64(medical_metadata.metadata)?;
+        serde_deserialize_from_base64(medical_metadata.metadata).context(current_fn!())?;
+
+    let versions = medical_metadata_versions(medical_metadata)
+        .into_iter()
+        .enumerate()
+        .map(
+            |(version, medical_metadata)| CommandGetMedicalRecordVersionsResponseData {
+                created_at: medical_metadata.created_at,
+                created_by: medical_metadata.created_by,
+                version: version as u64,
+            },
+        )
+        .collect();
+
+    Ok(SuccessResponse {
+        data: versions,
+        status: ResponseStatus::Success,
+    })
+}
+
+/**
+ * Searches the local record index, only records opened on this device are indexed
+ * return: `{ entries, indexed_count }`, newest first
+ */
+#[tauri::command]
+pub async fn search_medical_records(
+    state: State<'_, Mutex<AppState>>,
+    filter: CommandSearchMedicalRecordsInput,
+) -> Result<SuccessResponse<Value>, PatientError> {
+    let mut state = state.lock().await;
+    let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+        .context(current_fn!())?;
+
+    let record_index_key = {
+        let pin = state.auth_state.session.pin().context(current_fn!())?;
+        let pre_seed = get_pre_seed_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+
+        derive_record_index_key(&pre_seed)
+    };
+    let record_index = load_record_index(
+        &active_record_index_path(&state).context(current_fn!())?,
+        &record_index_key,
+    )
+    .context(current_fn!())?;
+
+    let created_from = filter.created_from.as_deref().map(parse_date).transpose()?;
+    let created_until = filter
+        .created_until
+        .as_deref()
+        .map(parse_date)
+        .transpose()?;
+    let query_words = filter
+        .query
+        .as_deref()
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<String>>();
+
+    let mut entries = vec![];
+    for entry in &record_index.entries {
+        if filter
+            .created_by
+            .as_deref()
+            .is_some_and(|created_by| entry.created_by.as_deref() != Some(created_by))
+        {
+            continue;
+        }
+
+        let created_at = iso_to_local_date(&entry.created_at).context(current_fn!())?;
+        if created_from.is_some_and(|created_from| created_at < created_from)
+            || created_until.is_some_and(|created_until| created_at > created_until)
+        {
+            continue;
+        }
+
+        let fields = [
+            ("anamnesis", entry.medical_data.anamnesis.to_lowercase()),
+            (
+                "physical_check",
+                entry.medical_data.physical_check.to_lowercase(),
+            ),
+            (
+                "psychological_check",
+                entry.medical_data.psychological_check.to_lowercase(),
+            ),
+            ("diagnose", entry.medical_data.diagnose.to_lowercase()),
+            ("therapy", entry.medical_data.therapy.to_lowercase()),
+        ];
+        if !query_words
+            .iter()
+            .all(|word| fields.iter().any(|(_, field)| field.contains(word)))
+        {
+            continue;
+        }
+
+        let matched_fields = fields
+            .iter()
+            .filter(|(_, field)| query_words.iter().any(|word| field.contains(word)))
+            .map(|(name, _)| *name)
+            .collect::<Vec<&str>>();
+
+        entries.push((
+            entry.index,
+            json!({
+                "createdAt": entry.created_at,
+                "createdBy": entry.created_by,
+                "index": entry.index,
+                "matchedFields": matched_fields,
+            }),
+        ));
+    }
+    entries.sort_unstable_by(|(a, _), (b, _)| b.cmp(a));
 
+    Ok(SuccessResponse {
+        data: json!({
+            "entries": entries.into_iter().map(|(_, entry)| entry).collect::<Vec<Value>>(),
+            "indexed_count": record_index.entries.len(),
+        }),
+        status: ResponseStatus::Success,
+    })
+}
+
+fn active_record_index_path(state: &AppState) -> anyhow::Result<PathBuf> {
+    let profile_registry =
+        load_profile_registry(&state.profile_registry_entry).context(current_fn!())?;
+
+    Ok(record_index_path(
+        &state.record_index_dir,
+        &profile_registry.active_profile_id,
+    ))
+}
+
+/**
+ * Refreshes the offline cache in the record index, called on start and when the connection is
+ * back. Cached records whose current version changed are fetched again, a record failing to
+ * fetch keeps its cached content until the next sync.
+ */
+#[tauri::command]
+pub async fn sync_medical_records(
+    state: State<'_, Mutex<AppState>>,
+) -> Result<SuccessResponse<CommandSyncMedicalRecordsResponseData>, PatientError> {
+    let mut state = state.lock().await;
+    let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+        .context(current_fn!())?;
+
+    let (patient_iota_address, patient_iota_key_pair, patient_pre_secret_key, record_index_key) = {
+        let pin = state.auth_state.session.pin().context(current_fn!())?;
+        let patient_iota_address =
+            get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
+        let patient_iota_key_pair =
+            get_iota_key_pair_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+        let pre_seed = get_pre_seed_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+        let (patient_pre_secret_key, _) = compute_pre_keys(&pre_seed).context(current_fn!())?;
+
+        (
+            patient_iota_address,
+            patient_iota_key_pair,
+            patient_pre_secret_key,
+            derive_record_index_key(&pre_seed),
+        )
+    };
+
+    let mut medical_records: Vec<MovePatientMedicalMetadata> = vec![];
+    loop {
+        let medical_records_page = state
+            .move_call
+            .get_medical_records(
+                medical_records.len() as u64,
+                MOVE_PAGE_SIZE_MAX,
+                patient_iota_address,
+            )
+            .await
+            .context(current_fn!())?;
+
+        if medical_records_page.is_empty() {
+            break;
+        }
+        medical_records.extend(medical_records_page);
+    }
+
+    let path = active_record_index_path(&state).context(current_fn!())?;
+    let mut record_index = load_record_index(&path, &record_index_key).context(current_fn!())?;
+
+    let new_count = medical_records
+        .iter()
+        .filter(|metadata| {
+            record_index
+                .medical_metadata
+                .iter()
+                .all(|cached_metadata| cached_metadata.index != metadata.index)
+        })
+        .count() as u64;
+
+    let mut updated_count = 0;
+    for entry in record_index.entries.iter_mut() {
+        let Some(metadata) = medical_records
+            .iter()
+            .find(|metadata| metadata.index == entry.index)
+        else {
+            continue;
+        };
+
+        let medical_metadata: MedicalMetadata =
+            serde_deserialize_from_base64(metadata.metadata.clone()).context(current_fn!())?;
+        let mut medical_metadata_versions = medical_metadata_versions(medical_metadata);
+        let version = medical_metadata_versions.len() as u64 - 1;
+        let medical_metadata = medical_metadata_versions.swap_remove(version as usize);
+
+        if entry.locator.as_deref() == Some(medical_metadata.locator.as_str()) {
+            continue;
+        }
+
+        let source = MedicalDataSource {
+            guardian_iota_address: None,
+            index: entry.index,
+            iota_key_pair: &patient_iota_key_pair,
+            ipfs_gateway_fallback_base_urls: &keys_entry.settings.ipfs_gateway_fallback_base_urls,
+            patient_iota_address,
+            version: Some(version),
+        };
+        let medical_data =
+            match decrypt_medical_record_key_nonce(&medical_metadata, &patient_pre_secret_key) {
+                Ok(medical_record_key_nonce) => {
+                    get_medical_data(&medical_metadata, &medical_record_key_nonce, &source).await
+                }
+                Err(err) => Err(err.into()),
+            };
+        match medical_data {
+            Ok(medical_data) => {
+                *entry = RecordIndexEntry {
+                    created_at: medical_metadata.created_at,
+                    created_by: medical_metadata.created_by,
+                    index: entry.index,
+                    locator: Some(medical_metadata.locator),
+                    medical_data,
+                    version,
+                };
+                updated_count += 1;
+            }
+            Err(err) => println!("{:#?}", err),
+        }
+    }
+
+    let last_synced_at = Utc::now().to_rfc3339();
+    record_index.last_synced_at = Some(last_synced_at.clone());
+    record_index.medical_metadata = medical_records;
+
+    save_record_index(&path, &record_index_key, &record_index).context(current_fn!())?;
+
+    Ok(SuccessResponse {
+        data: CommandSyncMedicalRecordsResponseData {
+            last_synced_at,
+            new_count,
+            updated_count,
+        },
+        status: ResponseStatus::Success,
+    })
+}
+
+/**
+ * Adds the record to the local index of the active profile, replacing an older version of it.
+ * Its on-chain metadata is cached too, so the record is listed offline before the next sync.
+ */
+fn index_medical_record(
+    state: &AppState,
+    record_index_key: &[u8],
+    record_index_entry: RecordIndexEntry,
+    medical_metadata: MovePatientMedicalMetadata,
+) -> anyhow::Result<()> {
+    let path = active_record_index_path(state).context(current_fn!())?;
+    let mut record_index = load_record_index(&path, record_index_key).context(current_fn!())?;
+
+    record_index
+        .entries
+        .retain(|entry| entry.index != record_index_entry.index);
+    record_index.entries.push(record_index_entry);
+
+    record_index
+        .medical_metadata
+        .retain(|metadata| metadata.index != medical_metadata.index);
+    record_index.medical_metadata.push(medical_metadata);
+    record_index
+        .medical_metadata
+        .sort_unstable_by(|a, b| b.index.cmp(&a.index));
+
+    save_record_index(&path, record_index_key, &record_index).context(current_fn!())
+}
+
+/**
+ * Needs an unlocked session, the key is derived from the PRE seed
+ */
+fn load_active_record_index(state: &mut AppState) -> anyhow::Result<RecordIndex> {
+    let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+        .context(current_fn!())?;
+    let record_index_key = {
+        let pin = state.auth_state.session.pin().context(current_fn!())?;
+        let pre_seed = get_pre_seed_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+
+        derive_record_index_key(&pre_seed)
+    };
+
+    load_record_index(
+        &active_record_index_path(state).context(current_fn!())?,
+        &record_index_key,
+    )
+}
+
+/**
+ * Answers `get_medical_record` from the record index, `err` is returned when the record (version)
+ * was never opened on this device
+ */
+fn get_cached_medical_record(
+    state: &AppState,
+    record_index_key: &[u8],
+    index: u64,
+    version: Option<u64>,
+    err: PatientError,
+) -> Result<SuccessResponse<Value>, PatientError> {
+    let Ok(record_index) = load_record_index(
+        &active_record_index_path(state).context(current_fn!())?,
+        record_index_key,
+    ) else {
+        return Err(err);
+    };
+    let Some(entry) = record_index.entries.iter().find(|entry| {
+        entry.index == index && version.is_none_or(|version| version == entry.version)
+    }) else {
+        return Err(err);
+    };
+
+    Ok(SuccessResponse {
+        data: json!({
+            "createdAt": entry.created_at,
+            "createdBy": entry.created_by,
+            "lastSyncedAt": record_index.last_synced_at,
+            "medicalData": entry.medical_data,
+            "offline": true,
+            "version": entry.version,
+            // Older versions are not cached
+            "versionCount": entry.version + 1,
+        }),
+        status: ResponseStatus::Success,
+    })
+}
+
+/**
+ * Newest first, `cursor` counts records from the newest one
+ */
+async fn get_medical_records_range(
+    move_call: &MoveCall,
+    cursor: u64,
+    patient_iota_address: IotaAddress,
+    size: u64,
+    ward_iota_address: Option<&IotaAddress>,
+) -> Result<Vec<MovePatientMedicalMetadata>, PatientError> {
+    let mut medical_records: Vec<MovePatientMedicalMetadata> = vec![];
+    while (medical_records.len() as u64) < size {
+        let page_cursor = cursor + medical_records.len() as u64;
+        let page_size = MOVE_PAGE_SIZE_MAX.min(size - medical_records.len() as u64);
+        let medical_records_page = match ward_iota_address {
+            Some(ward_iota_address) => {
+                move_call
+                    .get_medical_records_as_guardian(
+                        page_cursor,
+                        ward_iota_address,
+                        page_size,
+                        patient_iota_address,
+                    )
+                    .await
+            }
+            None => {
+                move_call
+                    .get_medical_records(page_cursor, page_size, patient_iota_address)
+                    .await
+            }
+        }
+        .context(current_fn!())?;
+
+        if medical_records_page.is_empty() {
+            break;
+        }
+        medical_records.extend(medical_records_page);
+    }
+
+    Ok(medical_records)
+}
+
+/**
+ * Key of an own record version, a guardian gets the key of a ward's record from
+ * `get_ward_medical_record_key_nonce`
+ */
+pub fn decrypt_medical_record_key_nonce(
+    medical_metadata: &MedicalMetadataVersion,
+    pre_secret_key: &SecretKey,
+) -> anyhow::Result<KeyNonce> {
     let medical_record_key_nonce = decrypt_original(
-        &patient_pre_secret_key,
-        &serde_deserialize_from_base64(medical_metadata.capsule).context(current_fn!())?,
+        pre_secret_key,
+        &serde_deserialize_from_base64(medical_metadata.capsule.clone()).context(current_fn!())?,
         &STANDARD
-            .decode(medical_metadata.enc_key_and_nonce)
+            .decode(&medical_metadata.enc_key_and_nonce)
             .context(current_fn!())?,
     )
+    .map(Zeroizing::new)
     .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
-    let medical_record_key_nonce: KeyNonce =
-        serde_json::from_slice(&medical_record_key_nonce).context(current_fn!())?;
 
-    let medical_record_content = get_data_ipfs(medical_metadata.cid)
+    serde_json::from_slice(&medical_record_key_nonce).context(current_fn!())
+}
+
+/**
+ * Fetches and decrypts the content of a record version
+ */
+pub async fn get_medical_data(
+    medical_metadata: &MedicalMetadataVersion,
+    medical_record_key_nonce: &KeyNonce,
+    source: &MedicalDataSource<'_>,
+) -> Result<MedicalData, PatientError> {
+    // IPFS is read from the gateways directly, the other backends only through the proxy
+    let medical_record_content = match medical_metadata.backend {
+        BlobStoreBackend::IpfsCluster | BlobStoreBackend::Kubo => get_data_ipfs(
+            medical_metadata.locator.clone(),
+            source.ipfs_gateway_fallback_base_urls,
+        )
         .await
-        .context(current_fn!())?;
-    let medical_record_content = aes_decrypt(
-        &STANDARD
-            .decode(medical_record_content)
-            .context(current_fn!())?,
-        &STANDARD
-            .decode(medical_record_key_nonce.key)
-            .context(current_fn!())?,
+        .context(current_fn!())?,
+        BlobStoreBackend::LocalFs | BlobStoreBackend::S3 => {
+            let req_client = reqwest::Client::new();
+            let signer_iota_address = source
+                .guardian_iota_address
+                .unwrap_or(source.patient_iota_address);
+
+            let payload = ProxyReencryptionMedicalRecordContentPayload {
+                guardian_iota_address: source
+                    .guardian_iota_address
+                    .map(|guardian_iota_address| guardian_iota_address.to_string()),
+                index: source.index,
+                patient_iota_address: source.patient_iota_address.to_string(),
+                signature: sign_proxy_nonce(&req_client, signer_iota_address, source.iota_key_pair)
+                    .await
+                    .context(current_fn!())?,
+                version: source.version,
+            };
+            do_http_post_json_request_stream::<_, ProxyReencryptionErrorResponse>(
+                &format!("{}/medical-record/content/patient", PROXY_BASE_URL),
+                &payload,
+                &req_client,
+                StatusCode::OK,
+            )
+            .await
+            .context(current_fn!())?
+        }
+    };
+    let mut medical_data = Zeroizing::new(Vec::new());
+    aes_stream_decrypt_response(
+        medical_record_content,
+        &Zeroizing::new(
+            STANDARD
+                .decode(&medical_record_key_nonce.key)
+                .context(current_fn!())?,
+        ),
         &STANDARD
-            .decode(medical_record_key_nonce.nonce)
+            .decode(&medical_record_key_nonce.nonce)
             .context(current_fn!())?,
+        medical_metadata.sha256.as_deref(),
+        &mut *medical_data,
     )
+    .await
     .context(current_fn!())?;
-    let medical_record_content: MedicalData =
-        serde_json::from_slice(&medical_record_content).context(current_fn!())?;
-
-    let res_data = json!({
-        "createdAt": medical_metadata.created_at,
-        "medicalData": medical_record_content,
-    });
 
-    Ok(SuccessResponse {
-        data: res_data,
-        status: ResponseStatus::Success,
-    })
+    Ok(serde_json::from_slice(&medical_data).context(current_fn!())?)
 }
@@ -1,10 +1,17 @@
 mod access;
+mod backup;
 mod constants;
+mod emergency;
+mod fhir;
+mod guardian;
 mod home;
 mod macros;
 mod move_call;
+mod notification;
 mod patient_error;
+mod profile;
 mod scan;
+mod session;
 mod shared_cmds;
 mod signin;
 mod signout;
@@ -14,19 +21,22 @@ mod utils;
 
 use anyhow::Context;
 use constants::{
-    DECMED_ADDRESS_ID_OBJECT_ID, DECMED_ADDRESS_ID_OBJECT_VERSION, DECMED_GLOBAL_ADMIN_CAP_ID,
-    DECMED_HOSPITAL_ID_METADATA_OBJECT_ID, DECMED_HOSPITAL_ID_METADATA_OBJECT_VERSION,
-    DECMED_HOSPITAL_PERSONNEL_ID_ACCOUNT_OBJECT_ID,
+    ACCESS_EVENT_CHECK_INTERVAL, DECMED_ADDRESS_ID_OBJECT_ID, DECMED_ADDRESS_ID_OBJECT_VERSION,
+    DECMED_GLOBAL_ADMIN_CAP_ID, DECMED_HOSPITAL_ID_METADATA_OBJECT_ID,
+    DECMED_HOSPITAL_ID_METADATA_OBJECT_VERSION, DECMED_HOSPITAL_PERSONNEL_ID_ACCOUNT_OBJECT_ID,
     DECMED_HOSPITAL_PERSONNEL_ID_ACCOUNT_OBJECT_VERSION, DECMED_MODULE_ADMIN,
     DECMED_MODULE_PATIENT, DECMED_PACKAGE_ID, DECMED_PATIENT_ID_ACCOUNT_OBJECT_ID,
-    DECMED_PATIENT_ID_ACCOUNT_OBJECT_VERSION,
+    DECMED_PATIENT_ID_ACCOUNT_OBJECT_VERSION, PROFILE_REGISTRY_SERVICE, PROFILE_REGISTRY_USER,
+    RECORD_INDEX_DIR, SCHEDULED_REVOCATION_CHECK_INTERVAL, SESSION_LOCK_CHECK_INTERVAL,
 };
 use iota_types::{base_types::ObjectID, Identifier};
 use keyring::Entry;
 use move_call::MoveCall;
-use std::str::FromStr;
+use session::Session;
+use std::{collections::HashMap, str::FromStr, thread, time::Duration};
 use tauri::{async_runtime::Mutex, Manager};
-use types::{AppState, AuthState, DecmedPackage, KeysEntry, ScanState, SignInState, SignUpState};
+use types::{AppState, AuthState, DecmedPackage, ScanState, SignInState, SignUpState};
+use utils::{load_profile_registry, open_profile_keys_entry, open_profile_pin_attempts_entry};
 
 fn setup(app: &mut tauri::App) -> std::result::Result<(), Box<dyn std::error::Error>> {
     // #[cfg(target_os = "android")]
@@ -56,8 +66,18 @@ fn setup(app: &mut tauri::App) -> std::result::Result<(), Box<dyn std::error::Er
     //
     //
 
+    let profile_registry_entry =
+        Entry::new(PROFILE_REGISTRY_SERVICE, PROFILE_REGISTRY_USER).context(current_fn!())?;
+    let profile_registry = load_profile_registry(&profile_registry_entry).context(current_fn!())?;
     let keys_entry =
-        Entry::new("decmed_patient_service_keys", "decmed_patient").context(current_fn!())?;
+        open_profile_keys_entry(&profile_registry.active_profile_id).context(current_fn!())?;
+    let pin_attempts_entry = open_profile_pin_attempts_entry(&profile_registry.active_profile_id)
+        .context(current_fn!())?;
+    let record_index_dir = app
+        .path()
+        .app_local_data_dir()
+        .context(current_fn!())?
+        .join(RECORD_INDEX_DIR);
 
     let decmed_package = DecmedPackage {
         package_id: ObjectID::from_str(DECMED_PACKAGE_ID)?,
@@ -78,23 +98,6 @@ fn setup(app: &mut tauri::App) -> std::result::Result<(), Box<dyn std::error::Er
 
         global_admin_cap_id: ObjectID::from_str(DECMED_GLOBAL_ADMIN_CAP_ID)?,
     };
-    let new_keys_entry = KeysEntry {
-        admin_secret_key: Some(String::from(
-            "iotaprivkey1qpfc5nqsvs64p40347h0vcdxz3pgfn72uznw4pfvkak59fhpevxs73z6kwn",
-        )),
-        admin_address: Some(String::from(
-            "0x52a65ae806223e49aaff1cf7f670fee87c1767de1d200a661c1fee44a61fc37f",
-        )),
-        activation_key: None,
-        id: None,
-        iota_address: None,
-        iota_key_pair: None,
-        iota_nonce: None,
-        pre_nonce: None,
-        proxy_jwt: None,
-        pre_public_key: None,
-        pre_secret_key: None,
-    };
     let signin_state = SignInState { pin: None };
     let signup_state = SignUpState {
         seed_words: None,
@@ -103,51 +106,99 @@ fn setup(app: &mut tauri::App) -> std::result::Result<(), Box<dyn std::error::Er
     let auth_state = AuthState {
         is_registered: false,
         role: None,
-        session_pin: Some("123456".to_string()),
+        session: Session::default(),
     };
     let move_call = MoveCall { decmed_package };
     let scan_state = ScanState {
-        hospital_personnel_qr_content: None,
+        access_request_id: None,
+        access_requests: HashMap::new(),
+        hospital_personnel_qr: None,
+        hospital_personnel_role: None,
     };
 
-    match keys_entry.get_secret() {
-        Ok(_) => {
-            // let new_keys_entry = serde_json::to_vec(&new_keys_entry).context(current_fn!())?;
-            // keys_entry
-            //     .set_secret(&new_keys_entry)
-            //     .context(current_fn!())?;
-        }
-        Err(err @ keyring::Error::NoEntry) => {
-            let new_keys_entry = serde_json::to_vec(&new_keys_entry).context(current_fn!())?;
-            keys_entry
-                .set_secret(&new_keys_entry)
-                .context(current_fn!())?;
-
-            println!("{:#?}", err);
-        }
-        Err(err) => {
-            println!("{:#?}", err);
-        }
-    }
-
     app.manage(Mutex::new(AppState {
         administrative_data: None,
         auth_state,
         keys_entry,
         move_call,
+        pin_attempts_entry,
+        profile_registry_entry,
+        record_index_dir,
         scan_state,
         signin_state,
         signup_state,
     }));
 
+    // Bundles register `decmed://` on install, development and portable builds only at runtime
+    #[cfg(any(windows, target_os = "linux"))]
+    {
+        use tauri_plugin_deep_link::DeepLinkExt;
+        app.deep_link().register_all()?;
+    }
+
+    // Idle sessions are locked even when no command comes in
+    let app_handle = app.handle().clone();
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(SESSION_LOCK_CHECK_INTERVAL));
+
+        let state = app_handle.state::<Mutex<AppState>>();
+        tauri::async_runtime::block_on(async {
+            state.lock().await.auth_state.session.lock_if_idle();
+        });
+    });
+
+    // Due scheduled revocations are executed even when no command comes in
+    let app_handle = app.handle().clone();
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(SCHEDULED_REVOCATION_CHECK_INTERVAL));
+
+        let state = app_handle.state::<Mutex<AppState>>();
+        tauri::async_runtime::block_on(async {
+            if let Err(err) = access::execute_scheduled_revocations(&app_handle, &state).await {
+                println!("{:#?}", err);
+            }
+        });
+    });
+
+    // New access events are notified even when no command comes in
+    let app_handle = app.handle().clone();
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(ACCESS_EVENT_CHECK_INTERVAL));
+
+        let state = app_handle.state::<Mutex<AppState>>();
+        tauri::async_runtime::block_on(async {
+            if let Err(err) = notification::watch_access_events(&app_handle, &state).await {
+                println!("{:#?}", err);
+            }
+        });
+    });
+
     Ok(())
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    tauri::Builder::default()
+    let mut builder = tauri::Builder::default();
+
+    // Registered first: on Windows and Linux a `decmed://` link opens a new process, the URL is
+    // forwarded to the running instance through the deep link `onOpenUrl` event instead
+    #[cfg(desktop)]
+    {
+        builder = builder.plugin(tauri_plugin_single_instance::init(|app, _argv, _cwd| {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.unminimize();
+                let _ = window.set_focus();
+            }
+        }));
+    }
+
+    builder
         .setup(setup)
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_deep_link::init())
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_fs::init())
         .invoke_handler(tauri::generate_handler![
             signin::signin,
             signup::generate_mnemonic,
@@ -160,12 +211,44 @@ pub fn run() {
             shared_cmds::get_profile,
             shared_cmds::validate_seed_words,
             shared_cmds::update_profile,
+            shared_cmds::lock_session,
+            shared_cmds::unlock_session,
+            shared_cmds::get_settings,
+            shared_cmds::update_settings,
+            shared_cmds::update_ipfs_gateway_settings,
+            shared_cmds::update_pin_wipe_settings,
+            shared_cmds::update_notification_settings,
             scan::process_qr,
+            scan::process_access_code,
             scan::create_access,
+            scan::get_access_requests,
+            scan::process_access_request,
+            scan::reject_access_request,
             home::get_medical_records,
             home::get_medical_record,
+            home::get_medical_record_versions,
+            home::search_medical_records,
+            home::sync_medical_records,
             access::revoke_access,
             access::get_access_log,
+            access::revoke_all_access,
+            access::schedule_revocation,
+            access::cancel_scheduled_revocation,
+            backup::export_backup,
+            backup::import_backup,
+            guardian::get_guardian_code,
+            guardian::add_guardian,
+            guardian::remove_guardian,
+            guardian::get_guardians,
+            guardian::get_wards,
+            emergency::get_emergency_access,
+            emergency::set_emergency_access,
+            emergency::acknowledge_emergency_access,
+            fhir::export_fhir_bundle,
+            profile::list_profiles,
+            profile::add_profile,
+            profile::switch_profile,
+            profile::remove_profile,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -13,17 +13,19 @@ use crate::{
     current_fn,
     patient_error::PatientError,
     types::{
-        DecmedPackage, MovePatientAccessLog, MovePatientAdministrativeMetadata,
+        DecmedPackage, HospitalPersonnelRole, MovePatientAccessLog,
+        MovePatientAdministrativeMetadata, MovePatientEmergencyAccessLog, MovePatientGuardian,
         MovePatientMedicalMetadata,
     },
     utils::{
-        construct_pt, construct_shared_object_call_arg, construct_sponsored_tx_data, execute_tx,
-        get_iota_client, get_ref_gas_price, handle_error_execute_tx,
-        handle_error_move_call_read_only, move_call_read_only, parse_move_read_only_result,
-        reserve_gas,
+        construct_pt, construct_pt_batch, construct_shared_object_call_arg,
+        construct_sponsored_tx_data, execute_tx, get_iota_client, get_ref_gas_price,
+        handle_error_execute_tx, handle_error_move_call_read_only, move_call_read_only,
+        parse_move_read_only_result, reserve_gas,
     },
 };
 
+#[derive(Clone)]
 pub struct MoveCall {
     pub decmed_package: DecmedPackage,
 }
@@ -72,7 +74,11 @@ impl MoveCall {
     pub async fn create_access(
         &self,
         date: String,
+        exp_dur_read: u64,
+        exp_dur_update: u64,
         hospital_personnel_address: &IotaAddress,
+        is_medical_read: bool,
+        medical_metadata_scope: Option<Vec<u64>>,
         metadata: Vec<String>,
         sender: IotaAddress,
         sender_key_pair: IotaKeyPair,
@@ -87,9 +93,13 @@ impl MoveCall {
                 self.construct_address_id_object_call_arg(false),
                 self.construct_clock_call_arg(),
                 CallArg::Pure(bcs::to_bytes(&date).context(current_fn!())?),
+                CallArg::Pure(bcs::to_bytes(&exp_dur_read).context(current_fn!())?),
+                CallArg::Pure(bcs::to_bytes(&exp_dur_update).context(current_fn!())?),
                 self.construct_hospital_id_metadata_object_call_arg(false),
                 CallArg::Pure(bcs::to_bytes(hospital_personnel_address).context(current_fn!())?),
                 self.construct_hospital_personnel_id_account_object_call_arg(true),
+                CallArg::Pure(bcs::to_bytes(&is_medical_read).context(current_fn!())?),
+                CallArg::Pure(bcs::to_bytes(&medical_metadata_scope).context(current_fn!())?),
                 CallArg::Pure(bcs::to_bytes(&metadata).context(current_fn!())?),
                 self.construct_patient_id_account_object_call_arg(true),
             ],
@@ -124,6 +134,175 @@ impl MoveCall {
         Ok(())
     }
 
+    pub async fn add_guardian(
+        &self,
+        can_grant: bool,
+        can_read: bool,
+        can_revoke: bool,
+        guardian_address: &IotaAddress,
+        metadata: Option<String>,
+        sender: IotaAddress,
+        sender_key_pair: IotaKeyPair,
+    ) -> Result<(), PatientError> {
+        let iota_client = get_iota_client().await.context(current_fn!())?;
+        let pt = construct_pt(
+            String::from("add_guardian"),
+            self.decmed_package.package_id,
+            self.decmed_package.module_patient.clone(),
+            vec![],
+            vec![
+                self.construct_address_id_object_call_arg(false),
+                CallArg::Pure(bcs::to_bytes(&can_grant).context(current_fn!())?),
+                CallArg::Pure(bcs::to_bytes(&can_read).context(current_fn!())?),
+                CallArg::Pure(bcs::to_bytes(&can_revoke).context(current_fn!())?),
+                CallArg::Pure(bcs::to_bytes(guardian_address).context(current_fn!())?),
+                CallArg::Pure(bcs::to_bytes(&metadata).context(current_fn!())?),
+                self.construct_patient_id_account_object_call_arg(true),
+            ],
+        )
+        .context(current_fn!())?;
+
+        let (sponsor_account, reservation_id, gas_coins) = reserve_gas(NANOS_PER_IOTA * 2, 10)
+            .await
+            .context(current_fn!())?;
+        let ref_gas_price = get_ref_gas_price(&iota_client)
+            .await
+            .context(current_fn!())?;
+
+        let tx_data = construct_sponsored_tx_data(
+            sender,
+            gas_coins,
+            pt,
+            GAS_BUDGET,
+            ref_gas_price,
+            sponsor_account,
+        );
+
+        let signer = sender_key_pair;
+        let tx = Transaction::from_data_and_signer(tx_data, vec![&signer]);
+
+        let response = execute_tx(tx, reservation_id)
+            .await
+            .context(current_fn!())?;
+
+        handle_error_execute_tx(response).context(current_fn!())?;
+
+        Ok(())
+    }
+
+    pub async fn acknowledge_emergency_access(
+        &self,
+        index: u64,
+        sender: IotaAddress,
+        sender_key_pair: IotaKeyPair,
+    ) -> Result<(), PatientError> {
+        let iota_client = get_iota_client().await.context(current_fn!())?;
+        let pt = construct_pt(
+            String::from("acknowledge_emergency_access"),
+            self.decmed_package.package_id,
+            self.decmed_package.module_patient.clone(),
+            vec![],
+            vec![
+                self.construct_address_id_object_call_arg(false),
+                CallArg::Pure(bcs::to_bytes(&index).context(current_fn!())?),
+                self.construct_patient_id_account_object_call_arg(true),
+            ],
+        )
+        .context(current_fn!())?;
+
+        let (sponsor_account, reservation_id, gas_coins) = reserve_gas(NANOS_PER_IOTA * 2, 10)
+            .await
+            .context(current_fn!())?;
+        let ref_gas_price = get_ref_gas_price(&iota_client)
+            .await
+            .context(current_fn!())?;
+
+        let tx_data = construct_sponsored_tx_data(
+            sender,
+            gas_coins,
+            pt,
+            GAS_BUDGET,
+            ref_gas_price,
+            sponsor_account,
+        );
+
+        let signer = sender_key_pair;
+        let tx = Transaction::from_data_and_signer(tx_data, vec![&signer]);
+
+        let response = execute_tx(tx, reservation_id)
+            .await
+            .context(current_fn!())?;
+
+        handle_error_execute_tx(response).context(current_fn!())?;
+
+        Ok(())
+    }
+
+    pub async fn create_access_as_guardian(
+        &self,
+        date: String,
+        exp_dur_read: u64,
+        exp_dur_update: u64,
+        hospital_personnel_address: &IotaAddress,
+        is_medical_read: bool,
+        medical_metadata_scope: Option<Vec<u64>>,
+        metadata: Vec<String>,
+        patient_address: &IotaAddress,
+        sender: IotaAddress,
+        sender_key_pair: IotaKeyPair,
+    ) -> Result<(), PatientError> {
+        let iota_client = get_iota_client().await.context(current_fn!())?;
+        let pt = construct_pt(
+            "create_access_as_guardian".to_string(),
+            self.decmed_package.package_id,
+            self.decmed_package.module_patient.clone(),
+            vec![],
+            vec![
+                self.construct_address_id_object_call_arg(false),
+                self.construct_clock_call_arg(),
+                CallArg::Pure(bcs::to_bytes(&date).context(current_fn!())?),
+                CallArg::Pure(bcs::to_bytes(&exp_dur_read).context(current_fn!())?),
+                CallArg::Pure(bcs::to_bytes(&exp_dur_update).context(current_fn!())?),
+                self.construct_hospital_id_metadata_object_call_arg(false),
+                CallArg::Pure(bcs::to_bytes(hospital_personnel_address).context(current_fn!())?),
+                self.construct_hospital_personnel_id_account_object_call_arg(true),
+                CallArg::Pure(bcs::to_bytes(&is_medical_read).context(current_fn!())?),
+                CallArg::Pure(bcs::to_bytes(&medical_metadata_scope).context(current_fn!())?),
+                CallArg::Pure(bcs::to_bytes(&metadata).context(current_fn!())?),
+                CallArg::Pure(bcs::to_bytes(patient_address).context(current_fn!())?),
+                self.construct_patient_id_account_object_call_arg(true),
+            ],
+        )
+        .context(current_fn!())?;
+
+        let (sponsor_account, reservation_id, gas_coins) = reserve_gas(NANOS_PER_IOTA * 5, 10)
+            .await
+            .context(current_fn!())?;
+        let ref_gas_price = get_ref_gas_price(&iota_client)
+            .await
+            .context(current_fn!())?;
+
+        let tx_data = construct_sponsored_tx_data(
+            sender,
+            gas_coins,
+            pt,
+            GAS_BUDGET,
+            ref_gas_price,
+            sponsor_account,
+        );
+
+        let signer = sender_key_pair;
+        let tx = Transaction::from_data_and_signer(tx_data, vec![&signer]);
+
+        let response = execute_tx(tx, reservation_id)
+            .await
+            .context(current_fn!())?;
+
+        handle_error_execute_tx(response).context(current_fn!())?;
+
+        Ok(())
+    }
+
     pub async fn is_account_registered(&self, sender: IotaAddress) -> Result<bool, PatientError> {
         let iota_client = get_iota_client().await.context(current_fn!())?;
         let pt = construct_pt(
@@ -204,7 +383,7 @@ impl MoveCall {
         &self,
         hospital_personnel_iota_address: &IotaAddress,
         sender: IotaAddress,
-    ) -> Result<(String, String), PatientError> {
+    ) -> Result<(String, String, HospitalPersonnelRole), PatientError> {
         let iota_client = get_iota_client().await.context(current_fn!())?;
         let pt = construct_pt(
             "get_hospital_personnel_info".to_string(),
@@ -230,11 +409,14 @@ impl MoveCall {
         let hospital_personnel_public_administrative_metadata: String =
             parse_move_read_only_result(response.clone(), 0).context(current_fn!())?;
         let hospital_name: String =
-            parse_move_read_only_result(response, 1).context(current_fn!())?;
+            parse_move_read_only_result(response.clone(), 1).context(current_fn!())?;
+        let hospital_personnel_role: HospitalPersonnelRole =
+            parse_move_read_only_result(response, 2).context(current_fn!())?;
 
         Ok((
             hospital_personnel_public_administrative_metadata,
             hospital_name,
+            hospital_personnel_role,
         ))
     }
 
@@ -271,6 +453,135 @@ impl MoveCall {
         Ok(access_log)
     }
 
+    pub async fn get_access_log_as_guardian(
+        &self,
+        cursor: u64,
+        patient_address: &IotaAddress,
+        size: u64,
+        sender: IotaAddress,
+    ) -> Result<Vec<MovePatientAccessLog>, PatientError> {
+        let iota_client = get_iota_client().await.context(current_fn!())?;
+        let pt = construct_pt(
+            "get_access_log_as_guardian".to_string(),
+            self.decmed_package.package_id,
+            self.decmed_package.module_patient.clone(),
+            vec![],
+            vec![
+                self.construct_address_id_object_call_arg(false),
+                CallArg::Pure(bcs::to_bytes(&cursor).context(current_fn!())?),
+                CallArg::Pure(bcs::to_bytes(patient_address).context(current_fn!())?),
+                self.construct_patient_id_account_object_call_arg(false),
+                CallArg::Pure(bcs::to_bytes(&size).context(current_fn!())?),
+            ],
+        )
+        .context(current_fn!())?;
+
+        let response = move_call_read_only(sender, &iota_client, pt)
+            .await
+            .context(current_fn!())?;
+
+        handle_error_move_call_read_only(response.clone()).context(current_fn!())?;
+
+        let access_log: Vec<MovePatientAccessLog> =
+            parse_move_read_only_result(response.clone(), 0)?;
+
+        Ok(access_log)
+    }
+
+    pub async fn get_guardians(
+        &self,
+        sender: IotaAddress,
+    ) -> Result<Vec<MovePatientGuardian>, PatientError> {
+        let iota_client = get_iota_client().await.context(current_fn!())?;
+        let pt = construct_pt(
+            "get_guardians".to_string(),
+            self.decmed_package.package_id,
+            self.decmed_package.module_patient.clone(),
+            vec![],
+            vec![
+                self.construct_address_id_object_call_arg(false),
+                self.construct_patient_id_account_object_call_arg(false),
+            ],
+        )
+        .context(current_fn!())?;
+
+        let response = move_call_read_only(sender, &iota_client, pt)
+            .await
+            .context(current_fn!())?;
+        handle_error_move_call_read_only(response.clone()).context(current_fn!())?;
+
+        let guardians: Vec<MovePatientGuardian> =
+            parse_move_read_only_result(response.clone(), 0).context(current_fn!())?;
+
+        Ok(guardians)
+    }
+
+    /**
+     * return: ward address with the guardian entry of the sender
+     */
+    pub async fn get_wards(
+        &self,
+        sender: IotaAddress,
+    ) -> Result<Vec<(IotaAddress, MovePatientGuardian)>, PatientError> {
+        let iota_client = get_iota_client().await.context(current_fn!())?;
+        let pt = construct_pt(
+            "get_wards".to_string(),
+            self.decmed_package.package_id,
+            self.decmed_package.module_patient.clone(),
+            vec![],
+            vec![
+                self.construct_address_id_object_call_arg(false),
+                self.construct_patient_id_account_object_call_arg(false),
+            ],
+        )
+        .context(current_fn!())?;
+
+        let response = move_call_read_only(sender, &iota_client, pt)
+            .await
+            .context(current_fn!())?;
+        handle_error_move_call_read_only(response.clone()).context(current_fn!())?;
+
+        let ward_addresses: Vec<IotaAddress> =
+            parse_move_read_only_result(response.clone(), 0).context(current_fn!())?;
+        let guardians: Vec<MovePatientGuardian> =
+            parse_move_read_only_result(response, 1).context(current_fn!())?;
+
+        Ok(ward_addresses.into_iter().zip(guardians).collect())
+    }
+
+    /**
+     * return: `(is_enabled, unacknowledged emergency access logs)`
+     */
+    pub async fn get_emergency_access(
+        &self,
+        sender: IotaAddress,
+    ) -> Result<(bool, Vec<MovePatientEmergencyAccessLog>), PatientError> {
+        let iota_client = get_iota_client().await.context(current_fn!())?;
+        let pt = construct_pt(
+            "get_emergency_access".to_string(),
+            self.decmed_package.package_id,
+            self.decmed_package.module_patient.clone(),
+            vec![],
+            vec![
+                self.construct_address_id_object_call_arg(false),
+                self.construct_patient_id_account_object_call_arg(false),
+            ],
+        )
+        .context(current_fn!())?;
+
+        let response = move_call_read_only(sender, &iota_client, pt)
+            .await
+            .context(current_fn!())?;
+        handle_error_move_call_read_only(response.clone()).context(current_fn!())?;
+
+        let is_enabled: bool =
+            parse_move_read_only_result(response.clone(), 0).context(current_fn!())?;
+        let emergency_access_log: Vec<MovePatientEmergencyAccessLog> =
+            parse_move_read_only_result(response, 1).context(current_fn!())?;
+
+        Ok((is_enabled, emergency_access_log))
+    }
+
     pub async fn get_medical_records(
         &self,
         cursor: u64,
@@ -304,6 +615,41 @@ impl MoveCall {
         Ok(medical_metadata)
     }
 
+    pub async fn get_medical_records_as_guardian(
+        &self,
+        cursor: u64,
+        patient_address: &IotaAddress,
+        size: u64,
+        sender: IotaAddress,
+    ) -> Result<Vec<MovePatientMedicalMetadata>, PatientError> {
+        let iota_client = get_iota_client().await.context(current_fn!())?;
+        let pt = construct_pt(
+            "get_medical_records_as_guardian".to_string(),
+            self.decmed_package.package_id,
+            self.decmed_package.module_patient.clone(),
+            vec![],
+            vec![
+                self.construct_address_id_object_call_arg(false),
+                CallArg::Pure(bcs::to_bytes(&cursor).context(current_fn!())?),
+                CallArg::Pure(bcs::to_bytes(patient_address).context(current_fn!())?),
+                self.construct_patient_id_account_object_call_arg(false),
+                CallArg::Pure(bcs::to_bytes(&size).context(current_fn!())?),
+            ],
+        )
+        .context(current_fn!())?;
+
+        let response = move_call_read_only(sender, &iota_client, pt)
+            .await
+            .context(current_fn!())?;
+
+        handle_error_move_call_read_only(response.clone()).context(current_fn!())?;
+
+        let medical_metadata: Vec<MovePatientMedicalMetadata> =
+            parse_move_read_only_result(response.clone(), 0)?;
+
+        Ok(medical_metadata)
+    }
+
     pub async fn get_medical_record(
         &self,
         index: u64,
@@ -334,6 +680,88 @@ impl MoveCall {
         Ok(medical_metadata)
     }
 
+    pub async fn get_medical_record_as_guardian(
+        &self,
+        index: u64,
+        patient_address: &IotaAddress,
+        sender: IotaAddress,
+    ) -> Result<MovePatientMedicalMetadata, PatientError> {
+        let iota_client = get_iota_client().await.context(current_fn!())?;
+        let pt = construct_pt(
+            "get_medical_record_as_guardian".to_string(),
+            self.decmed_package.package_id,
+            self.decmed_package.module_patient.clone(),
+            vec![],
+            vec![
+                self.construct_address_id_object_call_arg(false),
+                CallArg::Pure(bcs::to_bytes(&index).context(current_fn!())?),
+                CallArg::Pure(bcs::to_bytes(patient_address).context(current_fn!())?),
+                self.construct_patient_id_account_object_call_arg(false),
+            ],
+        )
+        .context(current_fn!())?;
+
+        let response = move_call_read_only(sender, &iota_client, pt)
+            .await
+            .context(current_fn!())?;
+        handle_error_move_call_read_only(response.clone()).context(current_fn!())?;
+
+        let medical_metadata: MovePatientMedicalMetadata =
+            parse_move_read_only_result(response.clone(), 0).context(current_fn!())?;
+
+        Ok(medical_metadata)
+    }
+
+    pub async fn remove_guardian(
+        &self,
+        guardian_address: &IotaAddress,
+        patient_address: &IotaAddress,
+        sender: IotaAddress,
+        sender_key_pair: IotaKeyPair,
+    ) -> Result<(), PatientError> {
+        let iota_client = get_iota_client().await.context(current_fn!())?;
+        let pt = construct_pt(
+            String::from("remove_guardian"),
+            self.decmed_package.package_id,
+            self.decmed_package.module_patient.clone(),
+            vec![],
+            vec![
+                self.construct_address_id_object_call_arg(false),
+                CallArg::Pure(bcs::to_bytes(guardian_address).context(current_fn!())?),
+                CallArg::Pure(bcs::to_bytes(patient_address).context(current_fn!())?),
+                self.construct_patient_id_account_object_call_arg(true),
+            ],
+        )
+        .context(current_fn!())?;
+
+        let (sponsor_account, reservation_id, gas_coins) = reserve_gas(NANOS_PER_IOTA * 2, 10)
+            .await
+            .context(current_fn!())?;
+        let ref_gas_price = get_ref_gas_price(&iota_client)
+            .await
+            .context(current_fn!())?;
+
+        let tx_data = construct_sponsored_tx_data(
+            sender,
+            gas_coins,
+            pt,
+            GAS_BUDGET,
+            ref_gas_price,
+            sponsor_account,
+        );
+
+        let signer = sender_key_pair;
+        let tx = Transaction::from_data_and_signer(tx_data, vec![&signer]);
+
+        let response = execute_tx(tx, reservation_id)
+            .await
+            .context(current_fn!())?;
+
+        handle_error_execute_tx(response).context(current_fn!())?;
+
+        Ok(())
+    }
+
     pub async fn revoke_access(
         &self,
         hospital_personnel_address: IotaAddress,
@@ -384,6 +812,163 @@ impl MoveCall {
         Ok(())
     }
 
+    pub async fn revoke_access_batch(
+        &self,
+        accesses: &[(IotaAddress, u64)],
+        sender: IotaAddress,
+        sender_key_pair: &IotaKeyPair,
+    ) -> Result<(), PatientError> {
+        let iota_client = get_iota_client().await.context(current_fn!())?;
+        let call_args_batch = accesses
+            .iter()
+            .map(|(hospital_personnel_address, index)| {
+                Ok(vec![
+                    self.construct_address_id_object_call_arg(true),
+                    CallArg::Pure(
+                        bcs::to_bytes(hospital_personnel_address).context(current_fn!())?,
+                    ),
+                    self.construct_hospital_personnel_id_account_object_call_arg(true),
+                    CallArg::Pure(bcs::to_bytes(index).context(current_fn!())?),
+                    self.construct_patient_id_account_object_call_arg(true),
+                ])
+            })
+            .collect::<anyhow::Result<Vec<Vec<CallArg>>>>()?;
+        let pt = construct_pt_batch(
+            String::from("revoke_access"),
+            self.decmed_package.package_id,
+            self.decmed_package.module_patient.clone(),
+            vec![],
+            call_args_batch,
+        )
+        .context(current_fn!())?;
+        let (sponsor_account, reservation_id, gas_coins) = reserve_gas(NANOS_PER_IOTA * 2, 10)
+            .await
+            .context(current_fn!())?;
+        let ref_gas_price = get_ref_gas_price(&iota_client)
+            .await
+            .context(current_fn!())?;
+
+        let tx_data = construct_sponsored_tx_data(
+            sender,
+            gas_coins,
+            pt,
+            GAS_BUDGET * accesses.len() as u64,
+            ref_gas_price,
+            sponsor_account,
+        );
+
+        let tx = Transaction::from_data_and_signer(tx_data, vec![sender_key_pair]);
+
+        let response = execute_tx(tx, reservation_id)
+            .await
+            .context(current_fn!())?;
+
+        handle_error_execute_tx(response).context(current_fn!())?;
+
+        Ok(())
+    }
+
+    pub async fn revoke_access_as_guardian(
+        &self,
+        hospital_personnel_address: IotaAddress,
+        index: u64,
+        patient_address: &IotaAddress,
+        sender: IotaAddress,
+        sender_key_pair: IotaKeyPair,
+    ) -> Result<(), PatientError> {
+        let iota_client = get_iota_client().await.context(current_fn!())?;
+        let pt = construct_pt(
+            String::from("revoke_access_as_guardian"),
+            self.decmed_package.package_id,
+            self.decmed_package.module_patient.clone(),
+            vec![],
+            vec![
+                self.construct_address_id_object_call_arg(true),
+                CallArg::Pure(bcs::to_bytes(&hospital_personnel_address).context(current_fn!())?),
+                self.construct_hospital_personnel_id_account_object_call_arg(true),
+                CallArg::Pure(bcs::to_bytes(&index).context(current_fn!())?),
+                CallArg::Pure(bcs::to_bytes(patient_address).context(current_fn!())?),
+                self.construct_patient_id_account_object_call_arg(true),
+            ],
+        )
+        .context(current_fn!())?;
+
+        let (sponsor_account, reservation_id, gas_coins) = reserve_gas(NANOS_PER_IOTA * 2, 10)
+            .await
+            .context(current_fn!())?;
+        let ref_gas_price = get_ref_gas_price(&iota_client)
+            .await
+            .context(current_fn!())?;
+
+        let tx_data = construct_sponsored_tx_data(
+            sender,
+            gas_coins,
+            pt,
+            GAS_BUDGET,
+            ref_gas_price,
+            sponsor_account,
+        );
+
+        let signer = sender_key_pair;
+        let tx = Transaction::from_data_and_signer(tx_data, vec![&signer]);
+
+        let response = execute_tx(tx, reservation_id)
+            .await
+            .context(current_fn!())?;
+
+        handle_error_execute_tx(response).context(current_fn!())?;
+
+        Ok(())
+    }
+
+    pub async fn set_emergency_access(
+        &self,
+        is_enabled: bool,
+        sender: IotaAddress,
+        sender_key_pair: IotaKeyPair,
+    ) -> Result<(), PatientError> {
+        let iota_client = get_iota_client().await.context(current_fn!())?;
+        let pt = construct_pt(
+            String::from("set_emergency_access"),
+            self.decmed_package.package_id,
+            self.decmed_package.module_patient.clone(),
+            vec![],
+            vec![
+                self.construct_address_id_object_call_arg(false),
+                CallArg::Pure(bcs::to_bytes(&is_enabled).context(current_fn!())?),
+                self.construct_patient_id_account_object_call_arg(true),
+            ],
+        )
+        .context(current_fn!())?;
+
+        let (sponsor_account, reservation_id, gas_coins) = reserve_gas(NANOS_PER_IOTA * 2, 10)
+            .await
+            .context(current_fn!())?;
+        let ref_gas_price = get_ref_gas_price(&iota_client)
+            .await
+            .context(current_fn!())?;
+
+        let tx_data = construct_sponsored_tx_data(
+            sender,
+            gas_coins,
+            pt,
+            GAS_BUDGET,
+            ref_gas_price,
+            sponsor_account,
+        );
+
+        let signer = sender_key_pair;
+        let tx = Transaction::from_data_and_signer(tx_data, vec![&signer]);
+
+        let response = execute_tx(tx, reservation_id)
+            .await
+            .context(current_fn!())?;
+
+        handle_error_execute_tx(response).context(current_fn!())?;
+
+        Ok(())
+    }
+
     pub async fn signup(
         &self,
         patient_id: String,
@@ -0,0 +1,258 @@
+use anyhow::Context;
+use chrono::{TimeDelta, Utc};
+use tauri::{async_runtime::Mutex, AppHandle, Emitter};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::{
+    access::{access_log_exp, access_log_status, get_full_access_log},
+    constants::{ACCESS_EVENT, ACCESS_EXPIRING_WINDOW, MOVE_PAGE_SIZE_MAX},
+    current_fn,
+    types::{
+        AccessEvent, AccessEventType, AccessLogStatus, AppState,
+        HospitalPersonnelPublicAdministrativeData, MedicalMetadata,
+        MoveHospitalPersonnelAccessType, MovePatientAccessLog, MovePatientMedicalMetadata,
+    },
+    utils::{get_iota_address_from_keys_entry, parse_keys_entry, serde_deserialize_from_base64},
+};
+
+/**
+ * Looks for new accesses, new medical records and accesses about to expire, called periodically
+ * in the background. Only reads the chain, so it also runs while the session is locked. The state
+ * is not locked while reading the chain, commands are not held up by a slow node.
+ */
+pub async fn watch_access_events(
+    app_handle: &AppHandle,
+    state: &Mutex<AppState>,
+) -> anyhow::Result<()> {
+    let (move_call, patient_iota_address, mut watcher) = {
+        let state = state.lock().await;
+        let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+            .context(current_fn!())?;
+
+        // Signed out or not signed up yet
+        if keys_entry.iota_address.is_none() {
+            return Ok(());
+        }
+
+        (
+            state.move_call.clone(),
+            get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?,
+            keys_entry.access_event_watcher.clone(),
+        )
+    };
+    let now = Utc::now();
+    let mut access_events = vec![];
+
+    let access_log = get_full_access_log(&move_call, patient_iota_address)
+        .await
+        .context(current_fn!())?;
+
+    // An entry that can't be decoded is skipped, it must not hold back the other notifications
+    if let Some(access_log_len) = watcher.access_log_len {
+        for metadata in access_log
+            .iter()
+            .filter(|metadata| metadata.index >= access_log_len && !metadata.is_revoked)
+        {
+            match access_granted_event(metadata) {
+                Ok(access_event) => access_events.push(access_event),
+                Err(err) => println!("{:#?}", err),
+            }
+        }
+    }
+    watcher.access_log_len = Some(access_log.len() as u64);
+
+    let mut expiring_notified = vec![];
+    for metadata in access_log.iter() {
+        match access_log_status(metadata, now) {
+            Ok(AccessLogStatus::Active) => {}
+            Ok(_) => continue,
+            Err(err) => {
+                println!("{:#?}", err);
+                continue;
+            }
+        }
+
+        if watcher.expiring_notified.contains(&metadata.index) {
+            expiring_notified.push(metadata.index);
+            continue;
+        }
+
+        let access_event = access_log_exp(metadata).and_then(|exp| {
+            if exp - now <= TimeDelta::minutes(ACCESS_EXPIRING_WINDOW) {
+                access_expiring_event(metadata).map(Some)
+            } else {
+                Ok(None)
+            }
+        });
+        match access_event {
+            Ok(Some(access_event)) => {
+                access_events.push(access_event);
+                expiring_notified.push(metadata.index);
+            }
+            Ok(None) => {}
+            Err(err) => println!("{:#?}", err),
+        }
+    }
+    watcher.expiring_notified = expiring_notified;
+
+    // Pages are newest first, paging stops at the first record already seen
+    let mut medical_records: Vec<MovePatientMedicalMetadata> = vec![];
+    loop {
+        let medical_records_page = move_call
+            .get_medical_records(
+                medical_records.len() as u64,
+                MOVE_PAGE_SIZE_MAX,
+                patient_iota_address,
+            )
+            .await
+            .context(current_fn!())?;
+
+        let is_last_page = medical_records_page.is_empty()
+            || medical_records_page.iter().any(|metadata| {
+                watcher
+                    .medical_records_len
+                    .is_none_or(|medical_records_len| metadata.index < medical_records_len)
+            });
+        medical_records.extend(medical_records_page);
+
+        if is_last_page {
+            break;
+        }
+    }
+
+    let medical_records_len = medical_records
+        .first()
+        .map_or(0, |metadata| metadata.index + 1)
+        .max(watcher.medical_records_len.unwrap_or(0));
+    if let Some(prev_medical_records_len) = watcher.medical_records_len {
+        for metadata in medical_records
+            .iter()
+            .rev()
+            .filter(|metadata| metadata.index >= prev_medical_records_len)
+        {
+            match medical_record_added_event(metadata) {
+                Ok(access_event) => access_events.push(access_event),
+                Err(err) => println!("{:#?}", err),
+            }
+        }
+    }
+    watcher.medical_records_len = Some(medical_records_len);
+
+    // Locked again only to persist the watcher, the patient may have signed out in the meantime
+    let notifications = {
+        let state = state.lock().await;
+        let mut keys_entry =
+            parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+                .context(current_fn!())?;
+
+        if keys_entry.iota_address.is_none()
+            || get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?
+                != patient_iota_address
+        {
+            return Ok(());
+        }
+
+        if watcher != keys_entry.access_event_watcher {
+            keys_entry.access_event_watcher = watcher;
+            state
+                .keys_entry
+                .set_secret(&serde_json::to_vec(&keys_entry).context(current_fn!())?)
+                .context(current_fn!())?;
+        }
+
+        keys_entry.settings.notifications.clone()
+    };
+
+    for access_event in access_events
+        .iter()
+        .filter(|access_event| notifications.is_enabled(access_event.event_type))
+    {
+        if let Err(err) = app_handle.emit(ACCESS_EVENT, access_event) {
+            println!("{:#?}", err);
+        }
+        show_notification(app_handle, &access_event.title, &access_event.body);
+    }
+
+    Ok(())
+}
+
+/**
+ * Shows a desktop notification, a failure is only logged
+ */
+pub fn show_notification(app_handle: &AppHandle, title: &str, body: &str) {
+    if let Err(err) = app_handle
+        .notification()
+        .builder()
+        .title(title)
+        .body(body)
+        .show()
+    {
+        println!("{:#?}", err);
+    }
+}
+
+fn access_granted_event(metadata: &MovePatientAccessLog) -> anyhow::Result<AccessEvent> {
+    let access = match metadata.access_type {
+        MoveHospitalPersonnelAccessType::Read => "read",
+        MoveHospitalPersonnelAccessType::Update => "add to",
+    };
+
+    Ok(AccessEvent {
+        body: format!(
+            "{} can {} your records for {} minutes",
+            hospital_personnel_label(metadata).context(current_fn!())?,
+            access,
+            metadata.exp_dur
+        ),
+        event_type: AccessEventType::AccessGranted,
+        index: metadata.index,
+        title: "New access granted".to_string(),
+    })
+}
+
+fn access_expiring_event(metadata: &MovePatientAccessLog) -> anyhow::Result<AccessEvent> {
+    Ok(AccessEvent {
+        body: format!(
+            "The access of {} expires within {} minutes",
+            hospital_personnel_label(metadata).context(current_fn!())?,
+            ACCESS_EXPIRING_WINDOW
+        ),
+        event_type: AccessEventType::AccessExpiring,
+        index: metadata.index,
+        title: "Access about to expire".to_string(),
+    })
+}
+
+fn medical_record_added_event(
+    metadata: &MovePatientMedicalMetadata,
+) -> anyhow::Result<AccessEvent> {
+    let medical_metadata: MedicalMetadata =
+        serde_deserialize_from_base64(metadata.metadata.clone()).context(current_fn!())?;
+
+    Ok(AccessEvent {
+        body: match medical_metadata.created_by {
+            Some(created_by) => format!("A record was added to your history by {}", created_by),
+            None => "A record was added to your history".to_string(),
+        },
+        event_type: AccessEventType::MedicalRecordAdded,
+        index: metadata.index,
+        title: "New medical record".to_string(),
+    })
+}
+
+/**
+ * return: `{name} ({hospital name})`, the personnel address when its name is unknown
+ */
+fn hospital_personnel_label(metadata: &MovePatientAccessLog) -> anyhow::Result<String> {
+    let hospital_personnel_metadata: HospitalPersonnelPublicAdministrativeData =
+        serde_deserialize_from_base64(metadata.hospital_personnel_metadata.clone())
+            .context(current_fn!())?;
+
+    Ok(format!(
+        "{} ({})",
+        hospital_personnel_metadata
+            .name
+            .unwrap_or_else(|| metadata.hospital_personnel_address.to_string()),
+        metadata.hospital_metadata.name
+    ))
+}
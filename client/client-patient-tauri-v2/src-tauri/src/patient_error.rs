@@ -1,16 +1,79 @@
+use serde::ser::SerializeStruct;
+use tauri_plugin_http::reqwest;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum PatientError {
     #[error(transparent)]
     Anyhow(#[from] anyhow::Error),
+    #[error("Integrity mismatch: expected sha256 {expected}, got {actual}")]
+    IntegrityMismatch { actual: String, expected: String },
+    #[error("QR decode failed: {0}")]
+    QrDecode(QrDecodeError),
 }
 
+/// A request that got no answer, the IOTA node or every IPFS gateway being unreachable. Only these,
+/// and `reqwest` connect and timeout errors, fall back to the offline record index.
+#[derive(Debug, Error)]
+#[error("Unreachable: {0}")]
+pub struct UnreachableError(pub String);
+
+/// Why no DecMed QR could be read from an image, so the UI can tell the user what to retake
+#[derive(Debug, Error)]
+pub enum QrDecodeError {
+    #[error("Not a supported image")]
+    InvalidImage,
+    #[error("No QR code found")]
+    NoCodeFound,
+    #[error("{decoded} QR code(s) found, none of them is a DecMed QR")]
+    NotDecMed { decoded: usize },
+    #[error("{detected} QR code(s) found, none of them could be read")]
+    Unreadable { detected: usize },
+}
+
+impl PatientError {
+    /// Move aborts, decode errors and a locked session are not unreachable, the cache must not hide
+    /// them
+    pub fn is_unreachable(&self) -> bool {
+        let PatientError::Anyhow(err) = self else {
+            return false;
+        };
+
+        err.chain().any(|cause| {
+            cause.is::<UnreachableError>()
+                || cause
+                    .downcast_ref::<reqwest::Error>()
+                    .is_some_and(|err| err.is_connect() || err.is_timeout())
+        })
+    }
+
+    /// Matched on by the UI, `QrDecode` carries what to retake
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PatientError::Anyhow(_) => "Anyhow",
+            PatientError::IntegrityMismatch { .. } => "IntegrityMismatch",
+            PatientError::QrDecode(QrDecodeError::InvalidImage) => "QrDecodeInvalidImage",
+            PatientError::QrDecode(QrDecodeError::NoCodeFound) => "QrDecodeNoCodeFound",
+            PatientError::QrDecode(QrDecodeError::NotDecMed { .. }) => "QrDecodeNotDecMed",
+            PatientError::QrDecode(QrDecodeError::Unreadable { .. }) => "QrDecodeUnreadable",
+        }
+    }
+}
+
+/// `{ kind, message }`, the context chain of `Anyhow` is kept in the message
 impl serde::Serialize for PatientError {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::ser::Serializer,
     {
-        serializer.serialize_str(&format!("{:?}", self))
+        let message = match self {
+            PatientError::Anyhow(err) => format!("{:?}", err),
+            _ => self.to_string(),
+        };
+
+        let mut state = serializer.serialize_struct("PatientError", 2)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &message)?;
+        state.end()
     }
 }
@@ -0,0 +1,202 @@
+use anyhow::{anyhow, Context};
+use keyring::Entry;
+use serde_json::{json, Value};
+use tauri::{async_runtime::Mutex, State};
+use zeroize::Zeroizing;
+
+use crate::{
+    constants::KEYS_ENTRY_SERVICE,
+    current_fn,
+    patient_error::PatientError,
+    types::{AppState, Profile, ResponseStatus, SuccessResponse},
+    utils::{
+        load_profile_registry, open_profile_keys_entry, open_profile_pin_attempts_entry,
+        parse_keys_entry, record_index_path, remove_record_index, save_profile_registry,
+        unlock_keys_entry, validate_by_regex,
+    },
+};
+
+#[tauri::command]
+pub async fn list_profiles(
+    state: State<'_, Mutex<AppState>>,
+) -> Result<SuccessResponse<Vec<Value>>, PatientError> {
+    let state = state.lock().await;
+    let profile_registry =
+        load_profile_registry(&state.profile_registry_entry).context(current_fn!())?;
+
+    let profiles = profile_registry
+        .profiles
+        .iter()
+        .map(|profile| {
+            // A profile whose keys entry can't be read is listed as signed out
+            let keys_entry = Entry::new(KEYS_ENTRY_SERVICE, &profile.id)
+                .and_then(|entry| entry.get_secret())
+                .ok()
+                .and_then(|keys_entry| parse_keys_entry(&keys_entry).ok());
+
+            json!({
+                "id": profile.id,
+                "label": profile.label,
+                "isActive": profile.id == profile_registry.active_profile_id,
+                "isSignedIn": keys_entry
+                    .as_ref()
+                    .is_some_and(|keys_entry| keys_entry.iota_key_pair.is_some()),
+                "iotaAddress": keys_entry.and_then(|keys_entry| keys_entry.iota_address),
+            })
+        })
+        .collect();
+
+    Ok(SuccessResponse {
+        data: profiles,
+        status: ResponseStatus::Success,
+    })
+}
+
+/**
+ * The new profile is not switched to
+ * return: id of the new profile
+ */
+#[tauri::command]
+pub async fn add_profile(
+    state: State<'_, Mutex<AppState>>,
+    label: String,
+) -> Result<SuccessResponse<String>, PatientError> {
+    let state = state.lock().await;
+    let mut profile_registry =
+        load_profile_registry(&state.profile_registry_entry).context(current_fn!())?;
+
+    let label = label.trim().to_string();
+    if !validate_by_regex(&label, "^[a-zA-Z0-9 ]{1,50}$").context(current_fn!())? {
+        return Err(PatientError::Anyhow(anyhow!(
+            "Invalid args: label is invalid"
+        )));
+    }
+
+    if profile_registry
+        .profiles
+        .iter()
+        .any(|profile| profile.label == label)
+    {
+        return Err(PatientError::Anyhow(anyhow!("Profile label already used")));
+    }
+
+    let profile = Profile {
+        id: format!("decmed_patient_{}", uuid::Uuid::new_v4().simple()),
+        label,
+    };
+    let _ = open_profile_keys_entry(&profile.id).context(current_fn!())?;
+
+    let profile_id = profile.id.clone();
+    profile_registry.profiles.push(profile);
+    save_profile_registry(&state.profile_registry_entry, &profile_registry)
+        .context(current_fn!())?;
+
+    Ok(SuccessResponse {
+        data: profile_id,
+        status: ResponseStatus::Success,
+    })
+}
+
+/**
+ * Every in-memory state belongs to the previous profile, the session is locked so the PIN of the
+ * switched to profile has to be entered.
+ */
+#[tauri::command]
+pub async fn switch_profile(
+    state: State<'_, Mutex<AppState>>,
+    profile_id: String,
+) -> Result<SuccessResponse<()>, PatientError> {
+    let mut state = state.lock().await;
+    let mut profile_registry =
+        load_profile_registry(&state.profile_registry_entry).context(current_fn!())?;
+
+    if !profile_registry
+        .profiles
+        .iter()
+        .any(|profile| profile.id == profile_id)
+    {
+        return Err(PatientError::Anyhow(anyhow!("Profile not found")));
+    }
+
+    state.keys_entry = open_profile_keys_entry(&profile_id).context(current_fn!())?;
+    state.pin_attempts_entry =
+        open_profile_pin_attempts_entry(&profile_id).context(current_fn!())?;
+    profile_registry.active_profile_id = profile_id;
+    save_profile_registry(&state.profile_registry_entry, &profile_registry)
+        .context(current_fn!())?;
+
+    state.administrative_data = None;
+    state.auth_state.session.lock();
+    state.scan_state.hospital_personnel_qr = None;
+    state.scan_state.hospital_personnel_role = None;
+    state.signin_state.pin = None;
+    state.signup_state.pin = None;
+    state.signup_state.seed_words = None;
+
+    Ok(SuccessResponse {
+        data: (),
+        status: ResponseStatus::Success,
+    })
+}
+
+/**
+ * Deletes the keys entry of a profile other than the active one, `pin` of that profile is needed
+ * when it is signed in.
+ */
+#[tauri::command]
+pub async fn remove_profile(
+    state: State<'_, Mutex<AppState>>,
+    profile_id: String,
+    pin: Option<Zeroizing<String>>,
+) -> Result<SuccessResponse<()>, PatientError> {
+    let state = state.lock().await;
+    let mut profile_registry =
+        load_profile_registry(&state.profile_registry_entry).context(current_fn!())?;
+
+    if profile_registry.active_profile_id == profile_id {
+        return Err(PatientError::Anyhow(anyhow!(
+            "Switch to another profile before removing this one"
+        )));
+    }
+
+    let profile_index = profile_registry
+        .profiles
+        .iter()
+        .position(|profile| profile.id == profile_id)
+        .ok_or(anyhow!("Profile not found"))?;
+
+    let keys_entry = Entry::new(KEYS_ENTRY_SERVICE, &profile_id).context(current_fn!())?;
+    let pin_attempts_entry = open_profile_pin_attempts_entry(&profile_id).context(current_fn!())?;
+    let is_signed_in = match keys_entry.get_secret() {
+        Ok(secret) => parse_keys_entry(&secret)
+            .context(current_fn!())?
+            .iota_key_pair
+            .is_some(),
+        Err(keyring::Error::NoEntry) => false,
+        Err(err) => return Err(PatientError::Anyhow(anyhow!(err).context(current_fn!()))),
+    };
+
+    if is_signed_in {
+        let pin = pin.ok_or(anyhow!("PIN of the profile is required"))?;
+        let _ = unlock_keys_entry(&keys_entry, &pin_attempts_entry, &pin).context(current_fn!())?;
+    }
+
+    for entry in [&keys_entry, &pin_attempts_entry] {
+        match entry.delete_credential() {
+            Ok(_) | Err(keyring::Error::NoEntry) => {}
+            Err(err) => return Err(PatientError::Anyhow(anyhow!(err).context(current_fn!()))),
+        }
+    }
+
+    remove_record_index(&record_index_path(&state.record_index_dir, &profile_id))
+        .context(current_fn!())?;
+
+    profile_registry.profiles.remove(profile_index);
+    save_profile_registry(&state.profile_registry_entry, &profile_registry)
+        .context(current_fn!())?;
+
+    Ok(SuccessResponse {
+        data: (),
+        status: ResponseStatus::Success,
+    })
+}
@@ -1,66 +1,224 @@
+use std::str::FromStr;
+
 use anyhow::{anyhow, Context};
-use iota_types::crypto::{EncodeDecodeBase64, Signature};
+use iota_types::{
+    base_types::IotaAddress,
+    crypto::{EncodeDecodeBase64, IotaKeyPair, Signature},
+};
 use serde_json::json;
 use shared_crypto::intent::{Intent, IntentMessage};
 use tauri::{async_runtime::Mutex, http::StatusCode, State};
 use tauri_plugin_http::reqwest;
-use umbral_pre::{encrypt, generate_kfrags, SecretKey, Signer};
+use umbral_pre::{encrypt, generate_kfrags, PublicKey, SecretKey, Signer};
+use zeroize::Zeroizing;
 
 use crate::{
-    constants::PROXY_BASE_URL,
+    constants::{
+        ACCESS_DUR_MIN, ADMINISTRATIVE_READ_DUR_MAX, MEDICAL_READ_DUR_MAX, MEDICAL_UPDATE_DUR_MAX,
+        MOVE_PAGE_SIZE_MAX, PROXY_BASE_URL,
+    },
     current_fn,
+    guardian::get_ward_key_material,
+    move_call::MoveCall,
     patient_error::PatientError,
     types::{
-        AppState, CommandProcessQrResponse, HospitalPersonnelPublicAdministrativeData,
-        MoveCreateAccessData, MoveCreateAccessMetadata, ProxyReencryptionErrorResponse,
+        AppState, CommandAccessRequestEntry, CommandCreateAccessInput,
+        CommandMedicalRecordScopeInput, CommandProcessQrResponse,
+        HospitalPersonnelPublicAdministrativeData, HospitalPersonnelQr, HospitalPersonnelRole,
+        MedicalMetadata, MoveCreateAccessData, MoveCreateAccessMetadata,
+        ProxyReencryptionAccessRequest, ProxyReencryptionAccessRequestStatus,
+        ProxyReencryptionAccessRequestsPayload, ProxyReencryptionErrorResponse,
         ProxyReencryptionNoncePayload, ProxyReencryptionPostKeysResponseData,
-        ProxyReencryptionSuccessResponse, ResponseStatus, SuccessResponse,
+        ProxyReencryptionRespondAccessRequestPayload, ProxyReencryptionSuccessResponse,
+        ResponseStatus, SuccessResponse,
     },
     utils::{
-        compute_pre_keys, decode_hospital_personnel_qr, do_http_post_json_request,
-        generate_64_bytes_seed, get_iota_address_from_keys_entry,
-        get_iota_key_pair_from_keys_entry, get_pre_keys_from_keys_entry, parse_keys_entry,
-        process_qr_image, serde_deserialize_from_base64, serde_serialize_to_base64,
-        sys_time_to_iso,
+        compute_pre_keys, decode_access_handoff_code, decode_hospital_personnel_qr,
+        do_http_post_json_request, generate_64_bytes_seed, get_iota_address_from_keys_entry,
+        get_iota_key_pair_from_keys_entry, get_pre_keys_from_keys_entry, iso_to_local_date,
+        parse_date, parse_keys_entry, process_qr_image, serde_deserialize_from_base64,
+        serde_serialize_to_base64, sys_time_to_iso, unlock_keys_entry,
     },
 };
 
 use base64::{engine::general_purpose::STANDARD, Engine as _};
 
+/**
+ * With `ward_iota_address` the access is granted on behalf of a ward the signed in patient is
+ * guardian of, with the keys the ward shared. The guardian signs the proxy request and the
+ * transaction.
+ */
 #[tauri::command]
 pub async fn create_access(
     state: State<'_, Mutex<AppState>>,
-    pin: String,
+    pin: Zeroizing<String>,
+    scope: CommandCreateAccessInput,
+    ward_iota_address: Option<String>,
 ) -> Result<SuccessResponse<()>, PatientError> {
-    let state = state.lock().await;
-    let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+    let mut state = state.lock().await;
+    let keys_entry = unlock_keys_entry(&state.keys_entry, &state.pin_attempts_entry, &pin)
         .context(current_fn!())?;
     let req_client = reqwest::Client::new();
+    let ward_iota_address = ward_iota_address
+        .as_deref()
+        .map(IotaAddress::from_str)
+        .transpose()
+        .context(current_fn!())?;
+
+    if ward_iota_address.is_some() && state.scan_state.access_request_id.is_some() {
+        return Err(PatientError::Anyhow(anyhow!(
+            "Invalid args: access requests can only be approved by the patient they address"
+        )));
+    }
+
+    // The same policy is enforced by the proxy and on chain, checked here to fail early
+    let (read_dur_max, update_dur_max) =
+        match state.scan_state.hospital_personnel_role.ok_or(
+            anyhow!("Hospital personnel role not found on scan state").context(current_fn!()),
+        )? {
+            HospitalPersonnelRole::AdministrativePersonnel => (ADMINISTRATIVE_READ_DUR_MAX, None),
+            HospitalPersonnelRole::MedicalPersonnel => {
+                (MEDICAL_READ_DUR_MAX, Some(MEDICAL_UPDATE_DUR_MAX))
+            }
+            HospitalPersonnelRole::Admin => {
+                return Err(PatientError::Anyhow(anyhow!("Invalid personnel account")))
+            }
+        };
+
+    if !(ACCESS_DUR_MIN..=read_dur_max).contains(&scope.exp_dur_read) {
+        return Err(PatientError::Anyhow(anyhow!(
+            "Invalid args: read access duration must be between {} and {} minutes",
+            ACCESS_DUR_MIN,
+            read_dur_max
+        )));
+    }
 
+    if scope.is_medical_read && update_dur_max.is_none() {
+        return Err(PatientError::Anyhow(anyhow!(
+            "Invalid args: administrative personnel can only read administrative data"
+        )));
+    }
+
+    if scope.medical_record_scope.is_some() && !scope.is_medical_read {
+        return Err(PatientError::Anyhow(anyhow!(
+            "Invalid args: medical record scope needs medical history read access"
+        )));
+    }
+
+    match (scope.exp_dur_update, update_dur_max) {
+        (Some(_), None) => {
+            return Err(PatientError::Anyhow(anyhow!(
+                "Invalid args: administrative personnel can't be granted update access"
+            )))
+        }
+        (Some(exp_dur_update), Some(update_dur_max))
+            if !(ACCESS_DUR_MIN..=update_dur_max).contains(&exp_dur_update) =>
+        {
+            return Err(PatientError::Anyhow(anyhow!(
+                "Invalid args: update access duration must be between {} and {} minutes",
+                ACCESS_DUR_MIN,
+                update_dur_max
+            )))
+        }
+        _ => {}
+    }
+
+    // The signer is the patient itself, or its guardian when granting on behalf of a ward. A
+    // guardian has no patient secret key, it grants with the delegated data key instead.
     let (
+        signer_iota_address,
+        signer_iota_key_pair,
         patient_iota_address,
-        patient_iota_key_pair,
+        patient_name,
         patient_pre_secret_key,
         patient_pre_public_key,
+        grant_data_pre_secret_key_seed,
     ) = {
-        let patient_iota_address =
+        let signer_iota_address =
             get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
-        let patient_iota_key_pair =
-            get_iota_key_pair_from_keys_entry(&keys_entry, pin.clone()).context(current_fn!())?;
-        let (patient_pre_secret_key, patient_pre_public_key) =
-            get_pre_keys_from_keys_entry(&keys_entry, pin).context(current_fn!())?;
+        let signer_iota_key_pair =
+            get_iota_key_pair_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+        let (signer_pre_secret_key, signer_pre_public_key) =
+            get_pre_keys_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
 
-        (
-            patient_iota_address,
-            patient_iota_key_pair,
-            patient_pre_secret_key,
-            patient_pre_public_key,
-        )
+        match ward_iota_address {
+            Some(ward_iota_address) => {
+                let ward_key_material = get_ward_key_material(
+                    &state.move_call,
+                    signer_iota_address,
+                    &signer_pre_secret_key,
+                    ward_iota_address,
+                )
+                .await
+                .context(current_fn!())?;
+                let grant_data_pre_secret_key_seed = Zeroizing::new(
+                    STANDARD
+                        .decode(
+                            ward_key_material
+                                .grant_data_pre_secret_key_seed
+                                .as_ref()
+                                .ok_or(anyhow!(
+                                    "The patient shared no grant keys with its guardian"
+                                ))?,
+                        )
+                        .context(current_fn!())?,
+                );
+                let ward_pre_public_key: PublicKey =
+                    serde_deserialize_from_base64(ward_key_material.pre_public_key.clone())
+                        .context(current_fn!())?;
+
+                (
+                    signer_iota_address,
+                    signer_iota_key_pair,
+                    ward_iota_address,
+                    ward_key_material.name.clone(),
+                    None,
+                    ward_pre_public_key,
+                    Some(grant_data_pre_secret_key_seed),
+                )
+            }
+            None => {
+                let patient_name = state
+                    .administrative_data
+                    .as_ref()
+                    .ok_or(
+                        anyhow!("Administrative data not found on state").context(current_fn!()),
+                    )?
+                    .private
+                    .name
+                    .clone()
+                    .context(current_fn!())?;
+
+                (
+                    signer_iota_address,
+                    signer_iota_key_pair,
+                    signer_iota_address,
+                    patient_name,
+                    Some(signer_pre_secret_key),
+                    signer_pre_public_key,
+                    None,
+                )
+            }
+        }
+    };
+
+    let medical_record_scope = match &scope.medical_record_scope {
+        Some(medical_record_scope) => Some(
+            resolve_medical_record_scope(
+                &state.move_call,
+                medical_record_scope,
+                signer_iota_address,
+                ward_iota_address,
+            )
+            .await
+            .context(current_fn!())?,
+        ),
+        None => None,
     };
 
     let nonce = {
         let payload = ProxyReencryptionNoncePayload {
-            iota_address: patient_iota_address.to_string(),
+            iota_address: signer_iota_address.to_string(),
         };
         do_http_post_json_request::<
             _,
@@ -85,25 +243,27 @@ pub async fn create_access(
         enc_data_pre_secret_key_seed,
         data_pre_public_key,
     ) = {
-        let (hospital_personnel_iota_address, hospital_personnel_pre_public_key) =
-            decode_hospital_personnel_qr(
-                state
-                    .scan_state
-                    .hospital_personnel_qr_content
-                    .clone()
-                    .context(current_fn!())?,
-            )
-            .context(current_fn!())?;
+        // Verified by `process_qr`, its validity only matters until the patient confirms
+        let hospital_personnel_qr =
+            state.scan_state.hospital_personnel_qr.clone().ok_or(
+                anyhow!("Hospital personnel QR not found on state").context(current_fn!()),
+            )?;
+        let (hospital_personnel_iota_address, hospital_personnel_pre_public_key) = (
+            hospital_personnel_qr.iota_address,
+            hospital_personnel_qr.pre_public_key,
+        );
 
-        let data_pre_secret_key_seed = generate_64_bytes_seed();
+        // The proxy holds the kfrag to the delegated data key of a guardian
+        let data_pre_secret_key_seed = grant_data_pre_secret_key_seed
+            .unwrap_or_else(|| Zeroizing::new(generate_64_bytes_seed()[0..32].to_vec()));
         let (data_pre_secret_key_seed_capsule, enc_data_pre_secret_key_seed) = encrypt(
             &hospital_personnel_pre_public_key,
-            &data_pre_secret_key_seed[0..32],
+            &data_pre_secret_key_seed,
         )
         .map_err(|e| anyhow!(e.to_string()))?;
 
         let (_, data_pre_public_key) =
-            compute_pre_keys(&data_pre_secret_key_seed[0..32]).context(current_fn!())?;
+            compute_pre_keys(&data_pre_secret_key_seed).context(current_fn!())?;
 
         (
             hospital_personnel_iota_address,
@@ -114,43 +274,53 @@ pub async fn create_access(
         )
     };
 
-    let (signer_secret_key, signer_public_key) = {
-        let signer_secret_key = Signer::new(SecretKey::random());
-        let signer_public_key = signer_secret_key.verifying_key();
+    let (k_frag, signer_public_key) = match &patient_pre_secret_key {
+        Some(patient_pre_secret_key) => {
+            let signer_secret_key = Signer::new(SecretKey::random());
+            let signer_public_key = signer_secret_key.verifying_key();
 
-        (signer_secret_key, signer_public_key)
-    };
+            let k_frags = generate_kfrags(
+                patient_pre_secret_key,
+                &data_pre_public_key,
+                &signer_secret_key,
+                1,
+                1,
+                true,
+                true,
+            );
 
-    let k_frag = {
-        let k_frags = generate_kfrags(
-            &patient_pre_secret_key,
-            &data_pre_public_key,
-            &signer_secret_key,
-            1,
-            1,
-            true,
-            true,
-        );
-        k_frags[0].clone().unverify()
+            (
+                Some(
+                    serde_serialize_to_base64(&k_frags[0].clone().unverify())
+                        .context(current_fn!())?,
+                ),
+                Some(serde_serialize_to_base64(&signer_public_key).context(current_fn!())?),
+            )
+        }
+        None => (None, None),
     };
 
     let signature = {
         let intent_message = IntentMessage::new(Intent::personal_message(), nonce);
-        Signature::new_secure(&intent_message, &patient_iota_key_pair)
+        Signature::new_secure(&intent_message, &signer_iota_key_pair)
     };
 
     let payload = json!({
         "enc_data_pre_secret_key_seed": STANDARD.encode(enc_data_pre_secret_key_seed),
+        "exp_dur_read": scope.exp_dur_read,
+        "exp_dur_update": scope.exp_dur_update,
+        "guardian_iota_address": ward_iota_address.map(|_| signer_iota_address.to_string()),
         "hospital_personnel_iota_address": hospital_personnel_iota_address.to_string(),
-        "k_frag": serde_serialize_to_base64(&k_frag).context(current_fn!())?,
+        "is_medical_read": scope.is_medical_read,
+        "k_frag": k_frag,
         "data_pre_public_key": serde_serialize_to_base64(&data_pre_public_key).context(current_fn!())?,
         "data_pre_secret_key_seed_capsule": serde_serialize_to_base64(&data_pre_secret_key_seed_capsule).context(current_fn!())?,
+        "medical_record_scope": medical_record_scope,
         "patient_iota_address": patient_iota_address.to_string(),
         "patient_pre_public_key": serde_serialize_to_base64(&patient_pre_public_key)
             .context(current_fn!())?,
         "signature": signature.encode_base64(),
-        "signer_pre_public_key": serde_serialize_to_base64(&signer_public_key)
-            .context(current_fn!())?,
+        "signer_pre_public_key": signer_public_key,
     });
     let access_token = do_http_post_json_request::<
         _,
@@ -168,15 +338,6 @@ pub async fn create_access(
     .data;
 
     let (metadata_read, metadata_update, date) = {
-        let patient_name = state
-            .administrative_data
-            .as_ref()
-            .ok_or(anyhow!("Administrative data not found on state").context(current_fn!()))?
-            .private
-            .name
-            .clone()
-            .context(current_fn!())?;
-
         let data_read = MoveCreateAccessData {
             patient_name: patient_name.clone(),
             patient_iota_address: patient_iota_address.to_string(),
@@ -227,17 +388,238 @@ pub async fn create_access(
         metadata.push(serde_serialize_to_base64(&metadata_update).context(current_fn!())?);
     }
 
-    let _ = state
-        .move_call
-        .create_access(
-            date,
-            &hospital_personnel_iota_address,
-            metadata,
+    match ward_iota_address {
+        Some(ward_iota_address) => {
+            state
+                .move_call
+                .create_access_as_guardian(
+                    date,
+                    scope.exp_dur_read,
+                    scope.exp_dur_update.unwrap_or(0),
+                    &hospital_personnel_iota_address,
+                    scope.is_medical_read,
+                    medical_record_scope,
+                    metadata,
+                    &ward_iota_address,
+                    signer_iota_address,
+                    signer_iota_key_pair,
+                )
+                .await
+        }
+        None => {
+            state
+                .move_call
+                .create_access(
+                    date,
+                    scope.exp_dur_read,
+                    scope.exp_dur_update.unwrap_or(0),
+                    &hospital_personnel_iota_address,
+                    scope.is_medical_read,
+                    medical_record_scope,
+                    metadata,
+                    signer_iota_address,
+                    signer_iota_key_pair,
+                )
+                .await
+        }
+    }
+    .context(current_fn!())?;
+
+    // Access already exists on chain, a failed answer only leaves the request pending
+    if let Some(access_request_id) = state.scan_state.access_request_id.take() {
+        let patient_iota_key_pair =
+            get_iota_key_pair_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+
+        if let Err(err) = respond_access_request(
+            &req_client,
+            &access_request_id,
             patient_iota_address,
-            patient_iota_key_pair,
+            &patient_iota_key_pair,
+            ProxyReencryptionAccessRequestStatus::Approved,
         )
         .await
+        {
+            println!("{:#?}", err);
+        }
+        state.scan_state.access_requests.remove(&access_request_id);
+    }
+
+    Ok(SuccessResponse {
+        data: (),
+        status: ResponseStatus::Success,
+    })
+}
+
+/**
+ * Pending access requests hospital personnel sent through the proxy. Requests failing
+ * verification are left out.
+ */
+#[tauri::command]
+pub async fn get_access_requests(
+    state: State<'_, Mutex<AppState>>,
+) -> Result<SuccessResponse<Vec<CommandAccessRequestEntry>>, PatientError> {
+    let mut state = state.lock().await;
+    let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
         .context(current_fn!())?;
+    let req_client = reqwest::Client::new();
+
+    let (patient_iota_address, patient_iota_key_pair) = {
+        let pin = state.auth_state.session.pin().context(current_fn!())?;
+        let patient_iota_address =
+            get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
+        let patient_iota_key_pair =
+            get_iota_key_pair_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+
+        (patient_iota_address, patient_iota_key_pair)
+    };
+
+    let payload = ProxyReencryptionAccessRequestsPayload {
+        patient_iota_address: patient_iota_address.to_string(),
+        signature: sign_proxy_nonce(&req_client, patient_iota_address, &patient_iota_key_pair)
+            .await
+            .context(current_fn!())?,
+    };
+    let access_requests = do_http_post_json_request::<
+        _,
+        ProxyReencryptionSuccessResponse<Vec<ProxyReencryptionAccessRequest>>,
+        ProxyReencryptionErrorResponse,
+    >(
+        None,
+        &format!("{}/access-request/list", PROXY_BASE_URL),
+        &payload,
+        &req_client,
+        StatusCode::OK,
+    )
+    .await
+    .context(current_fn!())?
+    .data;
+
+    state.scan_state.access_requests.clear();
+    let mut entries = vec![];
+    for access_request in access_requests {
+        let hospital_personnel_qr = match decode_hospital_personnel_qr(&access_request.code) {
+            Ok(hospital_personnel_qr)
+                if hospital_personnel_qr.patient_iota_address == Some(patient_iota_address) =>
+            {
+                hospital_personnel_qr
+            }
+            Ok(_) => continue,
+            Err(err) => {
+                println!("{:#?}", err);
+                continue;
+            }
+        };
+
+        // Fails when the signing address is not a hospital personnel account
+        let (hospital_personnel_public_administrative_data, hospital_name, hospital_personnel_role) =
+            match state
+                .move_call
+                .get_hospital_personnel_info(
+                    &hospital_personnel_qr.iota_address,
+                    patient_iota_address,
+                )
+                .await
+            {
+                Ok(hospital_personnel_info) => hospital_personnel_info,
+                Err(err) => {
+                    println!("{:#?}", err);
+                    continue;
+                }
+            };
+        let hospital_personnel_public_administrative_data: HospitalPersonnelPublicAdministrativeData =
+            serde_deserialize_from_base64(hospital_personnel_public_administrative_data)
+                .context(current_fn!())?;
+
+        entries.push(CommandAccessRequestEntry {
+            created_at: access_request.created_at,
+            hospital_personnel_hospital_name: hospital_name,
+            hospital_personnel_name: hospital_personnel_public_administrative_data
+                .name
+                .unwrap_or_default(),
+            hospital_personnel_role,
+            id: access_request.id.clone(),
+            reason: hospital_personnel_qr.reason.clone(),
+            requested_scope: hospital_personnel_qr.requested_scope.clone(),
+        });
+        state
+            .scan_state
+            .access_requests
+            .insert(access_request.id, hospital_personnel_qr);
+    }
+    entries.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+
+    Ok(SuccessResponse {
+        data: entries,
+        status: ResponseStatus::Success,
+    })
+}
+
+/**
+ * Same as `process_qr` for an access request listed by `get_access_requests`, `create_access`
+ * then approves it
+ */
+#[tauri::command]
+pub async fn process_access_request(
+    state: State<'_, Mutex<AppState>>,
+    id: String,
+) -> Result<SuccessResponse<CommandProcessQrResponse>, PatientError> {
+    let mut state = state.lock().await;
+
+    state.scan_state.access_request_id = None;
+    state.scan_state.hospital_personnel_qr = None;
+    state.scan_state.hospital_personnel_role = None;
+    let hospital_personnel_qr = state
+        .scan_state
+        .access_requests
+        .get(&id)
+        .cloned()
+        .ok_or(anyhow!("Access request not found, refresh the list"))?;
+
+    let data = verify_hospital_personnel_qr(&mut state, hospital_personnel_qr).await?;
+    state.scan_state.access_request_id = Some(id);
+
+    Ok(SuccessResponse {
+        data,
+        status: ResponseStatus::Success,
+    })
+}
+
+#[tauri::command]
+pub async fn reject_access_request(
+    state: State<'_, Mutex<AppState>>,
+    id: String,
+) -> Result<SuccessResponse<()>, PatientError> {
+    let mut state = state.lock().await;
+    let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+        .context(current_fn!())?;
+    let req_client = reqwest::Client::new();
+
+    let (patient_iota_address, patient_iota_key_pair) = {
+        let pin = state.auth_state.session.pin().context(current_fn!())?;
+        let patient_iota_address =
+            get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
+        let patient_iota_key_pair =
+            get_iota_key_pair_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
+
+        (patient_iota_address, patient_iota_key_pair)
+    };
+
+    respond_access_request(
+        &req_client,
+        &id,
+        patient_iota_address,
+        &patient_iota_key_pair,
+        ProxyReencryptionAccessRequestStatus::Rejected,
+    )
+    .await
+    .context(current_fn!())?;
+
+    state.scan_state.access_requests.remove(&id);
+    if state.scan_state.access_request_id.as_ref() == Some(&id) {
+        state.scan_state.access_request_id = None;
+        state.scan_state.hospital_personnel_qr = None;
+        state.scan_state.hospital_personnel_role = None;
+    }
 
     Ok(SuccessResponse {
         data: (),
@@ -245,40 +627,235 @@ pub async fn create_access(
     })
 }
 
+/**
+ * Same as `process_qr` for a `decmed://grant` deep link or a pasted access code
+ */
+#[tauri::command]
+pub async fn process_access_code(
+    state: State<'_, Mutex<AppState>>,
+    code: String,
+) -> Result<SuccessResponse<CommandProcessQrResponse>, PatientError> {
+    let mut state = state.lock().await;
+
+    // A code failing verification leaves nothing to confirm
+    state.scan_state.access_request_id = None;
+    state.scan_state.hospital_personnel_qr = None;
+    state.scan_state.hospital_personnel_role = None;
+    let hospital_personnel_qr = decode_access_handoff_code(&code)?;
+
+    Ok(SuccessResponse {
+        data: verify_hospital_personnel_qr(&mut state, hospital_personnel_qr).await?,
+        status: ResponseStatus::Success,
+    })
+}
+
 #[tauri::command]
 pub async fn process_qr(
     state: State<'_, Mutex<AppState>>,
     qr_bytes: Vec<u8>,
 ) -> Result<SuccessResponse<CommandProcessQrResponse>, PatientError> {
     let mut state = state.lock().await;
+
+    // A QR failing verification leaves nothing to confirm
+    state.scan_state.access_request_id = None;
+    state.scan_state.hospital_personnel_qr = None;
+    state.scan_state.hospital_personnel_role = None;
+    let hospital_personnel_qr = process_qr_image(&qr_bytes, decode_hospital_personnel_qr)?;
+
+    Ok(SuccessResponse {
+        data: verify_hospital_personnel_qr(&mut state, hospital_personnel_qr).await?,
+        status: ResponseStatus::Success,
+    })
+}
+
+/**
+ * Keeps the QR for `create_access` once the signing address is confirmed on chain
+ */
+async fn verify_hospital_personnel_qr(
+    state: &mut AppState,
+    hospital_personnel_qr: HospitalPersonnelQr,
+) -> Result<CommandProcessQrResponse, PatientError> {
     let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
         .context(current_fn!())?;
     let patient_iota_address =
         get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
 
-    // hp_addr_pub_key = hospital personnel {iota_address}@{hex_pre_pub_key_bytes}
-    let (_meta, hp_addr_pub_key) = process_qr_image(&qr_bytes).context(current_fn!())?;
-    state.scan_state.hospital_personnel_qr_content = Some(hp_addr_pub_key.clone());
-    let (hospital_personnel_iota_address, _) =
-        decode_hospital_personnel_qr(hp_addr_pub_key).context(current_fn!())?;
+    if hospital_personnel_qr
+        .patient_iota_address
+        .is_some_and(|address| address != patient_iota_address)
+    {
+        return Err(PatientError::Anyhow(anyhow!(
+            "Access request addressed to another patient"
+        )));
+    }
 
-    let (hospital_personnel_public_administrative_data, hospital_name) = state
-        .move_call
-        .get_hospital_personnel_info(&hospital_personnel_iota_address, patient_iota_address)
-        .await
-        .context(current_fn!())?;
+    // Fails when the signing address is not a hospital personnel account
+    let (hospital_personnel_public_administrative_data, hospital_name, hospital_personnel_role) =
+        state
+            .move_call
+            .get_hospital_personnel_info(&hospital_personnel_qr.iota_address, patient_iota_address)
+            .await
+            .context(current_fn!())?;
+    let reason = hospital_personnel_qr.reason.clone();
+    let requested_scope = hospital_personnel_qr.requested_scope.clone();
+    state.scan_state.hospital_personnel_qr = Some(hospital_personnel_qr);
+    state.scan_state.hospital_personnel_role = Some(hospital_personnel_role);
 
     let hospital_personnel_public_administrative_data: HospitalPersonnelPublicAdministrativeData =
         serde_deserialize_from_base64(hospital_personnel_public_administrative_data)
             .context(current_fn!())?;
 
-    let res_data = CommandProcessQrResponse {
+    Ok(CommandProcessQrResponse {
         hospital_personnel_hospital_name: hospital_name,
         hospital_personnel_name: hospital_personnel_public_administrative_data.name.unwrap(),
+        hospital_personnel_role,
+        reason,
+        requested_scope,
+    })
+}
+
+/**
+ * return: base64 encoded signature over a fresh proxy nonce, consumed by the next signed request
+ */
+pub async fn sign_proxy_nonce(
+    req_client: &reqwest::Client,
+    iota_address: IotaAddress,
+    iota_key_pair: &IotaKeyPair,
+) -> Result<String, PatientError> {
+    let nonce = do_http_post_json_request::<
+        _,
+        ProxyReencryptionSuccessResponse<String>,
+        ProxyReencryptionErrorResponse,
+    >(
+        None,
+        &format!("{}/nonce", PROXY_BASE_URL),
+        &ProxyReencryptionNoncePayload {
+            iota_address: iota_address.to_string(),
+        },
+        req_client,
+        StatusCode::OK,
+    )
+    .await
+    .context(current_fn!())?
+    .data;
+
+    let intent_message = IntentMessage::new(Intent::personal_message(), nonce);
+
+    Ok(Signature::new_secure(&intent_message, iota_key_pair).encode_base64())
+}
+
+async fn respond_access_request(
+    req_client: &reqwest::Client,
+    id: &str,
+    patient_iota_address: IotaAddress,
+    patient_iota_key_pair: &IotaKeyPair,
+    status: ProxyReencryptionAccessRequestStatus,
+) -> Result<(), PatientError> {
+    let payload = ProxyReencryptionRespondAccessRequestPayload {
+        id: id.to_string(),
+        patient_iota_address: patient_iota_address.to_string(),
+        signature: sign_proxy_nonce(req_client, patient_iota_address, patient_iota_key_pair)
+            .await
+            .context(current_fn!())?,
+        status,
     };
+    do_http_post_json_request::<
+        _,
+        ProxyReencryptionSuccessResponse<()>,
+        ProxyReencryptionErrorResponse,
+    >(
+        None,
+        &format!("{}/access-request/respond", PROXY_BASE_URL),
+        &payload,
+        req_client,
+        StatusCode::OK,
+    )
+    .await
+    .context(current_fn!())?;
 
-    Ok(SuccessResponse {
-        data: res_data,
-        status: ResponseStatus::Success,
-    })
+    Ok(())
+}
+
+/**
+ * Pages through every medical record of the patient, or of the ward when the sender is its
+ * guardian
+ * return: ascending indexes of the records matching the scope
+ */
+async fn resolve_medical_record_scope(
+    move_call: &MoveCall,
+    medical_record_scope: &CommandMedicalRecordScopeInput,
+    sender: IotaAddress,
+    ward_iota_address: Option<IotaAddress>,
+) -> Result<Vec<u64>, PatientError> {
+    let created_from = medical_record_scope
+        .created_from
+        .as_deref()
+        .map(parse_date)
+        .transpose()?;
+    let created_until = medical_record_scope
+        .created_until
+        .as_deref()
+        .map(parse_date)
+        .transpose()?;
+
+    let mut indexes = vec![];
+    let mut cursor = 0;
+
+    loop {
+        let medical_records = match &ward_iota_address {
+            Some(ward_iota_address) => {
+                move_call
+                    .get_medical_records_as_guardian(
+                        cursor,
+                        ward_iota_address,
+                        MOVE_PAGE_SIZE_MAX,
+                        sender,
+                    )
+                    .await
+            }
+            None => {
+                move_call
+                    .get_medical_records(cursor, MOVE_PAGE_SIZE_MAX, sender)
+                    .await
+            }
+        }
+        .context(current_fn!())?;
+
+        if medical_records.is_empty() {
+            break;
+        }
+        cursor += medical_records.len() as u64;
+
+        for medical_record in medical_records {
+            if medical_record_scope
+                .indexes
+                .as_ref()
+                .is_some_and(|indexes| !indexes.contains(&medical_record.index))
+            {
+                continue;
+            }
+
+            let medical_metadata: MedicalMetadata =
+                serde_deserialize_from_base64(medical_record.metadata).context(current_fn!())?;
+            let created_at =
+                iso_to_local_date(&medical_metadata.created_at).context(current_fn!())?;
+
+            if created_from.is_some_and(|created_from| created_at < created_from)
+                || created_until.is_some_and(|created_until| created_at > created_until)
+            {
+                continue;
+            }
+
+            indexes.push(medical_record.index);
+        }
+    }
+
+    if indexes.is_empty() {
+        return Err(PatientError::Anyhow(anyhow!(
+            "No medical record matches the selected scope"
+        )));
+    }
+    indexes.sort_unstable();
+
+    Ok(indexes)
 }
@@ -0,0 +1,89 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use zeroize::Zeroizing;
+
+/**
+ * In-memory PIN of the signed in user, the session locks itself after `idle_timeout` without
+ * activity and the PIN has to be entered again to unlock it.
+ */
+#[derive(Default)]
+pub struct Session {
+    idle_timeout: Duration,
+    last_activity_at: Option<Instant>,
+    pin: Option<Zeroizing<String>>,
+}
+
+impl Session {
+    fn is_idle(&self) -> bool {
+        !matches!(
+            self.last_activity_at,
+            Some(last_activity_at) if last_activity_at.elapsed() < self.idle_timeout
+        )
+    }
+
+    pub fn is_locked(&mut self) -> bool {
+        self.lock_if_idle();
+        self.pin.is_none()
+    }
+
+    /**
+     * Zeroizes the PIN
+     */
+    pub fn lock(&mut self) {
+        self.last_activity_at = None;
+        self.pin = None;
+    }
+
+    pub fn lock_if_idle(&mut self) {
+        if self.pin.is_some() && self.is_idle() {
+            self.lock();
+        }
+    }
+
+    /**
+     * Counts as activity
+     * return: session PIN
+     */
+    pub fn pin(&mut self) -> Result<Zeroizing<String>> {
+        self.lock_if_idle();
+
+        let pin = self.pin.clone().ok_or(anyhow!("Session locked"))?;
+        self.last_activity_at = Some(Instant::now());
+
+        Ok(pin)
+    }
+
+    /**
+     * Unlike `pin`, doesn't count as activity, for work the app does on its own
+     * return: session PIN, `None` when locked
+     */
+    pub fn peek_pin(&mut self) -> Option<Zeroizing<String>> {
+        self.lock_if_idle();
+
+        self.pin.clone()
+    }
+
+    pub fn set_idle_timeout(&mut self, idle_timeout: u64) {
+        self.idle_timeout = Duration::from_secs(idle_timeout);
+    }
+
+    pub fn touch(&mut self) {
+        self.lock_if_idle();
+
+        if self.pin.is_some() {
+            self.last_activity_at = Some(Instant::now());
+        }
+    }
+
+    /**
+     * `pin` has to be checked by the caller first, e.g. with `unlock_keys_entry`
+     */
+    pub fn unlock(&mut self, pin: Zeroizing<String>, idle_timeout: u64) {
+        self.lock();
+
+        self.idle_timeout = Duration::from_secs(idle_timeout);
+        self.last_activity_at = Some(Instant::now());
+        self.pin = Some(pin);
+    }
+}
@@ -4,20 +4,27 @@ use anyhow::{anyhow, Context};
 use bip39::Mnemonic;
 use serde_json::{json, Value};
 use tauri::{async_runtime::Mutex, State};
+use tauri_plugin_http::reqwest;
 use umbral_pre::{decrypt_original, encrypt};
+use zeroize::Zeroizing;
 
 use crate::{
+    access::overdue_scheduled_revocations_len,
+    constants::{
+        IPFS_GATEWAY_FALLBACK_BASE_URLS_MAX, PIN_WIPE_AFTER_FAILED_ATTEMPTS_MAX,
+        PIN_WIPE_AFTER_FAILED_ATTEMPTS_MIN, SESSION_IDLE_TIMEOUT_MAX, SESSION_IDLE_TIMEOUT_MIN,
+    },
     current_fn,
     patient_error::PatientError,
     types::{
-        AdministrativeData, AppState, CommandUpdateProfileInput, KeyNonce,
+        AdministrativeData, AppState, CommandUpdateProfileInput, KeyNonce, NotificationSettings,
         PrivateAdministrativeData, PrivateAdministrativeMetadata, ResponseStatus, SuccessResponse,
     },
     utils::{
         aes_decrypt, aes_encrypt, argon_hash, generate_64_bytes_seed, generate_iota_keys_ed,
         get_iota_address_from_keys_entry, get_iota_key_pair_from_keys_entry,
         get_pre_keys_from_keys_entry, parse_keys_entry, serde_deserialize_from_base64,
-        serde_serialize_to_base64, validate_by_regex,
+        serde_serialize_to_base64, unlock_keys_entry, validate_by_regex,
     },
 };
 use base64::{engine::general_purpose::STANDARD, Engine as _};
@@ -25,7 +32,7 @@ use base64::{engine::general_purpose::STANDARD, Engine as _};
 #[tauri::command]
 pub async fn validate_pin(
     state: State<'_, Mutex<AppState>>,
-    pin: String,
+    pin: Zeroizing<String>,
     auth_type: String,
 ) -> Result<SuccessResponse<()>, PatientError> {
     let mut state = state.lock().await;
@@ -53,7 +60,7 @@ pub async fn validate_pin(
 #[tauri::command]
 pub async fn validate_confirm_pin(
     state: State<'_, Mutex<AppState>>,
-    confirm_pin: String,
+    confirm_pin: Zeroizing<String>,
     auth_type: String,
 ) -> Result<SuccessResponse<()>, PatientError> {
     let state = state.lock().await;
@@ -105,16 +112,18 @@ pub async fn validate_confirm_pin(
 #[tauri::command]
 pub async fn validate_seed_words(
     state: State<'_, Mutex<AppState>>,
-    seed_words: String,
+    seed_words: Zeroizing<String>,
     auth_type: String,
 ) -> Result<SuccessResponse<()>, PatientError> {
     let state = state.lock().await;
 
-    let seed_words = Mnemonic::from_str(&seed_words)
-        .context(current_fn!())?
-        .words()
-        .collect::<Vec<&str>>()
-        .join(" ");
+    let seed_words = Zeroizing::new(
+        Mnemonic::from_str(&seed_words)
+            .context(current_fn!())?
+            .words()
+            .collect::<Vec<&str>>()
+            .join(" "),
+    );
 
     match auth_type.as_str() {
         "Signin" => {}
@@ -140,17 +149,223 @@ pub async fn validate_seed_words(
 pub async fn is_session_pin_exist(
     state: State<'_, Mutex<AppState>>,
 ) -> Result<SuccessResponse<()>, PatientError> {
+    let mut state = state.lock().await;
+
+    if state.auth_state.session.is_locked() {
+        return Err(PatientError::Anyhow(anyhow!("Session locked")));
+    }
+
+    Ok(SuccessResponse {
+        data: (),
+        status: ResponseStatus::Success,
+    })
+}
+
+#[tauri::command]
+pub async fn lock_session(
+    state: State<'_, Mutex<AppState>>,
+) -> Result<SuccessResponse<()>, PatientError> {
+    let mut state = state.lock().await;
+
+    state.auth_state.session.lock();
+
+    Ok(SuccessResponse {
+        data: (),
+        status: ResponseStatus::Success,
+    })
+}
+
+#[tauri::command]
+pub async fn unlock_session(
+    state: State<'_, Mutex<AppState>>,
+    pin: Zeroizing<String>,
+) -> Result<SuccessResponse<()>, PatientError> {
+    let mut state = state.lock().await;
+
+    let keys_entry = unlock_keys_entry(&state.keys_entry, &state.pin_attempts_entry, &pin)
+        .context(current_fn!())?;
+
+    state
+        .auth_state
+        .session
+        .unlock(pin, keys_entry.settings.session_idle_timeout);
+
+    Ok(SuccessResponse {
+        data: (),
+        status: ResponseStatus::Success,
+    })
+}
+
+#[tauri::command]
+pub async fn get_settings(
+    state: State<'_, Mutex<AppState>>,
+) -> Result<SuccessResponse<Value>, PatientError> {
     let state = state.lock().await;
+    let keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+        .context(current_fn!())?;
 
-    match state.auth_state.session_pin {
-        Some(_) => {
-            return Ok(SuccessResponse {
-                data: (),
-                status: ResponseStatus::Success,
-            })
+    Ok(SuccessResponse {
+        data: json!({
+            "ipfsGatewayFallbackBaseUrls": keys_entry.settings.ipfs_gateway_fallback_base_urls,
+            "notifications": keys_entry.settings.notifications,
+            "pinWipeAfterFailedAttempts": keys_entry.settings.pin_wipe_after_failed_attempts,
+            "sessionIdleTimeout": keys_entry.settings.session_idle_timeout,
+        }),
+        status: ResponseStatus::Success,
+    })
+}
+
+#[tauri::command]
+pub async fn update_settings(
+    state: State<'_, Mutex<AppState>>,
+    session_idle_timeout: u64,
+) -> Result<SuccessResponse<()>, PatientError> {
+    let mut state = state.lock().await;
+    let mut keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+        .context(current_fn!())?;
+
+    // Only an unlocked session can change its own lock policy
+    state.auth_state.session.pin().context(current_fn!())?;
+
+    if !(SESSION_IDLE_TIMEOUT_MIN..=SESSION_IDLE_TIMEOUT_MAX).contains(&session_idle_timeout) {
+        return Err(PatientError::Anyhow(anyhow!(
+            "Invalid args: session_idle_timeout must be between {} and {} seconds",
+            SESSION_IDLE_TIMEOUT_MIN,
+            SESSION_IDLE_TIMEOUT_MAX
+        )));
+    }
+
+    keys_entry.settings.session_idle_timeout = session_idle_timeout;
+    state
+        .keys_entry
+        .set_secret(&serde_json::to_vec(&keys_entry).context(current_fn!())?)
+        .context(current_fn!())?;
+
+    state
+        .auth_state
+        .session
+        .set_idle_timeout(session_idle_timeout);
+
+    Ok(SuccessResponse {
+        data: (),
+        status: ResponseStatus::Success,
+    })
+}
+
+/**
+ * Turned off event types are still tracked by the watcher, they are only not notified
+ */
+#[tauri::command]
+pub async fn update_notification_settings(
+    state: State<'_, Mutex<AppState>>,
+    notifications: NotificationSettings,
+) -> Result<SuccessResponse<()>, PatientError> {
+    let mut state = state.lock().await;
+    let mut keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+        .context(current_fn!())?;
+
+    state.auth_state.session.pin().context(current_fn!())?;
+
+    keys_entry.settings.notifications = notifications;
+    state
+        .keys_entry
+        .set_secret(&serde_json::to_vec(&keys_entry).context(current_fn!())?)
+        .context(current_fn!())?;
+
+    Ok(SuccessResponse {
+        data: (),
+        status: ResponseStatus::Success,
+    })
+}
+
+/**
+ * Gateways tried after `IPFS_GATEWAY_BASE_URL` when a record content is fetched from IPFS
+ */
+#[tauri::command]
+pub async fn update_ipfs_gateway_settings(
+    state: State<'_, Mutex<AppState>>,
+    ipfs_gateway_fallback_base_urls: Vec<String>,
+) -> Result<SuccessResponse<()>, PatientError> {
+    let mut state = state.lock().await;
+    let mut keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+        .context(current_fn!())?;
+
+    state.auth_state.session.pin().context(current_fn!())?;
+
+    if ipfs_gateway_fallback_base_urls.len() > IPFS_GATEWAY_FALLBACK_BASE_URLS_MAX {
+        return Err(PatientError::Anyhow(anyhow!(
+            "Invalid args: at most {} IPFS gateways",
+            IPFS_GATEWAY_FALLBACK_BASE_URLS_MAX
+        )));
+    }
+
+    let mut base_urls: Vec<String> = Vec::new();
+    for base_url in ipfs_gateway_fallback_base_urls
+        .iter()
+        .map(|base_url| base_url.trim().trim_end_matches('/'))
+        .filter(|base_url| !base_url.is_empty())
+    {
+        let is_http =
+            reqwest::Url::parse(base_url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"));
+        if !is_http {
+            return Err(PatientError::Anyhow(anyhow!(
+                "Invalid args: {} is not an HTTP URL",
+                base_url
+            )));
+        }
+        if !base_urls.iter().any(|existing| existing == base_url) {
+            base_urls.push(base_url.to_string());
         }
-        None => return Err(PatientError::Anyhow(anyhow!("Session PIN not found"))),
     }
+
+    keys_entry.settings.ipfs_gateway_fallback_base_urls = base_urls;
+    state
+        .keys_entry
+        .set_secret(&serde_json::to_vec(&keys_entry).context(current_fn!())?)
+        .context(current_fn!())?;
+
+    Ok(SuccessResponse {
+        data: (),
+        status: ResponseStatus::Success,
+    })
+}
+
+/**
+ * `pin_wipe_after_failed_attempts`: none turns the wipe off
+ */
+#[tauri::command]
+pub async fn update_pin_wipe_settings(
+    state: State<'_, Mutex<AppState>>,
+    pin_wipe_after_failed_attempts: Option<u32>,
+) -> Result<SuccessResponse<()>, PatientError> {
+    let state = state.lock().await;
+    let mut keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
+        .context(current_fn!())?;
+
+    state.auth_state.session.pin().context(current_fn!())?;
+
+    let is_in_range = pin_wipe_after_failed_attempts.is_none_or(|max_attempts| {
+        (PIN_WIPE_AFTER_FAILED_ATTEMPTS_MIN..=PIN_WIPE_AFTER_FAILED_ATTEMPTS_MAX)
+            .contains(&max_attempts)
+    });
+    if !is_in_range {
+        return Err(PatientError::Anyhow(anyhow!(
+            "Invalid args: pin_wipe_after_failed_attempts must be between {} and {}",
+            PIN_WIPE_AFTER_FAILED_ATTEMPTS_MIN,
+            PIN_WIPE_AFTER_FAILED_ATTEMPTS_MAX
+        )));
+    }
+
+    keys_entry.settings.pin_wipe_after_failed_attempts = pin_wipe_after_failed_attempts;
+    state
+        .keys_entry
+        .set_secret(&serde_json::to_vec(&keys_entry).context(current_fn!())?)
+        .context(current_fn!())?;
+
+    Ok(SuccessResponse {
+        data: (),
+        status: ResponseStatus::Success,
+    })
 }
 
 #[tauri::command]
@@ -162,15 +377,11 @@ pub async fn get_profile(
         .context(current_fn!())?;
 
     let (patient_iota_address, patient_pre_secret_key, patient_pre_public_key) = {
-        let pin = state
-            .auth_state
-            .session_pin
-            .clone()
-            .ok_or(anyhow!("Session PIN not found").context(current_fn!()))?;
+        let pin = state.auth_state.session.pin().context(current_fn!())?;
         let patient_iota_address =
             get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
         let (patient_pre_secret_key, patient_pre_public_key) =
-            get_pre_keys_from_keys_entry(&keys_entry, pin).context(current_fn!())?;
+            get_pre_keys_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
 
         (
             patient_iota_address,
@@ -197,6 +408,7 @@ pub async fn get_profile(
             .decode(private_administrative_metadata.enc_key_nonce)
             .context(current_fn!())?,
     )
+    .map(Zeroizing::new)
     .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
     let key_nonce: KeyNonce = serde_json::from_slice(&key_nonce).context(current_fn!())?;
 
@@ -204,8 +416,8 @@ pub async fn get_profile(
         &STANDARD
             .decode(private_administrative_metadata.enc_data)
             .context(current_fn!())?,
-        &STANDARD.decode(key_nonce.key).context(current_fn!())?,
-        &STANDARD.decode(key_nonce.nonce).context(current_fn!())?,
+        &Zeroizing::new(STANDARD.decode(&key_nonce.key).context(current_fn!())?),
+        &STANDARD.decode(&key_nonce.nonce).context(current_fn!())?,
     )
     .context(current_fn!())?;
     let private_administrative_data: PrivateAdministrativeData =
@@ -254,17 +466,13 @@ pub async fn update_profile(
     }
 
     let (patient_iota_key_pair, patient_iota_address, patient_pre_public_key) = {
-        let pin = state
-            .auth_state
-            .session_pin
-            .clone()
-            .ok_or(anyhow!("Session PIN not found").context(current_fn!()))?;
+        let pin = state.auth_state.session.pin().context(current_fn!())?;
         let patient_iota_key_pair =
-            get_iota_key_pair_from_keys_entry(&keys_entry, pin.clone()).context(current_fn!())?;
+            get_iota_key_pair_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
         let patient_iota_address =
             get_iota_address_from_keys_entry(&keys_entry).context(current_fn!())?;
         let (_, patient_pre_public_key) =
-            get_pre_keys_from_keys_entry(&keys_entry, pin).context(current_fn!())?;
+            get_pre_keys_from_keys_entry(&keys_entry, &pin).context(current_fn!())?;
 
         (
             patient_iota_key_pair,
@@ -308,7 +516,10 @@ pub async fn update_profile(
             enc_private_administrative_data_key_nonce,
         ) = encrypt(
             &patient_pre_public_key,
-            &serde_json::to_vec(&private_administrative_data_key_nonce).context(current_fn!())?,
+            &Zeroizing::new(
+                serde_json::to_vec(&private_administrative_data_key_nonce)
+                    .context(current_fn!())?,
+            ),
         )
         .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
 
@@ -347,10 +558,13 @@ pub async fn update_profile(
  * $<0>$: redirect to auth page (signin/signup)
  * $<1>$: redirect to complete-profile page
  * $<2>$: redirect to pin page
+ * return: `{ overdueScheduledRevocations }`, scheduled revocations that should have run already
  */
 #[tauri::command]
-pub async fn auth_status(state: State<'_, Mutex<AppState>>) -> Result<(), PatientError> {
-    let state = state.lock().await;
+pub async fn auth_status(
+    state: State<'_, Mutex<AppState>>,
+) -> Result<SuccessResponse<Value>, PatientError> {
+    let mut state = state.lock().await;
     let keys_entry = parse_keys_entry(
         &state
             .keys_entry
@@ -373,7 +587,7 @@ pub async fn auth_status(state: State<'_, Mutex<AppState>>) -> Result<(), Patien
     let (patient_id, random_iota_address) = {
         let id = keys_entry.id.clone().unwrap();
         let seed = generate_64_bytes_seed();
-        let (random_iota_address, _) = generate_iota_keys_ed(&seed)
+        let (random_iota_address, _) = generate_iota_keys_ed(seed.as_slice())
             .context("$<0>$")
             .context(current_fn!())?;
         let patient_id = argon_hash(id).context(current_fn!())?;
@@ -409,11 +623,18 @@ pub async fn auth_status(state: State<'_, Mutex<AppState>>) -> Result<(), Patien
         ));
     }
 
-    if state.auth_state.session_pin.is_none() {
+    if state.auth_state.session.is_locked() {
         return Err(PatientError::Anyhow(
-            anyhow!("Session PIN not found").context("$<2>$"),
+            anyhow!("Session locked").context("$<2>$"),
         ));
     }
 
-    Ok(())
+    state.auth_state.session.touch();
+
+    Ok(SuccessResponse {
+        data: json!({
+            "overdueScheduledRevocations": overdue_scheduled_revocations_len(&keys_entry),
+        }),
+        status: ResponseStatus::Success,
+    })
 }
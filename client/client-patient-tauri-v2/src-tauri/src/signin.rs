@@ -1,22 +1,21 @@
 use anyhow::{anyhow, Context};
 use tauri::{async_runtime::Mutex, State};
+use zeroize::Zeroizing;
 
 use crate::{
     current_fn,
     patient_error::PatientError,
-    types::{AppState, ResponseStatus, SuccessResponse},
+    types::{AppState, PinAttempts, ResponseStatus, SuccessResponse},
     utils::{
-        aes_encrypt_custom_key, compute_pre_keys, compute_seed_from_seed_words,
-        generate_iota_keys_ed, parse_keys_entry, serde_serialize_to_base64, sha_hash,
-        validate_by_regex,
+        compute_pre_keys, compute_seed_from_seed_words, generate_iota_keys_ed, parse_keys_entry,
+        save_pin_attempts, seal_keys_entry, serde_serialize_to_base64, validate_by_regex,
     },
 };
-use base64::{engine::general_purpose::STANDARD, Engine as _};
 
 #[tauri::command]
 pub async fn signin(
     state: State<'_, Mutex<AppState>>,
-    seed_words: String,
+    seed_words: Zeroizing<String>,
     id: String,
 ) -> Result<SuccessResponse<()>, PatientError> {
     let mut state = state.lock().await;
@@ -27,15 +26,7 @@ pub async fn signin(
         return Err(PatientError::Anyhow(anyhow!("Invalid id")));
     }
 
-    let (
-        pin,
-        patient_iota_address,
-        patient_pre_public_key,
-        enc_patient_iota_key_pair,
-        enc_patient_pre_secret_key,
-        patient_pre_secret_key_nonce,
-        patient_iota_key_pair_nonce,
-    ) = {
+    let (pin, patient_iota_address, patient_iota_key_pair, patient_pre_public_key, seed) = {
         let pin = state
             .signin_state
             .pin
@@ -43,29 +34,15 @@ pub async fn signin(
             .ok_or(anyhow!("PIN not found on signin state").context(current_fn!()))?;
         let seed = compute_seed_from_seed_words(&seed_words, &id).context(current_fn!())?;
         let (patient_iota_address, patient_iota_key_pair) =
-            generate_iota_keys_ed(&seed).context(current_fn!())?;
+            generate_iota_keys_ed(seed.as_slice()).context(current_fn!())?;
         let (_, patient_pre_public_key) = compute_pre_keys(&seed[0..32]).context(current_fn!())?;
 
-        let (enc_patient_pre_secret_key, patient_pre_secret_key_nonce) =
-            aes_encrypt_custom_key(&sha_hash(pin.as_bytes()), &seed[0..32])
-                .context(current_fn!())?;
-        let (enc_patient_iota_key_pair, patient_iota_key_pair_nonce) = aes_encrypt_custom_key(
-            &sha_hash(pin.as_bytes()),
-            patient_iota_key_pair
-                .encode()
-                .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?
-                .as_bytes(),
-        )
-        .context(current_fn!())?;
-
         (
             pin,
             patient_iota_address,
+            patient_iota_key_pair,
             patient_pre_public_key,
-            enc_patient_iota_key_pair,
-            enc_patient_pre_secret_key,
-            patient_pre_secret_key_nonce,
-            patient_iota_key_pair_nonce,
+            seed,
         )
     };
 
@@ -83,19 +60,21 @@ pub async fn signin(
 
     keys_entry.id = Some(id);
     keys_entry.iota_address = Some(patient_iota_address.to_string());
-    keys_entry.iota_key_pair = Some(STANDARD.encode(enc_patient_iota_key_pair));
-    keys_entry.pre_secret_key = Some(STANDARD.encode(enc_patient_pre_secret_key));
     keys_entry.pre_public_key =
         Some(serde_serialize_to_base64(&patient_pre_public_key).context(current_fn!())?);
-    keys_entry.pre_nonce = Some(STANDARD.encode(patient_pre_secret_key_nonce));
-    keys_entry.iota_nonce = Some(STANDARD.encode(patient_iota_key_pair_nonce));
-    let keys_entry = serde_json::to_vec(&keys_entry).context(current_fn!())?;
+    // Re-sealing with the PIN also migrates entries written with an older vault version
+    seal_keys_entry(&mut keys_entry, &pin, &patient_iota_key_pair, &seed[0..32])
+        .context(current_fn!())?;
     state
         .keys_entry
-        .set_secret(&keys_entry)
+        .set_secret(&serde_json::to_vec(&keys_entry).context(current_fn!())?)
         .context(current_fn!())?;
+    save_pin_attempts(&state.pin_attempts_entry, &PinAttempts::default()).context(current_fn!())?;
 
-    state.auth_state.session_pin = Some(pin);
+    state
+        .auth_state
+        .session
+        .unlock(pin, keys_entry.settings.session_idle_timeout);
 
     // drop SigninState form state
     state.signin_state.pin = None;
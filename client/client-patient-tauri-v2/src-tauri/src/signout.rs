@@ -4,15 +4,15 @@ use tauri::{async_runtime::Mutex, State};
 use crate::{
     current_fn,
     patient_error::PatientError,
-    types::{AppState, ResponseStatus, SuccessResponse},
-    utils::parse_keys_entry,
+    types::{AccessEventWatcher, AppState, ResponseStatus, SuccessResponse},
+    utils::{load_profile_registry, parse_keys_entry, record_index_path, remove_record_index},
 };
 
 #[tauri::command]
 pub async fn signout(
     state: State<'_, Mutex<AppState>>,
 ) -> Result<SuccessResponse<()>, PatientError> {
-    let state = state.lock().await;
+    let mut state = state.lock().await;
 
     let mut keys_entry = parse_keys_entry(&state.keys_entry.get_secret().context(current_fn!())?)
         .context(current_fn!())?;
@@ -22,12 +22,25 @@ pub async fn signout(
     keys_entry.pre_nonce = None;
     keys_entry.pre_secret_key = None;
     keys_entry.pre_public_key = None;
+    keys_entry.scheduled_revocations.clear();
+    keys_entry.access_event_watcher = AccessEventWatcher::default();
     let keys_entry = serde_json::to_vec(&keys_entry).context(current_fn!())?;
     state
         .keys_entry
         .set_secret(&keys_entry)
         .context(current_fn!())?;
 
+    // The index can't be decrypted without the signed out keys anyway
+    let profile_registry =
+        load_profile_registry(&state.profile_registry_entry).context(current_fn!())?;
+    remove_record_index(&record_index_path(
+        &state.record_index_dir,
+        &profile_registry.active_profile_id,
+    ))
+    .context(current_fn!())?;
+
+    state.auth_state.session.lock();
+
     Ok(SuccessResponse {
         data: (),
         status: ResponseStatus::Success,
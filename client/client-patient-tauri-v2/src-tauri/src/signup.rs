@@ -1,18 +1,19 @@
 use anyhow::{anyhow, Context};
 use tauri::{async_runtime::Mutex, State};
 use umbral_pre::encrypt;
+use zeroize::Zeroizing;
 
 use crate::{
     current_fn,
     patient_error::PatientError,
     types::{
-        AdministrativeData, AppState, KeyNonce, PrivateAdministrativeData,
+        AdministrativeData, AppState, KeyNonce, PinAttempts, PrivateAdministrativeData,
         PrivateAdministrativeMetadata, ResponseStatus, SuccessResponse,
     },
     utils::{
-        aes_encrypt, aes_encrypt_custom_key, argon_hash, compute_pre_keys,
-        compute_seed_from_seed_words, generate_iota_keys_ed, parse_keys_entry,
-        serde_serialize_to_base64, sha_hash,
+        aes_encrypt, argon_hash, compute_pre_keys, compute_seed_from_seed_words,
+        generate_iota_keys_ed, parse_keys_entry, save_pin_attempts, seal_keys_entry,
+        serde_serialize_to_base64,
     },
 };
 
@@ -27,7 +28,7 @@ pub async fn generate_mnemonic(
     let mnemonic = bip39::Mnemonic::generate(12).context(current_fn!())?;
     let words = mnemonic.words().collect::<Vec<&str>>().join(" ");
 
-    state.signup_state.seed_words = Some(words.clone());
+    state.signup_state.seed_words = Some(Zeroizing::new(words.clone()));
 
     Ok(SuccessResponse {
         status: ResponseStatus::Success,
@@ -50,13 +51,13 @@ pub async fn signup(
             &state
                 .signup_state
                 .seed_words
-                .clone()
+                .as_ref()
                 .ok_or(anyhow!("Seed words not found").context(current_fn!()))?,
             &id,
         )
         .context(current_fn!())?;
         let (patient_iota_address, patient_iota_key_pair) =
-            generate_iota_keys_ed(&seed).context(current_fn!())?;
+            generate_iota_keys_ed(seed.as_slice()).context(current_fn!())?;
         let (_, patient_pre_public_key) = compute_pre_keys(&seed[0..32]).context(current_fn!())?;
         let id_hash = argon_hash(id.clone()).context(current_fn!())?;
 
@@ -92,7 +93,10 @@ pub async fn signup(
             enc_private_administrative_data_key_nonce,
         ) = encrypt(
             &patient_pre_public_key,
-            &serde_json::to_vec(&private_administrative_data_key_nonce).context(current_fn!())?,
+            &Zeroizing::new(
+                serde_json::to_vec(&private_administrative_data_key_nonce)
+                    .context(current_fn!())?,
+            ),
         )
         .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
 
@@ -106,52 +110,18 @@ pub async fn signup(
         (private_administrative_data, private_administrative_metadata)
     };
 
-    let (
-        enc_patient_iota_key_pair,
-        enc_patient_pre_secret_key,
-        patient_iota_key_pair_nonce,
-        patient_pre_secret_key_nonce,
-    ) = {
-        // Encrypt PRE secret key
-        let (enc_patient_pre_secret_key, patient_pre_secret_key_nonce) = aes_encrypt_custom_key(
-            sha_hash(
-                state
-                    .signup_state
-                    .pin
-                    .as_ref()
-                    .ok_or(anyhow!("PIN not found on signup state").context(current_fn!()))?
-                    .as_bytes(),
-            )
-            .as_slice(),
-            &seed[0..32],
-        )
-        .context(current_fn!())?;
-
-        // Encrypt IOTA keypair
-        let (enc_patient_iota_key_pair, patient_iota_key_pair_nonce) = aes_encrypt_custom_key(
-            sha_hash(
-                state
-                    .signup_state
-                    .pin
-                    .as_ref()
-                    .ok_or(anyhow!("PIN not found on signup state").context(current_fn!()))?
-                    .as_bytes(),
-            )
-            .as_slice(),
-            patient_iota_key_pair
-                .encode()
-                .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?
-                .as_bytes(),
-        )
-        .context(current_fn!())?;
-
-        (
-            enc_patient_iota_key_pair,
-            enc_patient_pre_secret_key,
-            patient_iota_key_pair_nonce,
-            patient_pre_secret_key_nonce,
-        )
-    };
+    // Encrypt IOTA keypair and PRE secret key
+    seal_keys_entry(
+        &mut keys_entry,
+        state
+            .signup_state
+            .pin
+            .as_ref()
+            .ok_or(anyhow!("PIN not found on signup state").context(current_fn!()))?,
+        &patient_iota_key_pair,
+        &seed[0..32],
+    )
+    .context(current_fn!())?;
 
     // Process tx
     let _ = state
@@ -167,18 +137,24 @@ pub async fn signup(
 
     keys_entry.id = Some(id);
     keys_entry.iota_address = Some(patient_iota_address.to_string());
-    keys_entry.iota_key_pair = Some(STANDARD.encode(enc_patient_iota_key_pair));
-    keys_entry.iota_nonce = Some(STANDARD.encode(patient_iota_key_pair_nonce));
-    keys_entry.pre_nonce = Some(STANDARD.encode(patient_pre_secret_key_nonce));
     keys_entry.pre_public_key =
         Some(serde_serialize_to_base64(&patient_pre_public_key).context(current_fn!())?);
-    keys_entry.pre_secret_key = Some(STANDARD.encode(enc_patient_pre_secret_key));
 
-    let keys_entry = serde_json::to_vec(&keys_entry).context(current_fn!())?;
     state
         .keys_entry
-        .set_secret(&keys_entry)
+        .set_secret(&serde_json::to_vec(&keys_entry).context(current_fn!())?)
         .context(current_fn!())?;
+    save_pin_attempts(&state.pin_attempts_entry, &PinAttempts::default()).context(current_fn!())?;
+
+    let pin = state
+        .signup_state
+        .pin
+        .take()
+        .ok_or(anyhow!("PIN not found on signup state").context(current_fn!()))?;
+    state
+        .auth_state
+        .session
+        .unlock(pin, keys_entry.settings.session_idle_timeout);
 
     state.administrative_data = Some(AdministrativeData {
         private: private_administrative_data,
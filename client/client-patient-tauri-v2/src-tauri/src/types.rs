@@ -1,18 +1,59 @@
-use std::fmt;
+use std::{collections::HashMap, fmt, path::PathBuf};
 
 use iota_json_rpc_types::{IotaObjectRef, IotaTransactionBlockEffects};
 use iota_types::{
     base_types::{IotaAddress, ObjectID},
+    crypto::IotaKeyPair,
     Identifier,
 };
 use keyring::Entry;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use umbral_pre::PublicKey;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
-use crate::move_call::MoveCall;
+use crate::{constants::SESSION_IDLE_TIMEOUT, move_call::MoveCall, session::Session};
 
 // Enum.
 
+/// Status of a `MovePatientAccessLog` entry, computed against the current time
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum AccessLogStatus {
+    Active,
+    Expired,
+    Revoked,
+}
+
+/// Kind of an `AccessEvent`, each can be turned off in `NotificationSettings`
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum AccessEventType {
+    AccessExpiring,
+    AccessGranted,
+    MedicalRecordAdded,
+}
+
+/// Where a medical record's encrypted content is stored, `IpfsCluster` on older records
+#[derive(Copy, Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlobStoreBackend {
+    #[default]
+    IpfsCluster,
+    Kubo,
+    LocalFs,
+    S3,
+}
+
+/// FHIR R4 resources a `FhirBundle` is made of
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "resourceType")]
+pub enum FhirResource {
+    CarePlan(FhirCarePlan),
+    Condition(FhirCondition),
+    Encounter(FhirEncounter),
+    Observation(FhirObservation),
+    Patient(FhirPatient),
+}
+
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum HospitalPersonnelRole {
     Admin,
@@ -38,12 +79,19 @@ pub enum MoveHospitalPersonnelAccessDataType {
     Medical,
 }
 
-#[derive(Copy, Clone, Debug, Deserialize, Serialize)]
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub enum MoveHospitalPersonnelAccessType {
     Read,
     Update,
 }
 
+/// Answer to a hospital personnel access request, see `ProxyReencryptionRespondAccessRequestPayload`
+#[derive(Copy, Clone, Debug, Deserialize, Serialize)]
+pub enum ProxyReencryptionAccessRequestStatus {
+    Approved,
+    Rejected,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub enum ResponseStatus {
     Error,
@@ -52,6 +100,27 @@ pub enum ResponseStatus {
 
 // Struct
 
+/// Emitted to the frontend as `ACCESS_EVENT` and shown as a desktop notification
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AccessEvent {
+    pub body: String,
+    #[serde(rename = "eventType")]
+    pub event_type: AccessEventType,
+    /// Access log index, or medical record index on `MedicalRecordAdded`
+    pub index: u64,
+    pub title: String,
+}
+
+/// Where the access event watcher left off, lengths are `None` until its first run so existing
+/// entries are not notified
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct AccessEventWatcher {
+    pub access_log_len: Option<u64>,
+    /// Access log indexes already notified as expiring, dropped once no longer active
+    pub expiring_notified: Vec<u64>,
+    pub medical_records_len: Option<u64>,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct AdministrativeData {
     pub private: PrivateAdministrativeData,
@@ -60,8 +129,14 @@ pub struct AdministrativeData {
 pub struct AppState {
     pub administrative_data: Option<AdministrativeData>,
     pub auth_state: AuthState,
+    /// Keys entry of the active profile
     pub keys_entry: Entry,
     pub move_call: MoveCall,
+    /// `PinAttempts` of the active profile
+    pub pin_attempts_entry: Entry,
+    pub profile_registry_entry: Entry,
+    /// Holds the `EncRecordIndex` file of every profile
+    pub record_index_dir: PathBuf,
     pub scan_state: ScanState,
     pub signin_state: SignInState,
     pub signup_state: SignUpState,
@@ -71,15 +146,134 @@ pub struct AppState {
 pub struct AuthState {
     pub is_registered: bool,
     pub role: Option<HospitalPersonnelRole>,
-    pub session_pin: Option<String>,
+    #[serde(skip)]
+    pub session: Session,
+}
+
+/// Passphrase-encrypted backup file, `enc_payload` is the AES-256-GCM encrypted `BackupPayload`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Backup {
+    pub enc_payload: String,
+    pub kdf: PinKdf,
+    pub nonce: String,
+    /// See `BACKUP_VERSION`
+    pub version: u8,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BackupPayload {
+    pub administrative_data: Option<PrivateAdministrativeData>,
+    /// The IOTA key pair and PRE seed stay encrypted with the PIN
+    pub keys_entry: KeysEntry,
+    pub settings: Settings,
+}
+
+/// Every given filter has to match
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CommandAccessLogFilterInput {
+    #[serde(rename = "accessType")]
+    pub access_type: Option<MoveHospitalPersonnelAccessType>,
+    /// Case-insensitive, matches part of the name
+    #[serde(rename = "hospitalName")]
+    pub hospital_name: Option<String>,
+    #[serde(rename = "hospitalPersonnelAddress")]
+    pub hospital_personnel_address: Option<String>,
+    /// Case-insensitive, matches part of the name
+    #[serde(rename = "hospitalPersonnelName")]
+    pub hospital_personnel_name: Option<String>,
+    pub status: Option<AccessLogStatus>,
+}
+
+/// Pending access request whose hospital personnel signature is verified
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CommandAccessRequestEntry {
+    /// Unix timestamp in seconds
+    #[serde(rename = "createdAt")]
+    pub created_at: u64,
+    #[serde(rename = "hospitalPersonnelHospitalName")]
+    pub hospital_personnel_hospital_name: String,
+    #[serde(rename = "hospitalPersonnelName")]
+    pub hospital_personnel_name: String,
+    #[serde(rename = "hospitalPersonnelRole")]
+    pub hospital_personnel_role: HospitalPersonnelRole,
+    pub id: String,
+    pub reason: Option<String>,
+    #[serde(rename = "requestedScope")]
+    pub requested_scope: Option<HospitalPersonnelQrScope>,
+}
+
+/// Durations are in minutes, see `ACCESS_DUR_MIN` and the `*_DUR_MAX` bounds
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CommandCreateAccessInput {
+    #[serde(rename = "expDurRead")]
+    pub exp_dur_read: u64,
+    /// `None` grants no update access
+    #[serde(rename = "expDurUpdate")]
+    pub exp_dur_update: Option<u64>,
+    /// Administrative data is always readable
+    #[serde(rename = "isMedicalRead")]
+    pub is_medical_read: bool,
+    /// `None` shares every medical record
+    #[serde(rename = "medicalRecordScope")]
+    pub medical_record_scope: Option<CommandMedicalRecordScopeInput>,
+}
+
+/// Break-glass access the patient has not acknowledged yet
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CommandEmergencyAccessEntry {
+    /// Index in the access log, revokes the access like any other
+    #[serde(rename = "accessLogIndex")]
+    pub access_log_index: u64,
+    pub date: String,
+    /// Minutes
+    #[serde(rename = "expDur")]
+    pub exp_dur: u64,
+    #[serde(rename = "hospitalName")]
+    pub hospital_name: String,
+    #[serde(rename = "hospitalPersonnelIotaAddress")]
+    pub hospital_personnel_iota_address: String,
+    #[serde(rename = "hospitalPersonnelName")]
+    pub hospital_personnel_name: Option<String>,
+    pub index: u64,
+    pub justification: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CommandGetEmergencyAccessResponseData {
+    pub entries: Vec<CommandEmergencyAccessEntry>,
+    #[serde(rename = "isEnabled")]
+    pub is_enabled: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CommandGetMedicalRecordVersionsResponseData {
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "createdBy")]
+    pub created_by: Option<String>,
+    pub version: u64,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct CommandGetMedicalRecordsResponseData {
-    pub cid: String,
     #[serde(rename = "createdAt")]
     pub created_at: String,
+    #[serde(rename = "createdBy")]
+    pub created_by: Option<String>,
     pub index: u64,
+    pub locator: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CommandSyncMedicalRecordsResponseData {
+    #[serde(rename = "lastSyncedAt")]
+    pub last_synced_at: String,
+    /// Records added since the previous sync
+    #[serde(rename = "newCount")]
+    pub new_count: u64,
+    /// Cached records whose current version moved to another locator since they were cached
+    #[serde(rename = "updatedCount")]
+    pub updated_count: u64,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -94,12 +288,68 @@ pub struct CommandGetProfileResponse {
     pub pre_public_key: String,
 }
 
+/// Guardian of the patient, see `add_guardian`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CommandGuardianEntry {
+    #[serde(rename = "iotaAddress")]
+    pub iota_address: String,
+    pub rights: CommandGuardianRightsInput,
+}
+
+/// Revoking is the least a guardian can do, reading or granting shares the patient keys with it
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct CommandGuardianRightsInput {
+    #[serde(rename = "canGrant")]
+    pub can_grant: bool,
+    #[serde(rename = "canRead")]
+    pub can_read: bool,
+    #[serde(rename = "canRevoke")]
+    pub can_revoke: bool,
+}
+
+/// A record is shared when it matches every given filter.
+/// There is no category filter: records carry no category, and `MedicalMetadata` is readable by
+/// anyone on chain, a category kept there would tell e.g. a psychiatric record apart without
+/// decrypting it. It can be added here once the category is part of the encrypted `MedicalData`.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CommandMedicalRecordScopeInput {
+    /// YYYY-MM-DD, inclusive
+    #[serde(rename = "createdFrom")]
+    pub created_from: Option<String>,
+    /// YYYY-MM-DD, inclusive
+    #[serde(rename = "createdUntil")]
+    pub created_until: Option<String>,
+    pub indexes: Option<Vec<u64>>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct CommandProcessQrResponse {
     #[serde(rename = "hospitalPersonnelHospitalName")]
     pub hospital_personnel_hospital_name: String,
     #[serde(rename = "hospitalPersonnelName")]
     pub hospital_personnel_name: String,
+    #[serde(rename = "hospitalPersonnelRole")]
+    pub hospital_personnel_role: HospitalPersonnelRole,
+    #[serde(rename = "requestedScope")]
+    pub requested_scope: Option<HospitalPersonnelQrScope>,
+    /// Set when the personnel asked remotely, through an access request
+    pub reason: Option<String>,
+}
+
+/// Every given filter has to match
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CommandSearchMedicalRecordsInput {
+    /// Author IOTA address
+    #[serde(rename = "createdBy")]
+    pub created_by: Option<String>,
+    /// YYYY-MM-DD, inclusive
+    #[serde(rename = "createdFrom")]
+    pub created_from: Option<String>,
+    /// YYYY-MM-DD, inclusive
+    #[serde(rename = "createdUntil")]
+    pub created_until: Option<String>,
+    /// Case-insensitive, every word has to appear in one of the `MedicalData` fields
+    pub query: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -117,6 +367,16 @@ pub struct CommandUpdateProfileInput {
     pub marital_status: String,
 }
 
+/// Patient the signed in patient is guardian of
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CommandWardEntry {
+    #[serde(rename = "iotaAddress")]
+    pub iota_address: String,
+    /// `None` when the ward shared no keys, i.e. revoking only
+    pub name: Option<String>,
+    pub rights: CommandGuardianRightsInput,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct DecmedPackage {
     pub package_id: ObjectID,
@@ -135,13 +395,166 @@ pub struct DecmedPackage {
     pub global_admin_cap_id: ObjectID,
 }
 
+/// Local record index file, `enc_data` is the AES-256-GCM encrypted `RecordIndex`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EncRecordIndex {
+    pub enc_data: String,
+    pub nonce: String,
+    /// See `RECORD_INDEX_VERSION`
+    pub version: u8,
+}
+
 #[derive(Debug, Deserialize, JsonSchema, Serialize)]
 pub struct ExecuteTxResponse {
     pub effects: Option<IotaTransactionBlockEffects>,
     pub error: Option<String>,
 }
 
+/// FHIR R4 `Bundle` of type `collection`, see `export_fhir_bundle`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FhirBundle {
+    #[serde(rename = "type")]
+    pub bundle_type: String,
+    pub entry: Vec<FhirBundleEntry>,
+    pub id: String,
+    #[serde(rename = "resourceType")]
+    pub resource_type: String,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FhirBundleEntry {
+    /// `urn:uuid:{uuid}`, resources of the bundle reference each other by it
+    #[serde(rename = "fullUrl")]
+    pub full_url: String,
+    pub resource: FhirResource,
+}
+
+/// Therapy, free text so it is not split into `MedicationRequest`s
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FhirCarePlan {
+    pub created: String,
+    pub description: String,
+    pub encounter: FhirReference,
+    pub intent: String,
+    pub status: String,
+    pub subject: FhirReference,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FhirCodeableConcept {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub coding: Vec<FhirCoding>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FhirCoding {
+    pub code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display: Option<String>,
+    pub system: String,
+}
+
+/// Diagnose
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FhirCondition {
+    pub code: FhirCodeableConcept,
+    pub encounter: FhirReference,
+    #[serde(rename = "recordedDate")]
+    pub recorded_date: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recorder: Option<FhirReference>,
+    pub subject: FhirReference,
+}
+
+/// A medical record, the resources mapped from its `MedicalData` reference it
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FhirEncounter {
+    pub class: FhirCoding,
+    pub identifier: Vec<FhirIdentifier>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub participant: Vec<FhirEncounterParticipant>,
+    pub period: FhirPeriod,
+    pub status: String,
+    pub subject: FhirReference,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FhirEncounterParticipant {
+    pub individual: FhirReference,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
+pub struct FhirExtension {
+    pub url: String,
+    #[serde(rename = "valueAddress", skip_serializing_if = "Option::is_none")]
+    pub value_address: Option<FhirText>,
+    #[serde(
+        rename = "valueCodeableConcept",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub value_codeable_concept: Option<FhirCodeableConcept>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FhirIdentifier {
+    pub system: String,
+    pub value: String,
+}
+
+/// Anamnesis, physical and psychological check of a record, education and occupation of the patient
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FhirObservation {
+    pub code: FhirCodeableConcept,
+    #[serde(rename = "effectiveDateTime", skip_serializing_if = "Option::is_none")]
+    pub effective_date_time: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encounter: Option<FhirReference>,
+    pub status: String,
+    pub subject: FhirReference,
+    #[serde(rename = "valueString")]
+    pub value_string: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FhirPatient {
+    #[serde(rename = "birthDate", skip_serializing_if = "Option::is_none")]
+    pub birth_date: Option<String>,
+    /// Birth place and religion
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extension: Vec<FhirExtension>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gender: Option<String>,
+    pub identifier: Vec<FhirIdentifier>,
+    #[serde(rename = "maritalStatus", skip_serializing_if = "Option::is_none")]
+    pub marital_status: Option<FhirCodeableConcept>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub name: Vec<FhirText>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FhirPeriod {
+    pub start: String,
+}
+
+/// Either a bundle `fullUrl` or, for actors outside the bundle, a logical `identifier`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FhirReference {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identifier: Option<FhirIdentifier>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+}
+
+/// `Address` and `HumanName` with only their `text`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FhirText {
+    pub text: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Zeroize, ZeroizeOnDrop)]
 pub struct KeyNonce {
     pub key: String,
     pub nonce: String,
@@ -149,6 +562,8 @@ pub struct KeyNonce {
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct KeysEntry {
+    #[serde(default)]
+    pub access_event_watcher: AccessEventWatcher,
     pub activation_key: Option<String>,
     pub admin_address: Option<String>,
     pub admin_secret_key: Option<String>,
@@ -156,10 +571,72 @@ pub struct KeysEntry {
     pub iota_address: Option<String>,
     pub iota_key_pair: Option<String>,
     pub iota_nonce: Option<String>,
+    pub pin_kdf: Option<PinKdf>,
     pub pre_nonce: Option<String>,
     pub pre_public_key: Option<String>,
     pub pre_secret_key: Option<String>,
     pub proxy_jwt: Option<String>,
+    #[serde(default)]
+    pub scheduled_revocations: Vec<ScheduledRevocation>,
+    #[serde(default)]
+    pub settings: Settings,
+    /// See `KEYS_ENTRY_VAULT_VERSION`, entries written before versioning are `0`
+    #[serde(default)]
+    pub vault_version: u8,
+}
+
+/// Guardian code whose signature is checked
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GuardianCode {
+    pub iota_address: IotaAddress,
+    pub pre_public_key: PublicKey,
+}
+
+/// Signed by the guardian IOTA key, proves the guardian holds both keys
+#[derive(Debug, Deserialize, Serialize)]
+pub struct GuardianCodePayload {
+    pub iota_address: String,
+    /// Base64 encoded
+    pub pre_public_key: String,
+}
+
+/// Hospital personnel QR whose signature and validity are checked
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HospitalPersonnelQr {
+    pub iota_address: IotaAddress,
+    /// Set on access requests, which only that patient can approve
+    pub patient_iota_address: Option<IotaAddress>,
+    pub pre_public_key: PublicKey,
+    pub reason: Option<String>,
+    pub requested_scope: Option<HospitalPersonnelQrScope>,
+}
+
+/// Signed by the hospital personnel IOTA key
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HospitalPersonnelQrPayload {
+    /// Unix timestamp in seconds
+    pub exp: u64,
+    /// Unix timestamp in seconds
+    pub iat: u64,
+    pub iota_address: String,
+    #[serde(default)]
+    pub patient_iota_address: Option<String>,
+    /// Base64 encoded
+    pub pre_public_key: String,
+    #[serde(default)]
+    pub reason: Option<String>,
+    #[serde(default)]
+    pub scope: Option<HospitalPersonnelQrScope>,
+}
+
+/// Access the personnel asks for, only a suggestion the patient can change before granting
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HospitalPersonnelQrScope {
+    /// Minutes
+    pub exp_dur_read: u64,
+    /// Minutes, `None` asks for no update access
+    pub exp_dur_update: Option<u64>,
+    pub is_medical_read: bool,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -167,7 +644,7 @@ pub struct HospitalPersonnelPublicAdministrativeData {
     pub name: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct MedicalData {
     pub anamnesis: String,
     pub physical_check: String,
@@ -178,10 +655,48 @@ pub struct MedicalData {
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct MedicalMetadata {
+    #[serde(default)]
+    pub backend: BlobStoreBackend,
     pub capsule: String,
-    pub cid: String,
     pub created_at: String,
+    /// Author IOTA address, `None` on records created before it was recorded
+    #[serde(default)]
+    pub created_by: Option<String>,
     pub enc_key_and_nonce: String,
+    /// Backend specific, the CID on IPFS backends and the object name otherwise
+    #[serde(alias = "cid")]
+    pub locator: String,
+    /// Hex SHA-256 of the stored ciphertext, `None` on records created before it was recorded
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// Replaced versions, oldest first
+    #[serde(default)]
+    pub versions: Vec<MedicalMetadataVersion>,
+}
+
+/// A single version of `MedicalMetadata`, without the history
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MedicalMetadataVersion {
+    pub backend: BlobStoreBackend,
+    pub capsule: String,
+    pub created_at: String,
+    pub created_by: Option<String>,
+    pub enc_key_and_nonce: String,
+    pub locator: String,
+    pub sha256: Option<String>,
+}
+
+/// What `get_medical_data` needs to fetch a record content, directly from IPFS or through the
+/// patient content route of the proxy on the other backends
+pub struct MedicalDataSource<'a> {
+    /// Set when a guardian reads a record of its ward, signs instead of the patient
+    pub guardian_iota_address: Option<IotaAddress>,
+    pub index: u64,
+    pub iota_key_pair: &'a IotaKeyPair,
+    pub ipfs_gateway_fallback_base_urls: &'a [String],
+    pub patient_iota_address: IotaAddress,
+    /// `None` selects the current version
+    pub version: Option<u64>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -228,11 +743,55 @@ pub struct MovePatientAdministrativeMetadata {
 }
 
 #[derive(Debug, Deserialize, Serialize)]
+pub struct MovePatientEmergencyAccessLog {
+    pub access_log_index: u64,
+    pub date: String,
+    pub exp_dur: u64,
+    pub hospital_metadata: MoveHospitalMetadata,
+    pub hospital_personnel_address: IotaAddress,
+    pub hospital_personnel_metadata: String,
+    pub index: u64,
+    pub is_acknowledged: bool,
+    pub justification: String,
+    pub patient_address: IotaAddress,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MovePatientGuardian {
+    pub address: IotaAddress,
+    pub can_grant: bool,
+    pub can_read: bool,
+    pub can_revoke: bool,
+    /// Base64 encoded `MoveCreateAccessMetadata` of `WardKeyMaterial`, encrypted for the guardian
+    pub metadata: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct MovePatientMedicalMetadata {
     pub index: u64,
     pub metadata: String,
 }
 
+/// Failed PIN attempts of a profile on this device. Kept on its own keyring entry, so restoring an
+/// older copy of the `KeysEntry` doesn't reset them.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct PinAttempts {
+    pub failed_attempts: u32,
+    /// Unix timestamp in seconds
+    pub locked_until: Option<u64>,
+}
+
+/// Argon2id parameters the key of a `KeysEntry` or `Backup` is derived from the PIN/passphrase with
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PinKdf {
+    /// Memory cost in KiB
+    pub m_cost: u32,
+    pub p_cost: u32,
+    /// Random per install, regenerated each time the keys are sealed
+    pub salt: String,
+    pub t_cost: u32,
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct PrivateAdministrativeData {
     pub id: String,
@@ -253,18 +812,106 @@ pub struct PrivateAdministrativeMetadata {
     pub enc_key_nonce: String,
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Profile {
+    /// Keyring user the `KeysEntry` of the profile is stored under
+    pub id: String,
+    pub label: String,
+}
+
+/// Profiles on this device, each one has its own `KeysEntry`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProfileRegistry {
+    pub active_profile_id: String,
+    pub profiles: Vec<Profile>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProxyReencryptionAccessRequest {
+    /// Signed hospital personnel QR content, addressed to the patient
+    pub code: String,
+    /// Unix timestamp in seconds
+    pub created_at: u64,
+    pub id: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProxyReencryptionAccessRequestsPayload {
+    pub patient_iota_address: String,
+    pub signature: String,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ProxyReencryptionPostKeysResponseData {
     pub access_token_read: String,
     pub access_token_update: Option<String>,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProxyReencryptionEmergencyKeys {
+    pub data_pre_public_key: String,
+    pub data_pre_secret_key_seed_capsule: String,
+    /// Encrypted for `hospital_personnel_pre_public_key`
+    pub enc_data_pre_secret_key_seed: String,
+    /// Encrypted for `hospital_personnel_pre_public_key`
+    pub enc_patient_name: MoveCreateAccessMetadata,
+    pub hospital_personnel_pre_public_key: String,
+    pub k_frag: String,
+    pub patient_pre_public_key: String,
+    pub signer_pre_public_key: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProxyReencryptionEmergencyKeysPayload {
+    /// By recipient IOTA address
+    pub keys: HashMap<String, ProxyReencryptionEmergencyKeys>,
+    pub patient_iota_address: String,
+    pub signature: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProxyReencryptionEmergencyRecipient {
+    pub hospital_personnel_iota_address: String,
+    pub pre_public_key: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProxyReencryptionEmergencyRecipientsPayload {
+    pub patient_iota_address: String,
+    pub signature: String,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ProxyReencryptionErrorResponse {
     pub error: String,
     pub status_code: u16,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProxyReencryptionGuardianKeys {
+    pub data_pre_public_key: Option<String>,
+    pub grant_k_frag: Option<String>,
+    pub guardian_pre_public_key: String,
+    pub patient_pre_public_key: String,
+    pub read_k_frag: Option<String>,
+    pub signer_pre_public_key: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProxyReencryptionGuardianMedicalRecordCFragPayload {
+    pub guardian_iota_address: String,
+    pub index: u64,
+    pub patient_iota_address: String,
+    pub signature: String,
+    pub version: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProxyReencryptionGuardianMedicalRecordCFragResponseData {
+    pub c_frag: String,
+    pub signer_pre_public_key: String,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ProxyReencryptionKeysPayload {
     pub enc_hospital_personnel_pre_secret_key_seed: String,
@@ -278,17 +925,93 @@ pub struct ProxyReencryptionKeysPayload {
     pub signer_pre_public_key: String,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProxyReencryptionMedicalRecordContentPayload {
+    pub guardian_iota_address: Option<String>,
+    pub index: u64,
+    pub patient_iota_address: String,
+    pub signature: String,
+    pub version: Option<u64>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ProxyReencryptionNoncePayload {
     pub iota_address: String,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProxyReencryptionRespondAccessRequestPayload {
+    pub id: String,
+    pub patient_iota_address: String,
+    pub signature: String,
+    pub status: ProxyReencryptionAccessRequestStatus,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProxyReencryptionRevokeEmergencyKeysPayload {
+    pub patient_iota_address: String,
+    pub signature: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProxyReencryptionRevokeGuardianKeysPayload {
+    pub guardian_iota_address: String,
+    pub patient_iota_address: String,
+    pub signature: String,
+    pub signer_iota_address: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProxyReencryptionRevokeKeysPayload {
+    /// Signs instead of the patient when revoking on behalf of it
+    pub guardian_iota_address: Option<String>,
+    pub hospital_personnel_iota_addresses: Vec<String>,
+    pub patient_iota_address: String,
+    pub signature: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProxyReencryptionStoreGuardianKeysPayload {
+    pub guardian_iota_address: String,
+    pub keys: ProxyReencryptionGuardianKeys,
+    pub patient_iota_address: String,
+    pub signature: String,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ProxyReencryptionSuccessResponse<T> {
     pub data: T,
     pub status_code: u16,
 }
 
+/// Decrypted medical records of a profile, only the current version of a record is indexed.
+/// Doubles as the offline cache, records are listed from `medical_metadata` and opened from
+/// `entries` when the chain or IPFS is unreachable.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct RecordIndex {
+    pub entries: Vec<RecordIndexEntry>,
+    /// ISO 8601, `None` until the first `sync_medical_records`
+    #[serde(default)]
+    pub last_synced_at: Option<String>,
+    /// On-chain metadata of every record, newest first
+    #[serde(default)]
+    pub medical_metadata: Vec<MovePatientMedicalMetadata>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RecordIndexEntry {
+    pub created_at: String,
+    pub created_by: Option<String>,
+    pub index: u64,
+    /// Locator of the cached version, `None` on entries indexed before it was recorded
+    #[serde(default)]
+    pub locator: Option<String>,
+    pub medical_data: MedicalData,
+    /// Version of the cached content, the current one when it was cached
+    #[serde(default)]
+    pub version: u64,
+}
+
 #[derive(Debug, Deserialize, JsonSchema, Serialize)]
 pub struct ReserveGasResponse {
     pub error: Option<String>,
@@ -304,16 +1027,89 @@ pub struct ReserveGasResult {
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ScanState {
-    pub hospital_personnel_qr_content: Option<String>,
+    /// Set when the QR comes from an access request, answered once access is created
+    pub access_request_id: Option<String>,
+    /// Verified by `get_access_requests`, keyed by request id
+    pub access_requests: HashMap<String, HospitalPersonnelQr>,
+    pub hospital_personnel_qr: Option<HospitalPersonnelQr>,
+    pub hospital_personnel_role: Option<HospitalPersonnelRole>,
+}
+
+/// Revocation of an access log entry the app executes once `revoke_at` has passed
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ScheduledRevocation {
+    pub hospital_personnel_address: IotaAddress,
+    pub index: u64,
+    /// Set once the patient was notified that it is overdue because the session is locked
+    #[serde(default)]
+    pub is_overdue_notified: bool,
+    /// Unix timestamp in seconds
+    pub revoke_at: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct NotificationSettings {
+    #[serde(rename = "isAccessExpiringEnabled")]
+    pub is_access_expiring_enabled: bool,
+    #[serde(rename = "isAccessGrantedEnabled")]
+    pub is_access_granted_enabled: bool,
+    #[serde(rename = "isMedicalRecordAddedEnabled")]
+    pub is_medical_record_added_enabled: bool,
+}
+
+impl NotificationSettings {
+    pub fn is_enabled(&self, event_type: AccessEventType) -> bool {
+        match event_type {
+            AccessEventType::AccessExpiring => self.is_access_expiring_enabled,
+            AccessEventType::AccessGranted => self.is_access_granted_enabled,
+            AccessEventType::MedicalRecordAdded => self.is_medical_record_added_enabled,
+        }
+    }
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            is_access_expiring_enabled: true,
+            is_access_granted_enabled: true,
+            is_medical_record_added_enabled: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Settings {
+    #[serde(default)]
+    pub notifications: NotificationSettings,
+    /// Tried in order when `IPFS_GATEWAY_BASE_URL` is unreachable, e.g. gateways of other cluster peers
+    #[serde(default)]
+    pub ipfs_gateway_fallback_base_urls: Vec<String>,
+    /// Key material is wiped from this device after this many failed PIN attempts, `None` disables
+    /// it. The account can be restored from its seed words or a backup.
+    #[serde(default)]
+    pub pin_wipe_after_failed_attempts: Option<u32>,
+    /// Seconds without activity before the session locks
+    pub session_idle_timeout: u64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            notifications: NotificationSettings::default(),
+            ipfs_gateway_fallback_base_urls: Vec::new(),
+            pin_wipe_after_failed_attempts: None,
+            session_idle_timeout: SESSION_IDLE_TIMEOUT,
+        }
+    }
 }
 
 pub struct SignInState {
-    pub pin: Option<String>,
+    pub pin: Option<Zeroizing<String>>,
 }
 
 pub struct SignUpState {
-    pub pin: Option<String>,
-    pub seed_words: Option<String>,
+    pub pin: Option<Zeroizing<String>>,
+    pub seed_words: Option<Zeroizing<String>>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -330,6 +1126,19 @@ pub struct UtilIpfsAddResponse {
     pub size: u64,
 }
 
+/// Shared with a guardian that can grant access or read on behalf of the ward, per right: the
+/// ward's own PRE seed is never part of it
+#[derive(Debug, Deserialize, Serialize, Zeroize, ZeroizeOnDrop)]
+pub struct WardKeyMaterial {
+    /// Base64 encoded, seed of the delegated data key the proxy holds the grant kfrag to. Only
+    /// shared with a guardian that can grant access.
+    #[serde(default)]
+    pub grant_data_pre_secret_key_seed: Option<String>,
+    pub name: String,
+    /// Base64 encoded, verifies the record keys re-encrypted for a guardian that can read
+    pub pre_public_key: String,
+}
+
 impl fmt::Display for ProxyReencryptionErrorResponse {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.error)
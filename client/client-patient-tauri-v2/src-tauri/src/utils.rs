@@ -1,14 +1,27 @@
 use anyhow::{anyhow, Context, Result};
 use bip39::Mnemonic;
-use chrono::{DateTime, Utc};
-use image::{EncodableLayout, ImageReader};
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use image::{
+    imageops::{self, FilterType},
+    DynamicImage, EncodableLayout, GrayImage, ImageDecoder, ImageReader, Luma,
+};
 use iota_sdk::{IotaClient, IotaClientBuilder};
 use serde::{de::DeserializeOwned, Serialize};
-use tauri_plugin_http::reqwest::{self, Client, IntoUrl, StatusCode};
-
-use std::{fmt::Debug, io::Cursor, str::FromStr, time::SystemTime};
+use tauri_plugin_http::reqwest::{self, Client, StatusCode};
+
+use std::{
+    fmt::Debug,
+    fs,
+    io::{self, Cursor, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-use aes_gcm::{aead::Aead, AeadCore, Aes256Gcm, KeyInit, Nonce};
+use aes_gcm::{
+    aead::{generic_array::GenericArray, stream::DecryptorBE32, Aead},
+    AeadCore, Aes256Gcm, KeyInit, Nonce,
+};
 use argon2::{
     password_hash::{PasswordHasher, SaltString},
     Algorithm, Argon2, Params, PasswordHash, PasswordVerifier, Version,
@@ -18,7 +31,9 @@ use iota_json_rpc_types::{
 };
 use iota_keys::key_derive::derive_key_pair_from_path;
 use iota_types::base_types::{IotaAddress, ObjectID, ObjectRef};
-use iota_types::crypto::{EmptySignInfo, IotaKeyPair, SignatureScheme};
+use iota_types::crypto::{
+    EmptySignInfo, EncodeDecodeBase64, IotaKeyPair, IotaSignature, Signature, SignatureScheme,
+};
 use iota_types::message_envelope::Envelope;
 use iota_types::programmable_transaction_builder::ProgrammableTransactionBuilder;
 use iota_types::transaction::{
@@ -26,15 +41,33 @@ use iota_types::transaction::{
     TransactionDataAPI,
 };
 use iota_types::{Identifier, TypeTag};
+use keyring::Entry;
 use rand::Rng;
 use regex::Regex;
 use serde_json::json;
 use sha2::{Digest, Sha256};
+use shared_crypto::intent::{Intent, IntentMessage};
 use umbral_pre::{PublicKey, SecretKey, SecretKeyFactory};
+use zeroize::Zeroizing;
 
 use crate::{
-    constants::IPFS_GATEWAY_BASE_URL,
-    types::{ExecuteTxResponse, KeysEntry, ReserveGasResponse},
+    constants::{
+        ACCESS_HANDOFF_URI_CODE_PARAM, ACCESS_HANDOFF_URI_HOST, ACCESS_HANDOFF_URI_SCHEME,
+        AES_STREAM_HEADER_LEN, AES_STREAM_MAGIC, AES_STREAM_VERSION, DEFAULT_PROFILE_ID,
+        DEFAULT_PROFILE_LABEL, GUARDIAN_CODE_PREFIX, GUARDIAN_CODE_VERSION,
+        HOSPITAL_PERSONNEL_QR_CLOCK_SKEW, HOSPITAL_PERSONNEL_QR_PREFIX,
+        HOSPITAL_PERSONNEL_QR_VALIDITY_MAX, HOSPITAL_PERSONNEL_QR_VERSION, IPFS_GATEWAY_BASE_URL,
+        KEYS_ENTRY_SERVICE, KEYS_ENTRY_VAULT_VERSION, PIN_ATTEMPTS_SERVICE, PIN_FREE_ATTEMPTS,
+        PIN_KDF_M_COST, PIN_KDF_P_COST, PIN_KDF_SALT_LEN, PIN_KDF_T_COST, PIN_LOCKOUT_BASE_DUR,
+        PIN_LOCKOUT_MAX_DUR, QR_IMAGE_DIMENSION_MAX, RECORD_INDEX_KEY_DOMAIN, RECORD_INDEX_VERSION,
+    },
+    patient_error::{PatientError, QrDecodeError, UnreachableError},
+    types::{
+        AccessEventWatcher, EncRecordIndex, ExecuteTxResponse, GuardianCode, GuardianCodePayload,
+        HospitalPersonnelQr, HospitalPersonnelQrPayload, KeysEntry, MedicalMetadata,
+        MedicalMetadataVersion, PinAttempts, PinKdf, Profile, ProfileRegistry, RecordIndex,
+        ReserveGasResponse, Settings,
+    },
 };
 use crate::{
     constants::{GAS_STATION_BASE_URL, HASH_SALT, IOTA_URL},
@@ -107,10 +140,113 @@ pub fn parse_keys_entry(keys_entry: &Vec<u8>) -> Result<KeysEntry> {
     serde_json::from_slice(keys_entry).context(current_fn!())
 }
 
-pub fn generate_64_bytes_seed() -> [u8; 64] {
+/**
+ * return: keys entry of a profile nobody has signed in to yet
+ */
+pub fn new_keys_entry() -> KeysEntry {
+    KeysEntry {
+        admin_secret_key: Some(String::from(
+            "iotaprivkey1qpfc5nqsvs64p40347h0vcdxz3pgfn72uznw4pfvkak59fhpevxs73z6kwn",
+        )),
+        admin_address: Some(String::from(
+            "0x52a65ae806223e49aaff1cf7f670fee87c1767de1d200a661c1fee44a61fc37f",
+        )),
+        access_event_watcher: AccessEventWatcher::default(),
+        activation_key: None,
+        id: None,
+        iota_address: None,
+        iota_key_pair: None,
+        iota_nonce: None,
+        pin_kdf: None,
+        pre_nonce: None,
+        proxy_jwt: None,
+        pre_public_key: None,
+        pre_secret_key: None,
+        scheduled_revocations: vec![],
+        settings: Settings::default(),
+        vault_version: KEYS_ENTRY_VAULT_VERSION,
+    }
+}
+
+/**
+ * Opens the keyring entry of `profile_id`, a new keys entry is written if there is none yet
+ */
+pub fn open_profile_keys_entry(profile_id: &str) -> Result<Entry> {
+    let keys_entry = Entry::new(KEYS_ENTRY_SERVICE, profile_id).context(current_fn!())?;
+
+    match keys_entry.get_secret() {
+        Ok(_) => {}
+        Err(keyring::Error::NoEntry) => {
+            keys_entry
+                .set_secret(&serde_json::to_vec(&new_keys_entry()).context(current_fn!())?)
+                .context(current_fn!())?;
+        }
+        Err(err) => {
+            println!("{:#?}", err);
+        }
+    }
+
+    Ok(keys_entry)
+}
+
+/**
+ * Opens the keyring entry holding the `PinAttempts` of `profile_id`, it is written on the first
+ * failed attempt
+ */
+pub fn open_profile_pin_attempts_entry(profile_id: &str) -> Result<Entry> {
+    Entry::new(PIN_ATTEMPTS_SERVICE, profile_id).context(current_fn!())
+}
+
+/**
+ * return: the `PinAttempts` on `entry`, none failed if it wasn't written yet
+ */
+pub fn load_pin_attempts(entry: &Entry) -> Result<PinAttempts> {
+    match entry.get_secret() {
+        Ok(pin_attempts) => serde_json::from_slice(&pin_attempts).context(current_fn!()),
+        Err(keyring::Error::NoEntry) => Ok(PinAttempts::default()),
+        Err(err) => Err(anyhow!(err).context(current_fn!())),
+    }
+}
+
+pub fn save_pin_attempts(entry: &Entry, pin_attempts: &PinAttempts) -> Result<()> {
+    entry
+        .set_secret(&serde_json::to_vec(pin_attempts).context(current_fn!())?)
+        .context(current_fn!())
+}
+
+/**
+ * Reads the profile registry on `entry`, a registry holding only the default profile is written
+ * if there is none yet, so keys entries from before profiles existed are kept.
+ */
+pub fn load_profile_registry(entry: &Entry) -> Result<ProfileRegistry> {
+    match entry.get_secret() {
+        Ok(profile_registry) => serde_json::from_slice(&profile_registry).context(current_fn!()),
+        Err(keyring::Error::NoEntry) => {
+            let profile_registry = ProfileRegistry {
+                active_profile_id: String::from(DEFAULT_PROFILE_ID),
+                profiles: vec![Profile {
+                    id: String::from(DEFAULT_PROFILE_ID),
+                    label: String::from(DEFAULT_PROFILE_LABEL),
+                }],
+            };
+            save_profile_registry(entry, &profile_registry).context(current_fn!())?;
+
+            Ok(profile_registry)
+        }
+        Err(err) => Err(anyhow!(err).context(current_fn!())),
+    }
+}
+
+pub fn save_profile_registry(entry: &Entry, profile_registry: &ProfileRegistry) -> Result<()> {
+    entry
+        .set_secret(&serde_json::to_vec(profile_registry).context(current_fn!())?)
+        .context(current_fn!())
+}
+
+pub fn generate_64_bytes_seed() -> Zeroizing<[u8; 64]> {
     let mut rng = rand::rng();
-    let mut random_seed = [0u8; 64];
-    rng.fill(&mut random_seed);
+    let mut random_seed = Zeroizing::new([0u8; 64]);
+    rng.fill(&mut *random_seed);
 
     random_seed
 }
@@ -141,6 +277,34 @@ pub fn construct_pt(
     Ok(builder.finish())
 }
 
+/**
+ * One move call per element of `call_args_batch`, all executed in the same transaction
+ */
+pub fn construct_pt_batch(
+    function_name: String,
+    package: ObjectID,
+    module: Identifier,
+    type_arguments: Vec<TypeTag>,
+    call_args_batch: Vec<Vec<CallArg>>,
+) -> Result<ProgrammableTransaction> {
+    let mut builder = ProgrammableTransactionBuilder::new();
+    let function = Identifier::from_str(function_name.as_str()).context(current_fn!())?;
+
+    for call_args in call_args_batch {
+        builder
+            .move_call(
+                package,
+                module.clone(),
+                function.clone(),
+                type_arguments.clone(),
+                call_args,
+            )
+            .context(current_fn!())?;
+    }
+
+    Ok(builder.finish())
+}
+
 pub fn construct_sponsored_tx_data(
     sender: IotaAddress,
     gas_payment: Vec<ObjectRef>,
@@ -215,6 +379,7 @@ pub async fn move_call_read_only(
             None,
         )
         .await
+        .map_err(|err| UnreachableError(err.to_string()))
         .context(current_fn!())?)
 }
 
@@ -257,7 +422,7 @@ pub fn _argon_verify(hash: String, password: String) -> Result<bool> {
 * nonce: 12 bytes
 * return: `(ciphertext, key, nonce)`
 */
-pub fn aes_encrypt(data: &[u8]) -> Result<(Vec<u8>, Vec<u8>, Vec<u8>)> {
+pub fn aes_encrypt(data: &[u8]) -> Result<(Vec<u8>, Zeroizing<Vec<u8>>, Vec<u8>)> {
     let key = Aes256Gcm::generate_key(aes_gcm::aead::OsRng);
     let cipher = Aes256Gcm::new(&key);
     let nonce = Aes256Gcm::generate_nonce(&mut aes_gcm::aead::OsRng);
@@ -266,7 +431,7 @@ pub fn aes_encrypt(data: &[u8]) -> Result<(Vec<u8>, Vec<u8>, Vec<u8>)> {
         .encrypt(&nonce, data)
         .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
 
-    Ok((ciphertext, key.to_vec(), nonce.to_vec()))
+    Ok((ciphertext, Zeroizing::new(key.to_vec()), nonce.to_vec()))
 }
 
 pub fn aes_encrypt_custom_key(key: &[u8], data: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
@@ -280,7 +445,7 @@ pub fn aes_encrypt_custom_key(key: &[u8], data: &[u8]) -> Result<(Vec<u8>, Vec<u
     Ok((ciphertext, nonce.to_vec()))
 }
 
-pub fn aes_decrypt(ciphertext: &[u8], key: &[u8], nonce: &[u8]) -> Result<Vec<u8>> {
+pub fn aes_decrypt(ciphertext: &[u8], key: &[u8], nonce: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
     let cipher = Aes256Gcm::new_from_slice(key).context(current_fn!())?;
     let nonce = Nonce::from_slice(nonce);
 
@@ -288,7 +453,110 @@ pub fn aes_decrypt(ciphertext: &[u8], key: &[u8], nonce: &[u8]) -> Result<Vec<u8
         .decrypt(nonce, ciphertext)
         .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
 
-    Ok(original)
+    Ok(Zeroizing::new(original))
+}
+
+/**
+* Decrypts the body of `res` while it is downloaded, only one encrypted chunk is buffered and
+* every decrypted chunk is written to `writer` right away.
+* layout: `magic | version | chunk_size | nonce_prefix | chunk_0 | ... | chunk_n`,
+* every chunk is `chunk_size + 16` bytes except the last one (STREAM, 32-bit big endian counter).
+* Bodies without the stream header are the older single-shot base64 ciphertext,
+* those are decrypted with `nonce`.
+* When `sha256` is given, the received bytes are checked against it before decrypting the last chunk.
+* A truncated or tampered body is only detected at the end, discard `writer` on error.
+*/
+pub async fn aes_stream_decrypt_response<W>(
+    mut res: reqwest::Response,
+    key: &[u8],
+    nonce: &[u8],
+    sha256: Option<&str>,
+    mut writer: W,
+) -> Result<()>
+where
+    W: Write,
+{
+    let mut hasher = Sha256::new();
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut decryptor: Option<(DecryptorBE32<Aes256Gcm>, usize)> = None;
+    let mut is_stream = true;
+
+    while let Some(bytes) = res.chunk().await.context(current_fn!())? {
+        hasher.update(&bytes);
+        buffer.extend_from_slice(&bytes);
+
+        if is_stream && decryptor.is_none() {
+            if buffer.len() < AES_STREAM_HEADER_LEN {
+                continue;
+            }
+
+            if &buffer[0..4] != AES_STREAM_MAGIC {
+                is_stream = false;
+                continue;
+            }
+
+            if buffer[4] != AES_STREAM_VERSION {
+                return Err(
+                    anyhow!(format!("Unsupported stream version: {}", buffer[4]))
+                        .context(current_fn!()),
+                );
+            }
+
+            let chunk_size: [u8; 4] = buffer[5..9].try_into().context(current_fn!())?;
+            let cipher = Aes256Gcm::new_from_slice(key)
+                .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
+
+            decryptor = Some((
+                DecryptorBE32::from_aead(
+                    cipher,
+                    GenericArray::from_slice(&buffer[9..AES_STREAM_HEADER_LEN]),
+                ),
+                u32::from_be_bytes(chunk_size) as usize + 16,
+            ));
+            buffer.drain(..AES_STREAM_HEADER_LEN);
+        }
+
+        // Keep the remainder, the last chunk is only known once the body ends
+        if let Some((decryptor, enc_chunk_size)) = decryptor.as_mut() {
+            while buffer.len() > *enc_chunk_size {
+                let enc_chunk: Vec<u8> = buffer.drain(..*enc_chunk_size).collect();
+                let chunk = decryptor
+                    .decrypt_next(enc_chunk.as_slice())
+                    .map(Zeroizing::new)
+                    .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
+                writer.write_all(&chunk).context(current_fn!())?;
+            }
+        }
+    }
+
+    if let Some(expected) = sha256 {
+        let actual = hex::encode(hasher.finalize());
+
+        if actual != expected {
+            return Err(PatientError::IntegrityMismatch {
+                actual,
+                expected: expected.to_string(),
+            }
+            .into());
+        }
+    }
+
+    let data = match decryptor {
+        Some((decryptor, _)) => decryptor
+            .decrypt_last(buffer.as_slice())
+            .map(Zeroizing::new)
+            .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?,
+        None => aes_decrypt(
+            &STANDARD.decode(&buffer).context(current_fn!())?,
+            key,
+            nonce,
+        )
+        .context(current_fn!())?,
+    };
+    writer.write_all(&data).context(current_fn!())?;
+    writer.flush().context(current_fn!())?;
+
+    Ok(())
 }
 
 pub fn sha_hash(data: &[u8]) -> Vec<u8> {
@@ -320,6 +588,7 @@ pub async fn get_iota_client() -> Result<IotaClient> {
     Ok(IotaClientBuilder::default()
         .build(IOTA_URL)
         .await
+        .map_err(|err| UnreachableError(err.to_string()))
         .context(current_fn!())?)
 }
 
@@ -348,18 +617,121 @@ pub fn handle_error_execute_tx(response: ExecuteTxResponse) -> Result<()> {
 }
 
 /**
- * Return: `(metadata, content)`
+ * Tries the image as is, then downscaled, contrast normalized, binarized and inverted, until a
+ * detected code parses with `parse`. A parse error of a code that is a DecMed QR, e.g. an expired
+ * one, is preferred over `QrDecodeError::NotDecMed`.
  */
-pub fn process_qr_image(qr_image_bytes: &[u8]) -> Result<(rqrr::MetaData, String)> {
-    let reader = ImageReader::new(Cursor::new(qr_image_bytes))
+pub fn process_qr_image<T>(
+    qr_image_bytes: &[u8],
+    parse: impl Fn(&str) -> Result<T, PatientError>,
+) -> Result<T, PatientError> {
+    let image = ImageReader::new(Cursor::new(qr_image_bytes))
         .with_guessed_format()
-        .context(current_fn!())?;
-    let image = reader.decode().context(current_fn!())?;
+        .ok()
+        .and_then(|reader| reader.into_decoder().ok())
+        .and_then(|mut decoder| {
+            let orientation = decoder.orientation().ok();
+            let mut image = DynamicImage::from_decoder(decoder).ok()?;
+            if let Some(orientation) = orientation {
+                image.apply_orientation(orientation);
+            }
+
+            Some(image)
+        })
+        .ok_or(PatientError::QrDecode(QrDecodeError::InvalidImage))?;
+
+    let image = if image.width().max(image.height()) > QR_IMAGE_DIMENSION_MAX {
+        image.resize(
+            QR_IMAGE_DIMENSION_MAX,
+            QR_IMAGE_DIMENSION_MAX,
+            FilterType::Triangle,
+        )
+    } else {
+        image
+    };
     let image = image.to_luma8();
-    let mut prepared_image = rqrr::PreparedImage::prepare(image);
-    let grids = prepared_image.detect_grids();
+    let normalized_image = normalize_qr_image_contrast(&image);
+    let binarized_image = binarize_qr_image(&normalized_image);
+    let mut inverted_image = binarized_image.clone();
+    imageops::invert(&mut inverted_image);
+
+    let mut detected_max = 0;
+    let mut decoded_max = 0;
+    let mut parse_err = None;
+
+    for image in [image, normalized_image, binarized_image, inverted_image] {
+        let mut prepared_image = rqrr::PreparedImage::prepare(image);
+        let grids = prepared_image.detect_grids();
+        let mut decoded = 0;
+
+        for grid in &grids {
+            let Ok((_, content)) = grid.decode() else {
+                continue;
+            };
+            decoded += 1;
+
+            match parse(&content) {
+                Ok(value) => return Ok(value),
+                Err(PatientError::QrDecode(QrDecodeError::NotDecMed { .. })) => {}
+                Err(err) => parse_err = parse_err.or(Some(err)),
+            }
+        }
+
+        detected_max = detected_max.max(grids.len());
+        decoded_max = decoded_max.max(decoded);
+    }
+
+    if let Some(err) = parse_err {
+        return Err(err);
+    }
+
+    Err(PatientError::QrDecode(if decoded_max > 0 {
+        QrDecodeError::NotDecMed {
+            decoded: decoded_max,
+        }
+    } else if detected_max > 0 {
+        QrDecodeError::Unreadable {
+            detected: detected_max,
+        }
+    } else {
+        QrDecodeError::NoCodeFound
+    }))
+}
+
+/**
+ * Stretches the luma range to the full 0..=255, low-contrast photos are otherwise missed
+ */
+fn normalize_qr_image_contrast(image: &GrayImage) -> GrayImage {
+    let (min, max) = image
+        .pixels()
+        .fold((u8::MAX, u8::MIN), |(min, max), pixel| {
+            (min.min(pixel[0]), max.max(pixel[0]))
+        });
+
+    if max <= min {
+        return image.clone();
+    }
 
-    Ok(grids[0].decode().context(current_fn!())?)
+    let range = (max - min) as u32;
+    GrayImage::from_fn(image.width(), image.height(), |x, y| {
+        Luma([((image.get_pixel(x, y)[0] - min) as u32 * 255 / range) as u8])
+    })
+}
+
+/**
+ * Thresholds at the mean luma
+ */
+fn binarize_qr_image(image: &GrayImage) -> GrayImage {
+    let pixel_count = (image.width() as u64 * image.height() as u64).max(1);
+    let mean = image.pixels().map(|pixel| pixel[0] as u64).sum::<u64>() / pixel_count;
+
+    GrayImage::from_fn(image.width(), image.height(), |x, y| {
+        if image.get_pixel(x, y)[0] as u64 > mean {
+            Luma([u8::MAX])
+        } else {
+            Luma([u8::MIN])
+        }
+    })
 }
 
 pub fn validate_by_regex(value: &str, regex: &str) -> Result<bool> {
@@ -367,22 +739,165 @@ pub fn validate_by_regex(value: &str, regex: &str) -> Result<bool> {
     Ok(re.is_match(value))
 }
 
-pub fn decode_hospital_personnel_qr(content: String) -> Result<(IotaAddress, PublicKey)> {
-    let content: Vec<&str> = content.split("@").collect();
+/**
+ * Checks the version, validity and the signature by the personnel IOTA key. Whether the address
+ * belongs to a hospital personnel account is checked on chain by the caller.
+ * content: `{HOSPITAL_PERSONNEL_QR_PREFIX}:{version}:{base64 payload}:{base64 signature}`
+ */
+pub fn decode_hospital_personnel_qr(content: &str) -> Result<HospitalPersonnelQr, PatientError> {
+    let content: Vec<&str> = content.trim().split(':').collect();
 
-    if content.len() != 2 {
-        return Err(anyhow!(
-            "Invalid content length, expected 2 found {}",
-            content.len()
-        ))
-        .context(current_fn!());
+    if content.len() != 4 || content[0] != HOSPITAL_PERSONNEL_QR_PREFIX {
+        return Err(PatientError::QrDecode(QrDecodeError::NotDecMed {
+            decoded: 1,
+        }));
+    }
+
+    let version: u8 = content[1]
+        .parse()
+        .map_err(|_| anyhow!("Not a DecMed hospital personnel QR"))?;
+    if version != HOSPITAL_PERSONNEL_QR_VERSION {
+        return Err(anyhow!("Unsupported hospital personnel QR version: {}", version).into());
+    }
+
+    let payload: HospitalPersonnelQrPayload =
+        serde_json::from_slice(&STANDARD.decode(content[2]).context(current_fn!())?)
+            .context(current_fn!())?;
+    let iota_address = IotaAddress::from_str(&payload.iota_address).context(current_fn!())?;
+
+    // The payload is signed as sent, before decoding
+    let signature = Signature::from_str(content[3])
+        .map_err(|_| anyhow!("Invalid hospital personnel QR signature"))?;
+    signature
+        .verify_secure(
+            &IntentMessage::new(Intent::personal_message(), content[2].to_string()),
+            iota_address,
+            SignatureScheme::ED25519,
+        )
+        .map_err(|_| anyhow!("Invalid hospital personnel QR signature"))?;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .context(current_fn!())?
+        .as_secs();
+    if payload.iat > now + HOSPITAL_PERSONNEL_QR_CLOCK_SKEW
+        || payload.exp < payload.iat
+        || payload.exp - payload.iat > HOSPITAL_PERSONNEL_QR_VALIDITY_MAX
+    {
+        return Err(anyhow!("Invalid hospital personnel QR validity").into());
+    }
+    if payload.exp <= now {
+        return Err(anyhow!("Hospital personnel QR expired, ask for a new one").into());
     }
 
-    let iota_address = IotaAddress::from_str(content[0]).context(current_fn!())?;
     let pre_public_key =
-        serde_deserialize_from_base64(content[1].to_string()).context(current_fn!())?;
+        serde_deserialize_from_base64(payload.pre_public_key).context(current_fn!())?;
+
+    let patient_iota_address = payload
+        .patient_iota_address
+        .as_deref()
+        .map(IotaAddress::from_str)
+        .transpose()
+        .context(current_fn!())?;
+
+    Ok(HospitalPersonnelQr {
+        iota_address,
+        patient_iota_address,
+        pre_public_key,
+        reason: payload.reason,
+        requested_scope: payload.scope,
+    })
+}
 
-    Ok((iota_address, pre_public_key))
+/**
+ * code: `decmed://grant?code=...` deep link or the pasted hospital personnel QR content
+ */
+pub fn decode_access_handoff_code(code: &str) -> Result<HospitalPersonnelQr, PatientError> {
+    let code = code.trim();
+
+    if !code.starts_with(&format!("{}:", ACCESS_HANDOFF_URI_SCHEME)) {
+        return decode_hospital_personnel_qr(code);
+    }
+
+    let uri = reqwest::Url::parse(code).context(current_fn!())?;
+    if uri.host_str() != Some(ACCESS_HANDOFF_URI_HOST) {
+        return Err(anyhow!("Unsupported DecMed link").into());
+    }
+
+    let content = uri
+        .query_pairs()
+        .find(|(key, _)| key == ACCESS_HANDOFF_URI_CODE_PARAM)
+        .map(|(_, value)| value.into_owned())
+        .ok_or(anyhow!("DecMed link without an access code"))?;
+
+    decode_hospital_personnel_qr(&content)
+}
+
+/**
+ * content: `{GUARDIAN_CODE_PREFIX}:{version}:{base64 payload}:{base64 signature}`
+ */
+pub fn encode_guardian_code(
+    iota_key_pair: &IotaKeyPair,
+    pre_public_key: &PublicKey,
+) -> Result<String> {
+    let payload = GuardianCodePayload {
+        iota_address: IotaAddress::from(&iota_key_pair.public()).to_string(),
+        pre_public_key: serde_serialize_to_base64(pre_public_key).context(current_fn!())?,
+    };
+    let payload = STANDARD.encode(serde_json::to_vec(&payload).context(current_fn!())?);
+
+    let signature = Signature::new_secure(
+        &IntentMessage::new(Intent::personal_message(), payload.clone()),
+        iota_key_pair,
+    );
+
+    Ok(format!(
+        "{}:{}:{}:{}",
+        GUARDIAN_CODE_PREFIX,
+        GUARDIAN_CODE_VERSION,
+        payload,
+        signature.encode_base64()
+    ))
+}
+
+/**
+ * Checks the version and the signature by the guardian IOTA key. Whether the address belongs to a
+ * patient account is checked on chain.
+ */
+pub fn decode_guardian_code(content: &str) -> Result<GuardianCode> {
+    let content: Vec<&str> = content.trim().split(':').collect();
+
+    if content.len() != 4 || content[0] != GUARDIAN_CODE_PREFIX {
+        return Err(anyhow!("Not a DecMed guardian code"));
+    }
+
+    let version: u8 = content[1]
+        .parse()
+        .map_err(|_| anyhow!("Not a DecMed guardian code"))?;
+    if version != GUARDIAN_CODE_VERSION {
+        return Err(anyhow!("Unsupported guardian code version: {}", version));
+    }
+
+    let payload: GuardianCodePayload =
+        serde_json::from_slice(&STANDARD.decode(content[2]).context(current_fn!())?)
+            .context(current_fn!())?;
+    let iota_address = IotaAddress::from_str(&payload.iota_address).context(current_fn!())?;
+
+    // The payload is signed as sent, before decoding
+    Signature::from_str(content[3])
+        .map_err(|_| anyhow!("Invalid guardian code signature"))?
+        .verify_secure(
+            &IntentMessage::new(Intent::personal_message(), content[2].to_string()),
+            iota_address,
+            SignatureScheme::ED25519,
+        )
+        .map_err(|_| anyhow!("Invalid guardian code signature"))?;
+
+    Ok(GuardianCode {
+        iota_address,
+        pre_public_key: serde_deserialize_from_base64(payload.pre_public_key)
+            .context(current_fn!())?,
+    })
 }
 
 pub async fn do_http_post_json_request<P, T, E>(
@@ -416,6 +931,35 @@ where
     Ok(res_body)
 }
 
+/**
+* return: response with the body not consumed yet
+*/
+pub async fn do_http_post_json_request_stream<P, E>(
+    endpoint: &str,
+    payload: &P,
+    req_client: &Client,
+    success_status_code: StatusCode,
+) -> Result<reqwest::Response>
+where
+    P: Serialize,
+    E: DeserializeOwned + Debug,
+{
+    let res = req_client
+        .post(endpoint)
+        .json(payload)
+        .send()
+        .await
+        .context(current_fn!())?;
+
+    if res.status() != success_status_code {
+        let res_body = res.bytes().await.context(current_fn!())?;
+        let error: E = serde_json::from_slice(res_body.as_bytes()).context(current_fn!())?;
+        return Err(anyhow!(format!("{:#?}", error)).context(current_fn!()));
+    }
+
+    Ok(res)
+}
+
 pub fn sys_time_to_iso(system_time: SystemTime) -> String {
     let iso: DateTime<Utc> = system_time.into();
     iso.to_rfc3339()
@@ -430,10 +974,10 @@ pub fn get_iota_address_from_keys_entry(keys_entry: &KeysEntry) -> Result<IotaAd
     Ok(IotaAddress::from_str(&iota_address).context(current_fn!())?)
 }
 
-pub fn get_iota_key_pair_from_keys_entry(
-    keys_entry: &KeysEntry,
-    pin: String,
-) -> Result<IotaKeyPair> {
+/**
+* The decrypted key pair encoding is wiped once decoded, `IotaKeyPair` wipes itself on drop
+*/
+pub fn get_iota_key_pair_from_keys_entry(keys_entry: &KeysEntry, pin: &str) -> Result<IotaKeyPair> {
     let iota_key_pair = STANDARD
         .decode(
             keys_entry
@@ -450,10 +994,11 @@ pub fn get_iota_key_pair_from_keys_entry(
             .context(current_fn!())?;
     let iota_key_pair = aes_decrypt(
         &iota_key_pair,
-        &sha_hash(pin.as_bytes()),
+        &derive_pin_key(keys_entry, pin).context(current_fn!())?,
         &iota_key_pair_nonce,
     )?;
-    let iota_key_pair = String::from_utf8(iota_key_pair).context(current_fn!())?;
+    let iota_key_pair =
+        Zeroizing::new(String::from_utf8(iota_key_pair.to_vec()).context(current_fn!())?);
     let iota_key_pair = IotaKeyPair::decode(&iota_key_pair)
         .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
 
@@ -470,10 +1015,22 @@ pub fn _get_pre_public_key_from_keys_entry(keys_entry: &KeysEntry) -> Result<Pub
     .context(current_fn!())
 }
 
+/**
+* The decrypted PRE seed is wiped once the keys are derived, `SecretKey` wipes itself on drop
+*/
 pub fn get_pre_keys_from_keys_entry(
     keys_entry: &KeysEntry,
-    pin: String,
+    pin: &str,
 ) -> Result<(SecretKey, PublicKey)> {
+    let pre_seed = get_pre_seed_from_keys_entry(keys_entry, pin).context(current_fn!())?;
+
+    compute_pre_keys(&pre_seed).context(current_fn!())
+}
+
+pub fn get_pre_seed_from_keys_entry(
+    keys_entry: &KeysEntry,
+    pin: &str,
+) -> Result<Zeroizing<Vec<u8>>> {
     let pre_seed = STANDARD
         .decode(
             keys_entry
@@ -492,14 +1049,302 @@ pub fn get_pre_keys_from_keys_entry(
         )
         .context(current_fn!())?;
 
-    let pre_seed = aes_decrypt(&pre_seed, &sha_hash(pin.as_bytes()), &pre_seed_nonce)?;
+    aes_decrypt(
+        &pre_seed,
+        &derive_pin_key(keys_entry, pin).context(current_fn!())?,
+        &pre_seed_nonce,
+    )
+}
+
+/**
+ * Derives the key the IOTA key pair and PRE seed are encrypted with, according to the vault
+ * version of `keys_entry`
+ * return: 32 bytes key
+ */
+pub fn derive_pin_key(keys_entry: &KeysEntry, pin: &str) -> Result<Zeroizing<Vec<u8>>> {
+    match keys_entry.vault_version {
+        0 => Ok(Zeroizing::new(sha_hash(pin.as_bytes()))),
+        1 => {
+            let pin_kdf = keys_entry
+                .pin_kdf
+                .as_ref()
+                .ok_or(anyhow!("PIN KDF not found on keys entry").context(current_fn!()))?;
 
-    let pre_secret_key = SecretKeyFactory::from_secure_randomness(&pre_seed)
-        .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?
-        .make_key(&pre_seed);
-    let pre_public_key = pre_secret_key.public_key();
+            derive_argon2id_key(pin.as_bytes(), pin_kdf).context(current_fn!())
+        }
+        vault_version => Err(
+            anyhow!("Unsupported keys entry vault version: {}", vault_version)
+                .context(current_fn!())
+                .into(),
+        ),
+    }
+}
+
+/**
+ * return: 32 bytes key
+ */
+pub fn derive_argon2id_key(secret: &[u8], pin_kdf: &PinKdf) -> Result<Zeroizing<Vec<u8>>> {
+    let params = Params::new(pin_kdf.m_cost, pin_kdf.t_cost, pin_kdf.p_cost, Some(32))
+        .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
+    let salt = STANDARD.decode(&pin_kdf.salt).context(current_fn!())?;
+
+    let mut key = Zeroizing::new(vec![0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(secret, &salt, &mut key)
+        .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
+
+    Ok(key)
+}
+
+/**
+ * return: `PIN_KDF_*` parameters with a fresh salt
+ */
+pub fn generate_pin_kdf() -> PinKdf {
+    let mut salt = [0u8; PIN_KDF_SALT_LEN];
+    rand::rng().fill(&mut salt);
+
+    PinKdf {
+        m_cost: PIN_KDF_M_COST,
+        p_cost: PIN_KDF_P_COST,
+        salt: STANDARD.encode(salt),
+        t_cost: PIN_KDF_T_COST,
+    }
+}
+
+/**
+ * Encrypts the IOTA key pair and PRE seed with a key derived from `pin` and a fresh salt, the
+ * entry is upgraded to `KEYS_ENTRY_VAULT_VERSION`.
+ */
+pub fn seal_keys_entry(
+    keys_entry: &mut KeysEntry,
+    pin: &str,
+    iota_key_pair: &IotaKeyPair,
+    pre_seed: &[u8],
+) -> Result<()> {
+    keys_entry.pin_kdf = Some(generate_pin_kdf());
+    keys_entry.vault_version = KEYS_ENTRY_VAULT_VERSION;
+
+    let pin_key = derive_pin_key(keys_entry, pin).context(current_fn!())?;
+    let (enc_pre_seed, pre_seed_nonce) =
+        aes_encrypt_custom_key(&pin_key, pre_seed).context(current_fn!())?;
+    let iota_key_pair = Zeroizing::new(
+        iota_key_pair
+            .encode()
+            .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?,
+    );
+    let (enc_iota_key_pair, iota_key_pair_nonce) =
+        aes_encrypt_custom_key(&pin_key, iota_key_pair.as_bytes()).context(current_fn!())?;
+
+    keys_entry.iota_key_pair = Some(STANDARD.encode(enc_iota_key_pair));
+    keys_entry.iota_nonce = Some(STANDARD.encode(iota_key_pair_nonce));
+    keys_entry.pre_nonce = Some(STANDARD.encode(pre_seed_nonce));
+    keys_entry.pre_secret_key = Some(STANDARD.encode(enc_pre_seed));
 
-    Ok((pre_secret_key, pre_public_key))
+    Ok(())
+}
+
+/**
+ * Checks `pin` against the keys entry stored on `entry`, attempts are counted on
+ * `pin_attempts_entry`:
+ * - a failed attempt is counted, past `PIN_FREE_ATTEMPTS` the PIN is locked for an escalating
+ *   duration and the key material is wiped once `Settings.pin_wipe_after_failed_attempts` is
+ *   reached
+ * - a successful attempt clears the counter and migrates entries on an older vault version
+ * return: the unlocked keys entry
+ */
+pub fn unlock_keys_entry(
+    entry: &Entry,
+    pin_attempts_entry: &Entry,
+    pin: &str,
+) -> Result<KeysEntry> {
+    let mut keys_entry =
+        parse_keys_entry(&entry.get_secret().context(current_fn!())?).context(current_fn!())?;
+    let mut pin_attempts = load_pin_attempts(pin_attempts_entry).context(current_fn!())?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .context(current_fn!())?
+        .as_secs();
+
+    if let Some(locked_until) = pin_attempts.locked_until {
+        if locked_until > now {
+            return Err(anyhow!(
+                "Too many failed PIN attempts, try again in {} seconds",
+                locked_until - now
+            )
+            .into());
+        }
+    }
+
+    let enc_pre_seed = STANDARD
+        .decode(
+            keys_entry
+                .pre_secret_key
+                .as_ref()
+                .ok_or(anyhow!("PRE Seed not found on keys entry").context(current_fn!()))?,
+        )
+        .context(current_fn!())?;
+    let pre_seed_nonce = STANDARD
+        .decode(
+            keys_entry
+                .pre_nonce
+                .as_ref()
+                .ok_or(anyhow!("PRE Seed nonce not found on keys entry").context(current_fn!()))?,
+        )
+        .context(current_fn!())?;
+    let pin_key = derive_pin_key(&keys_entry, pin).context(current_fn!())?;
+
+    let Ok(pre_seed) = aes_decrypt(&enc_pre_seed, &pin_key, &pre_seed_nonce) else {
+        pin_attempts.failed_attempts += 1;
+
+        let wipe = keys_entry
+            .settings
+            .pin_wipe_after_failed_attempts
+            .is_some_and(|max_attempts| pin_attempts.failed_attempts >= max_attempts);
+
+        if wipe {
+            keys_entry.iota_key_pair = None;
+            keys_entry.iota_nonce = None;
+            keys_entry.pin_kdf = None;
+            keys_entry.pre_nonce = None;
+            keys_entry.pre_secret_key = None;
+            keys_entry.proxy_jwt = None;
+            entry
+                .set_secret(&serde_json::to_vec(&keys_entry).context(current_fn!())?)
+                .context(current_fn!())?;
+
+            pin_attempts = PinAttempts::default();
+        } else if pin_attempts.failed_attempts > PIN_FREE_ATTEMPTS {
+            let exp = (pin_attempts.failed_attempts - PIN_FREE_ATTEMPTS - 1).min(16);
+            pin_attempts.locked_until =
+                Some(now + (PIN_LOCKOUT_BASE_DUR << exp).min(PIN_LOCKOUT_MAX_DUR));
+        }
+
+        save_pin_attempts(pin_attempts_entry, &pin_attempts).context(current_fn!())?;
+
+        if wipe {
+            return Err(
+                anyhow!("Too many failed PIN attempts, keys removed from this device").into(),
+            );
+        }
+
+        return Err(anyhow!("Invalid PIN").into());
+    };
+
+    if keys_entry.vault_version < KEYS_ENTRY_VAULT_VERSION {
+        let iota_key_pair =
+            get_iota_key_pair_from_keys_entry(&keys_entry, pin).context(current_fn!())?;
+        seal_keys_entry(&mut keys_entry, pin, &iota_key_pair, &pre_seed).context(current_fn!())?;
+
+        entry
+            .set_secret(&serde_json::to_vec(&keys_entry).context(current_fn!())?)
+            .context(current_fn!())?;
+    }
+
+    if pin_attempts.failed_attempts > 0 || pin_attempts.locked_until.is_some() {
+        save_pin_attempts(pin_attempts_entry, &PinAttempts::default()).context(current_fn!())?;
+    }
+
+    Ok(keys_entry)
+}
+
+/**
+ * The PRE seed is the same on every device the patient signs in to and doesn't change with the
+ * PIN, unlike the PIN key
+ * return: 32 bytes key
+ */
+pub fn derive_record_index_key(pre_seed: &[u8]) -> Zeroizing<Vec<u8>> {
+    let key_material = Zeroizing::new([RECORD_INDEX_KEY_DOMAIN, pre_seed].concat());
+
+    Zeroizing::new(sha_hash(&key_material))
+}
+
+pub fn record_index_path(record_index_dir: &Path, profile_id: &str) -> PathBuf {
+    record_index_dir.join(format!("{}.json", profile_id))
+}
+
+/**
+ * return: an empty index when the profile has none yet
+ */
+pub fn load_record_index(path: &Path, key: &[u8]) -> Result<RecordIndex> {
+    let enc_record_index = match fs::read(path) {
+        Ok(enc_record_index) => enc_record_index,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(RecordIndex::default()),
+        Err(err) => return Err(anyhow!(err).context(current_fn!())),
+    };
+    let enc_record_index: EncRecordIndex =
+        serde_json::from_slice(&enc_record_index).context(current_fn!())?;
+
+    if enc_record_index.version != RECORD_INDEX_VERSION {
+        return Err(anyhow!(
+            "Unsupported record index version: {}",
+            enc_record_index.version
+        )
+        .context(current_fn!()));
+    }
+
+    let record_index = aes_decrypt(
+        &STANDARD
+            .decode(&enc_record_index.enc_data)
+            .context(current_fn!())?,
+        key,
+        &STANDARD
+            .decode(&enc_record_index.nonce)
+            .context(current_fn!())?,
+    )
+    .context(current_fn!())?;
+
+    serde_json::from_slice(&record_index).context(current_fn!())
+}
+
+/**
+ * Written to a temporary file first, so an interrupted write keeps the previous index
+ */
+pub fn save_record_index(path: &Path, key: &[u8], record_index: &RecordIndex) -> Result<()> {
+    let record_index = Zeroizing::new(serde_json::to_vec(record_index).context(current_fn!())?);
+    let (enc_data, nonce) = aes_encrypt_custom_key(key, &record_index).context(current_fn!())?;
+    let enc_record_index = EncRecordIndex {
+        enc_data: STANDARD.encode(enc_data),
+        nonce: STANDARD.encode(nonce),
+        version: RECORD_INDEX_VERSION,
+    };
+
+    if let Some(record_index_dir) = path.parent() {
+        fs::create_dir_all(record_index_dir).context(current_fn!())?;
+    }
+
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(
+        &tmp_path,
+        serde_json::to_vec(&enc_record_index).context(current_fn!())?,
+    )
+    .context(current_fn!())?;
+    fs::rename(&tmp_path, path).context(current_fn!())
+}
+
+pub fn remove_record_index(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Ok(_) => Ok(()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(anyhow!(err).context(current_fn!())),
+    }
+}
+
+/**
+ * return: date of an RFC 3339 timestamp in the local timezone
+ */
+pub fn iso_to_local_date(iso: &str) -> Result<NaiveDate> {
+    Ok(DateTime::parse_from_rfc3339(iso)
+        .context(current_fn!())?
+        .with_timezone(&Local)
+        .date_naive())
+}
+
+/**
+ * `date` is YYYY-MM-DD
+ */
+pub fn parse_date(date: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| anyhow!("Invalid args: date is invalid"))
 }
 
 pub fn serde_serialize_to_base64<T>(val: &T) -> Result<String>
@@ -520,61 +1365,76 @@ where
     Ok(ori_val)
 }
 
-pub fn compute_seed_from_seed_words(seed_words: &str, passphrase: &str) -> Result<[u8; 64]> {
+pub fn compute_seed_from_seed_words(
+    seed_words: &str,
+    passphrase: &str,
+) -> Result<Zeroizing<[u8; 64]>> {
     let mnemonic = Mnemonic::from_str(seed_words).context(current_fn!())?;
-    Ok(mnemonic.to_seed_normalized(passphrase))
+    Ok(Zeroizing::new(mnemonic.to_seed_normalized(passphrase)))
 }
 
-pub async fn do_http_get_request<T, E, U>(
-    req_client: &Client,
-    success_status_code: StatusCode,
-    url: U,
-) -> Result<T>
-where
-    T: DeserializeOwned + From<String>,
-    E: DeserializeOwned + Debug,
-    U: IntoUrl,
-{
-    let res = req_client.get(url).send().await.context(current_fn!())?;
-    let res_status = res.status();
-    let content_type = res
-        .headers()
-        .get(reqwest::header::CONTENT_TYPE)
-        .and_then(|v| v.to_str().ok())
-        .ok_or(anyhow!("Failed to get content type from header").context(current_fn!()))?
-        .to_string();
-    let res_body = res.bytes().await.context(current_fn!())?;
-
-    if res_status != success_status_code {
-        let error: E = serde_json::from_slice(&res_body.to_vec()).context(current_fn!())?;
-
-        return Err(anyhow!(format!("{:#?}", error)).context(current_fn!()));
+/**
+* Tries `IPFS_GATEWAY_BASE_URL` first, then `fallback_base_urls` in order.
+* return: gateway response with the body not consumed yet
+*/
+pub async fn get_data_ipfs(
+    cid: String,
+    fallback_base_urls: &[String],
+) -> Result<reqwest::Response> {
+    let req_client = reqwest::Client::new();
+    let mut errors: Vec<String> = Vec::new();
+    // A gateway answering other than 5xx is reachable, e.g. a 404 is not hidden by the cache
+    let mut is_unreachable = true;
+
+    for base_url in
+        std::iter::once(IPFS_GATEWAY_BASE_URL).chain(fallback_base_urls.iter().map(String::as_str))
+    {
+        match req_client
+            .get(format!("{}/ipfs/{}", base_url, cid))
+            .send()
+            .await
+        {
+            Ok(res) if res.status() == StatusCode::OK => return Ok(res),
+            Ok(res) => {
+                is_unreachable &= res.status().is_server_error();
+                errors.push(format!("{}: {}", base_url, res.status()))
+            }
+            Err(e) => errors.push(format!("{}: {}", base_url, e)),
+        }
     }
 
-    match content_type.as_str() {
-        "application/json" => {
-            Ok(serde_json::from_slice(&res_body.to_vec()).context(current_fn!())?)
-        }
-        "text/plain; charset=utf-8" => Ok(T::from(
-            String::from_utf8(res_body.to_vec()).context(current_fn!())?,
-        )),
-        _ => {
-            return Err(
-                anyhow!(format!("Unknown content-type: {}", content_type)).context(current_fn!())
-            )
-        }
+    let message = format!("Failed to get content from IPFS: {}", errors.join(", "));
+    if is_unreachable {
+        return Err(anyhow::Error::new(UnreachableError(message)).context(current_fn!()));
     }
-}
 
-pub async fn get_data_ipfs(cid: String) -> Result<String> {
-    let req_client = reqwest::Client::new();
-    let content = do_http_get_request::<String, String, _>(
-        &req_client,
-        StatusCode::OK,
-        format!("{}/ipfs/{}", IPFS_GATEWAY_BASE_URL, cid),
-    )
-    .await
-    .context(current_fn!())?;
+    Err(anyhow!(message).context(current_fn!()))
+}
 
-    Ok(content)
+/**
+* return: every version of a record, oldest first and the current one last
+*/
+pub fn medical_metadata_versions(medical_metadata: MedicalMetadata) -> Vec<MedicalMetadataVersion> {
+    let MedicalMetadata {
+        backend,
+        capsule,
+        created_at,
+        created_by,
+        enc_key_and_nonce,
+        locator,
+        sha256,
+        mut versions,
+    } = medical_metadata;
+
+    versions.push(MedicalMetadataVersion {
+        backend,
+        capsule,
+        created_at,
+        created_by,
+        enc_key_and_nonce,
+        locator,
+        sha256,
+    });
+
+    versions
 }
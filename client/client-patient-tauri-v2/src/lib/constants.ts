@@ -6,3 +6,22 @@ export const ONE_MB = ONE_KB * ONE_KB;
 export const MAX_IMAGE_FILE_SIZE = ONE_MB;
 export const PNG_MIME_TYPE = 'image/png';
 export const JPEG_MIME_TYPE = 'image/jpeg';
+export const JSON_MIME_TYPE = 'application/json';
+export const BACKUP_PASSPHRASE_MIN_LEN = 12;
+// Bounds of the PIN wipe setting, mirror the backend
+export const PIN_WIPE_AFTER_FAILED_ATTEMPTS_MIN = 5;
+export const PIN_WIPE_AFTER_FAILED_ATTEMPTS_MAX = 20;
+export const GUARDIAN_CODE_PREFIX = 'decmed-guardian';
+// Tauri event the background watcher emits `TauriAccessEvent`s on
+export const ACCESS_EVENT = 'access-event';
+// Access durations in minutes, bounds mirror the on-chain policy
+export const ACCESS_DUR_MIN = 1;
+export const ADMINISTRATIVE_READ_DUR_DEFAULT = 5;
+export const ADMINISTRATIVE_READ_DUR_MAX = 60;
+export const MEDICAL_READ_DUR_DEFAULT = 15;
+export const MEDICAL_READ_DUR_MAX = 24 * 60;
+export const MEDICAL_UPDATE_DUR_DEFAULT = 2 * 60;
+export const MEDICAL_UPDATE_DUR_MAX = 24 * 60;
+export const MEDICAL_RECORDS_PAGE_SIZE_MAX = 50;
+// FHIR R4 bundles, the hospital app imports the same format
+export const FHIR_EXPORT_RECORDS_MAX = 50;
@@ -1,5 +1,12 @@
 import { z } from 'zod';
-import { JPEG_MIME_TYPE, ONE_MB, PNG_MIME_TYPE } from './constants';
+import {
+	BACKUP_PASSPHRASE_MIN_LEN,
+	GUARDIAN_CODE_PREFIX,
+	JPEG_MIME_TYPE,
+	JSON_MIME_TYPE,
+	ONE_MB,
+	PNG_MIME_TYPE
+} from './constants';
 
 const confirmPinSchema = {
 	confirmPin: z
@@ -24,6 +31,16 @@ const nameSchema = {
 		.transform((val) => val.trim())
 };
 
+const backupSchema = {
+	backup: z
+		.instanceof(File, { message: 'Please upload a file.' })
+		.refine((f) => f.size <= ONE_MB, 'Max 1 MB upload size.')
+		.refine(
+			(f) => f.type === JSON_MIME_TYPE || f.name.endsWith('.json'),
+			'Should be application/json'
+		)
+};
+
 const birthPlaceSchema = {
 	birthPlace: z
 		.string({
@@ -111,6 +128,17 @@ const nikSchema = {
 		.transform((val) => val.trim())
 };
 
+const passphraseSchema = {
+	passphrase: z
+		.string({
+			required_error: 'Passphrase is required.',
+			invalid_type_error: 'Passphrase is invalid.'
+		})
+		.min(BACKUP_PASSPHRASE_MIN_LEN, {
+			message: `Passphrase minimum ${BACKUP_PASSPHRASE_MIN_LEN} characters.`
+		})
+};
+
 const pinSchema = {
 	pin: z
 		.string({
@@ -144,6 +172,23 @@ const seedWordsSchema = {
 		)
 };
 
+const guardianCodeSchema = {
+	guardianCode: z
+		.string({
+			required_error: 'Guardian code is required.',
+			invalid_type_error: 'Guardian code is invalid.'
+		})
+		.trim()
+		.min(1, { message: 'Guardian code is required.' })
+		.startsWith(`${GUARDIAN_CODE_PREFIX}:`, { message: 'Not a DecMed guardian code.' })
+};
+
+const guardianRightsSchema = {
+	canGrant: z.boolean().default(false),
+	canRead: z.boolean().default(false),
+	canRevoke: z.boolean().default(true)
+};
+
 const qrSchema = {
 	qr: z
 		.instanceof(File, { message: 'Please upload a file.' })
@@ -154,6 +199,15 @@ const qrSchema = {
 		)
 };
 
+export const addGuardianSchema = z
+	.object(guardianCodeSchema)
+	.extend(guardianRightsSchema)
+	.extend(pinSchema)
+	.refine((val) => val.canGrant || val.canRead || val.canRevoke, {
+		path: ['canRevoke'],
+		message: 'A guardian needs at least one right.'
+	});
+
 export const completeProfileSchema = z
 	.object(nameSchema)
 	.extend(birthPlaceSchema)
@@ -166,8 +220,12 @@ export const completeProfileSchema = z
 
 export const enterPinSchema = z.object(pinSchema);
 
+export const exportBackupSchema = z.object(passphraseSchema).extend(pinSchema);
+
 export const hospitalQrSchema = z.object(qrSchema);
 
+export const importBackupSchema = z.object(backupSchema).extend(passphraseSchema);
+
 export const signInSchemaStep1 = z.object(pinSchema);
 export const signInSchemaStep2 = signInSchemaStep1.extend(confirmPinSchema);
 export const signInSchemaStep3 = signInSchemaStep2
@@ -7,10 +7,24 @@ export type Account = {
 
 export type CompleteProfileSchema = typeof completeProfileSchema;
 
-export type InvokeGetMedicalRecordsResponse = {
-	cid: string;
+export type InvokeGetMedicalRecordVersionsResponse = {
+	createdAt: string;
+	createdBy: string | null;
+	version: number;
+};
+
+export type InvokeGetMedicalRecords = {
+	locator: string;
 	createdAt: string;
-	index: string;
+	createdBy: string | null;
+	index: number;
+};
+
+export type InvokeGetMedicalRecordsResponse = {
+	entries: InvokeGetMedicalRecords[];
+	last_synced_at: string | null;
+	next_cursor: number | null;
+	offline: boolean;
 };
 
 export type InvokeGetAccessLog = {
@@ -26,17 +40,133 @@ export type InvokeGetAccessLog = {
 		name: string;
 	};
 	index: number;
+	is_active: boolean;
 	is_revoked: boolean;
+	scheduled_revoke_at: number | null;
+};
+
+export type InvokeGetAccessLogFilter = {
+	accessType: 'Read' | 'Update' | null;
+	hospitalName: string | null;
+	hospitalPersonnelAddress: string | null;
+	hospitalPersonnelName: string | null;
+	status: 'Active' | 'Expired' | 'Revoked' | null;
+};
+
+export type InvokeGetAccessLogResponse = {
+	entries: InvokeGetAccessLog[];
+	next_cursor: number | null;
+};
+
+// Break-glass accesses the patient has not acknowledged yet, `expDur` is in minutes
+export type InvokeGetEmergencyAccessResponse = {
+	entries: {
+		accessLogIndex: number;
+		date: string;
+		expDur: number;
+		hospitalName: string;
+		hospitalPersonnelIotaAddress: string;
+		hospitalPersonnelName: string | null;
+		index: number;
+		justification: string;
+	}[];
+	isEnabled: boolean;
+};
+
+export type InvokeGetGuardiansResponse = {
+	iotaAddress: string;
+	rights: TauriGuardianRights;
+}[];
+
+// `name` is null when the ward shared no keys, i.e. the guardian can only revoke
+export type InvokeGetWardsResponse = {
+	iotaAddress: string;
+	name: string | null;
+	rights: TauriGuardianRights;
+}[];
+
+export type InvokeListProfilesResponse = {
+	id: string;
+	label: string;
+	isActive: boolean;
+	isSignedIn: boolean;
+	iotaAddress: string | null;
+};
+
+export type InvokeSearchMedicalRecordsFilter = {
+	createdBy: string | null;
+	createdFrom: string | null;
+	createdUntil: string | null;
+	query: string | null;
+};
+
+export type InvokeSyncMedicalRecordsResponse = {
+	lastSyncedAt: string;
+	newCount: number;
+	updatedCount: number;
+};
+
+export type InvokeSearchMedicalRecordsResponse = {
+	entries: {
+		createdAt: string;
+		createdBy: string | null;
+		index: number;
+		matchedFields: string[];
+	}[];
+	indexed_count: number;
 };
 
 export type InvokeProcessQrResponse = {
 	hospitalPersonnelHospitalName: string;
 	hospitalPersonnelName: string;
+	hospitalPersonnelRole: TauriHospitalPersonnelRole;
+	requestedScope: TauriHospitalPersonnelQrScope | null;
+	reason: string | null;
+};
+
+export type InvokeGetAccessRequestsResponse = {
+	createdAt: number;
+	hospitalPersonnelHospitalName: string;
+	hospitalPersonnelName: string;
+	hospitalPersonnelRole: TauriHospitalPersonnelRole;
+	id: string;
+	reason: string | null;
+	requestedScope: TauriHospitalPersonnelQrScope | null;
+}[];
+
+// Access asked for by the hospital personnel, only used to prefill the grant form
+export type TauriHospitalPersonnelQrScope = {
+	exp_dur_read: number;
+	exp_dur_update: number | null;
+	is_medical_read: boolean;
 };
 
 export type InvokeGetMedicalRecordResponse = {
 	createdAt: string;
+	lastSyncedAt: string | null;
 	medicalData: TauriMedicalData;
+	offline: boolean;
+};
+
+export type InvokeAuthStatusResponse = {
+	// Scheduled revocations past their time, they only run while the session is unlocked
+	overdueScheduledRevocations: number;
+};
+
+export type InvokeGetSettingsResponse = {
+	ipfsGatewayFallbackBaseUrls: string[];
+	notifications: TauriNotificationSettings;
+	// `null` when the PIN wipe is off
+	pinWipeAfterFailedAttempts: number | null;
+	sessionIdleTimeout: number;
+};
+
+export type TauriAccessEvent = {
+	body: string;
+	eventType: 'AccessExpiring' | 'AccessGranted' | 'MedicalRecordAdded';
+	// Access log index, or medical record index on `MedicalRecordAdded`
+	index: number;
+	title: string;
 };
 
 export type TauriAdministrativeData = {
@@ -54,6 +184,20 @@ export type TauriAdministrativeData = {
 	maritalStatus: string | null;
 };
 
+export type TauriGuardianRights = {
+	canGrant: boolean;
+	canRead: boolean;
+	canRevoke: boolean;
+};
+
+export type TauriNotificationSettings = {
+	isAccessExpiringEnabled: boolean;
+	isAccessGrantedEnabled: boolean;
+	isMedicalRecordAddedEnabled: boolean;
+};
+
+export type TauriHospitalPersonnelRole = 'Admin' | 'AdministrativePersonnel' | 'MedicalPersonnel';
+
 export type TauriMedicalData = {
 	anamnesis: string;
 	physical_check: string;
@@ -82,6 +226,7 @@ export type SuccessResponse<T> = {
 
 export type TryCatchAsValError = {
 	error: string;
+	errorKind: string | null;
 	success: false;
 };
 export type TryCatchAsValReturn<T> = TryCatchAsValSuccess<T> | TryCatchAsValError;
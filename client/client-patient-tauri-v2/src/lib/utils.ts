@@ -20,7 +20,11 @@ export async function tryCatchAsVal<T>(func: () => Promise<T>): Promise<TryCatch
 		const result = await func();
 		return { success: true, data: result };
 	} catch (e) {
-		return { success: false, error: e as string };
+		// Commands reject with `{ kind, message }`, see `PatientError`
+		if (typeof e === 'object' && e !== null && 'kind' in e && 'message' in e) {
+			return { success: false, error: e.message as string, errorKind: e.kind as string };
+		}
+		return { success: false, error: String(e), errorKind: null };
 	}
 }
 
@@ -1,7 +1,7 @@
 // Tauri doesn't have a Node.js server to do proper SSR
 // so we will use adapter-static to prerender the app (SSG)
 
-import type { SuccessResponse } from '$lib/types';
+import type { InvokeAuthStatusResponse, SuccessResponse } from '$lib/types';
 import { tryCatchAsVal } from '$lib/utils';
 import { invoke } from '@tauri-apps/api/core';
 import type { LayoutLoad } from './$types';
@@ -11,15 +11,19 @@ export const prerender = true;
 export const ssr = false;
 
 export type LayoutLoadData = {
+	overdue_scheduled_revocations: number;
 	redirect_to: string | null;
 };
 
 export const load: LayoutLoad = async ({ url }) => {
 	const resInvokeAuthState = await tryCatchAsVal(async () => {
-		return (await invoke('auth_status')) as SuccessResponse<null>;
+		return (await invoke('auth_status')) as SuccessResponse<InvokeAuthStatusResponse>;
 	});
 
 	const defaultData: LayoutLoadData = {
+		overdue_scheduled_revocations: resInvokeAuthState.success
+			? resInvokeAuthState.data.data.overdueScheduledRevocations
+			: 0,
 		redirect_to: null
 	};
 
@@ -27,8 +31,8 @@ export const load: LayoutLoad = async ({ url }) => {
 
 	if (!resInvokeAuthState.success) {
 		defaultData.redirect_to = '/signin';
-		if (url.pathname === '/signup') {
-			defaultData.redirect_to = '/signup';
+		if (url.pathname === '/signup' || url.pathname === '/restore') {
+			defaultData.redirect_to = url.pathname;
 		}
 
 		const redirect_code = resInvokeAuthState.error.match(/\$<(\d+)>\$/);
@@ -36,6 +40,10 @@ export const load: LayoutLoad = async ({ url }) => {
 		if (redirect_code && parseInt(redirect_code[1]) === 1) {
 			defaultData.redirect_to = '/complete-profile';
 		}
+
+		if (redirect_code && parseInt(redirect_code[1]) === 2) {
+			defaultData.redirect_to = '/pin';
+		}
 	}
 
 	if (!defaultData.redirect_to && !url.pathname.startsWith('/dashboard')) {
@@ -1,9 +1,11 @@
 import type { PageLoad } from './$types';
 
-export const load: PageLoad = async ({ parent, params }) => {
+export const load: PageLoad = async ({ parent, params, url }) => {
 	await parent();
 
 	const emrIndex = parseInt(params.emrIndex);
+	// Set when a guardian opens a record of its ward
+	const wardIotaAddress = url.searchParams.get('ward');
 
-	return { emrIndex };
+	return { emrIndex, wardIotaAddress };
 };
@@ -0,0 +1,14 @@
+import type { PageLoad } from './$types';
+import { superValidate } from 'sveltekit-superforms';
+import { zod } from 'sveltekit-superforms/adapters';
+import { enterPinSchema } from '$lib/schema';
+
+export const load: PageLoad = async ({ parent }) => {
+	await parent();
+
+	const enterPinForm = await superValidate(zod(enterPinSchema));
+
+	return {
+		enterPinForm
+	};
+};
@@ -1,11 +1,15 @@
-import { invoke } from '@tauri-apps/api/core';
 import type { PageLoad } from './$types';
-import type { InvokeGetAccessLog, SuccessResponse } from '$lib/types';
+import { superValidate } from 'sveltekit-superforms';
+import { zod } from 'sveltekit-superforms/adapters';
+import { enterPinSchema } from '$lib/schema';
 
-export const load: PageLoad = async () => {
-	const accessLog = invoke('get_access_log') as Promise<SuccessResponse<InvokeGetAccessLog[]>>;
+export const load: PageLoad = async ({ url }) => {
+	const enterPinForm = await superValidate(zod(enterPinSchema));
+	// Set when a guardian opens the access log of its ward
+	const wardIotaAddress = url.searchParams.get('ward');
 
 	return {
-		accessLog
+		enterPinForm,
+		wardIotaAddress
 	};
 };
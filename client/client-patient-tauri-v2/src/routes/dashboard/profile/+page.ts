@@ -1,5 +1,18 @@
 import type { PageLoad } from './$types';
+import { superValidate } from 'sveltekit-superforms';
+import { zod } from 'sveltekit-superforms/adapters';
+import { addGuardianSchema, enterPinSchema, exportBackupSchema } from '$lib/schema';
 
 export const load: PageLoad = async ({ parent }) => {
 	await parent();
+
+	const exportBackupForm = await superValidate(zod(exportBackupSchema));
+	const addGuardianForm = await superValidate(zod(addGuardianSchema));
+	const enterPinForm = await superValidate(zod(enterPinSchema));
+
+	return {
+		exportBackupForm,
+		addGuardianForm,
+		enterPinForm
+	};
 };
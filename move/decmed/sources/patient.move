@@ -1,6 +1,7 @@
 module decmed::patient;
 
 use decmed::std_enum_hospital_personnel_role::{
+    HospitalPersonnelRole,
     administrative_personnel as hospital_personnel_role_administrative_personnel,
     medical_personnel as hospital_personnel_role_medical_personnel,
 };
@@ -28,12 +29,22 @@ use decmed::std_struct_patient_access_log::{
     new as patient_access_log_new,
 };
 use decmed::std_struct_patient_account::{
+    PatientAccount,
     new as patient_account_new,
 };
 use decmed::std_struct_patient_administrative_metadata::{
     PatientAdministrativeMetadata,
     new as patient_administrative_metadata_new,
 };
+use decmed::std_struct_patient_emergency_access::new as patient_emergency_access_new;
+use decmed::std_struct_patient_emergency_access_log::{
+    PatientEmergencyAccessLog,
+    new as patient_emergency_access_log_new,
+};
+use decmed::std_struct_patient_guardian::{
+    PatientGuardian,
+    new as patient_guardian_new,
+};
 use decmed::std_struct_patient_id_account::PatientIdAccount;
 use decmed::std_struct_patient_medical_metadata::PatientMedicalMetadata;
 
@@ -50,6 +61,36 @@ const EAccountNotFound: u64 = 3002;
 const EAddressNotFound: u64 = 3003;
 const EHospitalPersonnelNotFound: u64 = 3004;
 const EInvalidMetadataLength: u64 = 3005;
+const EInvalidAccessDuration: u64 = 3006;
+const EInvalidAccessScope: u64 = 3007;
+const EInvalidMedicalMetadataScope: u64 = 3008;
+const EGuardianAlreadyAdded: u64 = 3009;
+const EGuardianNotFound: u64 = 3010;
+const EGuardianRightDenied: u64 = 3011;
+const EInvalidGuardian: u64 = 3012;
+const ETooManyGuardians: u64 = 3013;
+const EEmergencyAccessDisabled: u64 = 3014;
+const EEmergencyAccessNotNeeded: u64 = 3015;
+const EEmergencyAccessLogNotFound: u64 = 3016;
+const EInvalidHospitalPersonnelRole: u64 = 3017;
+const EInvalidJustificationLength: u64 = 3018;
+
+/// Access duration bounds, in minutes
+const ACCESS_DUR_MIN: u64 = 1;
+const ADMINISTRATIVE_READ_DUR_MAX: u64 = 60;
+const MEDICAL_READ_DUR_MAX: u64 = 24 * 60;
+const MEDICAL_UPDATE_DUR_MAX: u64 = 24 * 60;
+/// Break-glass read access duration, in minutes
+const EMERGENCY_READ_DUR: u64 = 60;
+/// Break-glass justification bounds, in bytes
+const EMERGENCY_JUSTIFICATION_LEN_MIN: u64 = 20;
+const EMERGENCY_JUSTIFICATION_LEN_MAX: u64 = 1000;
+
+const GUARDIANS_MAX: u64 = 5;
+/// Guardian rights, see `borrow_ward_id`
+const GUARDIAN_RIGHT_GRANT: u8 = 0;
+const GUARDIAN_RIGHT_READ: u8 = 1;
+const GUARDIAN_RIGHT_REVOKE: u8 = 2;
 
 // Enums
 
@@ -58,8 +99,11 @@ const EInvalidMetadataLength: u64 = 3005;
 // Functions
 
 /// ## Params:
+/// - `exp_dur_read`: minutes
+/// - `exp_dur_update`: minutes, ignored without update access
+/// - `is_medical_read`: medical history is readable, administrative data always is
 /// - `metadata`: vector<Base64 encoded>
-///     - length = 1 for administrative
+///     - length = 1 for administrative, or medical without update access
 ///     - length = 2 for medical
 ///         - 0: read
 ///         - 1: update
@@ -67,18 +111,94 @@ entry fun create_access(
     address_id: &AddressId,
     clock: &Clock,
     date: String,
+    exp_dur_read: u64,
+    exp_dur_update: u64,
     hospital_id_metadata: &HospitalIdMetadata,
     hospital_personnel_address: address,
     hospital_personnel_id_account: &mut HospitalPersonnelIdAccount,
+    is_medical_read: bool,
+    medical_metadata_scope: Option<vector<u64>>,
     metadata: vector<String>,
     patient_id_account: &mut PatientIdAccount,
     ctx: &TxContext,
 )
+{
+    let patient_id = *address_id.borrow_table().borrow(ctx.sender());
+
+    create_access_by_patient_id(
+        address_id,
+        clock,
+        date,
+        exp_dur_read,
+        exp_dur_update,
+        hospital_id_metadata,
+        hospital_personnel_address,
+        hospital_personnel_id_account,
+        is_medical_read,
+        medical_metadata_scope,
+        metadata,
+        patient_id,
+        patient_id_account,
+    );
+}
+
+/// Same as `create_access`, granted by a guardian of the patient with the grant right
+entry fun create_access_as_guardian(
+    address_id: &AddressId,
+    clock: &Clock,
+    date: String,
+    exp_dur_read: u64,
+    exp_dur_update: u64,
+    hospital_id_metadata: &HospitalIdMetadata,
+    hospital_personnel_address: address,
+    hospital_personnel_id_account: &mut HospitalPersonnelIdAccount,
+    is_medical_read: bool,
+    medical_metadata_scope: Option<vector<u64>>,
+    metadata: vector<String>,
+    patient_address: address,
+    patient_id_account: &mut PatientIdAccount,
+    ctx: &TxContext,
+)
+{
+    let patient_id = borrow_ward_id(address_id, ctx.sender(), patient_address, patient_id_account, GUARDIAN_RIGHT_GRANT);
+
+    create_access_by_patient_id(
+        address_id,
+        clock,
+        date,
+        exp_dur_read,
+        exp_dur_update,
+        hospital_id_metadata,
+        hospital_personnel_address,
+        hospital_personnel_id_account,
+        is_medical_read,
+        medical_metadata_scope,
+        metadata,
+        patient_id,
+        patient_id_account,
+    );
+}
+
+fun create_access_by_patient_id(
+    address_id: &AddressId,
+    clock: &Clock,
+    date: String,
+    exp_dur_read: u64,
+    exp_dur_update: u64,
+    hospital_id_metadata: &HospitalIdMetadata,
+    hospital_personnel_address: address,
+    hospital_personnel_id_account: &mut HospitalPersonnelIdAccount,
+    is_medical_read: bool,
+    medical_metadata_scope: Option<vector<u64>>,
+    metadata: vector<String>,
+    patient_id: String,
+    patient_id_account: &mut PatientIdAccount,
+)
 {
     let address_id_table = address_id.borrow_table();
-    let patient_id = *address_id_table.borrow(ctx.sender());
     let patient_id_account_table = patient_id_account.borrow_mut_table();
     let patient_account = patient_id_account_table.borrow_mut(patient_id);
+    let medical_metadata_length = patient_account.borrow_medical_metadata().length();
     let patient_access_log = patient_account.borrow_mut_access_log();
 
     assert!(address_id_table.contains(hospital_personnel_address), EHospitalPersonnelNotFound);
@@ -101,7 +221,7 @@ entry fun create_access(
 
 
     if (hospital_personnel_role == hospital_personnel_role_administrative_personnel()) {
-        let (read_access, access_data_type_read, exp_dur) = create_access_administrative_personnel(clock, metadata);
+        let (read_access, access_data_type_read, exp_dur) = create_access_administrative_personnel(clock, exp_dur_read, is_medical_read, medical_metadata_scope, metadata);
 
         let hospital_personnel_read_access = hospital_personnel_access.borrow_mut_read();
 
@@ -127,7 +247,7 @@ entry fun create_access(
 
     if (hospital_personnel_role == hospital_personnel_role_medical_personnel()) {
         let (read_access, access_data_type_read,
-            update_access, access_data_type_update, exp_dur_read, exp_dur_update) = create_access_medical_personnel(clock, metadata);
+            update_access, access_data_type_update) = create_access_medical_personnel(clock, exp_dur_read, exp_dur_update, is_medical_read, medical_metadata_length, medical_metadata_scope, metadata);
 
         let hospital_personnel_read_access = hospital_personnel_access.borrow_mut_read();
         if (hospital_personnel_read_access.contains(&patient_id)) {
@@ -135,11 +255,11 @@ entry fun create_access(
         };
         hospital_personnel_read_access.insert(patient_id, read_access);
 
+        // A new grant without update access also ends the previous one
         let hospital_personnel_update_access = hospital_personnel_access.borrow_mut_update();
         if (hospital_personnel_update_access.contains(&patient_id)) {
             hospital_personnel_update_access.remove(&patient_id);
         };
-        hospital_personnel_update_access.insert(patient_id, update_access);
 
         let patient_access_log_read = patient_access_log_new(
             access_data_type_read,
@@ -154,18 +274,22 @@ entry fun create_access(
         );
         patient_access_log.push_back(patient_access_log_read);
 
-        let patient_access_log_update = patient_access_log_new(
-            access_data_type_update,
-            hospital_personnel_access_type_update(),
-            date,
-            exp_dur_update,
-            *hospital_metadata,
-            hospital_personnel_address,
-            hospital_personnel_administrative_metadata_public,
-            patient_access_log.length(),
-            false,
-        );
-        patient_access_log.push_back(patient_access_log_update);
+        if (update_access.is_some()) {
+            hospital_personnel_update_access.insert(patient_id, update_access.destroy_some());
+
+            let patient_access_log_update = patient_access_log_new(
+                access_data_type_update,
+                hospital_personnel_access_type_update(),
+                date,
+                exp_dur_update,
+                *hospital_metadata,
+                hospital_personnel_address,
+                hospital_personnel_administrative_metadata_public,
+                patient_access_log.length(),
+                false,
+            );
+            patient_access_log.push_back(patient_access_log_update);
+        };
     };
 }
 
@@ -174,9 +298,13 @@ public(package) fun create_access_test(
     address_id: &AddressId,
     clock: &Clock,
     date: String,
+    exp_dur_read: u64,
+    exp_dur_update: u64,
     hospital_id_metadata: &HospitalIdMetadata,
     hospital_personnel_address: address,
     hospital_personnel_id_account: &mut HospitalPersonnelIdAccount,
+    is_medical_read: bool,
+    medical_metadata_scope: Option<vector<u64>>,
     metadata: vector<String>,
     patient_id_account: &mut PatientIdAccount,
     ctx: &TxContext,
@@ -185,90 +313,540 @@ public(package) fun create_access_test(
     create_access(
         address_id,
         clock, date,
+        exp_dur_read,
+        exp_dur_update,
         hospital_id_metadata,
         hospital_personnel_address,
         hospital_personnel_id_account,
+        is_medical_read,
+        medical_metadata_scope,
         metadata,
         patient_id_account,
         ctx
     );
 }
 
+#[test_only]
+public(package) fun create_access_as_guardian_test(
+    address_id: &AddressId,
+    clock: &Clock,
+    date: String,
+    exp_dur_read: u64,
+    exp_dur_update: u64,
+    hospital_id_metadata: &HospitalIdMetadata,
+    hospital_personnel_address: address,
+    hospital_personnel_id_account: &mut HospitalPersonnelIdAccount,
+    is_medical_read: bool,
+    medical_metadata_scope: Option<vector<u64>>,
+    metadata: vector<String>,
+    patient_address: address,
+    patient_id_account: &mut PatientIdAccount,
+    ctx: &TxContext,
+)
+{
+    create_access_as_guardian(
+        address_id,
+        clock,
+        date,
+        exp_dur_read,
+        exp_dur_update,
+        hospital_id_metadata,
+        hospital_personnel_address,
+        hospital_personnel_id_account,
+        is_medical_read,
+        medical_metadata_scope,
+        metadata,
+        patient_address,
+        patient_id_account,
+        ctx
+    );
+}
+
 /// ## Params:
-/// - `metadata`: vector<Base64 encoded>
-///     - length = 1 for administrative
-///     - length = 2 for medical
-///         - 0: read
-///         - 1: update
+/// - `exp_dur_read`: minutes
+/// - `metadata`: vector<Base64 encoded>, length = 1
 fun create_access_administrative_personnel(
     clock: &Clock,
+    exp_dur_read: u64,
+    is_medical_read: bool,
+    medical_metadata_scope: Option<vector<u64>>,
     metadata: vector<String>,
 ): (HospitalPersonnelAccessData, vector<HospitalPersonnelAccessDataType>, u64)
 {
     assert!(metadata.length() == 1, EInvalidMetadataLength);
+    assert!(!is_medical_read && medical_metadata_scope.is_none(), EInvalidAccessScope);
+    assert!(exp_dur_read >= ACCESS_DUR_MIN && exp_dur_read <= ADMINISTRATIVE_READ_DUR_MAX, EInvalidAccessDuration);
 
     let mut hospital_personnel_access_data_types_read = vector::empty<HospitalPersonnelAccessDataType>();
     hospital_personnel_access_data_types_read.push_back(hospital_personnel_access_data_type_administrative());
 
-    let exp_dur = 5;
-    let exp_read = clock.timestamp_ms() + (exp_dur * 60 * 1000); // 5 minutes
+    let exp_read = clock.timestamp_ms() + (exp_dur_read * 60 * 1000);
 
     let hospital_personnel_access_data_read = hospital_personnel_access_data_new(
         hospital_personnel_access_data_types_read,
         exp_read,
         *metadata.borrow(0),
         option::none(),
+        option::none(),
     );
 
-    (hospital_personnel_access_data_read, hospital_personnel_access_data_types_read, exp_dur)
+    (hospital_personnel_access_data_read, hospital_personnel_access_data_types_read, exp_dur_read)
 }
 
 
 /// ## Params:
+/// - `exp_dur_read`: minutes
+/// - `exp_dur_update`: minutes, ignored without update access
+/// - `medical_metadata_scope`: ascending medical record indexes readable through the read access,
+///   none for every record
 /// - `metadata`: vector<Base64 encoded>
-///     - length = 1 for administrative
-///     - length = 2 for medical
+///     - length = 1 for read only
+///     - length = 2 for read and update
 ///         - 0: read
 ///         - 1: update
 /// ## Return:
 /// - 0: `read_access`,
 /// - 1: `read_access_data_type`,
-/// - 2: `update_access`,
+/// - 2: `update_access`, none without update access
 /// - 3: `update_access_data_type`,
 fun create_access_medical_personnel(
     clock: &Clock,
+    exp_dur_read: u64,
+    exp_dur_update: u64,
+    is_medical_read: bool,
+    medical_metadata_length: u64,
+    medical_metadata_scope: Option<vector<u64>>,
     metadata: vector<String>,
 ): (HospitalPersonnelAccessData, vector<HospitalPersonnelAccessDataType>,
-    HospitalPersonnelAccessData, vector<HospitalPersonnelAccessDataType>, u64, u64)
+    Option<HospitalPersonnelAccessData>, vector<HospitalPersonnelAccessDataType>)
 {
-    assert!(metadata.length() == 2, EInvalidMetadataLength);
+    assert!(metadata.length() == 1 || metadata.length() == 2, EInvalidMetadataLength);
+    assert!(exp_dur_read >= ACCESS_DUR_MIN && exp_dur_read <= MEDICAL_READ_DUR_MAX, EInvalidAccessDuration);
+    assert!(is_medical_read || medical_metadata_scope.is_none(), EInvalidAccessScope);
+
+    if (medical_metadata_scope.is_some()) {
+        let medical_metadata_scope = medical_metadata_scope.borrow();
+        let scope_length = medical_metadata_scope.length();
+        assert!(scope_length > 0, EInvalidMedicalMetadataScope);
+        assert!(*medical_metadata_scope.borrow(scope_length - 1) < medical_metadata_length, EInvalidMedicalMetadataScope);
+
+        let mut i = 1;
+        while (i < scope_length) {
+            assert!(*medical_metadata_scope.borrow(i - 1) < *medical_metadata_scope.borrow(i), EInvalidMedicalMetadataScope);
+            i = i + 1;
+        };
+    };
 
     let mut hospital_personnel_access_data_types_read = vector::empty<HospitalPersonnelAccessDataType>();
-    hospital_personnel_access_data_types_read.push_back(hospital_personnel_access_data_type_medical());
+    if (is_medical_read) {
+        hospital_personnel_access_data_types_read.push_back(hospital_personnel_access_data_type_medical());
+    };
     hospital_personnel_access_data_types_read.push_back(hospital_personnel_access_data_type_administrative());
     let mut hospital_personnel_access_data_types_update = vector::empty<HospitalPersonnelAccessDataType>();
     hospital_personnel_access_data_types_update.push_back(hospital_personnel_access_data_type_medical());
 
-    let exp_dur_read = 15;
-    let exp_read = clock.timestamp_ms() + (exp_dur_read * 60 * 1000); // 15 minutes
-    let exp_dur_update = 2 * 60;
-    let exp_update = clock.timestamp_ms() + (exp_dur_update * 60 * 1000); // 2 hours
+    let exp_read = clock.timestamp_ms() + (exp_dur_read * 60 * 1000);
 
     let hospital_personnel_access_data_read = hospital_personnel_access_data_new(
         hospital_personnel_access_data_types_read,
         exp_read,
         *metadata.borrow(0),
         option::none(),
+        medical_metadata_scope,
+    );
+
+    let hospital_personnel_access_data_update = if (metadata.length() == 2) {
+        assert!(exp_dur_update >= ACCESS_DUR_MIN && exp_dur_update <= MEDICAL_UPDATE_DUR_MAX, EInvalidAccessDuration);
+
+        let exp_update = clock.timestamp_ms() + (exp_dur_update * 60 * 1000);
+
+        option::some(hospital_personnel_access_data_new(
+            hospital_personnel_access_data_types_update,
+            exp_update,
+            *metadata.borrow(1),
+            option::none(),
+            option::none(),
+        ))
+    } else {
+        option::none()
+    };
+
+    (hospital_personnel_access_data_read, hospital_personnel_access_data_types_read,
+     hospital_personnel_access_data_update, hospital_personnel_access_data_types_update)
+}
+
+/// ## Params
+/// - `metadata`: Base64 encoded, the patient keys encrypted for the guardian. Needed to grant
+///   access or read records, none when the guardian can only revoke
+entry fun add_guardian(
+    address_id: &AddressId,
+    can_grant: bool,
+    can_read: bool,
+    can_revoke: bool,
+    guardian_address: address,
+    metadata: Option<String>,
+    patient_id_account: &mut PatientIdAccount,
+    ctx: &TxContext,
+)
+{
+    let address_id_table = address_id.borrow_table();
+    let patient_id = *address_id_table.borrow(ctx.sender());
+
+    assert!(guardian_address != ctx.sender(), EInvalidGuardian);
+    assert!(address_id_table.contains(guardian_address), EInvalidGuardian);
+    assert!(can_grant || can_read || can_revoke, EInvalidGuardian);
+    assert!(metadata.is_some() || !(can_grant || can_read), EInvalidGuardian);
+
+    let guardian_id = *address_id_table.borrow(guardian_address);
+    let patient_id_account_table = patient_id_account.borrow_table();
+
+    assert!(patient_id_account_table.contains(patient_id), EAccountNotFound);
+    // Only patient accounts can be guardians
+    assert!(patient_id_account_table.contains(guardian_id), EInvalidGuardian);
+
+    let guardians = patient_id_account.borrow_mut_guardianship(patient_id).borrow_mut_guardians();
+    let (is_found, _) = find_guardian(guardians, guardian_address);
+
+    assert!(!is_found, EGuardianAlreadyAdded);
+    assert!(guardians.length() < GUARDIANS_MAX, ETooManyGuardians);
+
+    guardians.push_back(patient_guardian_new(guardian_address, can_grant, can_read, can_revoke, metadata));
+
+    patient_id_account.borrow_mut_guardianship(guardian_id).borrow_mut_wards().push_back(ctx.sender());
+}
+
+#[test_only]
+public(package) fun add_guardian_test(
+    address_id: &AddressId,
+    can_grant: bool,
+    can_read: bool,
+    can_revoke: bool,
+    guardian_address: address,
+    metadata: Option<String>,
+    patient_id_account: &mut PatientIdAccount,
+    ctx: &TxContext,
+)
+{
+    add_guardian(
+        address_id,
+        can_grant,
+        can_read,
+        can_revoke,
+        guardian_address,
+        metadata,
+        patient_id_account,
+        ctx
+    );
+}
+
+/// Called by the patient to remove a guardian, or by the guardian to step down
+entry fun remove_guardian(
+    address_id: &AddressId,
+    guardian_address: address,
+    patient_address: address,
+    patient_id_account: &mut PatientIdAccount,
+    ctx: &TxContext,
+)
+{
+    assert!(ctx.sender() == patient_address || ctx.sender() == guardian_address, EGuardianNotFound);
+
+    let address_id_table = address_id.borrow_table();
+    let patient_id = *address_id_table.borrow(patient_address);
+    let guardian_id = *address_id_table.borrow(guardian_address);
+
+    assert!(patient_id_account.contains_guardianship(patient_id), EGuardianNotFound);
+
+    let guardians = patient_id_account.borrow_mut_guardianship(patient_id).borrow_mut_guardians();
+    let (is_found, index) = find_guardian(guardians, guardian_address);
+    assert!(is_found, EGuardianNotFound);
+    guardians.remove(index);
+
+    let wards = patient_id_account.borrow_mut_guardianship(guardian_id).borrow_mut_wards();
+    let (is_found, index) = wards.index_of(&patient_address);
+    if (is_found) {
+        wards.remove(index);
+    };
+}
+
+#[test_only]
+public(package) fun remove_guardian_test(
+    address_id: &AddressId,
+    guardian_address: address,
+    patient_address: address,
+    patient_id_account: &mut PatientIdAccount,
+    ctx: &TxContext,
+)
+{
+    remove_guardian(
+        address_id,
+        guardian_address,
+        patient_address,
+        patient_id_account,
+        ctx
+    );
+}
+
+/// ## Params
+/// - `right`: `GUARDIAN_RIGHT_GRANT`, `GUARDIAN_RIGHT_READ` or `GUARDIAN_RIGHT_REVOKE`
+///
+/// ## Return:
+/// 0: patient id of the ward
+public(package) fun borrow_ward_id(
+    address_id: &AddressId,
+    guardian_address: address,
+    patient_address: address,
+    patient_id_account: &PatientIdAccount,
+    right: u8,
+): String
+{
+    let address_id_table = address_id.borrow_table();
+
+    assert!(address_id_table.contains(patient_address), EAccountNotFound);
+
+    let patient_id = *address_id_table.borrow(patient_address);
+    let patient_id_account_table = patient_id_account.borrow_table();
+
+    assert!(patient_id_account_table.contains(patient_id), EAccountNotFound);
+    assert!(patient_id_account.contains_guardianship(patient_id), EGuardianNotFound);
+
+    let guardians = patient_id_account.borrow_guardianship(patient_id).borrow_guardians();
+    let (is_found, index) = find_guardian(guardians, guardian_address);
+
+    assert!(is_found, EGuardianNotFound);
+
+    assert!(guardian_has_right(guardians.borrow(index), right), EGuardianRightDenied);
+
+    patient_id
+}
+
+fun is_guardian_allowed(
+    address_id: &AddressId,
+    guardian_address: address,
+    patient_address: address,
+    patient_id_account: &PatientIdAccount,
+    right: u8,
+): bool
+{
+    let address_id_table = address_id.borrow_table();
+
+    if (!address_id_table.contains(patient_address)) {
+        return false
+    };
+
+    let patient_id = *address_id_table.borrow(patient_address);
+    let patient_id_account_table = patient_id_account.borrow_table();
+
+    if (!patient_id_account_table.contains(patient_id) || !patient_id_account.contains_guardianship(patient_id)) {
+        return false
+    };
+
+    let guardians = patient_id_account.borrow_guardianship(patient_id).borrow_guardians();
+    let (is_found, index) = find_guardian(guardians, guardian_address);
+
+    is_found && guardian_has_right(guardians.borrow(index), right)
+}
+
+fun guardian_has_right(
+    guardian: &PatientGuardian,
+    right: u8,
+): bool
+{
+    (right == GUARDIAN_RIGHT_GRANT && guardian.borrow_can_grant())
+        || (right == GUARDIAN_RIGHT_READ && guardian.borrow_can_read())
+        || (right == GUARDIAN_RIGHT_REVOKE && guardian.borrow_can_revoke())
+}
+
+/// ## Return:
+/// 0: whether the guardian is found
+/// 1: index of the guardian
+fun find_guardian(
+    guardians: &vector<PatientGuardian>,
+    guardian_address: address,
+): (bool, u64)
+{
+    let mut i = 0;
+    while (i < guardians.length()) {
+        if (guardians.borrow(i).borrow_address() == guardian_address) {
+            return (true, i)
+        };
+        i = i + 1;
+    };
+
+    (false, 0)
+}
+
+/// Consent to break-glass access, the client deposits or deletes the emergency keys with the
+/// proxy alongside
+entry fun set_emergency_access(
+    address_id: &AddressId,
+    is_enabled: bool,
+    patient_id_account: &mut PatientIdAccount,
+    ctx: &mut TxContext,
+)
+{
+    let address_id_table = address_id.borrow_table();
+    let patient_id = *address_id_table.borrow(ctx.sender());
+
+    assert!(patient_id_account.borrow_table().contains(patient_id), EAccountNotFound);
+
+    if (!patient_id_account.contains_emergency_access(patient_id)) {
+        patient_id_account.add_emergency_access(patient_id, patient_emergency_access_new(ctx));
+    };
+
+    patient_id_account.borrow_mut_emergency_access(patient_id).set_is_enabled(is_enabled);
+}
+
+#[test_only]
+public(package) fun set_emergency_access_test(
+    address_id: &AddressId,
+    is_enabled: bool,
+    patient_id_account: &mut PatientIdAccount,
+    ctx: &mut TxContext,
+)
+{
+    set_emergency_access(
+        address_id,
+        is_enabled,
+        patient_id_account,
+        ctx
     );
-    let hospital_personnel_access_data_update = hospital_personnel_access_data_new(
-        hospital_personnel_access_data_types_update,
-        exp_update,
-        *metadata.borrow(1),
+}
+
+/// Marks a break-glass access as seen by the patient
+entry fun acknowledge_emergency_access(
+    address_id: &AddressId,
+    index: u64,
+    patient_id_account: &mut PatientIdAccount,
+    ctx: &TxContext,
+)
+{
+    let address_id_table = address_id.borrow_table();
+    let patient_id = *address_id_table.borrow(ctx.sender());
+
+    assert!(patient_id_account.contains_emergency_access(patient_id), EEmergencyAccessLogNotFound);
+
+    let emergency_access_log = patient_id_account.borrow_mut_emergency_access(patient_id).borrow_mut_log();
+
+    assert!(index < emergency_access_log.length(), EEmergencyAccessLogNotFound);
+
+    emergency_access_log.borrow_mut(index).set_is_acknowledged(true);
+}
+
+fun is_emergency_access_enabled(
+    patient_id_account: &PatientIdAccount,
+    patient_id: String,
+): bool
+{
+    patient_id_account.contains_emergency_access(patient_id)
+        && patient_id_account.borrow_emergency_access(patient_id).borrow_is_enabled()
+}
+
+/// Read access granted by the proxy without the patient, only to medical personnel and only
+/// when the patient consented with `set_emergency_access`. Logged in the access log like any
+/// other access, so it can be revoked, and in the emergency access log until acknowledged.
+///
+/// ## Params
+/// - `justification`: written by the medical personnel, stored as is
+/// - `metadata`: Base64 encoded, encrypted for the medical personnel by the proxy
+///
+/// ## Return:
+/// 0: index of the emergency access log
+public(package) fun create_emergency_access(
+    address_id: &AddressId,
+    clock: &Clock,
+    date: String,
+    hospital_id_metadata: &HospitalIdMetadata,
+    hospital_personnel_address: address,
+    hospital_personnel_id_account: &mut HospitalPersonnelIdAccount,
+    justification: String,
+    metadata: String,
+    patient_address: address,
+    patient_id_account: &mut PatientIdAccount,
+): u64
+{
+    let justification_length = justification.length();
+    assert!(justification_length >= EMERGENCY_JUSTIFICATION_LEN_MIN && justification_length <= EMERGENCY_JUSTIFICATION_LEN_MAX, EInvalidJustificationLength);
+
+    let address_id_table = address_id.borrow_table();
+
+    assert!(address_id_table.contains(patient_address), EAccountNotFound);
+    assert!(address_id_table.contains(hospital_personnel_address), EHospitalPersonnelNotFound);
+
+    let patient_id = *address_id_table.borrow(patient_address);
+
+    assert!(patient_id_account.borrow_table().contains(patient_id), EAccountNotFound);
+    assert!(is_emergency_access_enabled(patient_id_account, patient_id), EEmergencyAccessDisabled);
+
+    let patient_account = patient_id_account.borrow_mut_table().borrow_mut(patient_id);
+
+    let hospital_personnel_id = *address_id_table.borrow(hospital_personnel_address);
+    let hospital_personnel_id_account_table = hospital_personnel_id_account.borrow_mut_table();
+    let hospital_personnel_account = hospital_personnel_id_account_table.borrow(hospital_personnel_id);
+
+    assert!(*hospital_personnel_account.borrow_role() == hospital_personnel_role_medical_personnel(), EInvalidHospitalPersonnelRole);
+
+    let hospital_personnel_administrative_metadata_public = *hospital_personnel_account.borrow_administrative_metadata().borrow().borrow_public_metadata();
+
+    let hospital_id_metadata_table = hospital_id_metadata.borrow_table();
+    let hospital_id_metadata_vec = hospital_id_metadata.borrow_vec();
+    let hospital_index = *hospital_id_metadata_table.borrow(*hospital_personnel_account.borrow_hospital_id());
+    let hospital_metadata = *hospital_id_metadata_vec.borrow(hospital_index).borrow_hospital_metadata();
+
+    let hospital_personnel_account = hospital_personnel_id_account_table.borrow_mut(hospital_personnel_id);
+    let hospital_personnel_read_access = hospital_personnel_account.borrow_mut_access().borrow_mut().borrow_mut_read();
+
+    // Break-glass never replaces access the patient granted
+    if (hospital_personnel_read_access.contains(&patient_id)) {
+        assert!(hospital_personnel_read_access.get(&patient_id).borrow_exp() < clock.timestamp_ms(), EEmergencyAccessNotNeeded);
+        hospital_personnel_read_access.remove(&patient_id);
+    };
+
+    let mut access_data_types = vector::empty<HospitalPersonnelAccessDataType>();
+    access_data_types.push_back(hospital_personnel_access_data_type_medical());
+    access_data_types.push_back(hospital_personnel_access_data_type_administrative());
+
+    let exp = clock.timestamp_ms() + (EMERGENCY_READ_DUR * 60 * 1000);
+
+    hospital_personnel_read_access.insert(patient_id, hospital_personnel_access_data_new(
+        access_data_types,
+        exp,
+        metadata,
+        option::none(),
         option::none(),
+    ));
+
+    let patient_access_log = patient_account.borrow_mut_access_log();
+    let access_log_index = patient_access_log.length();
+    patient_access_log.push_back(patient_access_log_new(
+        access_data_types,
+        hospital_personnel_access_type_read(),
+        date,
+        EMERGENCY_READ_DUR,
+        hospital_metadata,
+        hospital_personnel_address,
+        hospital_personnel_administrative_metadata_public,
+        access_log_index,
+        false,
+    ));
+
+    let patient_emergency_access_log = patient_id_account.borrow_mut_emergency_access(patient_id).borrow_mut_log();
+    let index = patient_emergency_access_log.length();
+    let emergency_access_log = patient_emergency_access_log_new(
+        access_log_index,
+        date,
+        EMERGENCY_READ_DUR,
+        hospital_metadata,
+        hospital_personnel_address,
+        hospital_personnel_administrative_metadata_public,
+        index,
+        justification,
+        patient_address,
     );
+    patient_emergency_access_log.push_back(emergency_access_log);
 
-    (hospital_personnel_access_data_read, hospital_personnel_access_data_types_read,
-     hospital_personnel_access_data_update, hospital_personnel_access_data_types_update, exp_dur_read, exp_dur_update)
+    emergency_access_log.emit();
+
+    index
 }
 
 entry fun is_account_registered(
@@ -374,13 +952,14 @@ entry fun get_account_state(
 /// ## Return:
 /// 0: public administrative data
 /// 1: hospital name
+/// 2: hospital personnel role
 entry fun get_hospital_personnel_info(
     address_id: &AddressId,
     hospital_id_metadata: &HospitalIdMetadata,
     hospital_personnel_address: address,
     hospital_personnel_id_account: &HospitalPersonnelIdAccount,
     ctx: &TxContext,
-): (String, String)
+): (String, String, HospitalPersonnelRole)
 {
     let address_id_table = address_id.borrow_table();
 
@@ -398,8 +977,56 @@ entry fun get_hospital_personnel_info(
 
     let public_data = *hospital_personnel_administrative_metadata.borrow_public_metadata();
     let hospital_name = *hospital_metadata.borrow_name();
+    let role = *hospital_personnel_account.borrow_role();
 
-    (public_data, hospital_name)
+    (public_data, hospital_name, role)
+}
+
+entry fun get_guardians(
+    address_id: &AddressId,
+    patient_id_account: &PatientIdAccount,
+    ctx: &TxContext,
+): vector<PatientGuardian>
+{
+    let address_id_table = address_id.borrow_table();
+    let patient_id = *address_id_table.borrow(ctx.sender());
+
+    if (!patient_id_account.contains_guardianship(patient_id)) {
+        return vector::empty<PatientGuardian>()
+    };
+
+    *patient_id_account.borrow_guardianship(patient_id).borrow_guardians()
+}
+
+/// ## Return:
+/// 0: ward addresses
+/// 1: guardian entry of the sender for each ward, same order
+entry fun get_wards(
+    address_id: &AddressId,
+    patient_id_account: &PatientIdAccount,
+    ctx: &TxContext,
+): (vector<address>, vector<PatientGuardian>)
+{
+    let address_id_table = address_id.borrow_table();
+    let guardian_id = *address_id_table.borrow(ctx.sender());
+    let mut guardians = vector::empty<PatientGuardian>();
+
+    if (!patient_id_account.contains_guardianship(guardian_id)) {
+        return (vector::empty<address>(), guardians)
+    };
+
+    let wards = *patient_id_account.borrow_guardianship(guardian_id).borrow_wards();
+
+    let mut i = 0;
+    while (i < wards.length()) {
+        let ward_id = *address_id_table.borrow(*wards.borrow(i));
+        let ward_guardians = patient_id_account.borrow_guardianship(ward_id).borrow_guardians();
+        let (_, index) = find_guardian(ward_guardians, ctx.sender());
+        guardians.push_back(*ward_guardians.borrow(index));
+        i = i + 1;
+    };
+
+    (wards, guardians)
 }
 
 entry fun get_access_log(
@@ -413,7 +1040,33 @@ entry fun get_access_log(
     let address_id_table = address_id.borrow_table();
     let patient_id = *address_id_table.borrow(ctx.sender());
     let patient_id_account_table = patient_id_account.borrow_table();
-    let patient_account = patient_id_account_table.borrow(patient_id);
+
+    get_access_log_page(cursor, patient_id_account_table.borrow(patient_id), size)
+}
+
+/// Same as `get_access_log` for a guardian of the patient with the revoke right, revoking needs
+/// the log index
+entry fun get_access_log_as_guardian(
+    address_id: &AddressId,
+    cursor: u64,
+    patient_address: address,
+    patient_id_account: &PatientIdAccount,
+    size: u64,
+    ctx: &TxContext,
+): vector<PatientAccessLog>
+{
+    let patient_id = borrow_ward_id(address_id, ctx.sender(), patient_address, patient_id_account, GUARDIAN_RIGHT_REVOKE);
+    let patient_id_account_table = patient_id_account.borrow_table();
+
+    get_access_log_page(cursor, patient_id_account_table.borrow(patient_id), size)
+}
+
+fun get_access_log_page(
+    cursor: u64,
+    patient_account: &PatientAccount,
+    size: u64,
+): vector<PatientAccessLog>
+{
     let patient_access_log = patient_account.borrow_access_log();
 
     let patient_access_log_length = patient_access_log.length();
@@ -441,6 +1094,38 @@ entry fun get_access_log(
     result
 }
 
+/// ## Return:
+/// 0: whether break-glass access is enabled
+/// 1: unacknowledged emergency access logs, oldest first
+entry fun get_emergency_access(
+    address_id: &AddressId,
+    patient_id_account: &PatientIdAccount,
+    ctx: &TxContext,
+): (bool, vector<PatientEmergencyAccessLog>)
+{
+    let address_id_table = address_id.borrow_table();
+    let patient_id = *address_id_table.borrow(ctx.sender());
+    let mut result = vector::empty<PatientEmergencyAccessLog>();
+
+    if (!patient_id_account.contains_emergency_access(patient_id)) {
+        return (false, result)
+    };
+
+    let patient_emergency_access = patient_id_account.borrow_emergency_access(patient_id);
+    let patient_emergency_access_log = patient_emergency_access.borrow_log();
+
+    let mut i = 0;
+    while (i < patient_emergency_access_log.length()) {
+        let emergency_access_log = patient_emergency_access_log.borrow(i);
+        if (!emergency_access_log.borrow_is_acknowledged()) {
+            result.push_back(*emergency_access_log);
+        };
+        i = i + 1;
+    };
+
+    (patient_emergency_access.borrow_is_enabled(), result)
+}
+
 entry fun get_medical_record(
     address_id: &AddressId,
     index: u64,
@@ -457,6 +1142,23 @@ entry fun get_medical_record(
     *patient_medical_metadata.borrow(index)
 }
 
+/// Same as `get_medical_record` for a guardian of the patient with the read right
+entry fun get_medical_record_as_guardian(
+    address_id: &AddressId,
+    index: u64,
+    patient_address: address,
+    patient_id_account: &PatientIdAccount,
+    ctx: &TxContext,
+): PatientMedicalMetadata
+{
+    let patient_id = borrow_ward_id(address_id, ctx.sender(), patient_address, patient_id_account, GUARDIAN_RIGHT_READ);
+    let patient_id_account_table = patient_id_account.borrow_table();
+    let patient_account = patient_id_account_table.borrow(patient_id);
+    let patient_medical_metadata = patient_account.borrow_medical_metadata();
+
+    *patient_medical_metadata.borrow(index)
+}
+
 entry fun get_medical_records(
     address_id: &AddressId,
     cursor: u64,
@@ -468,7 +1170,38 @@ entry fun get_medical_records(
     let address_id_table = address_id.borrow_table();
     let patient_id = *address_id_table.borrow(ctx.sender());
     let patient_id_account_table = patient_id_account.borrow_table();
-    let patient_account = patient_id_account_table.borrow(patient_id);
+
+    get_medical_records_page(cursor, patient_id_account_table.borrow(patient_id), size)
+}
+
+/// Same as `get_medical_records` for a guardian of the patient with the read or grant right,
+/// granting can be limited to selected records
+entry fun get_medical_records_as_guardian(
+    address_id: &AddressId,
+    cursor: u64,
+    patient_address: address,
+    patient_id_account: &PatientIdAccount,
+    size: u64,
+    ctx: &TxContext,
+): vector<PatientMedicalMetadata>
+{
+    let guardian_right = if (is_guardian_allowed(address_id, ctx.sender(), patient_address, patient_id_account, GUARDIAN_RIGHT_READ)) {
+        GUARDIAN_RIGHT_READ
+    } else {
+        GUARDIAN_RIGHT_GRANT
+    };
+    let patient_id = borrow_ward_id(address_id, ctx.sender(), patient_address, patient_id_account, guardian_right);
+    let patient_id_account_table = patient_id_account.borrow_table();
+
+    get_medical_records_page(cursor, patient_id_account_table.borrow(patient_id), size)
+}
+
+fun get_medical_records_page(
+    cursor: u64,
+    patient_account: &PatientAccount,
+    size: u64,
+): vector<PatientMedicalMetadata>
+{
     let patient_medical_metadata = patient_account.borrow_medical_metadata();
 
     let patient_medical_metadata_length = patient_medical_metadata.length();
@@ -504,9 +1237,74 @@ entry fun revoke_access(
     patient_id_account: &mut PatientIdAccount,
     ctx: &TxContext,
 )
+{
+    let patient_id = *address_id.borrow_table().borrow(ctx.sender());
+
+    revoke_access_by_patient_id(
+        address_id,
+        hospital_personnel_address,
+        hospital_personnel_id_account,
+        index,
+        patient_id,
+        patient_id_account,
+    );
+}
+
+/// Same as `revoke_access`, revoked by a guardian of the patient with the revoke right
+entry fun revoke_access_as_guardian(
+    address_id: &AddressId,
+    hospital_personnel_address: address,
+    hospital_personnel_id_account: &mut HospitalPersonnelIdAccount,
+    index: u64,
+    patient_address: address,
+    patient_id_account: &mut PatientIdAccount,
+    ctx: &TxContext,
+)
+{
+    let patient_id = borrow_ward_id(address_id, ctx.sender(), patient_address, patient_id_account, GUARDIAN_RIGHT_REVOKE);
+
+    revoke_access_by_patient_id(
+        address_id,
+        hospital_personnel_address,
+        hospital_personnel_id_account,
+        index,
+        patient_id,
+        patient_id_account,
+    );
+}
+
+#[test_only]
+public(package) fun revoke_access_as_guardian_test(
+    address_id: &AddressId,
+    hospital_personnel_address: address,
+    hospital_personnel_id_account: &mut HospitalPersonnelIdAccount,
+    index: u64,
+    patient_address: address,
+    patient_id_account: &mut PatientIdAccount,
+    ctx: &TxContext,
+)
+{
+    revoke_access_as_guardian(
+        address_id,
+        hospital_personnel_address,
+        hospital_personnel_id_account,
+        index,
+        patient_address,
+        patient_id_account,
+        ctx
+    );
+}
+
+fun revoke_access_by_patient_id(
+    address_id: &AddressId,
+    hospital_personnel_address: address,
+    hospital_personnel_id_account: &mut HospitalPersonnelIdAccount,
+    index: u64,
+    patient_id: String,
+    patient_id_account: &mut PatientIdAccount,
+)
 {
     let address_id_table = address_id.borrow_table();
-    let patient_id = *address_id_table.borrow(ctx.sender());
     let hospital_personnel_id = *address_id_table.borrow(hospital_personnel_address);
 
     let patient_id_account_table = patient_id_account.borrow_mut_table();
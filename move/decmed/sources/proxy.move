@@ -10,6 +10,10 @@ use decmed::std_enum_hospital_personnel_role::{
 use decmed::std_enum_hospital_personnel_role::{
     HospitalPersonnelRole,
 };
+use decmed::patient::{
+    borrow_ward_id,
+    create_emergency_access as patient_create_emergency_access,
+};
 use decmed::shared::{
     GlobalAdminCap,
     ProxyCap,
@@ -17,6 +21,7 @@ use decmed::shared::{
     transfer_proxy_cap,
 };
 use decmed::std_struct_address_id::AddressId;
+use decmed::std_struct_hospital_id_metadata::HospitalIdMetadata;
 use decmed::std_struct_hospital_personnel_id_account::HospitalPersonnelIdAccount;
 use decmed::std_struct_patient_administrative_metadata::PatientAdministrativeMetadata;
 use decmed::std_struct_patient_id_account::PatientIdAccount;
@@ -38,6 +43,8 @@ const EInvalidAccessType: u64 = 4004;
 const EMedicalRecordCreationLimit: u64 = 4005;
 const EMedicalRecordNotFound: u64 = 4006;
 
+const MEDICAL_METADATA_PAGE_SIZE_MAX: u64 = 50;
+
 entry fun create_capability(
     proxy_address: address,
     _: &GlobalAdminCap,
@@ -47,6 +54,65 @@ entry fun create_capability(
    transfer_proxy_cap(proxy_address, ctx);
 }
 
+/// Break-glass access requested by medical personnel, see `patient::create_emergency_access`
+entry fun create_emergency_access(
+    address_id: &AddressId,
+    clock: &Clock,
+    date: String,
+    hospital_id_metadata: &HospitalIdMetadata,
+    hospital_personnel_address: address,
+    hospital_personnel_id_account: &mut HospitalPersonnelIdAccount,
+    justification: String,
+    metadata: String,
+    patient_address: address,
+    patient_id_account: &mut PatientIdAccount,
+    _: &ProxyCap,
+)
+{
+    let _ = patient_create_emergency_access(
+        address_id,
+        clock,
+        date,
+        hospital_id_metadata,
+        hospital_personnel_address,
+        hospital_personnel_id_account,
+        justification,
+        metadata,
+        patient_address,
+        patient_id_account,
+    );
+}
+
+#[test_only]
+public(package) fun create_emergency_access_test(
+    address_id: &AddressId,
+    clock: &Clock,
+    date: String,
+    hospital_id_metadata: &HospitalIdMetadata,
+    hospital_personnel_address: address,
+    hospital_personnel_id_account: &mut HospitalPersonnelIdAccount,
+    justification: String,
+    metadata: String,
+    patient_address: address,
+    patient_id_account: &mut PatientIdAccount,
+    proxy_cap: &ProxyCap,
+)
+{
+    create_emergency_access(
+        address_id,
+        clock,
+        date,
+        hospital_id_metadata,
+        hospital_personnel_address,
+        hospital_personnel_id_account,
+        justification,
+        metadata,
+        patient_address,
+        patient_id_account,
+        proxy_cap,
+    );
+}
+
 entry fun create_medical_record(
     address_id: &AddressId,
     clock: &Clock,
@@ -212,6 +278,48 @@ entry fun get_hospital_personnel_role(
     role
 }
 
+/// Page of a patient's medical metadata, oldest first starting at index `cursor`, used by the
+/// proxy to reconcile stored content
+entry fun get_medical_metadata(
+    address_id: &AddressId,
+    cursor: u64,
+    patient_address: address,
+    patient_id_account: &PatientIdAccount,
+    size: u64,
+    _: &ProxyCap,
+): vector<PatientMedicalMetadata>
+{
+    let address_id_table = address_id.borrow_table();
+
+    assert!(address_id_table.contains(patient_address), EAddressNotFound);
+
+    let patient_id = *address_id_table.borrow(patient_address);
+    let patient_id_account_table = patient_id_account.borrow_table();
+
+    assert!(patient_id_account_table.contains(patient_id), EAccountNotFound);
+
+    let patient_account = patient_id_account_table.borrow(patient_id);
+    let patient_medical_metadata = patient_account.borrow_medical_metadata();
+
+    let patient_medical_metadata_length = patient_medical_metadata.length();
+
+    let mut result = vector::empty<PatientMedicalMetadata>();
+
+    if (cursor >= patient_medical_metadata_length) {
+        return result
+    };
+
+    let end_idx = std::u64::min(cursor + std::u64::min(size, MEDICAL_METADATA_PAGE_SIZE_MAX), patient_medical_metadata_length);
+    let mut curr_idx = cursor;
+
+    while (curr_idx < end_idx) {
+        result.push_back(*patient_medical_metadata.borrow(curr_idx));
+        curr_idx = curr_idx + 1;
+    };
+
+    result
+}
+
 /// ## Returns:
 /// 1: prev_index
 /// 2: next_index
@@ -245,19 +353,33 @@ entry fun get_medical_record(
 
     let read_access_types = read_access.borrow_access_data_types();
     assert!(read_access_types.contains(&hospital_personnel_access_data_type_medical()), EInvalidAccessType);
+    let medical_metadata_scope = *read_access.borrow_medical_metadata_scope();
 
     if (read_access.borrow_exp() < clock.timestamp_ms()) {
         hospital_personnel_read_access.remove(&patient_id);
         assert!(false, EAccessExpired);
     };
 
+    // With a scoped access, `index` only counts the records inside the scope
     let patient_medical_metadata = patient_account.borrow_medical_metadata();
-    let medical_metadata = patient_medical_metadata.borrow(patient_medical_metadata.length() - index - 1);
+    let medical_metadata_length = if (medical_metadata_scope.is_some()) {
+        medical_metadata_scope.borrow().length()
+    } else {
+        patient_medical_metadata.length()
+    };
+    assert!(index < medical_metadata_length, EMedicalRecordNotFound);
+
+    let medical_metadata_index = if (medical_metadata_scope.is_some()) {
+        *medical_metadata_scope.borrow().borrow(medical_metadata_length - index - 1)
+    } else {
+        medical_metadata_length - index - 1
+    };
+    let medical_metadata = patient_medical_metadata.borrow(medical_metadata_index);
 
     let mut next_index = option::some(index + 1);
     let mut prev_index = option::none<u64>();
 
-    if (patient_medical_metadata.length() == index + 1) {
+    if (medical_metadata_length == index + 1) {
         next_index = option::none()
     };
     if (index > 0) {
@@ -266,7 +388,7 @@ entry fun get_medical_record(
 
     let patient_administrative_metadata = patient_account.borrow_administrative_metadata();
 
-    (*medical_metadata, *patient_administrative_metadata, patient_medical_metadata.length() - index - 1, prev_index, next_index)
+    (*medical_metadata, *patient_administrative_metadata, medical_metadata_index, prev_index, next_index)
 }
 
 #[test_only]
@@ -337,6 +459,61 @@ entry fun get_medical_record_update(
     (*medical_metadata, *administrative_metadata)
 }
 
+/// Access granted by the patient to the hospital personnel, read or update, not expired
+entry fun is_access_granted(
+    address_id: &AddressId,
+    clock: &Clock,
+    hospital_personnel_address: address,
+    hospital_personnel_id_account: &HospitalPersonnelIdAccount,
+    patient_address: address,
+    _: &ProxyCap,
+)
+{
+    let address_id_table = address_id.borrow_table();
+
+    assert!(address_id_table.contains(hospital_personnel_address), EAddressNotFound);
+    assert!(address_id_table.contains(patient_address), EAddressNotFound);
+
+    let hospital_personnel_id = *address_id_table.borrow(hospital_personnel_address);
+    let patient_id = *address_id_table.borrow(patient_address);
+
+    let hospital_personnel_id_account_table = hospital_personnel_id_account.borrow_table();
+
+    assert!(hospital_personnel_id_account_table.contains(hospital_personnel_id), EAccountNotFound);
+
+    let hospital_personnel_account = hospital_personnel_id_account_table.borrow(hospital_personnel_id);
+
+    assert!(hospital_personnel_account.borrow_access().is_some(), EAccessNotFound);
+
+    let hospital_personnel_access = hospital_personnel_account.borrow_access().borrow();
+    let hospital_personnel_read_access = hospital_personnel_access.borrow_read();
+    let hospital_personnel_update_access = hospital_personnel_access.borrow_update();
+    let now = clock.timestamp_ms();
+
+    let is_read_granted = hospital_personnel_read_access.contains(&patient_id)
+        && hospital_personnel_read_access.get(&patient_id).borrow_exp() >= now;
+    let is_update_granted = hospital_personnel_update_access.contains(&patient_id)
+        && hospital_personnel_update_access.get(&patient_id).borrow_exp() >= now;
+
+    assert!(is_read_granted || is_update_granted, EAccessNotFound);
+}
+
+/// Keys stored or revoked by a guardian on behalf of the patient
+///
+/// ## Params
+/// - `right`: 0 grant, 1 read, 2 revoke
+entry fun is_guardian_allowed(
+    address_id: &AddressId,
+    guardian_address: address,
+    patient_address: address,
+    patient_id_account: &PatientIdAccount,
+    right: u8,
+    _: &ProxyCap,
+)
+{
+    let _ = borrow_ward_id(address_id, guardian_address, patient_address, patient_id_account, right);
+}
+
 entry fun is_patient_registered(
     address_id: &AddressId,
     patient_id_account: &PatientIdAccount,
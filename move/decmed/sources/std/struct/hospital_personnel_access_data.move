@@ -11,6 +11,7 @@ public struct HospitalPersonnelAccessData has copy, drop, store {
     exp: u64,
     metadata: String,
     medical_metadata_index: Option<u64>,
+    medical_metadata_scope: Option<vector<u64>>,
 }
 
 public(package) fun new(
@@ -18,13 +19,15 @@ public(package) fun new(
     exp: u64,
     metadata: String,
     medical_metadata_index: Option<u64>,
+    medical_metadata_scope: Option<vector<u64>>,
 ): HospitalPersonnelAccessData
 {
     HospitalPersonnelAccessData {
         access_data_types,
         exp,
         metadata,
-        medical_metadata_index
+        medical_metadata_index,
+        medical_metadata_scope,
     }
 }
 
@@ -80,6 +83,13 @@ public(package) fun set_medical_metadata_index(
     self.medical_metadata_index = medical_metadata_index;
 }
 
+public(package) fun borrow_medical_metadata_scope(
+    self: &HospitalPersonnelAccessData,
+): &Option<vector<u64>>
+{
+    &self.medical_metadata_scope
+}
+
 #[test_only]
 public(package) fun default(): HospitalPersonnelAccessData
 {
@@ -88,5 +98,6 @@ public(package) fun default(): HospitalPersonnelAccessData
     	exp: 0,
     	metadata: string::utf8(b"Metadata"),
     	medical_metadata_index: option::none(),
+    	medical_metadata_scope: option::none(),
     }
 }
@@ -0,0 +1,50 @@
+module decmed::std_struct_patient_emergency_access;
+
+use decmed::std_struct_patient_emergency_access_log::PatientEmergencyAccessLog;
+
+use iota::table_vec::{Self, TableVec};
+
+/// Break-glass consent and log of a patient, stored as a dynamic field of `PatientIdAccount`
+/// keyed on the patient id so `PatientAccount` keeps its published layout
+public struct PatientEmergencyAccess has store {
+    /// Consent to break-glass access, the emergency keys are deposited with the proxy
+    is_enabled: bool,
+    log: TableVec<PatientEmergencyAccessLog>,
+}
+
+public(package) fun new(ctx: &mut TxContext): PatientEmergencyAccess
+{
+    PatientEmergencyAccess {
+        is_enabled: false,
+        log: table_vec::empty<PatientEmergencyAccessLog>(ctx),
+    }
+}
+
+public(package) fun borrow_is_enabled(
+    self: &PatientEmergencyAccess,
+): bool
+{
+    self.is_enabled
+}
+
+public(package) fun set_is_enabled(
+    self: &mut PatientEmergencyAccess,
+    is_enabled: bool,
+)
+{
+    self.is_enabled = is_enabled;
+}
+
+public(package) fun borrow_log(
+    self: &PatientEmergencyAccess,
+): &TableVec<PatientEmergencyAccessLog>
+{
+    &self.log
+}
+
+public(package) fun borrow_mut_log(
+    self: &mut PatientEmergencyAccess,
+): &mut TableVec<PatientEmergencyAccessLog>
+{
+    &mut self.log
+}
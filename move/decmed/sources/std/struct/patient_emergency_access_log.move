@@ -0,0 +1,100 @@
+module decmed::std_struct_patient_emergency_access_log;
+
+use decmed::std_struct_hospital_metadata::HospitalMetadata;
+
+use iota::event;
+
+use std::string::String;
+
+/// Break-glass read access the proxy granted without the patient, kept until the patient
+/// acknowledges it
+public struct PatientEmergencyAccessLog has copy, drop, store {
+    /// Index of the matching entry in the access log, used to revoke the access
+    access_log_index: u64,
+    date: String,
+    exp_dur: u64,
+    hospital_metadata: HospitalMetadata,
+    hospital_personnel_address: address,
+    hospital_personnel_metadata: String,
+    index: u64,
+    is_acknowledged: bool,
+    justification: String,
+    patient_address: address,
+}
+
+public(package) fun new(
+    access_log_index: u64,
+    date: String,
+    exp_dur: u64,
+    hospital_metadata: HospitalMetadata,
+    hospital_personnel_address: address,
+    hospital_personnel_metadata: String,
+    index: u64,
+    justification: String,
+    patient_address: address,
+): PatientEmergencyAccessLog
+{
+    PatientEmergencyAccessLog {
+        access_log_index,
+        date,
+        exp_dur,
+        hospital_metadata,
+        hospital_personnel_address,
+        hospital_personnel_metadata,
+        index,
+        is_acknowledged: false,
+        justification,
+        patient_address,
+    }
+}
+
+/// Emitted as an event, events can only be emitted by the module defining their type
+public(package) fun emit(
+    self: PatientEmergencyAccessLog,
+)
+{
+    event::emit(self);
+}
+
+public(package) fun borrow_access_log_index(
+    self: &PatientEmergencyAccessLog,
+): u64
+{
+    self.access_log_index
+}
+
+public(package) fun borrow_hospital_personnel_address(
+    self: &PatientEmergencyAccessLog,
+): address
+{
+    self.hospital_personnel_address
+}
+
+public(package) fun borrow_index(
+    self: &PatientEmergencyAccessLog,
+): u64
+{
+    self.index
+}
+
+public(package) fun borrow_is_acknowledged(
+    self: &PatientEmergencyAccessLog,
+): bool
+{
+    self.is_acknowledged
+}
+
+public(package) fun set_is_acknowledged(
+    self: &mut PatientEmergencyAccessLog,
+    is_acknowledged: bool,
+)
+{
+    self.is_acknowledged = is_acknowledged;
+}
+
+public(package) fun borrow_justification(
+    self: &PatientEmergencyAccessLog,
+): &String
+{
+    &self.justification
+}
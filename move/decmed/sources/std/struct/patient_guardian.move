@@ -0,0 +1,67 @@
+module decmed::std_struct_patient_guardian;
+
+use std::string::String;
+
+/// Patient account allowed to act on behalf of another patient, the ward
+public struct PatientGuardian has copy, drop, store {
+    address: address,
+    can_grant: bool,
+    can_read: bool,
+    can_revoke: bool,
+    metadata: Option<String>,
+}
+
+/// ## Params
+/// - `metadata`: Base64 encoded, ward keys encrypted for the guardian, none when the guardian can
+///   only revoke
+public(package) fun new(
+    address: address,
+    can_grant: bool,
+    can_read: bool,
+    can_revoke: bool,
+    metadata: Option<String>,
+): PatientGuardian
+{
+    PatientGuardian {
+        address,
+        can_grant,
+        can_read,
+        can_revoke,
+        metadata,
+    }
+}
+
+public(package) fun borrow_address(
+    self: &PatientGuardian,
+): address
+{
+    self.address
+}
+
+public(package) fun borrow_can_grant(
+    self: &PatientGuardian,
+): bool
+{
+    self.can_grant
+}
+
+public(package) fun borrow_can_read(
+    self: &PatientGuardian,
+): bool
+{
+    self.can_read
+}
+
+public(package) fun borrow_can_revoke(
+    self: &PatientGuardian,
+): bool
+{
+    self.can_revoke
+}
+
+public(package) fun borrow_metadata(
+    self: &PatientGuardian,
+): &Option<String>
+{
+    &self.metadata
+}
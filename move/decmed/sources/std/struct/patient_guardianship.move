@@ -0,0 +1,47 @@
+module decmed::std_struct_patient_guardianship;
+
+use decmed::std_struct_patient_guardian::PatientGuardian;
+
+/// Guardians and wards of a patient, stored as a dynamic field of `PatientIdAccount` keyed on the
+/// patient id so `PatientAccount` keeps its published layout
+public struct PatientGuardianship has store {
+    guardians: vector<PatientGuardian>,
+    /// Patients this account is a guardian of
+    wards: vector<address>,
+}
+
+public(package) fun new(): PatientGuardianship
+{
+    PatientGuardianship {
+        guardians: vector::empty<PatientGuardian>(),
+        wards: vector::empty<address>(),
+    }
+}
+
+public(package) fun borrow_guardians(
+    self: &PatientGuardianship,
+): &vector<PatientGuardian>
+{
+    &self.guardians
+}
+
+public(package) fun borrow_mut_guardians(
+    self: &mut PatientGuardianship,
+): &mut vector<PatientGuardian>
+{
+    &mut self.guardians
+}
+
+public(package) fun borrow_wards(
+    self: &PatientGuardianship,
+): &vector<address>
+{
+    &self.wards
+}
+
+public(package) fun borrow_mut_wards(
+    self: &mut PatientGuardianship,
+): &mut vector<address>
+{
+    &mut self.wards
+}
@@ -1,7 +1,13 @@
 module decmed::std_struct_patient_id_account;
 
 use decmed::std_struct_patient_account::PatientAccount;
+use decmed::std_struct_patient_emergency_access::PatientEmergencyAccess;
+use decmed::std_struct_patient_guardianship::{
+    PatientGuardianship,
+    new as patient_guardianship_new,
+};
 
+use iota::dynamic_field;
 use iota::table::{Self, Table};
 
 use std::string::String;
@@ -11,6 +17,16 @@ public struct PatientIdAccount has key, store {
 	table: Table<String, PatientAccount>,
 }
 
+/// Dynamic field key of a patient's `PatientEmergencyAccess`
+public struct PatientEmergencyAccessKey has copy, drop, store {
+    patient_id: String,
+}
+
+/// Dynamic field key of a patient's `PatientGuardianship`
+public struct PatientGuardianshipKey has copy, drop, store {
+    patient_id: String,
+}
+
 public(package) fun borrow_id(
     self: &PatientIdAccount,
 ): &UID
@@ -32,6 +48,69 @@ public(package) fun borrow_mut_table(
     &mut self.table
 }
 
+public(package) fun contains_emergency_access(
+    self: &PatientIdAccount,
+    patient_id: String,
+): bool
+{
+    dynamic_field::exists_(&self.id, PatientEmergencyAccessKey { patient_id })
+}
+
+public(package) fun borrow_emergency_access(
+    self: &PatientIdAccount,
+    patient_id: String,
+): &PatientEmergencyAccess
+{
+    dynamic_field::borrow(&self.id, PatientEmergencyAccessKey { patient_id })
+}
+
+public(package) fun borrow_mut_emergency_access(
+    self: &mut PatientIdAccount,
+    patient_id: String,
+): &mut PatientEmergencyAccess
+{
+    dynamic_field::borrow_mut(&mut self.id, PatientEmergencyAccessKey { patient_id })
+}
+
+public(package) fun add_emergency_access(
+    self: &mut PatientIdAccount,
+    patient_id: String,
+    emergency_access: PatientEmergencyAccess,
+)
+{
+    dynamic_field::add(&mut self.id, PatientEmergencyAccessKey { patient_id }, emergency_access);
+}
+
+public(package) fun contains_guardianship(
+    self: &PatientIdAccount,
+    patient_id: String,
+): bool
+{
+    dynamic_field::exists_(&self.id, PatientGuardianshipKey { patient_id })
+}
+
+public(package) fun borrow_guardianship(
+    self: &PatientIdAccount,
+    patient_id: String,
+): &PatientGuardianship
+{
+    dynamic_field::borrow(&self.id, PatientGuardianshipKey { patient_id })
+}
+
+/// Adds an empty guardianship on first use
+public(package) fun borrow_mut_guardianship(
+    self: &mut PatientIdAccount,
+    patient_id: String,
+): &mut PatientGuardianship
+{
+    let key = PatientGuardianshipKey { patient_id };
+
+    if (!dynamic_field::exists_(&self.id, key)) {
+        dynamic_field::add(&mut self.id, key, patient_guardianship_new());
+    };
+
+    dynamic_field::borrow_mut(&mut self.id, key)
+}
 
 fun init(ctx: &mut TxContext) {
     let hospital_personnel_id_account = PatientIdAccount{
@@ -9,10 +9,16 @@ use decmed::shared_tests::{
 };
 
 use decmed::patient::{
+    add_guardian_test,
+    create_access_as_guardian_test,
     create_access_test,
+    remove_guardian_test,
+    revoke_access_as_guardian_test,
+    set_emergency_access_test,
 };
 
 use decmed::proxy::{
+    create_emergency_access_test,
     create_medical_record_test,
     get_administrative_data_test,
     get_medical_record_test,
@@ -66,6 +72,26 @@ fun create_access(
     metadata: vector<String>,
     scenario: &mut test_scenario::Scenario,
 )
+{
+    // Durations and scope the patient client grants by default
+    if (hospital_personnel_address == MEDICAL_PERSONNEL_ADDR) {
+        create_access_with_scope(clock, 15, 2 * 60, hospital_personnel_address, true, option::none(), metadata, scenario);
+    } else {
+        create_access_with_scope(clock, 5, 0, hospital_personnel_address, false, option::none(), metadata, scenario);
+    };
+}
+
+#[test_only]
+fun create_access_with_scope(
+    clock: &Clock,
+    exp_dur_read: u64,
+    exp_dur_update: u64,
+    hospital_personnel_address: address,
+    is_medical_read: bool,
+    medical_metadata_scope: Option<vector<u64>>,
+    metadata: vector<String>,
+    scenario: &mut test_scenario::Scenario,
+)
 {
     let address_id = test_scenario::take_shared<AddressId>(scenario);
     let mut hospital_personnel_id_account = test_scenario::take_shared<HospitalPersonnelIdAccount>(scenario);
@@ -76,18 +102,146 @@ fun create_access(
         &address_id,
         clock,
         string::utf8(b"2025-07-28T14:40:49+00:00"),
+        exp_dur_read,
+        exp_dur_update,
         &hospital_id_metadata,
         hospital_personnel_address,
         &mut hospital_personnel_id_account,
+        is_medical_read,
+        medical_metadata_scope,
+        metadata,
+        &mut patient_id_account,
+        test_scenario::ctx(scenario),
+    );
+
+    test_scenario::return_shared(address_id);
+    test_scenario::return_shared(hospital_personnel_id_account);
+    test_scenario::return_shared(patient_id_account);
+    test_scenario::return_shared(hospital_id_metadata);
+}
+
+#[test_only]
+// Patient 2 becomes a guardian of patient 1
+fun add_guardian(
+    can_grant: bool,
+    can_read: bool,
+    can_revoke: bool,
+    scenario: &mut test_scenario::Scenario,
+)
+{
+    let address_id = test_scenario::take_shared<AddressId>(scenario);
+    let mut patient_id_account = test_scenario::take_shared<PatientIdAccount>(scenario);
+
+    let metadata = if (can_grant || can_read) {
+        option::some(string::utf8(b"WardKeysForGuardian"))
+    } else {
+        option::none()
+    };
+
+    add_guardian_test(
+        &address_id,
+        can_grant,
+        can_read,
+        can_revoke,
+        PATIENT_2_ADDR,
+        metadata,
+        &mut patient_id_account,
+        test_scenario::ctx(scenario),
+    );
+
+    test_scenario::return_shared(address_id);
+    test_scenario::return_shared(patient_id_account);
+}
+
+#[test_only]
+fun create_access_as_guardian(
+    clock: &Clock,
+    metadata: vector<String>,
+    scenario: &mut test_scenario::Scenario,
+)
+{
+    let address_id = test_scenario::take_shared<AddressId>(scenario);
+    let mut hospital_personnel_id_account = test_scenario::take_shared<HospitalPersonnelIdAccount>(scenario);
+    let mut patient_id_account = test_scenario::take_shared<PatientIdAccount>(scenario);
+    let hospital_id_metadata = test_scenario::take_shared<HospitalIdMetadata>(scenario);
+
+    create_access_as_guardian_test(
+        &address_id,
+        clock,
+        string::utf8(b"2025-07-28T14:40:49+00:00"),
+        5,
+        0,
+        &hospital_id_metadata,
+        ADMINISTRATIVE_PERSONNEL_ADDR,
+        &mut hospital_personnel_id_account,
+        false,
+        option::none(),
         metadata,
+        PATIENT_ADDR,
+        &mut patient_id_account,
+        test_scenario::ctx(scenario),
+    );
+
+    test_scenario::return_shared(address_id);
+    test_scenario::return_shared(hospital_personnel_id_account);
+    test_scenario::return_shared(patient_id_account);
+    test_scenario::return_shared(hospital_id_metadata);
+}
+
+#[test_only]
+fun set_emergency_access(
+    is_enabled: bool,
+    scenario: &mut test_scenario::Scenario,
+)
+{
+    let address_id = test_scenario::take_shared<AddressId>(scenario);
+    let mut patient_id_account = test_scenario::take_shared<PatientIdAccount>(scenario);
+
+    set_emergency_access_test(
+        &address_id,
+        is_enabled,
         &mut patient_id_account,
         test_scenario::ctx(scenario),
     );
 
+    test_scenario::return_shared(address_id);
+    test_scenario::return_shared(patient_id_account);
+}
+
+#[test_only]
+// Break-glass access to patient 1, requested through the proxy
+fun create_emergency_access(
+    clock: &Clock,
+    hospital_personnel_address: address,
+    justification: String,
+    scenario: &mut test_scenario::Scenario,
+)
+{
+    let address_id = test_scenario::take_shared<AddressId>(scenario);
+    let mut hospital_personnel_id_account = test_scenario::take_shared<HospitalPersonnelIdAccount>(scenario);
+    let mut patient_id_account = test_scenario::take_shared<PatientIdAccount>(scenario);
+    let hospital_id_metadata = test_scenario::take_shared<HospitalIdMetadata>(scenario);
+    let proxy_cap = test_scenario::take_from_address<ProxyCap>(scenario, PROXY_ADDR);
+
+    create_emergency_access_test(
+        &address_id,
+        clock,
+        string::utf8(b"2025-07-28T14:40:49+00:00"),
+        &hospital_id_metadata,
+        hospital_personnel_address,
+        &mut hospital_personnel_id_account,
+        justification,
+        string::utf8(b"EmergencyReadAccess"),
+        PATIENT_ADDR,
+        &mut patient_id_account,
+        &proxy_cap,
+    );
+
     test_scenario::return_shared(address_id);
     test_scenario::return_shared(hospital_personnel_id_account);
     test_scenario::return_shared(patient_id_account);
     test_scenario::return_shared(hospital_id_metadata);
+    test_scenario::return_to_address(PROXY_ADDR, proxy_cap);
 }
 
 #[test_only]
@@ -766,3 +920,514 @@ fun test_ill_10()
     clck.destroy_for_testing();
     test_scenario::end(scenario_val);
 }
+
+#[test, expected_failure(abort_code = ::decmed::patient::EInvalidAccessScope)]
+// Grant medical history read access
+// to administrative personnel
+fun test_ill_11()
+{
+    let mut scenario_val = test_scenario::begin(PUBLISHER_ADDR);
+    let scenario = &mut scenario_val;
+
+    let clck = clock::create_for_testing(test_scenario::ctx(scenario));
+
+    setup_shared_objects(test_scenario::ctx(scenario));
+    test_scenario::next_tx(scenario, PUBLISHER_ADDR);
+    setup_data(scenario);
+    test_scenario::next_tx(scenario, PUBLISHER_ADDR);
+    add_medical_record(scenario);
+
+    test_scenario::next_tx(scenario, PATIENT_ADDR);
+
+    {
+        let mut metadata = vector::empty<String>();
+        metadata.push_back(string::utf8(b"ReadAccessForAdmPersonnel"));
+
+        create_access_with_scope(
+            &clck,
+            5,
+            0,
+            ADMINISTRATIVE_PERSONNEL_ADDR,
+            true,
+            option::none(),
+            metadata,
+            scenario
+        );
+    };
+
+    clck.destroy_for_testing();
+    test_scenario::end(scenario_val);
+}
+
+#[test, expected_failure(abort_code = ::decmed::patient::EInvalidAccessDuration)]
+// Grant read access to administrative personnel
+// for longer than the policy allows
+fun test_ill_12()
+{
+    let mut scenario_val = test_scenario::begin(PUBLISHER_ADDR);
+    let scenario = &mut scenario_val;
+
+    let clck = clock::create_for_testing(test_scenario::ctx(scenario));
+
+    setup_shared_objects(test_scenario::ctx(scenario));
+    test_scenario::next_tx(scenario, PUBLISHER_ADDR);
+    setup_data(scenario);
+    test_scenario::next_tx(scenario, PUBLISHER_ADDR);
+    add_medical_record(scenario);
+
+    test_scenario::next_tx(scenario, PATIENT_ADDR);
+
+    {
+        let mut metadata = vector::empty<String>();
+        metadata.push_back(string::utf8(b"ReadAccessForAdmPersonnel"));
+
+        create_access_with_scope(
+            &clck,
+            61,
+            0,
+            ADMINISTRATIVE_PERSONNEL_ADDR,
+            false,
+            option::none(),
+            metadata,
+            scenario
+        );
+    };
+
+    clck.destroy_for_testing();
+    test_scenario::end(scenario_val);
+}
+
+#[test, expected_failure(abort_code = ::decmed::proxy::EInvalidAccessType)]
+// Read access medical part of medical record by medical personnel
+// granted administrative data only
+fun test_ill_13()
+{
+    let mut scenario_val = test_scenario::begin(PUBLISHER_ADDR);
+    let scenario = &mut scenario_val;
+
+    let clck = clock::create_for_testing(test_scenario::ctx(scenario));
+
+    setup_shared_objects(test_scenario::ctx(scenario));
+    test_scenario::next_tx(scenario, PUBLISHER_ADDR);
+    setup_data(scenario);
+    test_scenario::next_tx(scenario, PUBLISHER_ADDR);
+    add_medical_record(scenario);
+
+    test_scenario::next_tx(scenario, PATIENT_ADDR);
+
+    {
+        let mut metadata = vector::empty<String>();
+        metadata.push_back(string::utf8(b"ReadAccessForMedicalPersonnel"));
+
+        create_access_with_scope(
+            &clck,
+            15,
+            0,
+            MEDICAL_PERSONNEL_ADDR,
+            false,
+            option::none(),
+            metadata,
+            scenario
+        );
+    };
+
+    test_scenario::next_tx(scenario, PROXY_ADDR);
+
+    {
+        let address_id = test_scenario::take_shared<AddressId>(scenario);
+        let mut hospital_personnel_id_account = test_scenario::take_shared<HospitalPersonnelIdAccount>(scenario);
+        let patient_id_account = test_scenario::take_shared<PatientIdAccount>(scenario);
+        let hospital_id_metadata = test_scenario::take_shared<HospitalIdMetadata>(scenario);
+        let proxy_cap = test_scenario::take_from_address<ProxyCap>(scenario, PROXY_ADDR);
+
+        let (_, _, _, _, _) = get_medical_record_test(
+            &address_id,
+            &clck,
+            MEDICAL_PERSONNEL_ADDR,
+            &mut hospital_personnel_id_account,
+            0,
+            PATIENT_ADDR,
+            &patient_id_account,
+            &proxy_cap
+        );
+
+        test_scenario::return_shared(address_id);
+        test_scenario::return_shared(hospital_personnel_id_account);
+        test_scenario::return_shared(patient_id_account);
+        test_scenario::return_shared(hospital_id_metadata);
+        test_scenario::return_to_address(PROXY_ADDR, proxy_cap);
+    };
+
+    clck.destroy_for_testing();
+    test_scenario::end(scenario_val);
+}
+
+#[test, expected_failure(abort_code = ::decmed::proxy::EAccessNotFound)]
+// Write access a new medical record entry by medical personnel
+// granted read access only
+fun test_ill_14()
+{
+    let mut scenario_val = test_scenario::begin(PUBLISHER_ADDR);
+    let scenario = &mut scenario_val;
+
+    let clck = clock::create_for_testing(test_scenario::ctx(scenario));
+
+    setup_shared_objects(test_scenario::ctx(scenario));
+    test_scenario::next_tx(scenario, PUBLISHER_ADDR);
+    setup_data(scenario);
+    test_scenario::next_tx(scenario, PUBLISHER_ADDR);
+    add_medical_record(scenario);
+
+    test_scenario::next_tx(scenario, PATIENT_ADDR);
+
+    {
+        let mut metadata = vector::empty<String>();
+        metadata.push_back(string::utf8(b"ReadAccessForMedicalPersonnel"));
+
+        create_access_with_scope(
+            &clck,
+            15,
+            0,
+            MEDICAL_PERSONNEL_ADDR,
+            true,
+            option::none(),
+            metadata,
+            scenario
+        );
+    };
+
+    test_scenario::next_tx(scenario, PROXY_ADDR);
+
+    {
+        let address_id = test_scenario::take_shared<AddressId>(scenario);
+        let mut hospital_personnel_id_account = test_scenario::take_shared<HospitalPersonnelIdAccount>(scenario);
+        let mut patient_id_account = test_scenario::take_shared<PatientIdAccount>(scenario);
+        let hospital_id_metadata = test_scenario::take_shared<HospitalIdMetadata>(scenario);
+        let proxy_cap = test_scenario::take_from_address<ProxyCap>(scenario, PROXY_ADDR);
+
+        create_medical_record_test(
+            &address_id,
+            &clck,
+            MEDICAL_PERSONNEL_ADDR,
+            &mut hospital_personnel_id_account,
+            string::utf8(b"Metadata"),
+            PATIENT_ADDR,
+            &mut patient_id_account,
+            &proxy_cap
+        );
+
+        test_scenario::return_shared(address_id);
+        test_scenario::return_shared(hospital_personnel_id_account);
+        test_scenario::return_shared(patient_id_account);
+        test_scenario::return_shared(hospital_id_metadata);
+        test_scenario::return_to_address(PROXY_ADDR, proxy_cap);
+    };
+
+    clck.destroy_for_testing();
+    test_scenario::end(scenario_val);
+}
+
+#[test, expected_failure(abort_code = ::decmed::patient::EInvalidMedicalMetadataScope)]
+// Create access scoped to a medical record that doesn't exist
+fun test_ill_15()
+{
+    let mut scenario_val = test_scenario::begin(PUBLISHER_ADDR);
+    let scenario = &mut scenario_val;
+
+    let clck = clock::create_for_testing(test_scenario::ctx(scenario));
+
+    setup_shared_objects(test_scenario::ctx(scenario));
+    test_scenario::next_tx(scenario, PUBLISHER_ADDR);
+    setup_data(scenario);
+    test_scenario::next_tx(scenario, PUBLISHER_ADDR);
+    add_medical_record(scenario);
+
+    test_scenario::next_tx(scenario, PATIENT_ADDR);
+
+    {
+        let mut metadata = vector::empty<String>();
+        metadata.push_back(string::utf8(b"ReadAccessForMedicalPersonnel"));
+
+        let mut medical_metadata_scope = vector::empty<u64>();
+        medical_metadata_scope.push_back(0);
+        medical_metadata_scope.push_back(2);
+
+        create_access_with_scope(
+            &clck,
+            15,
+            0,
+            MEDICAL_PERSONNEL_ADDR,
+            true,
+            option::some(medical_metadata_scope),
+            metadata,
+            scenario
+        );
+    };
+
+    clck.destroy_for_testing();
+    test_scenario::end(scenario_val);
+}
+
+#[test, expected_failure(abort_code = ::decmed::proxy::EMedicalRecordNotFound)]
+// Read access a medical record outside of the scope
+// granted to medical personnel
+fun test_ill_16()
+{
+    let mut scenario_val = test_scenario::begin(PUBLISHER_ADDR);
+    let scenario = &mut scenario_val;
+
+    let clck = clock::create_for_testing(test_scenario::ctx(scenario));
+
+    setup_shared_objects(test_scenario::ctx(scenario));
+    test_scenario::next_tx(scenario, PUBLISHER_ADDR);
+    setup_data(scenario);
+    test_scenario::next_tx(scenario, PUBLISHER_ADDR);
+    add_medical_record(scenario);
+
+    test_scenario::next_tx(scenario, PATIENT_ADDR);
+
+    {
+        let mut metadata = vector::empty<String>();
+        metadata.push_back(string::utf8(b"ReadAccessForMedicalPersonnel"));
+
+        let mut medical_metadata_scope = vector::empty<u64>();
+        medical_metadata_scope.push_back(0);
+
+        create_access_with_scope(
+            &clck,
+            15,
+            0,
+            MEDICAL_PERSONNEL_ADDR,
+            true,
+            option::some(medical_metadata_scope),
+            metadata,
+            scenario
+        );
+    };
+
+    test_scenario::next_tx(scenario, PROXY_ADDR);
+
+    {
+        let address_id = test_scenario::take_shared<AddressId>(scenario);
+        let mut hospital_personnel_id_account = test_scenario::take_shared<HospitalPersonnelIdAccount>(scenario);
+        let patient_id_account = test_scenario::take_shared<PatientIdAccount>(scenario);
+        let hospital_id_metadata = test_scenario::take_shared<HospitalIdMetadata>(scenario);
+        let proxy_cap = test_scenario::take_from_address<ProxyCap>(scenario, PROXY_ADDR);
+
+        // Index 0 is the only record in the scope
+        let (_, _, _, _, _) = get_medical_record_test(
+            &address_id,
+            &clck,
+            MEDICAL_PERSONNEL_ADDR,
+            &mut hospital_personnel_id_account,
+            1,
+            PATIENT_ADDR,
+            &patient_id_account,
+            &proxy_cap
+        );
+
+        test_scenario::return_shared(address_id);
+        test_scenario::return_shared(hospital_personnel_id_account);
+        test_scenario::return_shared(patient_id_account);
+        test_scenario::return_shared(hospital_id_metadata);
+        test_scenario::return_to_address(PROXY_ADDR, proxy_cap);
+    };
+
+    clck.destroy_for_testing();
+    test_scenario::end(scenario_val);
+}
+
+#[test, expected_failure(abort_code = ::decmed::patient::EGuardianRightDenied)]
+// Grant access on behalf of a patient
+// by a guardian who can only revoke
+fun test_ill_17()
+{
+    let mut scenario_val = test_scenario::begin(PUBLISHER_ADDR);
+    let scenario = &mut scenario_val;
+
+    let clck = clock::create_for_testing(test_scenario::ctx(scenario));
+
+    setup_shared_objects(test_scenario::ctx(scenario));
+    test_scenario::next_tx(scenario, PUBLISHER_ADDR);
+    setup_data(scenario);
+
+    test_scenario::next_tx(scenario, PATIENT_ADDR);
+    add_guardian(false, false, true, scenario);
+
+    test_scenario::next_tx(scenario, PATIENT_2_ADDR);
+
+    {
+        let mut metadata = vector::empty<String>();
+        metadata.push_back(string::utf8(b"ReadAccessForAdmPersonnel"));
+
+        create_access_as_guardian(&clck, metadata, scenario);
+    };
+
+    clck.destroy_for_testing();
+    test_scenario::end(scenario_val);
+}
+
+#[test, expected_failure(abort_code = ::decmed::patient::EGuardianNotFound)]
+// Grant access on behalf of a patient
+// by a patient who is not a guardian
+fun test_ill_18()
+{
+    let mut scenario_val = test_scenario::begin(PUBLISHER_ADDR);
+    let scenario = &mut scenario_val;
+
+    let clck = clock::create_for_testing(test_scenario::ctx(scenario));
+
+    setup_shared_objects(test_scenario::ctx(scenario));
+    test_scenario::next_tx(scenario, PUBLISHER_ADDR);
+    setup_data(scenario);
+
+    test_scenario::next_tx(scenario, PATIENT_2_ADDR);
+
+    {
+        let mut metadata = vector::empty<String>();
+        metadata.push_back(string::utf8(b"ReadAccessForAdmPersonnel"));
+
+        create_access_as_guardian(&clck, metadata, scenario);
+    };
+
+    clck.destroy_for_testing();
+    test_scenario::end(scenario_val);
+}
+
+#[test, expected_failure(abort_code = ::decmed::patient::EGuardianNotFound)]
+// Revoke access on behalf of a patient
+// by a guardian the patient removed
+fun test_ill_19()
+{
+    let mut scenario_val = test_scenario::begin(PUBLISHER_ADDR);
+    let scenario = &mut scenario_val;
+
+    let clck = clock::create_for_testing(test_scenario::ctx(scenario));
+
+    setup_shared_objects(test_scenario::ctx(scenario));
+    test_scenario::next_tx(scenario, PUBLISHER_ADDR);
+    setup_data(scenario);
+
+    test_scenario::next_tx(scenario, PATIENT_ADDR);
+    add_guardian(true, false, true, scenario);
+
+    test_scenario::next_tx(scenario, PATIENT_2_ADDR);
+
+    {
+        let mut metadata = vector::empty<String>();
+        metadata.push_back(string::utf8(b"ReadAccessForAdmPersonnel"));
+
+        create_access_as_guardian(&clck, metadata, scenario);
+    };
+
+    test_scenario::next_tx(scenario, PATIENT_ADDR);
+
+    {
+        let address_id = test_scenario::take_shared<AddressId>(scenario);
+        let mut patient_id_account = test_scenario::take_shared<PatientIdAccount>(scenario);
+
+        remove_guardian_test(
+            &address_id,
+            PATIENT_2_ADDR,
+            PATIENT_ADDR,
+            &mut patient_id_account,
+            test_scenario::ctx(scenario),
+        );
+
+        test_scenario::return_shared(address_id);
+        test_scenario::return_shared(patient_id_account);
+    };
+
+    test_scenario::next_tx(scenario, PATIENT_2_ADDR);
+
+    {
+        let address_id = test_scenario::take_shared<AddressId>(scenario);
+        let mut hospital_personnel_id_account = test_scenario::take_shared<HospitalPersonnelIdAccount>(scenario);
+        let mut patient_id_account = test_scenario::take_shared<PatientIdAccount>(scenario);
+
+        revoke_access_as_guardian_test(
+            &address_id,
+            ADMINISTRATIVE_PERSONNEL_ADDR,
+            &mut hospital_personnel_id_account,
+            0,
+            PATIENT_ADDR,
+            &mut patient_id_account,
+            test_scenario::ctx(scenario),
+        );
+
+        test_scenario::return_shared(address_id);
+        test_scenario::return_shared(hospital_personnel_id_account);
+        test_scenario::return_shared(patient_id_account);
+    };
+
+    clck.destroy_for_testing();
+    test_scenario::end(scenario_val);
+}
+
+#[test, expected_failure(abort_code = ::decmed::patient::EEmergencyAccessDisabled)]
+// Break-glass access by medical personnel
+// when the patient did not consent
+fun test_ill_20()
+{
+    let mut scenario_val = test_scenario::begin(PUBLISHER_ADDR);
+    let scenario = &mut scenario_val;
+
+    let clck = clock::create_for_testing(test_scenario::ctx(scenario));
+
+    setup_shared_objects(test_scenario::ctx(scenario));
+    test_scenario::next_tx(scenario, PUBLISHER_ADDR);
+    setup_data(scenario);
+
+    test_scenario::next_tx(scenario, PROXY_ADDR);
+    create_emergency_access(&clck, MEDICAL_PERSONNEL_ADDR, string::utf8(b"Unconscious patient admitted to the emergency room"), scenario);
+
+    clck.destroy_for_testing();
+    test_scenario::end(scenario_val);
+}
+
+#[test, expected_failure(abort_code = ::decmed::patient::EInvalidHospitalPersonnelRole)]
+// Break-glass access by administrative personnel
+fun test_ill_21()
+{
+    let mut scenario_val = test_scenario::begin(PUBLISHER_ADDR);
+    let scenario = &mut scenario_val;
+
+    let clck = clock::create_for_testing(test_scenario::ctx(scenario));
+
+    setup_shared_objects(test_scenario::ctx(scenario));
+    test_scenario::next_tx(scenario, PUBLISHER_ADDR);
+    setup_data(scenario);
+
+    test_scenario::next_tx(scenario, PATIENT_ADDR);
+    set_emergency_access(true, scenario);
+
+    test_scenario::next_tx(scenario, PROXY_ADDR);
+    create_emergency_access(&clck, ADMINISTRATIVE_PERSONNEL_ADDR, string::utf8(b"Unconscious patient admitted to the emergency room"), scenario);
+
+    clck.destroy_for_testing();
+    test_scenario::end(scenario_val);
+}
+
+#[test, expected_failure(abort_code = ::decmed::patient::EInvalidJustificationLength)]
+// Break-glass access by medical personnel
+// without a meaningful justification
+fun test_ill_22()
+{
+    let mut scenario_val = test_scenario::begin(PUBLISHER_ADDR);
+    let scenario = &mut scenario_val;
+
+    let clck = clock::create_for_testing(test_scenario::ctx(scenario));
+
+    setup_shared_objects(test_scenario::ctx(scenario));
+    test_scenario::next_tx(scenario, PUBLISHER_ADDR);
+    setup_data(scenario);
+
+    test_scenario::next_tx(scenario, PATIENT_ADDR);
+    set_emergency_access(true, scenario);
+
+    test_scenario::next_tx(scenario, PROXY_ADDR);
+    create_emergency_access(&clck, MEDICAL_PERSONNEL_ADDR, string::utf8(b"Emergency"), scenario);
+
+    clck.destroy_for_testing();
+    test_scenario::end(scenario_val);
+}
@@ -0,0 +1,368 @@
+use std::{collections::HashMap, env, pin::Pin, str::FromStr, sync::Arc};
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use axum::{body::Bytes, http::StatusCode};
+use futures_util::{Stream, StreamExt, TryStreamExt};
+use object_store::{
+    aws::AmazonS3Builder, local::LocalFileSystem, path::Path, ObjectStore, WriteMultipart,
+};
+
+use crate::{
+    constants::{IPFS_BASE_URL, IPFS_GATEWAY_BASE_URL, IPFS_KUBO_RPC_BASE_URL},
+    current_fn,
+    proxy_error::ProxyError,
+    types::{BlobStoreBackend, UtilIpfsAddResponse, UtilKuboAddResponse},
+};
+
+pub type BlobStream = Pin<Box<dyn Stream<Item = Result<Bytes, ProxyError>> + Send>>;
+
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+    /**
+     * Unpins or deletes the content, only called for content no record references anymore
+     */
+    async fn delete(&self, locator: &str) -> Result<(), ProxyError>;
+
+    /**
+     * Return: stored content, integrity is checked by the caller against the recorded SHA-256
+     */
+    async fn get(&self, locator: &str) -> Result<BlobStream, ProxyError>;
+
+    /**
+     * Return: locator of the stored content, recorded on `MedicalMetadata` next to the backend
+     */
+    async fn put(&self, data: BlobStream) -> Result<String, ProxyError>;
+}
+
+/**
+ * Every backend that can be configured from the environment, reads are routed by the backend
+ * recorded on each medical record so switching `BLOB_STORE_BACKEND` keeps older records readable.
+ */
+pub struct BlobStores {
+    pub default_backend: BlobStoreBackend,
+    stores: HashMap<BlobStoreBackend, Arc<dyn BlobStore>>,
+}
+
+impl BlobStores {
+    /**
+     * Envs:
+     * - `BLOB_STORE_BACKEND`: `ipfs_cluster` (default), `kubo`, `local_fs` or `s3`
+     * - `IPFS_GATEWAY_FALLBACK_BASE_URLS`: comma separated, tried in order when
+     *   `IPFS_GATEWAY_BASE_URL` is unreachable, e.g. gateways of other cluster peers
+     * - `BLOB_STORE_LOCAL_DIR`: enables `local_fs`
+     * - `BLOB_STORE_S3_BUCKET`: enables `s3`, the rest is read from the standard `AWS_*` envs
+     *   (`AWS_ENDPOINT`, `AWS_REGION`, `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY`, ...)
+     */
+    pub fn from_env() -> Result<Self, ProxyError> {
+        let default_backend = match env::var("BLOB_STORE_BACKEND") {
+            Ok(backend) => BlobStoreBackend::from_str(&backend)
+                .map_err(|_| anyhow!("Invalid blob store backend: {}", backend))
+                .context(current_fn!())?,
+            Err(_) => BlobStoreBackend::default(),
+        };
+
+        let gateway_fallback_base_urls = env::var("IPFS_GATEWAY_FALLBACK_BASE_URLS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|base_url| base_url.trim().trim_end_matches('/').to_string())
+            .filter(|base_url| !base_url.is_empty())
+            .collect::<Vec<String>>();
+
+        let mut stores: HashMap<BlobStoreBackend, Arc<dyn BlobStore>> = HashMap::new();
+        stores.insert(
+            BlobStoreBackend::IpfsCluster,
+            Arc::new(IpfsClusterBlobStore {
+                api_base_url: IPFS_BASE_URL.to_string(),
+                gateway_base_urls: std::iter::once(IPFS_GATEWAY_BASE_URL.to_string())
+                    .chain(gateway_fallback_base_urls)
+                    .collect(),
+            }),
+        );
+        stores.insert(
+            BlobStoreBackend::Kubo,
+            Arc::new(KuboBlobStore {
+                rpc_base_url: IPFS_KUBO_RPC_BASE_URL.to_string(),
+            }),
+        );
+
+        if let Ok(dir) = env::var("BLOB_STORE_LOCAL_DIR") {
+            std::fs::create_dir_all(&dir).context(current_fn!())?;
+            let store = LocalFileSystem::new_with_prefix(&dir).context(current_fn!())?;
+
+            stores.insert(
+                BlobStoreBackend::LocalFs,
+                Arc::new(ObjectBlobStore {
+                    store: Arc::new(store),
+                }),
+            );
+        }
+
+        if let Ok(bucket) = env::var("BLOB_STORE_S3_BUCKET") {
+            let store = AmazonS3Builder::from_env()
+                .with_bucket_name(bucket)
+                .build()
+                .context(current_fn!())?;
+
+            stores.insert(
+                BlobStoreBackend::S3,
+                Arc::new(ObjectBlobStore {
+                    store: Arc::new(store),
+                }),
+            );
+        }
+
+        let blob_stores = Self {
+            default_backend,
+            stores,
+        };
+        blob_stores.get(default_backend).context(current_fn!())?;
+
+        Ok(blob_stores)
+    }
+
+    pub fn get(&self, backend: BlobStoreBackend) -> Result<Arc<dyn BlobStore>, ProxyError> {
+        self.stores
+            .get(&backend)
+            .cloned()
+            .ok_or(ProxyError::Anyhow {
+                source: anyhow!("Blob store backend {} is not configured", backend),
+                code: StatusCode::INTERNAL_SERVER_ERROR,
+            })
+    }
+
+    /**
+     * Return: `(backend, store)` new content is written to
+     */
+    pub fn get_default(&self) -> Result<(BlobStoreBackend, Arc<dyn BlobStore>), ProxyError> {
+        Ok((
+            self.default_backend,
+            self.get(self.default_backend).context(current_fn!())?,
+        ))
+    }
+}
+
+pub struct IpfsClusterBlobStore {
+    api_base_url: String,
+    /// Tried in order, the first one is the primary gateway
+    gateway_base_urls: Vec<String>,
+}
+
+#[async_trait]
+impl BlobStore for IpfsClusterBlobStore {
+    async fn delete(&self, locator: &str) -> Result<(), ProxyError> {
+        let req_client = reqwest::Client::new();
+        let res = req_client
+            .delete(format!("{}/pins/{}", self.api_base_url, locator))
+            .send()
+            .await
+            .context(current_fn!())?;
+
+        if !res.status().is_success() {
+            return Err(ProxyError::Anyhow {
+                source: anyhow!(
+                    "Failed to unpin content from IPFS cluster: {}",
+                    res.status()
+                )
+                .context(current_fn!()),
+                code: StatusCode::BAD_GATEWAY,
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn get(&self, locator: &str) -> Result<BlobStream, ProxyError> {
+        let req_client = reqwest::Client::new();
+        let mut errors: Vec<String> = Vec::new();
+
+        for base_url in self.gateway_base_urls.iter() {
+            match req_client
+                .get(format!("{}/ipfs/{}", base_url, locator))
+                .send()
+                .await
+            {
+                Ok(res) if res.status() == StatusCode::OK => {
+                    return Ok(Box::pin(
+                        res.bytes_stream()
+                            .map_err(|e| ProxyError::from(anyhow!(e).context(current_fn!()))),
+                    ))
+                }
+                Ok(res) => errors.push(format!("{}: {}", base_url, res.status())),
+                Err(e) => errors.push(format!("{}: {}", base_url, e)),
+            }
+        }
+
+        Err(ProxyError::Anyhow {
+            source: anyhow!(format!(
+                "Failed to get content from IPFS: {}",
+                errors.join(", ")
+            ))
+            .context(current_fn!()),
+            code: StatusCode::BAD_GATEWAY,
+        })
+    }
+
+    /**
+     * Uploads `data` as a binary part, the body is streamed as-is and pinned by the cluster.
+     */
+    async fn put(&self, data: BlobStream) -> Result<String, ProxyError> {
+        let path_part =
+            reqwest::multipart::Part::stream(reqwest::Body::wrap_stream(data)).file_name("data");
+        let form = reqwest::multipart::Form::new().part("path", path_part);
+        let req_client = reqwest::Client::new();
+        let res = req_client
+            .post(format!("{}/add", self.api_base_url))
+            .multipart(form)
+            .send()
+            .await
+            .context(current_fn!())?;
+
+        let res = res
+            .json::<UtilIpfsAddResponse>()
+            .await
+            .context(current_fn!())?;
+
+        Ok(res.cid)
+    }
+}
+
+pub struct KuboBlobStore {
+    rpc_base_url: String,
+}
+
+#[async_trait]
+impl BlobStore for KuboBlobStore {
+    async fn delete(&self, locator: &str) -> Result<(), ProxyError> {
+        let req_client = reqwest::Client::new();
+        let res = req_client
+            .post(format!("{}/api/v0/pin/rm", self.rpc_base_url))
+            .query(&[("arg", locator)])
+            .send()
+            .await
+            .context(current_fn!())?;
+
+        if res.status() != StatusCode::OK {
+            return Err(ProxyError::Anyhow {
+                source: anyhow!("Failed to unpin content from kubo: {}", res.status())
+                    .context(current_fn!()),
+                code: StatusCode::BAD_GATEWAY,
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn get(&self, locator: &str) -> Result<BlobStream, ProxyError> {
+        let req_client = reqwest::Client::new();
+        let res = req_client
+            .post(format!("{}/api/v0/cat", self.rpc_base_url))
+            .query(&[("arg", locator)])
+            .send()
+            .await
+            .context(current_fn!())?;
+
+        if res.status() != StatusCode::OK {
+            return Err(ProxyError::Anyhow {
+                source: anyhow!("Failed to get content from kubo: {}", res.status())
+                    .context(current_fn!()),
+                code: StatusCode::BAD_GATEWAY,
+            });
+        }
+
+        Ok(Box::pin(res.bytes_stream().map_err(|e| {
+            ProxyError::from(anyhow!(e).context(current_fn!()))
+        })))
+    }
+
+    async fn put(&self, data: BlobStream) -> Result<String, ProxyError> {
+        let file_part =
+            reqwest::multipart::Part::stream(reqwest::Body::wrap_stream(data)).file_name("data");
+        let form = reqwest::multipart::Form::new().part("file", file_part);
+        let req_client = reqwest::Client::new();
+        let res = req_client
+            .post(format!("{}/api/v0/add", self.rpc_base_url))
+            .query(&[("pin", "true"), ("cid-version", "1")])
+            .multipart(form)
+            .send()
+            .await
+            .context(current_fn!())?;
+
+        let res = res
+            .json::<UtilKuboAddResponse>()
+            .await
+            .context(current_fn!())?;
+
+        Ok(res.hash)
+    }
+}
+
+/**
+ * S3-compatible object stores and local directories, the locator is a random object name.
+ */
+pub struct ObjectBlobStore {
+    store: Arc<dyn ObjectStore>,
+}
+
+#[async_trait]
+impl BlobStore for ObjectBlobStore {
+    async fn delete(&self, locator: &str) -> Result<(), ProxyError> {
+        let path = Path::parse(locator)
+            .map_err(|_| anyhow!("Invalid blob locator"))
+            .context(current_fn!())?;
+
+        self.store.delete(&path).await.context(current_fn!())?;
+
+        Ok(())
+    }
+
+    async fn get(&self, locator: &str) -> Result<BlobStream, ProxyError> {
+        let path = Path::parse(locator)
+            .map_err(|_| anyhow!("Invalid blob locator"))
+            .context(current_fn!())?;
+
+        let res = self.store.get(&path).await.map_err(|e| match e {
+            object_store::Error::NotFound { .. } => ProxyError::Anyhow {
+                source: anyhow!("Content not found").context(current_fn!()),
+                code: StatusCode::NOT_FOUND,
+            },
+            e => ProxyError::from(anyhow!(e).context(current_fn!())),
+        })?;
+
+        Ok(Box::pin(res.into_stream().map_err(|e| {
+            ProxyError::from(anyhow!(e).context(current_fn!()))
+        })))
+    }
+
+    /**
+     * Written as a multipart upload so the body is never fully buffered.
+     */
+    async fn put(&self, mut data: BlobStream) -> Result<String, ProxyError> {
+        let locator = uuid::Uuid::new_v4().simple().to_string();
+        let path = Path::from(locator.as_str());
+
+        let mut upload = WriteMultipart::new(
+            self.store
+                .put_multipart(&path)
+                .await
+                .context(current_fn!())?,
+        );
+
+        while let Some(bytes) = data.next().await {
+            let bytes = match bytes {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    let _ = upload.abort().await;
+                    return Err(e);
+                }
+            };
+
+            upload.wait_for_capacity(8).await.context(current_fn!())?;
+            upload.write(&bytes);
+        }
+
+        upload.finish().await.context(current_fn!())?;
+
+        Ok(locator)
+    }
+}
@@ -6,14 +6,47 @@ pub const GAS_BUDGET: u64 = 10_000_000;
 pub const _HASH_SALT: &str = "169224A2BE2B267684F93A9CE38080D359BD774741FD3AE738D09B657A1A8104";
 pub const IPFS_BASE_URL: &str = "http://localhost:9094";
 pub const IPFS_GATEWAY_BASE_URL: &str = "http://127.0.0.1:8080";
+pub const IPFS_KUBO_RPC_BASE_URL: &str = "http://127.0.0.1:5001";
 /// Duration: 3 minutes
 pub const NONCE_EXP_DUR: u64 = 3 * 60;
-/// Duration: 5 minutes
-pub const ADMINISTRATIVE_KEYS_READ_DUR: u64 = 5 * 60;
-/// Duration: 15 minutes
-pub const MEDICAL_KEYS_READ_DUR: u64 = 15 * 60;
-/// Duration: 2 hours
-pub const MEDICAL_KEYS_UPDATE_DUR: u64 = 2 * 60 * 60;
+/// Duration: 1 minute, bounds of the access durations a patient chooses
+pub const KEYS_DUR_MIN: u64 = 60;
+/// Duration: 1 hour
+pub const ADMINISTRATIVE_KEYS_READ_DUR_MAX: u64 = 60 * 60;
+/// Duration: 1 day
+pub const MEDICAL_KEYS_READ_DUR_MAX: u64 = 24 * 60 * 60;
+/// Duration: 1 day
+pub const MEDICAL_KEYS_UPDATE_DUR_MAX: u64 = 24 * 60 * 60;
+/// Signed hospital personnel code format, shared with the hospital and patient apps
+pub const HOSPITAL_PERSONNEL_QR_PREFIX: &str = "decmed-hp";
+pub const HOSPITAL_PERSONNEL_QR_VERSION: u8 = 1;
+/// Guardian rights checked on chain, see `patient::borrow_ward_id`
+pub const GUARDIAN_RIGHT_GRANT: u8 = 0;
+pub const GUARDIAN_RIGHT_READ: u8 = 1;
+pub const GUARDIAN_RIGHT_REVOKE: u8 = 2;
+/// Duration: 1 day, longest an access request waits for the patient
+pub const ACCESS_REQUEST_DUR_MAX: u64 = 24 * 60 * 60;
+/// Characters
+pub const ACCESS_REQUEST_REASON_LEN_MAX: usize = 500;
+/// Pending access requests of a personnel account to the same patient
+pub const ACCESS_REQUESTS_PER_PAIR_MAX: usize = 3;
+/// Pending access requests of a personnel account across patients, one personnel can't fill
+/// the patients' request lists
+pub const ACCESS_REQUESTS_PER_PERSONNEL_MAX: usize = 20;
+/// Duration: 1 hour, break-glass read access, same as `patient::EMERGENCY_READ_DUR` on chain
+pub const EMERGENCY_KEYS_READ_DUR: u64 = 60 * 60;
+/// Bytes, same bounds as checked on chain
+pub const EMERGENCY_JUSTIFICATION_LEN_MIN: usize = 20;
+pub const EMERGENCY_JUSTIFICATION_LEN_MAX: usize = 1000;
+/// Medical metadata read per `get_medical_metadata` call, same as
+/// `proxy::MEDICAL_METADATA_PAGE_SIZE_MAX` on chain
+pub const MEDICAL_METADATA_PAGE_SIZE: u64 = 50;
+/// Duration: 1 hour
+pub const PIN_GC_INTERVAL: u64 = 60 * 60;
+/// Duration: 10 minutes, how long a submitted tx is given to land before its content is orphaned
+pub const PIN_GC_GRACE_DUR: u64 = 10 * 60;
+/// Duration: 30 days, how long superseded content is kept after it is first found unreferenced
+pub const PIN_SUPERSEDED_RETENTION_DUR: u64 = 30 * 24 * 60 * 60;
 
 pub const DECMED_MODULE_PROXY: &str = "proxy";
 pub const DECMED_MODULE_SHARED: &str = "shared";
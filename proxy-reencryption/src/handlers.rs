@@ -1,41 +1,378 @@
+use std::collections::HashMap;
 use std::str::FromStr;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use anyhow::{anyhow, Context};
+use axum::body::Body;
 use axum::extract::{Query, State};
 use axum::http::StatusCode;
 use axum::response::Response;
 use axum::{Extension, Json};
+use futures_util::TryStreamExt;
 use iota_types::base_types::IotaAddress;
-use iota_types::crypto::{
-    EncodeDecodeBase64, IotaKeyPair, IotaSignature, Signature, SignatureScheme,
-};
+use iota_types::crypto::{EncodeDecodeBase64, IotaKeyPair, Signature};
 use jwt_simple::claims::Claims;
 use jwt_simple::prelude::{Duration, ECDSAP256KeyPairLike};
 use redis::{Commands, SetExpiry, SetOptions};
 use serde_json::json;
+use sha2::{Digest, Sha256};
 use shared_crypto::intent::{Intent, IntentMessage};
-use umbral_pre::{reencrypt, Capsule, KeyFrag, PublicKey};
+use umbral_pre::{encrypt, reencrypt, Capsule, KeyFrag, PublicKey};
+use zeroize::Zeroizing;
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
 
+use crate::blob_store::BlobStream;
 use crate::constants::{
-    ADMINISTRATIVE_KEYS_READ_DUR, MEDICAL_KEYS_READ_DUR, MEDICAL_KEYS_UPDATE_DUR, NONCE_EXP_DUR,
+    ACCESS_REQUESTS_PER_PAIR_MAX, ACCESS_REQUESTS_PER_PERSONNEL_MAX, ACCESS_REQUEST_DUR_MAX,
+    ACCESS_REQUEST_REASON_LEN_MAX, ADMINISTRATIVE_KEYS_READ_DUR_MAX,
+    EMERGENCY_JUSTIFICATION_LEN_MAX, EMERGENCY_JUSTIFICATION_LEN_MIN, EMERGENCY_KEYS_READ_DUR,
+    GUARDIAN_RIGHT_GRANT, GUARDIAN_RIGHT_READ, GUARDIAN_RIGHT_REVOKE, KEYS_DUR_MIN,
+    MEDICAL_KEYS_READ_DUR_MAX, MEDICAL_KEYS_UPDATE_DUR_MAX, NONCE_EXP_DUR,
 };
 use crate::current_fn;
+use crate::pin_gc::PinGc;
 use crate::proxy_error::{ProxyError, ResultExt};
 use crate::types::{
-    AccessKeys, AppState, AuthRole, ClientMedicalMetadata, CurrentUser,
-    GenerateSignatureHandlerPayload, GetNonceHandlerPayload, HandlerCreateMedicalRecordPayload,
-    HandlerGetAdministrativeDataQueryParams, HandlerGetMedicalRecordQueryParams,
-    HandlerGetMedicalRecordUpdateQueryParams, HandlerUpdateMedicalRecordPayload, JwtClaims,
-    MedicalMetadata, MoveHospitalPersonnelRole, PatientPrivateAdministrativeMetadata,
+    AccessKeys, AccessMetadata, AccessMetadataEncrypted, AccessRequest, AccessRequestStatus,
+    AppState, AuthRole, ClientMedicalMetadata, CurrentUser, EmergencyAuditEntry, EmergencyKeys,
+    EmergencyRecipient, GenerateSignatureHandlerPayload, GetNonceHandlerPayload, GuardianKeys,
+    HandlerCreateAccessRequestPayload, HandlerCreateEmergencyAccessPayload,
+    HandlerCreateMedicalRecordPayload, HandlerGetAccessRequestStatusQueryParams,
+    HandlerGetAccessRequestsPayload, HandlerGetAdministrativeDataQueryParams,
+    HandlerGetEmergencyRecipientsPayload, HandlerGetGuardianMedicalRecordCFragPayload,
+    HandlerGetMedicalRecordContentQueryParams, HandlerGetMedicalRecordQueryParams,
+    HandlerGetMedicalRecordUpdateQueryParams, HandlerGetMedicalRecordVersionsQueryParams,
+    HandlerGetPatientMedicalRecordContentPayload, HandlerRespondAccessRequestPayload,
+    HandlerRevokeEmergencyKeysPayload, HandlerRevokeGuardianKeysPayload, HandlerRevokeKeysPayload,
+    HandlerStoreEmergencyKeysPayload, HandlerStoreEmergencyRecipientPayload,
+    HandlerStoreGuardianKeysPayload, HandlerUpdateMedicalRecordPayload, JwtClaims, MedicalMetadata,
+    MoveHospitalPersonnelRole, PatientPrivateAdministrativeMetadata, PinStatus,
     ReencryptionPurposeType,
 };
-use crate::types::{GenerateJwtHandlerResponse, HandlerStoreKeysPayload};
+use crate::types::{GenerateJwtHandlerResponse, HandlerStoreKeysPayload, UploadedContent};
 use crate::utils::Utils;
 
 pub struct Handlers {}
 
 impl Handlers {
+    pub async fn create_access_request(
+        State(state): State<Arc<AppState>>,
+        Json(payload): Json<HandlerCreateAccessRequestPayload>,
+    ) -> Result<Response, ProxyError> {
+        let (hospital_personnel_iota_address, code_payload) =
+            Utils::decode_hospital_personnel_qr(&payload.code)?;
+        let patient_iota_address = code_payload
+            .patient_iota_address
+            .as_deref()
+            .and_then(|address| IotaAddress::from_str(address).ok())
+            .ok_or(anyhow!("Invalid patient IOTA address"))
+            .code(StatusCode::BAD_REQUEST)?;
+        let reason = code_payload.reason.as_deref().unwrap_or_default().trim();
+        let proxy_iota_address =
+            IotaAddress::from_str(&state.proxy_iota_address).context(current_fn!())?;
+
+        if reason.is_empty() || reason.chars().count() > ACCESS_REQUEST_REASON_LEN_MAX {
+            return Err(ProxyError::Anyhow {
+                source: anyhow!("Invalid access request reason"),
+                code: StatusCode::BAD_REQUEST,
+            });
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .context(current_fn!())?
+            .as_secs();
+        if code_payload.exp <= now
+            || code_payload.exp < code_payload.iat
+            || code_payload.exp - code_payload.iat > ACCESS_REQUEST_DUR_MAX
+        {
+            return Err(ProxyError::Anyhow {
+                source: anyhow!("Invalid access request validity"),
+                code: StatusCode::BAD_REQUEST,
+            });
+        }
+
+        match state
+            .move_call
+            .get_hospital_personnel_role(&hospital_personnel_iota_address, proxy_iota_address)
+            .await
+            .context(current_fn!())?
+        {
+            MoveHospitalPersonnelRole::AdministrativePersonnel
+            | MoveHospitalPersonnelRole::MedicalPersonnel => {}
+            _ => {
+                return Err(ProxyError::Anyhow {
+                    source: anyhow!("Invalid personnel account"),
+                    code: StatusCode::BAD_REQUEST,
+                })
+            }
+        }
+
+        let _ = state
+            .move_call
+            .is_patient_registered(&patient_iota_address, proxy_iota_address)
+            .await
+            .context(current_fn!())?;
+
+        let id = hex::encode(Sha256::digest(payload.code.trim().as_bytes()));
+        let patient_access_requests_key =
+            format!("access-requests:{}", patient_iota_address.to_string());
+        let personnel_access_requests_key = format!(
+            "personnel-access-requests:{}",
+            hospital_personnel_iota_address.to_string()
+        );
+
+        let mut conn = state.redis_pool.get().context(current_fn!())?;
+
+        if conn
+            .exists(format!("access-request:{}", id))
+            .context(current_fn!())?
+        {
+            return Err(ProxyError::Anyhow {
+                source: anyhow!("Access request already submitted"),
+                code: StatusCode::CONFLICT,
+            });
+        }
+
+        // Limited per personnel, a single personnel account can't crowd out the other requesters
+        let pending_access_requests =
+            Self::pending_access_requests(&mut conn, &personnel_access_requests_key)?;
+
+        if pending_access_requests.len() >= ACCESS_REQUESTS_PER_PERSONNEL_MAX {
+            return Err(ProxyError::Anyhow {
+                source: anyhow!("Too many pending access requests"),
+                code: StatusCode::TOO_MANY_REQUESTS,
+            });
+        }
+
+        if pending_access_requests
+            .iter()
+            .filter(|access_request| {
+                access_request.patient_iota_address == patient_iota_address.to_string()
+            })
+            .count()
+            >= ACCESS_REQUESTS_PER_PAIR_MAX
+        {
+            return Err(ProxyError::Anyhow {
+                source: anyhow!("Too many pending access requests for this patient"),
+                code: StatusCode::TOO_MANY_REQUESTS,
+            });
+        }
+
+        let access_request = AccessRequest {
+            code: payload.code.trim().to_string(),
+            created_at: now,
+            exp: code_payload.exp,
+            hospital_personnel_iota_address: hospital_personnel_iota_address.to_string(),
+            patient_iota_address: patient_iota_address.to_string(),
+            status: AccessRequestStatus::Pending,
+        };
+
+        let _: () = conn
+            .set_options(
+                format!("access-request:{}", id),
+                serde_json::to_string(&access_request).context(current_fn!())?,
+                SetOptions::default().with_expiration(SetExpiry::EX(code_payload.exp - now)),
+            )
+            .context(current_fn!())?;
+        let _: () = conn
+            .sadd(&patient_access_requests_key, &id)
+            .context(current_fn!())?;
+        let _: () = conn
+            .expire(&patient_access_requests_key, ACCESS_REQUEST_DUR_MAX as i64)
+            .context(current_fn!())?;
+        let _: () = conn
+            .sadd(&personnel_access_requests_key, &id)
+            .context(current_fn!())?;
+        let _: () = conn
+            .expire(
+                &personnel_access_requests_key,
+                ACCESS_REQUEST_DUR_MAX as i64,
+            )
+            .context(current_fn!())?;
+
+        let res_data = json!({
+            "exp": code_payload.exp,
+            "id": id,
+        });
+
+        Ok(Utils::build_success_response(res_data, StatusCode::CREATED))
+    }
+
+    pub async fn create_emergency_access(
+        State(state): State<Arc<AppState>>,
+        Json(payload): Json<HandlerCreateEmergencyAccessPayload>,
+    ) -> Result<Response, ProxyError> {
+        let patient_iota_address = IotaAddress::from_str(&payload.patient_iota_address)
+            .map_err(|_| anyhow!("Invalid patient IOTA address"))
+            .code(StatusCode::BAD_REQUEST)?;
+        let hospital_personnel_iota_address =
+            IotaAddress::from_str(&payload.hospital_personnel_iota_address)
+                .map_err(|_| anyhow!("Invalid hospital personnel IOTA address"))
+                .code(StatusCode::BAD_REQUEST)?;
+        let justification = payload.justification.trim().to_string();
+        let (proxy_iota_address, proxy_iota_key_pair) = {
+            let proxy_iota_address =
+                IotaAddress::from_str(&state.proxy_iota_address).context(current_fn!())?;
+            let proxy_iota_key_pair = IotaKeyPair::decode(&state.proxy_iota_key_pair)
+                .map_err(|e| anyhow!(e.to_string()))
+                .context(current_fn!())?;
+
+            (proxy_iota_address, proxy_iota_key_pair)
+        };
+
+        if !(EMERGENCY_JUSTIFICATION_LEN_MIN..=EMERGENCY_JUSTIFICATION_LEN_MAX)
+            .contains(&justification.len())
+        {
+            return Err(ProxyError::Anyhow {
+                source: anyhow!("Invalid emergency access justification"),
+                code: StatusCode::BAD_REQUEST,
+            });
+        }
+
+        let mut conn = state.redis_pool.get().context(current_fn!())?;
+
+        Utils::verify_nonce_signature(
+            &mut conn,
+            hospital_personnel_iota_address,
+            &payload.signature,
+        )?;
+
+        // Checked on chain as well, done here to fail before handing out the keys
+        match state
+            .move_call
+            .get_hospital_personnel_role(&hospital_personnel_iota_address, proxy_iota_address)
+            .await
+            .context(current_fn!())?
+        {
+            MoveHospitalPersonnelRole::MedicalPersonnel => {}
+            _ => {
+                return Err(ProxyError::Anyhow {
+                    source: anyhow!("Illegal action. Invalid role"),
+                    code: StatusCode::FORBIDDEN,
+                })
+            }
+        }
+
+        // Built by the patient app for this personnel only, the proxy can't decrypt any of it
+        let emergency_keys: Option<String> = conn
+            .hget(
+                format!("emergency-keys:{}", patient_iota_address.to_string()),
+                hospital_personnel_iota_address.to_string(),
+            )
+            .context(current_fn!())?;
+        let emergency_keys: EmergencyKeys = Utils::serde_deserialize_from_base64(
+            emergency_keys
+                .ok_or(anyhow!(
+                    "Emergency access is not enabled by the patient for this hospital personnel"
+                ))
+                .code(StatusCode::NOT_FOUND)?,
+        )
+        .context(current_fn!())?;
+        let hospital_personnel_pre_public_key: PublicKey = Utils::serde_deserialize_from_base64(
+            emergency_keys.hospital_personnel_pre_public_key.clone(),
+        )
+        .context(current_fn!())?;
+
+        let es256_keypair = Utils::construct_es256_key_pair_from_pem(&state.jwt_ecdsa_key_pair)
+            .context(current_fn!())?;
+        let read_claims = Claims::with_custom_claims(
+            JwtClaims {
+                role: AuthRole::MedicalPersonnel,
+                purpose: ReencryptionPurposeType::Read,
+            },
+            Duration::from_secs(EMERGENCY_KEYS_READ_DUR),
+        )
+        .with_subject(hospital_personnel_iota_address);
+        let access_token_read = es256_keypair.sign(read_claims).context(current_fn!())?;
+
+        // Same metadata the patient app writes, so the access is listed like any other
+        let metadata = {
+            let access_metadata = AccessMetadata {
+                access_token: access_token_read.clone(),
+                enc_patient_name: Some(emergency_keys.enc_patient_name),
+                patient_iota_address: patient_iota_address.to_string(),
+                patient_name: String::new(),
+                patient_pre_public_key: None,
+            };
+            let (capsule, enc_data) = encrypt(
+                &hospital_personnel_pre_public_key,
+                &serde_json::to_vec(&access_metadata).context(current_fn!())?,
+            )
+            .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?;
+
+            Utils::serde_serialize_to_base64(&AccessMetadataEncrypted {
+                capsule: Utils::serde_serialize_to_base64(&capsule).context(current_fn!())?,
+                enc_data: STANDARD.encode(enc_data),
+            })
+            .context(current_fn!())?
+        };
+
+        let now = SystemTime::now();
+        state
+            .move_call
+            .create_emergency_access(
+                Utils::sys_time_to_iso(now),
+                &hospital_personnel_iota_address,
+                justification.clone(),
+                metadata,
+                &patient_iota_address,
+                proxy_iota_address,
+                proxy_iota_key_pair,
+            )
+            .await
+            .context(current_fn!())?;
+
+        let access_keys = AccessKeys {
+            enc_data_pre_secret_key_seed: emergency_keys.enc_data_pre_secret_key_seed,
+            k_frag: emergency_keys.k_frag,
+            data_pre_public_key: emergency_keys.data_pre_public_key,
+            data_pre_secret_key_seed_capsule: emergency_keys.data_pre_secret_key_seed_capsule,
+            medical_record_scope: None,
+            patient_pre_public_key: emergency_keys.patient_pre_public_key,
+            signer_pre_public_key: emergency_keys.signer_pre_public_key,
+        };
+
+        let _: () = conn
+            .set_options(
+                format!(
+                    "keys:{}@{}",
+                    hospital_personnel_iota_address.to_string(),
+                    patient_iota_address.to_string()
+                ),
+                Utils::serde_serialize_to_base64(&access_keys).context(current_fn!())?,
+                SetOptions::default().with_expiration(SetExpiry::EX(EMERGENCY_KEYS_READ_DUR)),
+            )
+            .context(current_fn!())?;
+
+        let created_at = now
+            .duration_since(UNIX_EPOCH)
+            .context(current_fn!())?
+            .as_secs();
+        let audit_entry = EmergencyAuditEntry {
+            created_at,
+            exp: created_at + EMERGENCY_KEYS_READ_DUR,
+            hospital_personnel_iota_address: hospital_personnel_iota_address.to_string(),
+            justification,
+            patient_iota_address: patient_iota_address.to_string(),
+        };
+        let _: () = conn
+            .rpush(
+                format!("emergency-audit:{}", patient_iota_address.to_string()),
+                serde_json::to_string(&audit_entry).context(current_fn!())?,
+            )
+            .context(current_fn!())?;
+
+        let res_data = json!({
+            "access_token_read": access_token_read,
+            "exp": audit_entry.exp,
+        });
+
+        Ok(Utils::build_success_response(res_data, StatusCode::OK))
+    }
+
     pub async fn create_medical_record(
         State(state): State<Arc<AppState>>,
         Extension(current_user): Extension<CurrentUser>,
@@ -86,19 +423,45 @@ impl Handlers {
             )
         };
 
-        let cid = Utils::add_and_pin_to_ipfs(medical_metadata.enc_data)
-            .await
-            .context(current_fn!())?;
+        let mut conn = state.redis_pool.get().context(current_fn!())?;
+
+        let uploaded_content: String = conn
+            .get(format!("content:{}", medical_metadata.locator))
+            .map_err(|_| anyhow!("Content not found"))
+            .code(StatusCode::BAD_REQUEST)?;
+        let uploaded_content: UploadedContent =
+            Utils::serde_deserialize_from_base64(uploaded_content).context(current_fn!())?;
+
+        if uploaded_content.iota_address != current_user.iota_address {
+            return Err(ProxyError::Anyhow {
+                source: anyhow!("Illegal action. Content uploaded by another personnel"),
+                code: StatusCode::UNAUTHORIZED,
+            });
+        }
+
         let created_at = Utils::sys_time_to_iso(std::time::SystemTime::now());
 
         let medical_metadata = MedicalMetadata {
+            backend: uploaded_content.backend,
             capsule: medical_metadata.capsule,
-            cid,
             created_at,
+            created_by: Some(current_user.iota_address.clone()),
             enc_key_and_nonce: medical_metadata.enc_key_and_nonce,
+            locator: medical_metadata.locator,
+            sha256: Some(uploaded_content.sha256),
+            versions: Vec::new(),
         };
 
-        let _ = state
+        PinGc::set_status(
+            &mut conn,
+            medical_metadata.backend,
+            &medical_metadata.locator,
+            &payload.patient_iota_address,
+            PinStatus::Pending,
+        )
+        .context(current_fn!())?;
+
+        let res = state
             .move_call
             .create_medical_record(
                 &hospital_personnel_iota_address,
@@ -107,8 +470,22 @@ impl Handlers {
                 proxy_iota_address,
                 proxy_iota_key_pair,
             )
-            .await
+            .await;
+
+        // On failure the content stays pending until reconciliation confirms the tx never landed
+        let _: () = conn
+            .del(format!("content:{}", medical_metadata.locator))
             .context(current_fn!())?;
+        let _ = res.context(current_fn!())?;
+
+        PinGc::set_status(
+            &mut conn,
+            medical_metadata.backend,
+            &medical_metadata.locator,
+            &payload.patient_iota_address,
+            PinStatus::Committed,
+        )
+        .context(current_fn!())?;
 
         Ok(Utils::build_success_response((), StatusCode::OK))
     }
@@ -123,17 +500,17 @@ impl Handlers {
 
         let seed_words: Vec<&str> = mnemonic.words().collect();
         let seed_words = seed_words.join(" ");
-        let seed = mnemonic.to_seed_normalized("proxy");
+        let seed = Zeroizing::new(mnemonic.to_seed_normalized("proxy"));
 
         let (proxy_iota_address, proxy_iota_keypair) =
-            Utils::generate_iota_keys_ed(&seed).context(current_fn!())?;
+            Utils::generate_iota_keys_ed(seed.as_slice()).context(current_fn!())?;
 
         let _ = state
             .move_call
             .create_capability(
                 &proxy_iota_address,
                 IotaAddress::from_str(&state.global_admin_iota_address).context(current_fn!())?,
-                IotaKeyPair::decode(&state.global_admin_iota_key_pair.clone())
+                IotaKeyPair::decode(&state.global_admin_iota_key_pair)
                     .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?,
             )
             .await
@@ -179,6 +556,79 @@ impl Handlers {
         ))
     }
 
+    pub async fn get_access_request_status(
+        State(state): State<Arc<AppState>>,
+        Query(params): Query<HandlerGetAccessRequestStatusQueryParams>,
+    ) -> Result<Response, ProxyError> {
+        let mut conn = state.redis_pool.get().context(current_fn!())?;
+
+        let access_request: Option<String> = conn
+            .get(format!("access-request:{}", params.id))
+            .context(current_fn!())?;
+        let access_request: AccessRequest = serde_json::from_str(
+            &access_request
+                .ok_or(anyhow!("Access request not found or expired"))
+                .code(StatusCode::NOT_FOUND)?,
+        )
+        .context(current_fn!())?;
+
+        let res_data = json!({
+            "exp": access_request.exp,
+            "status": access_request.status,
+        });
+
+        Ok(Utils::build_success_response(res_data, StatusCode::OK))
+    }
+
+    pub async fn get_access_requests(
+        State(state): State<Arc<AppState>>,
+        Json(payload): Json<HandlerGetAccessRequestsPayload>,
+    ) -> Result<Response, ProxyError> {
+        let patient_iota_address = IotaAddress::from_str(&payload.patient_iota_address)
+            .map_err(|_| anyhow!("Invalid patient IOTA address"))
+            .code(StatusCode::BAD_REQUEST)?;
+        let patient_access_requests_key =
+            format!("access-requests:{}", patient_iota_address.to_string());
+
+        let mut conn = state.redis_pool.get().context(current_fn!())?;
+
+        Utils::verify_nonce_signature(&mut conn, patient_iota_address, &payload.signature)?;
+
+        let ids: Vec<String> = conn
+            .smembers(&patient_access_requests_key)
+            .context(current_fn!())?;
+        let mut access_requests = vec![];
+        for id in ids {
+            let access_request: Option<String> = conn
+                .get(format!("access-request:{}", id))
+                .context(current_fn!())?;
+
+            match access_request {
+                Some(access_request) => {
+                    let access_request: AccessRequest =
+                        serde_json::from_str(&access_request).context(current_fn!())?;
+                    if access_request.status == AccessRequestStatus::Pending {
+                        access_requests.push(json!({
+                            "code": access_request.code,
+                            "created_at": access_request.created_at,
+                            "id": id,
+                        }));
+                    }
+                }
+                None => {
+                    let _: () = conn
+                        .srem(&patient_access_requests_key, &id)
+                        .context(current_fn!())?;
+                }
+            }
+        }
+
+        Ok(Utils::build_success_response(
+            access_requests,
+            StatusCode::OK,
+        ))
+    }
+
     pub async fn get_administrative_data(
         State(state): State<Arc<AppState>>,
         Extension(current_user): Extension<CurrentUser>,
@@ -308,54 +758,196 @@ impl Handlers {
         Ok(Utils::build_success_response(res_data, StatusCode::OK))
     }
 
-    pub async fn get_medical_record(
+    /**
+     * return: recipients the patient has no keys for yet, or built for a key they replaced since
+     */
+    pub async fn get_emergency_recipients(
         State(state): State<Arc<AppState>>,
-        Extension(current_user): Extension<CurrentUser>,
-        Query(query): Query<HandlerGetMedicalRecordQueryParams>,
+        Json(payload): Json<HandlerGetEmergencyRecipientsPayload>,
     ) -> Result<Response, ProxyError> {
-        if current_user.role != AuthRole::MedicalPersonnel {
-            return Err(ProxyError::Anyhow {
-                source: anyhow!("Illegal action. Invalid role"),
-                code: StatusCode::UNAUTHORIZED,
-            });
-        }
+        let patient_iota_address = IotaAddress::from_str(&payload.patient_iota_address)
+            .map_err(|_| anyhow!("Invalid patient IOTA address"))
+            .code(StatusCode::BAD_REQUEST)?;
 
-        if current_user.purpose != ReencryptionPurposeType::Read {
-            return Err(ProxyError::Anyhow {
-                source: anyhow!("Illegal action. Invalid purpose"),
-                code: StatusCode::BAD_REQUEST,
-            });
-        }
+        let mut conn = state.redis_pool.get().context(current_fn!())?;
 
-        let (hospital_personnel_iota_address, patient_iota_address, proxy_iota_address) = {
-            let hospital_personnel_iota_address = IotaAddress::from_str(&current_user.iota_address)
-                .map_err(|_| anyhow!("Invalid hospital personnel IOTA address"))
-                .code(StatusCode::BAD_REQUEST)?;
-            let patient_iota_address = IotaAddress::from_str(&query.patient_iota_address)
-                .map_err(|_| anyhow!("Invalid patient IOTA address"))
-                .code(StatusCode::UNAUTHORIZED)?;
-            let proxy_iota_address =
-                IotaAddress::from_str(&state.proxy_iota_address).context(current_fn!())?;
+        Utils::verify_nonce_signature(&mut conn, patient_iota_address, &payload.signature)?;
 
-            (
-                hospital_personnel_iota_address,
-                patient_iota_address,
-                proxy_iota_address,
-            )
-        };
+        let recipients: HashMap<String, String> = conn
+            .hgetall("emergency-recipients")
+            .context(current_fn!())?;
+        let emergency_keys: HashMap<String, String> = conn
+            .hgetall(format!(
+                "emergency-keys:{}",
+                patient_iota_address.to_string()
+            ))
+            .context(current_fn!())?;
 
-        let (
-            enc_administrative_data,
-            enc_medical_data,
-            access_keys,
-            c_frag_administrative,
-            c_frag_medical,
-            current_index,
-            prev_index,
-            next_index,
-            enc_medical_data_key_nonce,
-            medical_data_capsule,
+        let mut pending_recipients = vec![];
+        for (hospital_personnel_iota_address, pre_public_key) in recipients {
+            let is_up_to_date = match emergency_keys.get(&hospital_personnel_iota_address) {
+                Some(keys) => {
+                    let keys: EmergencyKeys = Utils::serde_deserialize_from_base64(keys.clone())
+                        .context(current_fn!())?;
+                    keys.hospital_personnel_pre_public_key == pre_public_key
+                }
+                None => false,
+            };
+
+            if !is_up_to_date {
+                pending_recipients.push(EmergencyRecipient {
+                    hospital_personnel_iota_address,
+                    pre_public_key,
+                });
+            }
+        }
+
+        Ok(Utils::build_success_response(
+            pending_recipients,
+            StatusCode::OK,
+        ))
+    }
+
+    /**
+     * Re-encrypts the key of a ward's record for a guardian with the read right, the guardian
+     * decrypts it with its own PRE key
+     */
+    pub async fn get_guardian_medical_record_c_frag(
+        State(state): State<Arc<AppState>>,
+        Json(payload): Json<HandlerGetGuardianMedicalRecordCFragPayload>,
+    ) -> Result<Response, ProxyError> {
+        let patient_iota_address = IotaAddress::from_str(&payload.patient_iota_address)
+            .map_err(|_| anyhow!("Invalid patient IOTA address"))
+            .code(StatusCode::BAD_REQUEST)?;
+        let guardian_iota_address = IotaAddress::from_str(&payload.guardian_iota_address)
+            .map_err(|_| anyhow!("Invalid guardian IOTA address"))
+            .code(StatusCode::BAD_REQUEST)?;
+        let proxy_iota_address =
+            IotaAddress::from_str(&state.proxy_iota_address).context(current_fn!())?;
+
+        let guardian_keys = {
+            let mut conn = state.redis_pool.get().context(current_fn!())?;
+
+            Utils::verify_nonce_signature(&mut conn, guardian_iota_address, &payload.signature)?;
+
+            Self::guardian_keys(&mut conn, &guardian_iota_address, &patient_iota_address)?
+        };
+
+        let _ = state
+            .move_call
+            .is_guardian_allowed(
+                &guardian_iota_address,
+                &patient_iota_address,
+                GUARDIAN_RIGHT_READ,
+                proxy_iota_address,
+            )
+            .await
+            .context(current_fn!())?;
+
+        let read_k_frag = guardian_keys
+            .read_k_frag
+            .ok_or(anyhow!("Guardian keys don't allow reading"))
+            .code(StatusCode::FORBIDDEN)?;
+
+        let medical_metadata = state
+            .move_call
+            .get_medical_metadata(payload.index, &patient_iota_address, 1, proxy_iota_address)
+            .await
+            .context(current_fn!())?
+            .into_iter()
+            .next()
+            .ok_or(anyhow!("Medical record not found"))
+            .code(StatusCode::NOT_FOUND)?;
+        let medical_metadata: MedicalMetadata =
+            Utils::serde_deserialize_from_base64(medical_metadata.metadata)
+                .context(current_fn!())?;
+        let (medical_metadata, _) =
+            Utils::select_medical_metadata_version(medical_metadata, payload.version)?;
+
+        let k_frag: KeyFrag =
+            Utils::serde_deserialize_from_base64(read_k_frag).context(current_fn!())?;
+        let signer_pre_public_key: PublicKey =
+            Utils::serde_deserialize_from_base64(guardian_keys.signer_pre_public_key.clone())
+                .context(current_fn!())?;
+        let patient_pre_public_key: PublicKey =
+            Utils::serde_deserialize_from_base64(guardian_keys.patient_pre_public_key)
+                .context(current_fn!())?;
+        let guardian_pre_public_key: PublicKey =
+            Utils::serde_deserialize_from_base64(guardian_keys.guardian_pre_public_key)
+                .context(current_fn!())?;
+        let medical_metadata_key_nonce_capsule: Capsule =
+            Utils::serde_deserialize_from_base64(medical_metadata.capsule)
+                .context(current_fn!())?;
+
+        let verified_kfrag = k_frag
+            .verify(
+                &signer_pre_public_key,
+                Some(&patient_pre_public_key),
+                Some(&guardian_pre_public_key),
+            )
+            .map_err(|e| anyhow!(e.0.to_string()).context(current_fn!()))?;
+        let c_frag = reencrypt(&medical_metadata_key_nonce_capsule, verified_kfrag).unverify();
+
+        let res_data = json!({
+            "c_frag": Utils::serde_serialize_to_base64(&c_frag).context(current_fn!())?,
+            "signer_pre_public_key": guardian_keys.signer_pre_public_key,
+        });
+
+        Ok(Utils::build_success_response(res_data, StatusCode::OK))
+    }
+
+    pub async fn get_medical_record(
+        State(state): State<Arc<AppState>>,
+        Extension(current_user): Extension<CurrentUser>,
+        Query(query): Query<HandlerGetMedicalRecordQueryParams>,
+    ) -> Result<Response, ProxyError> {
+        if current_user.role != AuthRole::MedicalPersonnel {
+            return Err(ProxyError::Anyhow {
+                source: anyhow!("Illegal action. Invalid role"),
+                code: StatusCode::UNAUTHORIZED,
+            });
+        }
+
+        if current_user.purpose != ReencryptionPurposeType::Read {
+            return Err(ProxyError::Anyhow {
+                source: anyhow!("Illegal action. Invalid purpose"),
+                code: StatusCode::BAD_REQUEST,
+            });
+        }
+
+        let (hospital_personnel_iota_address, patient_iota_address, proxy_iota_address) = {
+            let hospital_personnel_iota_address = IotaAddress::from_str(&current_user.iota_address)
+                .map_err(|_| anyhow!("Invalid hospital personnel IOTA address"))
+                .code(StatusCode::BAD_REQUEST)?;
+            let patient_iota_address = IotaAddress::from_str(&query.patient_iota_address)
+                .map_err(|_| anyhow!("Invalid patient IOTA address"))
+                .code(StatusCode::UNAUTHORIZED)?;
+            let proxy_iota_address =
+                IotaAddress::from_str(&state.proxy_iota_address).context(current_fn!())?;
+
+            (
+                hospital_personnel_iota_address,
+                patient_iota_address,
+                proxy_iota_address,
+            )
+        };
+
+        let (
+            enc_administrative_data,
+            medical_data_backend,
+            medical_data_locator,
+            medical_data_sha256,
+            access_keys,
+            c_frag_administrative,
+            c_frag_medical,
+            current_index,
+            prev_index,
+            next_index,
+            enc_medical_data_key_nonce,
+            medical_data_capsule,
             medical_data_created_at,
+            medical_data_created_by,
+            medical_data_versions,
             enc_administrative_data_key_nonce,
             administrative_data_capsule,
         ) = {
@@ -384,18 +976,19 @@ impl Handlers {
                     .await
                     .context(current_fn!())?;
 
+            // The chain already resolves indexes inside the scope, never re-encrypt outside of it
+            Self::check_medical_record_scope(&access_keys, current_index)?;
+
             let medical_metadata: MedicalMetadata =
                 Utils::serde_deserialize_from_base64(medical_metadata.metadata)
                     .context(current_fn!())?;
+            let (medical_metadata, medical_data_versions) =
+                Utils::select_medical_metadata_version(medical_metadata, query.version)?;
 
             let patient_private_adm_metadata: PatientPrivateAdministrativeMetadata =
                 Utils::serde_deserialize_from_base64(administrative_metadata.private_metadata)
                     .context(current_fn!())?;
 
-            let enc_medical_data = Utils::get_data_ipfs(medical_metadata.cid)
-                .await
-                .context(current_fn!())?;
-
             let k_frag: KeyFrag = Utils::serde_deserialize_from_base64(access_keys.k_frag.clone())
                 .context(current_fn!())?;
             let signer_pre_public_key: PublicKey =
@@ -433,7 +1026,9 @@ impl Handlers {
 
             (
                 patient_private_adm_metadata.enc_data,
-                enc_medical_data,
+                medical_metadata.backend,
+                medical_metadata.locator,
+                medical_metadata.sha256,
                 access_keys,
                 c_frag_administrative,
                 c_frag_medical,
@@ -443,10 +1038,15 @@ impl Handlers {
                 medical_metadata.enc_key_and_nonce,
                 medical_metadata.capsule,
                 medical_metadata.created_at,
+                medical_metadata.created_by,
+                medical_data_versions,
                 patient_private_adm_metadata.enc_key_nonce,
                 patient_private_adm_metadata.capsule,
             )
         };
+        let medical_data_version = query
+            .version
+            .unwrap_or(medical_data_versions.len() as u64 - 1);
 
         let res_data = json!({
             "administrative_data_capsule": administrative_data_capsule,
@@ -458,10 +1058,15 @@ impl Handlers {
             "enc_administrative_data": enc_administrative_data,
             "enc_administrative_data_key_nonce": enc_administrative_data_key_nonce,
             "enc_data_pre_secret_key_seed": access_keys.enc_data_pre_secret_key_seed,
-            "enc_medical_data": enc_medical_data,
             "enc_medical_data_key_nonce": enc_medical_data_key_nonce,
+            "medical_data_backend": medical_data_backend,
             "medical_data_capsule": medical_data_capsule,
             "medical_data_created_at": medical_data_created_at,
+            "medical_data_created_by": medical_data_created_by,
+            "medical_data_locator": medical_data_locator,
+            "medical_data_sha256": medical_data_sha256,
+            "medical_data_version": medical_data_version,
+            "medical_data_versions": medical_data_versions,
             "next_index": next_index,
             "patient_pre_public_key": access_keys.patient_pre_public_key,
             "prev_index": prev_index,
@@ -471,6 +1076,151 @@ impl Handlers {
         Ok(Utils::build_success_response(res_data, StatusCode::OK))
     }
 
+    pub async fn get_medical_record_content(
+        State(state): State<Arc<AppState>>,
+        Extension(current_user): Extension<CurrentUser>,
+        Query(query): Query<HandlerGetMedicalRecordContentQueryParams>,
+    ) -> Result<Response, ProxyError> {
+        if current_user.role != AuthRole::MedicalPersonnel {
+            return Err(ProxyError::Anyhow {
+                source: anyhow!("Illegal action. Invalid role"),
+                code: StatusCode::UNAUTHORIZED,
+            });
+        }
+
+        let (hospital_personnel_iota_address, patient_iota_address, proxy_iota_address) = {
+            let hospital_personnel_iota_address = IotaAddress::from_str(&current_user.iota_address)
+                .map_err(|_| anyhow!("Invalid hospital personnel IOTA address"))
+                .code(StatusCode::BAD_REQUEST)?;
+            let patient_iota_address = IotaAddress::from_str(&query.patient_iota_address)
+                .map_err(|_| anyhow!("Invalid patient IOTA address"))
+                .code(StatusCode::UNAUTHORIZED)?;
+            let proxy_iota_address =
+                IotaAddress::from_str(&state.proxy_iota_address).context(current_fn!())?;
+
+            (
+                hospital_personnel_iota_address,
+                patient_iota_address,
+                proxy_iota_address,
+            )
+        };
+
+        // Resolve the CID from chain so access is checked the same way as the metadata
+        let medical_metadata = match current_user.purpose {
+            ReencryptionPurposeType::Read => {
+                let access_keys: AccessKeys = {
+                    let mut conn = state.redis_pool.get().context(current_fn!())?;
+                    let access_keys: String = conn
+                        .get(format!(
+                            "keys:{}@{}",
+                            current_user.iota_address, query.patient_iota_address,
+                        ))
+                        .map_err(|_| anyhow!("Keys not found"))
+                        .code(StatusCode::BAD_REQUEST)?;
+
+                    Utils::serde_deserialize_from_base64(access_keys).context(current_fn!())?
+                };
+
+                let (medical_metadata, _, current_index, _, _) = state
+                    .move_call
+                    .get_medical_record(
+                        &hospital_personnel_iota_address,
+                        query.index.unwrap_or(0),
+                        &patient_iota_address,
+                        proxy_iota_address,
+                    )
+                    .await
+                    .context(current_fn!())?;
+
+                // Same scope as `get_medical_record`, the ciphertext of other records isn't served
+                Self::check_medical_record_scope(&access_keys, current_index)?;
+
+                medical_metadata
+            }
+            ReencryptionPurposeType::Update => {
+                let (medical_metadata, _) = state
+                    .move_call
+                    .get_medical_record_update(
+                        &hospital_personnel_iota_address,
+                        query.index.unwrap_or(0),
+                        &patient_iota_address,
+                        proxy_iota_address,
+                    )
+                    .await
+                    .context(current_fn!())?;
+
+                medical_metadata
+            }
+        };
+        let medical_metadata: MedicalMetadata =
+            Utils::serde_deserialize_from_base64(medical_metadata.metadata)
+                .context(current_fn!())?;
+        let (medical_metadata, _) =
+            Utils::select_medical_metadata_version(medical_metadata, query.version)?;
+
+        Utils::build_medical_record_content_response(&state.blob_stores, medical_metadata).await
+    }
+
+    /**
+     * Content of the patient's own record, or of a ward's record for a guardian with the read
+     * right. Needed for backends the patient app can't reach directly, e.g. LocalFs and S3.
+     */
+    pub async fn get_patient_medical_record_content(
+        State(state): State<Arc<AppState>>,
+        Json(payload): Json<HandlerGetPatientMedicalRecordContentPayload>,
+    ) -> Result<Response, ProxyError> {
+        let patient_iota_address = IotaAddress::from_str(&payload.patient_iota_address)
+            .map_err(|_| anyhow!("Invalid patient IOTA address"))
+            .code(StatusCode::BAD_REQUEST)?;
+        let guardian_iota_address = payload
+            .guardian_iota_address
+            .as_deref()
+            .map(IotaAddress::from_str)
+            .transpose()
+            .map_err(|_| anyhow!("Invalid guardian IOTA address"))
+            .code(StatusCode::BAD_REQUEST)?;
+        // A guardian signs instead of the patient
+        let signer_iota_address = guardian_iota_address.unwrap_or(patient_iota_address);
+        let proxy_iota_address =
+            IotaAddress::from_str(&state.proxy_iota_address).context(current_fn!())?;
+
+        {
+            let mut conn = state.redis_pool.get().context(current_fn!())?;
+
+            Utils::verify_nonce_signature(&mut conn, signer_iota_address, &payload.signature)?;
+        }
+
+        if let Some(guardian_iota_address) = guardian_iota_address {
+            let _ = state
+                .move_call
+                .is_guardian_allowed(
+                    &guardian_iota_address,
+                    &patient_iota_address,
+                    GUARDIAN_RIGHT_READ,
+                    proxy_iota_address,
+                )
+                .await
+                .context(current_fn!())?;
+        }
+
+        let medical_metadata = state
+            .move_call
+            .get_medical_metadata(payload.index, &patient_iota_address, 1, proxy_iota_address)
+            .await
+            .context(current_fn!())?
+            .into_iter()
+            .next()
+            .ok_or(anyhow!("Medical record not found"))
+            .code(StatusCode::NOT_FOUND)?;
+        let medical_metadata: MedicalMetadata =
+            Utils::serde_deserialize_from_base64(medical_metadata.metadata)
+                .context(current_fn!())?;
+        let (medical_metadata, _) =
+            Utils::select_medical_metadata_version(medical_metadata, payload.version)?;
+
+        Utils::build_medical_record_content_response(&state.blob_stores, medical_metadata).await
+    }
+
     pub async fn get_medical_record_update(
         State(state): State<Arc<AppState>>,
         Extension(current_user): Extension<CurrentUser>,
@@ -509,7 +1259,9 @@ impl Handlers {
 
         let (
             enc_administrative_data,
-            enc_medical_data,
+            medical_data_backend,
+            medical_data_locator,
+            medical_data_sha256,
             access_keys,
             c_frag_administrative,
             c_frag_medical,
@@ -551,10 +1303,6 @@ impl Handlers {
                 Utils::serde_deserialize_from_base64(administrative_metadata.private_metadata)
                     .context(current_fn!())?;
 
-            let enc_medical_data = Utils::get_data_ipfs(medical_metadata.cid)
-                .await
-                .context(current_fn!())?;
-
             let k_frag: KeyFrag = Utils::serde_deserialize_from_base64(access_keys.k_frag.clone())
                 .context(current_fn!())?;
             let signer_pre_public_key: PublicKey =
@@ -592,7 +1340,9 @@ impl Handlers {
 
             (
                 patient_private_adm_metadata.enc_data,
-                enc_medical_data,
+                medical_metadata.backend,
+                medical_metadata.locator,
+                medical_metadata.sha256,
                 access_keys,
                 c_frag_administrative,
                 c_frag_medical,
@@ -613,10 +1363,12 @@ impl Handlers {
             "enc_administrative_data": enc_administrative_data,
             "enc_administrative_data_key_nonce": enc_administrative_data_key_nonce,
             "enc_data_pre_secret_key_seed": access_keys.enc_data_pre_secret_key_seed,
-            "enc_medical_data": enc_medical_data,
             "enc_medical_data_key_nonce": enc_medical_data_key_nonce,
+            "medical_data_backend": medical_data_backend,
             "medical_data_capsule": medical_data_capsule,
             "medical_data_created_at": medical_data_created_at,
+            "medical_data_locator": medical_data_locator,
+            "medical_data_sha256": medical_data_sha256,
             "patient_pre_public_key": access_keys.patient_pre_public_key,
             "signer_pre_public_key": access_keys.signer_pre_public_key,
         });
@@ -624,6 +1376,65 @@ impl Handlers {
         Ok(Utils::build_success_response(res_data, StatusCode::OK))
     }
 
+    pub async fn get_medical_record_versions(
+        State(state): State<Arc<AppState>>,
+        Extension(current_user): Extension<CurrentUser>,
+        Query(query): Query<HandlerGetMedicalRecordVersionsQueryParams>,
+    ) -> Result<Response, ProxyError> {
+        if current_user.role != AuthRole::MedicalPersonnel {
+            return Err(ProxyError::Anyhow {
+                source: anyhow!("Illegal action. Invalid role"),
+                code: StatusCode::UNAUTHORIZED,
+            });
+        }
+
+        if current_user.purpose != ReencryptionPurposeType::Read {
+            return Err(ProxyError::Anyhow {
+                source: anyhow!("Illegal action. Invalid purpose"),
+                code: StatusCode::BAD_REQUEST,
+            });
+        }
+
+        let (hospital_personnel_iota_address, patient_iota_address, proxy_iota_address) = {
+            let hospital_personnel_iota_address = IotaAddress::from_str(&current_user.iota_address)
+                .map_err(|_| anyhow!("Invalid hospital personnel IOTA address"))
+                .code(StatusCode::BAD_REQUEST)?;
+            let patient_iota_address = IotaAddress::from_str(&query.patient_iota_address)
+                .map_err(|_| anyhow!("Invalid patient IOTA address"))
+                .code(StatusCode::UNAUTHORIZED)?;
+            let proxy_iota_address =
+                IotaAddress::from_str(&state.proxy_iota_address).context(current_fn!())?;
+
+            (
+                hospital_personnel_iota_address,
+                patient_iota_address,
+                proxy_iota_address,
+            )
+        };
+
+        let (medical_metadata, _, current_index, _, _) = state
+            .move_call
+            .get_medical_record(
+                &hospital_personnel_iota_address,
+                query.index.unwrap_or(0),
+                &patient_iota_address,
+                proxy_iota_address,
+            )
+            .await
+            .context(current_fn!())?;
+        let medical_metadata: MedicalMetadata =
+            Utils::serde_deserialize_from_base64(medical_metadata.metadata)
+                .context(current_fn!())?;
+        let (_, versions) = Utils::select_medical_metadata_version(medical_metadata, None)?;
+
+        let res_data = json!({
+            "current_index": current_index,
+            "versions": versions,
+        });
+
+        Ok(Utils::build_success_response(res_data, StatusCode::OK))
+    }
+
     pub async fn get_nonce_handler(
         State(state): State<Arc<AppState>>,
         Json(payload): Json<GetNonceHandlerPayload>,
@@ -634,11 +1445,21 @@ impl Handlers {
         let proxy_iota_address =
             IotaAddress::from_str(&state.proxy_iota_address).context(current_fn!())?;
 
-        let _ = state
+        let is_patient_registered = state
             .move_call
             .is_patient_registered(&patient_iota_address, proxy_iota_address)
             .await
-            .context(current_fn!())?;
+            .context(current_fn!());
+        // Hospital personnel sign nonces too, to request emergency access
+        if is_patient_registered.is_err()
+            && state
+                .move_call
+                .get_hospital_personnel_role(&patient_iota_address, proxy_iota_address)
+                .await
+                .is_err()
+        {
+            is_patient_registered?;
+        }
 
         let nonce = Utils::generate_64_bytes_seed();
         let nonce = hex::encode(&nonce);
@@ -656,45 +1477,547 @@ impl Handlers {
         Ok(Utils::build_success_response(nonce, StatusCode::OK))
     }
 
-    pub async fn store_keys(
+    pub async fn respond_access_request(
         State(state): State<Arc<AppState>>,
-        Json(payload): Json<HandlerStoreKeysPayload>,
+        Json(payload): Json<HandlerRespondAccessRequestPayload>,
     ) -> Result<Response, ProxyError> {
         let patient_iota_address = IotaAddress::from_str(&payload.patient_iota_address)
             .map_err(|_| anyhow!("Invalid patient IOTA address"))
             .code(StatusCode::BAD_REQUEST)?;
-        let hospital_personnel_iota_address =
-            IotaAddress::from_str(&payload.hospital_personnel_iota_address)
-                .map_err(|_| anyhow!("Invalid hospital personnel IOTA address"))
-                .code(StatusCode::BAD_REQUEST)?;
-        let signature = Utils::construct_signature_from_str(&payload.signature)
-            .map_err(|_| anyhow!("Invalid signature"))
-            .code(StatusCode::BAD_REQUEST)?;
-        let proxy_iota_address =
-            IotaAddress::from_str(&state.proxy_iota_address).context(current_fn!())?;
 
-        let mut conn = state.redis_pool.get().context(current_fn!())?;
+        if payload.status == AccessRequestStatus::Pending {
+            return Err(ProxyError::Anyhow {
+                source: anyhow!("Invalid access request status"),
+                code: StatusCode::BAD_REQUEST,
+            });
+        }
+
+        let mut conn = state.redis_pool.get().context(current_fn!())?;
+
+        Utils::verify_nonce_signature(&mut conn, patient_iota_address, &payload.signature)?;
+
+        let access_request: Option<String> = conn
+            .get(format!("access-request:{}", payload.id))
+            .context(current_fn!())?;
+        // Requests addressed to other patients are reported as missing
+        let mut access_request: AccessRequest = access_request
+            .and_then(|access_request| serde_json::from_str(&access_request).ok())
+            .filter(|access_request: &AccessRequest| {
+                access_request.patient_iota_address == patient_iota_address.to_string()
+            })
+            .ok_or(anyhow!("Access request not found or expired"))
+            .code(StatusCode::NOT_FOUND)?;
+
+        if access_request.status != AccessRequestStatus::Pending {
+            return Err(ProxyError::Anyhow {
+                source: anyhow!("Access request already answered"),
+                code: StatusCode::CONFLICT,
+            });
+        }
+
+        // Approved only once the patient app has granted the access on chain
+        if payload.status == AccessRequestStatus::Approved {
+            let hospital_personnel_iota_address =
+                IotaAddress::from_str(&access_request.hospital_personnel_iota_address)
+                    .context(current_fn!())?;
+            let proxy_iota_address =
+                IotaAddress::from_str(&state.proxy_iota_address).context(current_fn!())?;
+
+            state
+                .move_call
+                .is_access_granted(
+                    &hospital_personnel_iota_address,
+                    &patient_iota_address,
+                    proxy_iota_address,
+                )
+                .await
+                .map_err(|_| anyhow!("Access not granted on chain"))
+                .code(StatusCode::CONFLICT)?;
+        }
 
-        let nonce: String = conn
-            .get(format!("nonce:{}", patient_iota_address.to_string()))
-            .map_err(|_| anyhow!("Nonce not found"))
+        // Kept until it expires so the personnel can still poll the answer
+        access_request.status = payload.status;
+        let _: () = conn
+            .set_options(
+                format!("access-request:{}", payload.id),
+                serde_json::to_string(&access_request).context(current_fn!())?,
+                SetOptions::default().with_expiration(SetExpiry::KEEPTTL),
+            )
+            .context(current_fn!())?;
+        let _: () = conn
+            .srem(
+                format!("access-requests:{}", patient_iota_address.to_string()),
+                &payload.id,
+            )
+            .context(current_fn!())?;
+        let _: () = conn
+            .srem(
+                format!(
+                    "personnel-access-requests:{}",
+                    access_request.hospital_personnel_iota_address
+                ),
+                &payload.id,
+            )
+            .context(current_fn!())?;
+
+        Ok(Utils::build_success_response((), StatusCode::OK))
+    }
+
+    pub async fn revoke_emergency_keys(
+        State(state): State<Arc<AppState>>,
+        Json(payload): Json<HandlerRevokeEmergencyKeysPayload>,
+    ) -> Result<Response, ProxyError> {
+        let patient_iota_address = IotaAddress::from_str(&payload.patient_iota_address)
+            .map_err(|_| anyhow!("Invalid patient IOTA address"))
             .code(StatusCode::BAD_REQUEST)?;
 
-        let intent_message = IntentMessage::new(Intent::personal_message(), nonce);
+        let mut conn = state.redis_pool.get().context(current_fn!())?;
+
+        Utils::verify_nonce_signature(&mut conn, patient_iota_address, &payload.signature)?;
 
-        let _ = signature
-            .verify_secure(
-                &intent_message,
-                patient_iota_address,
-                SignatureScheme::ED25519,
+        // Keys already handed out by a break-glass access live on until they expire or the
+        // patient revokes that access
+        let _: u64 = conn
+            .del(format!(
+                "emergency-keys:{}",
+                patient_iota_address.to_string()
+            ))
+            .context(current_fn!())?;
+
+        Ok(Utils::build_success_response((), StatusCode::OK))
+    }
+
+    /**
+     * Called once the guardian is removed on chain, by the patient or by the guardian stepping
+     * down. Accesses the guardian granted keep their keys until they expire or are revoked.
+     */
+    pub async fn revoke_guardian_keys(
+        State(state): State<Arc<AppState>>,
+        Json(payload): Json<HandlerRevokeGuardianKeysPayload>,
+    ) -> Result<Response, ProxyError> {
+        let patient_iota_address = IotaAddress::from_str(&payload.patient_iota_address)
+            .map_err(|_| anyhow!("Invalid patient IOTA address"))
+            .code(StatusCode::BAD_REQUEST)?;
+        let guardian_iota_address = IotaAddress::from_str(&payload.guardian_iota_address)
+            .map_err(|_| anyhow!("Invalid guardian IOTA address"))
+            .code(StatusCode::BAD_REQUEST)?;
+        let signer_iota_address = IotaAddress::from_str(&payload.signer_iota_address)
+            .map_err(|_| anyhow!("Invalid signer IOTA address"))
+            .code(StatusCode::BAD_REQUEST)?;
+
+        if signer_iota_address != patient_iota_address
+            && signer_iota_address != guardian_iota_address
+        {
+            return Err(ProxyError::Anyhow {
+                source: anyhow!("Illegal action. Invalid signer"),
+                code: StatusCode::FORBIDDEN,
+            });
+        }
+
+        let mut conn = state.redis_pool.get().context(current_fn!())?;
+
+        Utils::verify_nonce_signature(&mut conn, signer_iota_address, &payload.signature)?;
+
+        // Keys already revoked are not an error
+        let _: u64 = conn
+            .del(format!(
+                "guardian-keys:{}@{}",
+                guardian_iota_address.to_string(),
+                patient_iota_address.to_string()
+            ))
+            .context(current_fn!())?;
+
+        Ok(Utils::build_success_response((), StatusCode::OK))
+    }
+
+    pub async fn revoke_keys(
+        State(state): State<Arc<AppState>>,
+        Json(payload): Json<HandlerRevokeKeysPayload>,
+    ) -> Result<Response, ProxyError> {
+        let patient_iota_address = IotaAddress::from_str(&payload.patient_iota_address)
+            .map_err(|_| anyhow!("Invalid patient IOTA address"))
+            .code(StatusCode::BAD_REQUEST)?;
+        let hospital_personnel_iota_addresses = payload
+            .hospital_personnel_iota_addresses
+            .iter()
+            .map(|address| IotaAddress::from_str(address))
+            .collect::<Result<Vec<IotaAddress>, _>>()
+            .map_err(|_| anyhow!("Invalid hospital personnel IOTA address"))
+            .code(StatusCode::BAD_REQUEST)?;
+        let guardian_iota_address = payload
+            .guardian_iota_address
+            .as_deref()
+            .map(IotaAddress::from_str)
+            .transpose()
+            .map_err(|_| anyhow!("Invalid guardian IOTA address"))
+            .code(StatusCode::BAD_REQUEST)?;
+        // A guardian signs instead of the patient
+        let signer_iota_address = guardian_iota_address.unwrap_or(patient_iota_address);
+        let proxy_iota_address =
+            IotaAddress::from_str(&state.proxy_iota_address).context(current_fn!())?;
+
+        if hospital_personnel_iota_addresses.is_empty() {
+            return Err(ProxyError::Anyhow {
+                source: anyhow!("No hospital personnel to revoke"),
+                code: StatusCode::BAD_REQUEST,
+            });
+        }
+
+        let mut conn = state.redis_pool.get().context(current_fn!())?;
+
+        Utils::verify_nonce_signature(&mut conn, signer_iota_address, &payload.signature)?;
+
+        if let Some(guardian_iota_address) = guardian_iota_address {
+            let _ = state
+                .move_call
+                .is_guardian_allowed(
+                    &guardian_iota_address,
+                    &patient_iota_address,
+                    GUARDIAN_RIGHT_REVOKE,
+                    proxy_iota_address,
+                )
+                .await
+                .context(current_fn!())?;
+        }
+
+        let keys = hospital_personnel_iota_addresses
+            .iter()
+            .map(|hospital_personnel_iota_address| {
+                format!(
+                    "keys:{}@{}",
+                    hospital_personnel_iota_address.to_string(),
+                    patient_iota_address.to_string()
+                )
+            })
+            .collect::<Vec<String>>();
+
+        // Unexpired access tokens can't re-encrypt without the keys, keys that already expired are
+        // not an error
+        let _: u64 = conn.del(keys).context(current_fn!())?;
+
+        Ok(Utils::build_success_response((), StatusCode::OK))
+    }
+
+    /**
+     * Merges keys built by the patient app for registered recipients. The proxy can only check
+     * the kfrag, the seed is encrypted for the recipient.
+     */
+    pub async fn store_emergency_keys(
+        State(state): State<Arc<AppState>>,
+        Json(payload): Json<HandlerStoreEmergencyKeysPayload>,
+    ) -> Result<Response, ProxyError> {
+        let patient_iota_address = IotaAddress::from_str(&payload.patient_iota_address)
+            .map_err(|_| anyhow!("Invalid patient IOTA address"))
+            .code(StatusCode::BAD_REQUEST)?;
+        let proxy_iota_address =
+            IotaAddress::from_str(&state.proxy_iota_address).context(current_fn!())?;
+
+        if payload.keys.is_empty() {
+            return Err(ProxyError::Anyhow {
+                source: anyhow!("No emergency keys to store"),
+                code: StatusCode::BAD_REQUEST,
+            });
+        }
+
+        let mut conn = state.redis_pool.get().context(current_fn!())?;
+
+        Utils::verify_nonce_signature(&mut conn, patient_iota_address, &payload.signature)?;
+
+        let _ = state
+            .move_call
+            .is_patient_registered(&patient_iota_address, proxy_iota_address)
+            .await
+            .context(current_fn!())?;
+
+        let mut entries = vec![];
+        for (hospital_personnel_iota_address, emergency_keys) in payload.keys {
+            let hospital_personnel_iota_address =
+                IotaAddress::from_str(&hospital_personnel_iota_address)
+                    .map_err(|_| anyhow!("Invalid hospital personnel IOTA address"))
+                    .code(StatusCode::BAD_REQUEST)?;
+
+            let pre_public_key: Option<String> = conn
+                .hget(
+                    "emergency-recipients",
+                    hospital_personnel_iota_address.to_string(),
+                )
+                .context(current_fn!())?;
+            if pre_public_key.as_ref() != Some(&emergency_keys.hospital_personnel_pre_public_key) {
+                return Err(ProxyError::Anyhow {
+                    source: anyhow!("Emergency keys are not built for a registered recipient key"),
+                    code: StatusCode::BAD_REQUEST,
+                });
+            }
+
+            // Break-glass access can't ask the patient again, keys that don't work are rejected now
+            let k_frag: KeyFrag =
+                Utils::serde_deserialize_from_base64(emergency_keys.k_frag.clone())
+                    .map_err(|_| anyhow!("Invalid kfrag"))
+                    .code(StatusCode::BAD_REQUEST)?;
+            let signer_pre_public_key: PublicKey =
+                Utils::serde_deserialize_from_base64(emergency_keys.signer_pre_public_key.clone())
+                    .map_err(|_| anyhow!("Invalid signer PRE public key"))
+                    .code(StatusCode::BAD_REQUEST)?;
+            let patient_pre_public_key: PublicKey =
+                Utils::serde_deserialize_from_base64(emergency_keys.patient_pre_public_key.clone())
+                    .map_err(|_| anyhow!("Invalid patient PRE public key"))
+                    .code(StatusCode::BAD_REQUEST)?;
+            let data_pre_public_key: PublicKey =
+                Utils::serde_deserialize_from_base64(emergency_keys.data_pre_public_key.clone())
+                    .map_err(|_| anyhow!("Invalid data PRE public key"))
+                    .code(StatusCode::BAD_REQUEST)?;
+
+            let _ = k_frag
+                .verify(
+                    &signer_pre_public_key,
+                    Some(&patient_pre_public_key),
+                    Some(&data_pre_public_key),
+                )
+                .map_err(|e| anyhow!(e.0.to_string()))
+                .code(StatusCode::BAD_REQUEST)?;
+
+            entries.push((
+                hospital_personnel_iota_address.to_string(),
+                Utils::serde_serialize_to_base64(&emergency_keys).context(current_fn!())?,
+            ));
+        }
+
+        // Kept until the patient disables emergency access
+        let _: () = conn
+            .hset_multiple(
+                format!("emergency-keys:{}", patient_iota_address.to_string()),
+                &entries,
             )
-            .map_err(|_| anyhow!("Failed to verify signature"))
-            .code(StatusCode::UNAUTHORIZED)?;
+            .context(current_fn!())?;
+
+        Ok(Utils::build_success_response((), StatusCode::OK))
+    }
+
+    /**
+     * Registers the PRE public key patients build break-glass keys for, only medical personnel
+     * can be a recipient
+     */
+    pub async fn store_emergency_recipient(
+        State(state): State<Arc<AppState>>,
+        Json(payload): Json<HandlerStoreEmergencyRecipientPayload>,
+    ) -> Result<Response, ProxyError> {
+        let hospital_personnel_iota_address =
+            IotaAddress::from_str(&payload.hospital_personnel_iota_address)
+                .map_err(|_| anyhow!("Invalid hospital personnel IOTA address"))
+                .code(StatusCode::BAD_REQUEST)?;
+        let _: PublicKey =
+            Utils::serde_deserialize_from_base64(payload.hospital_personnel_pre_public_key.clone())
+                .map_err(|_| anyhow!("Invalid hospital personnel PRE public key"))
+                .code(StatusCode::BAD_REQUEST)?;
+        let proxy_iota_address =
+            IotaAddress::from_str(&state.proxy_iota_address).context(current_fn!())?;
+
+        let mut conn = state.redis_pool.get().context(current_fn!())?;
+
+        Utils::verify_nonce_signature(
+            &mut conn,
+            hospital_personnel_iota_address,
+            &payload.signature,
+        )?;
+
+        match state
+            .move_call
+            .get_hospital_personnel_role(&hospital_personnel_iota_address, proxy_iota_address)
+            .await
+            .context(current_fn!())?
+        {
+            MoveHospitalPersonnelRole::MedicalPersonnel => {}
+            _ => {
+                return Err(ProxyError::Anyhow {
+                    source: anyhow!("Illegal action. Invalid role"),
+                    code: StatusCode::FORBIDDEN,
+                })
+            }
+        }
 
         let _: () = conn
-            .del(patient_iota_address.to_string())
-            .map_err(|_| anyhow!("Nonce expired"))
-            .code(StatusCode::UNAUTHORIZED)?;
+            .hset(
+                "emergency-recipients",
+                hospital_personnel_iota_address.to_string(),
+                payload.hospital_personnel_pre_public_key,
+            )
+            .context(current_fn!())?;
+
+        Ok(Utils::build_success_response((), StatusCode::OK))
+    }
+
+    /**
+     * Deposited by the patient before adding the guardian on chain, using the keys needs the
+     * matching right on chain as well
+     */
+    pub async fn store_guardian_keys(
+        State(state): State<Arc<AppState>>,
+        Json(payload): Json<HandlerStoreGuardianKeysPayload>,
+    ) -> Result<Response, ProxyError> {
+        let patient_iota_address = IotaAddress::from_str(&payload.patient_iota_address)
+            .map_err(|_| anyhow!("Invalid patient IOTA address"))
+            .code(StatusCode::BAD_REQUEST)?;
+        let guardian_iota_address = IotaAddress::from_str(&payload.guardian_iota_address)
+            .map_err(|_| anyhow!("Invalid guardian IOTA address"))
+            .code(StatusCode::BAD_REQUEST)?;
+        let guardian_keys = payload.keys;
+
+        let signer_pre_public_key: PublicKey =
+            Utils::serde_deserialize_from_base64(guardian_keys.signer_pre_public_key.clone())
+                .map_err(|_| anyhow!("Invalid signer PRE public key"))
+                .code(StatusCode::BAD_REQUEST)?;
+        let patient_pre_public_key: PublicKey =
+            Utils::serde_deserialize_from_base64(guardian_keys.patient_pre_public_key.clone())
+                .map_err(|_| anyhow!("Invalid patient PRE public key"))
+                .code(StatusCode::BAD_REQUEST)?;
+        let guardian_pre_public_key: PublicKey =
+            Utils::serde_deserialize_from_base64(guardian_keys.guardian_pre_public_key.clone())
+                .map_err(|_| anyhow!("Invalid guardian PRE public key"))
+                .code(StatusCode::BAD_REQUEST)?;
+
+        // Each kfrag must re-encrypt for the key of its right only
+        let mut k_frags = vec![];
+        match (
+            &guardian_keys.grant_k_frag,
+            &guardian_keys.data_pre_public_key,
+        ) {
+            (Some(grant_k_frag), Some(data_pre_public_key)) => {
+                let data_pre_public_key: PublicKey =
+                    Utils::serde_deserialize_from_base64(data_pre_public_key.clone())
+                        .map_err(|_| anyhow!("Invalid data PRE public key"))
+                        .code(StatusCode::BAD_REQUEST)?;
+                k_frags.push((grant_k_frag.clone(), data_pre_public_key));
+            }
+            (None, None) => {}
+            _ => {
+                return Err(ProxyError::Anyhow {
+                    source: anyhow!("Invalid guardian grant keys"),
+                    code: StatusCode::BAD_REQUEST,
+                })
+            }
+        }
+        if let Some(read_k_frag) = &guardian_keys.read_k_frag {
+            k_frags.push((read_k_frag.clone(), guardian_pre_public_key));
+        }
+
+        if k_frags.is_empty() {
+            return Err(ProxyError::Anyhow {
+                source: anyhow!("No guardian keys to store"),
+                code: StatusCode::BAD_REQUEST,
+            });
+        }
+
+        for (k_frag, receiving_pre_public_key) in k_frags {
+            let k_frag: KeyFrag = Utils::serde_deserialize_from_base64(k_frag)
+                .map_err(|_| anyhow!("Invalid kfrag"))
+                .code(StatusCode::BAD_REQUEST)?;
+            let _ = k_frag
+                .verify(
+                    &signer_pre_public_key,
+                    Some(&patient_pre_public_key),
+                    Some(&receiving_pre_public_key),
+                )
+                .map_err(|e| anyhow!(e.0.to_string()))
+                .code(StatusCode::BAD_REQUEST)?;
+        }
+
+        let mut conn = state.redis_pool.get().context(current_fn!())?;
+
+        Utils::verify_nonce_signature(&mut conn, patient_iota_address, &payload.signature)?;
+
+        let _: () = conn
+            .set(
+                format!(
+                    "guardian-keys:{}@{}",
+                    guardian_iota_address.to_string(),
+                    patient_iota_address.to_string()
+                ),
+                Utils::serde_serialize_to_base64(&guardian_keys).context(current_fn!())?,
+            )
+            .context(current_fn!())?;
+
+        Ok(Utils::build_success_response((), StatusCode::OK))
+    }
+
+    pub async fn store_keys(
+        State(state): State<Arc<AppState>>,
+        Json(payload): Json<HandlerStoreKeysPayload>,
+    ) -> Result<Response, ProxyError> {
+        let patient_iota_address = IotaAddress::from_str(&payload.patient_iota_address)
+            .map_err(|_| anyhow!("Invalid patient IOTA address"))
+            .code(StatusCode::BAD_REQUEST)?;
+        let hospital_personnel_iota_address =
+            IotaAddress::from_str(&payload.hospital_personnel_iota_address)
+                .map_err(|_| anyhow!("Invalid hospital personnel IOTA address"))
+                .code(StatusCode::BAD_REQUEST)?;
+        let guardian_iota_address = payload
+            .guardian_iota_address
+            .as_deref()
+            .map(IotaAddress::from_str)
+            .transpose()
+            .map_err(|_| anyhow!("Invalid guardian IOTA address"))
+            .code(StatusCode::BAD_REQUEST)?;
+        // A guardian signs instead of the patient
+        let signer_iota_address = guardian_iota_address.unwrap_or(patient_iota_address);
+        let proxy_iota_address =
+            IotaAddress::from_str(&state.proxy_iota_address).context(current_fn!())?;
+
+        let mut conn = state.redis_pool.get().context(current_fn!())?;
+
+        Utils::verify_nonce_signature(&mut conn, signer_iota_address, &payload.signature)?;
+
+        // A guardian holds the seed of its delegated data key but never the kfrag to it
+        let (k_frag, patient_pre_public_key, signer_pre_public_key) = match guardian_iota_address {
+            Some(guardian_iota_address) => {
+                let _ = state
+                    .move_call
+                    .is_guardian_allowed(
+                        &guardian_iota_address,
+                        &patient_iota_address,
+                        GUARDIAN_RIGHT_GRANT,
+                        proxy_iota_address,
+                    )
+                    .await
+                    .context(current_fn!())?;
+
+                let guardian_keys =
+                    Self::guardian_keys(&mut conn, &guardian_iota_address, &patient_iota_address)?;
+
+                match (
+                    guardian_keys.grant_k_frag,
+                    guardian_keys.data_pre_public_key,
+                ) {
+                    (Some(grant_k_frag), Some(data_pre_public_key))
+                        if data_pre_public_key == payload.data_pre_public_key =>
+                    {
+                        (
+                            grant_k_frag,
+                            guardian_keys.patient_pre_public_key,
+                            guardian_keys.signer_pre_public_key,
+                        )
+                    }
+                    _ => {
+                        return Err(ProxyError::Anyhow {
+                            source: anyhow!("Guardian keys don't allow granting access"),
+                            code: StatusCode::FORBIDDEN,
+                        })
+                    }
+                }
+            }
+            None => match (payload.k_frag, payload.signer_pre_public_key) {
+                (Some(k_frag), Some(signer_pre_public_key)) => (
+                    k_frag,
+                    payload.patient_pre_public_key,
+                    signer_pre_public_key,
+                ),
+                _ => {
+                    return Err(ProxyError::Anyhow {
+                        source: anyhow!("Invalid kfrag"),
+                        code: StatusCode::BAD_REQUEST,
+                    })
+                }
+            },
+        };
 
         // Get the role of hospital personnel
         let role = state
@@ -703,20 +2026,20 @@ impl Handlers {
             .await
             .context(current_fn!())?;
 
-        let (hospital_personnel_role, read_keys_duration, update_keys_duration): (
+        let (hospital_personnel_role, read_keys_duration_max, update_keys_duration_max): (
             AuthRole,
             u64,
             Option<u64>,
         ) = match role {
             MoveHospitalPersonnelRole::AdministrativePersonnel => (
                 AuthRole::AdministrativePersonnel,
-                ADMINISTRATIVE_KEYS_READ_DUR,
+                ADMINISTRATIVE_KEYS_READ_DUR_MAX,
                 None,
             ),
             MoveHospitalPersonnelRole::MedicalPersonnel => (
                 AuthRole::MedicalPersonnel,
-                MEDICAL_KEYS_READ_DUR,
-                Some(MEDICAL_KEYS_UPDATE_DUR),
+                MEDICAL_KEYS_READ_DUR_MAX,
+                Some(MEDICAL_KEYS_UPDATE_DUR_MAX),
             ),
             _ => {
                 return Err(ProxyError::Anyhow {
@@ -726,6 +2049,50 @@ impl Handlers {
             }
         };
 
+        // Keys live as long as the access the patient grants on chain
+        let read_keys_duration = payload.exp_dur_read.saturating_mul(60);
+        if !(KEYS_DUR_MIN..=read_keys_duration_max).contains(&read_keys_duration) {
+            return Err(ProxyError::Anyhow {
+                source: anyhow!("Invalid read access duration"),
+                code: StatusCode::BAD_REQUEST,
+            });
+        }
+
+        if payload.is_medical_read && update_keys_duration_max.is_none() {
+            return Err(ProxyError::Anyhow {
+                source: anyhow!("Invalid access scope"),
+                code: StatusCode::BAD_REQUEST,
+            });
+        }
+
+        if let Some(medical_record_scope) = &payload.medical_record_scope {
+            if !payload.is_medical_read
+                || medical_record_scope.is_empty()
+                || !medical_record_scope.windows(2).all(|w| w[0] < w[1])
+            {
+                return Err(ProxyError::Anyhow {
+                    source: anyhow!("Invalid medical record scope"),
+                    code: StatusCode::BAD_REQUEST,
+                });
+            }
+        }
+
+        let update_keys_duration = match (payload.exp_dur_update, update_keys_duration_max) {
+            (None, _) => None,
+            (Some(exp_dur_update), Some(update_keys_duration_max))
+                if (KEYS_DUR_MIN..=update_keys_duration_max)
+                    .contains(&exp_dur_update.saturating_mul(60)) =>
+            {
+                Some(exp_dur_update * 60)
+            }
+            _ => {
+                return Err(ProxyError::Anyhow {
+                    source: anyhow!("Invalid update access duration"),
+                    code: StatusCode::BAD_REQUEST,
+                })
+            }
+        };
+
         // Create access token for hospital personnel
         let es256_keypair = Utils::construct_es256_key_pair_from_pem(&state.jwt_ecdsa_key_pair)
             .context(current_fn!())?;
@@ -758,11 +2125,12 @@ impl Handlers {
 
         let access_keys = AccessKeys {
             enc_data_pre_secret_key_seed: payload.enc_data_pre_secret_key_seed,
-            k_frag: payload.k_frag,
+            k_frag,
             data_pre_public_key: payload.data_pre_public_key,
             data_pre_secret_key_seed_capsule: payload.data_pre_secret_key_seed_capsule,
-            patient_pre_public_key: payload.patient_pre_public_key,
-            signer_pre_public_key: payload.signer_pre_public_key,
+            medical_record_scope: payload.medical_record_scope,
+            patient_pre_public_key,
+            signer_pre_public_key,
         };
 
         let _: () = conn
@@ -774,7 +2142,9 @@ impl Handlers {
                 ),
                 Utils::serde_serialize_to_base64(&access_keys).context(current_fn!())?,
                 SetOptions::default().with_expiration(SetExpiry::EX(
-                    update_keys_duration.unwrap_or(read_keys_duration),
+                    update_keys_duration.map_or(read_keys_duration, |update_keys_duration| {
+                        update_keys_duration.max(read_keys_duration)
+                    }),
                 )),
             )
             .context(current_fn!())?;
@@ -837,19 +2207,63 @@ impl Handlers {
             )
         };
 
-        let cid = Utils::add_and_pin_to_ipfs(medical_metadata.enc_data)
-            .await
-            .context(current_fn!())?;
+        let mut conn = state.redis_pool.get().context(current_fn!())?;
+
+        let uploaded_content: String = conn
+            .get(format!("content:{}", medical_metadata.locator))
+            .map_err(|_| anyhow!("Content not found"))
+            .code(StatusCode::BAD_REQUEST)?;
+        let uploaded_content: UploadedContent =
+            Utils::serde_deserialize_from_base64(uploaded_content).context(current_fn!())?;
+
+        if uploaded_content.iota_address != current_user.iota_address {
+            return Err(ProxyError::Anyhow {
+                source: anyhow!("Illegal action. Content uploaded by another personnel"),
+                code: StatusCode::UNAUTHORIZED,
+            });
+        }
+
+        // Keep the replaced version so it stays readable from the record's timeline
+        let versions = {
+            let (current_medical_metadata, _) = state
+                .move_call
+                .get_medical_record_update(
+                    &hospital_personnel_iota_address,
+                    payload.index,
+                    &patient_iota_address,
+                    proxy_iota_address,
+                )
+                .await
+                .context(current_fn!())?;
+            let current_medical_metadata: MedicalMetadata =
+                Utils::serde_deserialize_from_base64(current_medical_metadata.metadata)
+                    .context(current_fn!())?;
+
+            Utils::medical_metadata_versions(current_medical_metadata)
+        };
         let created_at = Utils::sys_time_to_iso(std::time::SystemTime::now());
 
         let medical_metadata = MedicalMetadata {
+            backend: uploaded_content.backend,
             capsule: medical_metadata.capsule,
-            cid,
             created_at,
+            created_by: Some(current_user.iota_address.clone()),
             enc_key_and_nonce: medical_metadata.enc_key_and_nonce,
+            locator: medical_metadata.locator,
+            sha256: Some(uploaded_content.sha256),
+            versions,
         };
 
-        let _ = state
+        PinGc::set_status(
+            &mut conn,
+            medical_metadata.backend,
+            &medical_metadata.locator,
+            &payload.patient_iota_address,
+            PinStatus::Pending,
+        )
+        .context(current_fn!())?;
+
+        let res = state
             .move_call
             .update_medical_record(
                 &hospital_personnel_iota_address,
@@ -858,9 +2272,173 @@ impl Handlers {
                 proxy_iota_address,
                 proxy_iota_key_pair,
             )
-            .await
+            .await;
+
+        // On failure the content stays pending until reconciliation confirms the tx never landed
+        let _: () = conn
+            .del(format!("content:{}", medical_metadata.locator))
             .context(current_fn!())?;
+        let _ = res.context(current_fn!())?;
+
+        PinGc::set_status(
+            &mut conn,
+            medical_metadata.backend,
+            &medical_metadata.locator,
+            &payload.patient_iota_address,
+            PinStatus::Committed,
+        )
+        .context(current_fn!())?;
 
         Ok(Utils::build_success_response((), StatusCode::OK))
     }
+
+    pub async fn upload_medical_record_content(
+        State(state): State<Arc<AppState>>,
+        Extension(current_user): Extension<CurrentUser>,
+        body: Body,
+    ) -> Result<Response, ProxyError> {
+        if current_user.role != AuthRole::MedicalPersonnel {
+            return Err(ProxyError::Anyhow {
+                source: anyhow!("Illegal action. Invalid role"),
+                code: StatusCode::UNAUTHORIZED,
+            });
+        }
+
+        if current_user.purpose != ReencryptionPurposeType::Update {
+            return Err(ProxyError::Anyhow {
+                source: anyhow!("Illegal action. Invalid purpose"),
+                code: StatusCode::BAD_REQUEST,
+            });
+        }
+
+        let (backend, blob_store) = state.blob_stores.get_default().context(current_fn!())?;
+
+        let hasher = Arc::new(Mutex::new(Sha256::new()));
+        let body: BlobStream = {
+            let hasher = hasher.clone();
+            Box::pin(
+                body.into_data_stream()
+                    .inspect_ok(move |bytes| {
+                        if let Ok(mut hasher) = hasher.lock() {
+                            hasher.update(bytes);
+                        }
+                    })
+                    .map_err(|e| ProxyError::from(anyhow!(e).context(current_fn!()))),
+            )
+        };
+
+        let locator = blob_store.put(body).await.context(current_fn!())?;
+
+        let uploaded_content = UploadedContent {
+            backend,
+            iota_address: current_user.iota_address,
+            sha256: hex::encode(
+                hasher
+                    .lock()
+                    .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?
+                    .clone()
+                    .finalize(),
+            ),
+        };
+
+        let mut conn = state.redis_pool.get().context(current_fn!())?;
+
+        PinGc::track(
+            &mut conn,
+            backend,
+            current_user.iota_address.clone(),
+            locator.clone(),
+        )
+        .context(current_fn!())?;
+
+        // Only the uploader can reference the content on a medical record
+        let _: () = conn
+            .set_options(
+                format!("content:{}", locator),
+                Utils::serde_serialize_to_base64(&uploaded_content).context(current_fn!())?,
+                SetOptions::default().with_expiration(SetExpiry::EX(MEDICAL_KEYS_UPDATE_DUR_MAX)),
+            )
+            .context(current_fn!())?;
+
+        let res_data = json!({
+            "backend": backend,
+            "locator": locator,
+        });
+
+        Ok(Utils::build_success_response(res_data, StatusCode::OK))
+    }
+
+    fn check_medical_record_scope(
+        access_keys: &AccessKeys,
+        medical_record_index: u64,
+    ) -> Result<(), ProxyError> {
+        if access_keys
+            .medical_record_scope
+            .as_ref()
+            .is_some_and(|medical_record_scope| {
+                !medical_record_scope.contains(&medical_record_index)
+            })
+        {
+            return Err(ProxyError::Anyhow {
+                source: anyhow!("Illegal action. Medical record is out of the access scope"),
+                code: StatusCode::FORBIDDEN,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn guardian_keys(
+        conn: &mut redis::Connection,
+        guardian_iota_address: &IotaAddress,
+        patient_iota_address: &IotaAddress,
+    ) -> Result<GuardianKeys, ProxyError> {
+        let guardian_keys: Option<String> = conn
+            .get(format!(
+                "guardian-keys:{}@{}",
+                guardian_iota_address.to_string(),
+                patient_iota_address.to_string()
+            ))
+            .context(current_fn!())?;
+
+        Ok(Utils::serde_deserialize_from_base64(
+            guardian_keys
+                .ok_or(anyhow!("Guardian keys not found"))
+                .code(StatusCode::NOT_FOUND)?,
+        )
+        .context(current_fn!())?)
+    }
+
+    /**
+     * Pending access requests of a set, expired or answered ones are dropped from it lazily
+     */
+    fn pending_access_requests(
+        conn: &mut redis::Connection,
+        access_requests_key: &str,
+    ) -> Result<Vec<AccessRequest>, ProxyError> {
+        let ids: Vec<String> = conn.smembers(access_requests_key).context(current_fn!())?;
+        let mut access_requests = vec![];
+        for id in ids {
+            let access_request: Option<String> = conn
+                .get(format!("access-request:{}", id))
+                .context(current_fn!())?;
+            let access_request = match access_request {
+                Some(access_request) => {
+                    serde_json::from_str::<AccessRequest>(&access_request).context(current_fn!())?
+                }
+                None => {
+                    let _: () = conn.srem(access_requests_key, &id).context(current_fn!())?;
+                    continue;
+                }
+            };
+
+            if access_request.status == AccessRequestStatus::Pending {
+                access_requests.push(access_request);
+            } else {
+                let _: () = conn.srem(access_requests_key, &id).context(current_fn!())?;
+            }
+        }
+
+        Ok(access_requests)
+    }
 }
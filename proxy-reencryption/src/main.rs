@@ -1,8 +1,10 @@
+mod blob_store;
 mod constants;
 mod handlers;
 mod macros;
 mod middlewares;
 mod move_call;
+mod pin_gc;
 mod proxy_error;
 mod tes_error;
 mod types;
@@ -15,6 +17,7 @@ use axum::{
     routing::{get, post, put},
     Router,
 };
+use blob_store::BlobStores;
 use constants::{
     DECMED_ADDRESS_ID_OBJECT_ID, DECMED_ADDRESS_ID_OBJECT_VERSION, DECMED_GLOBAL_ADMIN_CAP_ID,
     DECMED_HOSPITAL_ID_METADATA_OBJECT_ID, DECMED_HOSPITAL_ID_METADATA_OBJECT_VERSION,
@@ -26,8 +29,10 @@ use constants::{
 use handlers::Handlers;
 use iota_types::{base_types::ObjectID, Identifier};
 use move_call::MoveCall;
+use pin_gc::PinGc;
 use tower::ServiceBuilder;
-use types::{AppState, DecmedPackage};
+use types::{AppState, DecmedPackage, PinRetentionPolicy};
+use zeroize::Zeroizing;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
@@ -38,11 +43,18 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let redis_connection_url = env::var("REDIS_CONNECTION_URL_DEV")?;
     let port = env::var("PORT")?;
     let global_admin_iota_address = env::var("GLOBAL_ADMIN_IOTA_ADDRESS")?;
-    let global_admin_iota_key_pair = env::var("GLOBAL_ADMIN_IOTA_KEY_PAIR")?;
+    let global_admin_iota_key_pair = Zeroizing::new(env::var("GLOBAL_ADMIN_IOTA_KEY_PAIR")?);
     let proxy_iota_address = env::var("PROXY_IOTA_ADDRESS")?;
-    let proxy_iota_key_pair = env::var("PROXY_IOTA_KEY_PAIR")?;
-    let jwt_ecdsa_key_pair = env::var("JWT_ECDSA_KEY_PAIR")?;
+    let proxy_iota_key_pair = Zeroizing::new(env::var("PROXY_IOTA_KEY_PAIR")?);
+    let jwt_ecdsa_key_pair = Zeroizing::new(env::var("JWT_ECDSA_KEY_PAIR")?);
     let jwt_ecdsa_pub_key = env::var("JWT_ECDSA_PUB_KEY")?;
+    let pin_retention_policy = match env::var("PIN_RETENTION_POLICY") {
+        Ok(policy) => PinRetentionPolicy::from_str(&policy)?,
+        Err(_) => PinRetentionPolicy::default(),
+    };
+
+    // Blob stores, selected by `BLOB_STORE_BACKEND`
+    let blob_stores = BlobStores::from_env()?;
 
     // Redis pool
     let redis_client = redis::Client::open(redis_connection_url.as_str())?;
@@ -70,21 +82,38 @@ async fn main() -> Result<(), Box<dyn Error>> {
     };
     let move_call = MoveCall { decmed_package };
     let shared_state = Arc::new(AppState {
+        blob_stores,
         global_admin_iota_address,
         global_admin_iota_key_pair,
         jwt_ecdsa_key_pair,
         jwt_ecdsa_pub_key,
         move_call,
+        pin_retention_policy,
         proxy_iota_address,
         proxy_iota_key_pair,
         redis_pool,
     });
 
+    // Pin garbage collection
+    tokio::spawn(PinGc::run(shared_state.clone()));
+
     let protected_routes = Router::new()
         .route("/", get(|| async { "Hello, world!" }))
         .route("/medical-record", get(Handlers::get_medical_record))
         .route("/medical-record", post(Handlers::create_medical_record))
         .route("/medical-record", put(Handlers::update_medical_record))
+        .route(
+            "/medical-record/content",
+            get(Handlers::get_medical_record_content),
+        )
+        .route(
+            "/medical-record/content",
+            post(Handlers::upload_medical_record_content),
+        )
+        .route(
+            "/medical-record/versions",
+            get(Handlers::get_medical_record_versions),
+        )
         .route(
             "/medical-record-update",
             get(Handlers::get_medical_record_update),
@@ -106,7 +135,45 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     let public_routes = Router::new()
         .route("/nonce", post(Handlers::get_nonce_handler))
-        .route("/keys", post(Handlers::store_keys));
+        .route("/keys", post(Handlers::store_keys))
+        .route(
+            "/medical-record/content/patient",
+            post(Handlers::get_patient_medical_record_content),
+        )
+        .route("/keys/revoke", post(Handlers::revoke_keys))
+        .route("/guardian/keys", post(Handlers::store_guardian_keys))
+        .route(
+            "/guardian/keys/revoke",
+            post(Handlers::revoke_guardian_keys),
+        )
+        .route(
+            "/medical-record/c-frag/guardian",
+            post(Handlers::get_guardian_medical_record_c_frag),
+        )
+        .route("/access-request", post(Handlers::create_access_request))
+        .route(
+            "/access-request/status",
+            get(Handlers::get_access_request_status),
+        )
+        .route("/access-request/list", post(Handlers::get_access_requests))
+        .route(
+            "/access-request/respond",
+            post(Handlers::respond_access_request),
+        )
+        .route("/emergency/access", post(Handlers::create_emergency_access))
+        .route("/emergency/keys", post(Handlers::store_emergency_keys))
+        .route(
+            "/emergency/keys/revoke",
+            post(Handlers::revoke_emergency_keys),
+        )
+        .route(
+            "/emergency/recipients",
+            post(Handlers::store_emergency_recipient),
+        )
+        .route(
+            "/emergency/recipients/pending",
+            post(Handlers::get_emergency_recipients),
+        );
 
     let api_routes = Router::new()
         .nest("/gen", gen_routes)
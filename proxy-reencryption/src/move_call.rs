@@ -50,6 +50,14 @@ impl MoveCall {
         .context(current_fn!())?)
     }
 
+    pub fn construct_hospital_id_metadata_object_call_arg(&self, mutable: bool) -> CallArg {
+        Utils::construct_shared_object_call_arg(
+            self.decmed_package.hospital_id_metadata_object_id,
+            self.decmed_package.hospital_id_metadata_object_version,
+            mutable,
+        )
+    }
+
     pub fn construct_hospital_personnel_id_account_object_call_arg(
         &self,
         mutable: bool,
@@ -138,6 +146,77 @@ impl MoveCall {
         Ok(())
     }
 
+    pub async fn create_emergency_access(
+        &self,
+        date: String,
+        hospital_personnel_address: &IotaAddress,
+        justification: String,
+        metadata: String,
+        patient_address: &IotaAddress,
+        sender: IotaAddress,
+        sender_key_pair: IotaKeyPair,
+    ) -> Result<(), ProxyError> {
+        let iota_client = Utils::get_iota_client().await.context(current_fn!())?;
+        let pt = Utils::construct_pt(
+            "create_emergency_access",
+            self.decmed_package.package_id,
+            self.decmed_package.module_proxy.clone(),
+            vec![],
+            vec![
+                self.construct_address_id_object_call_arg(false),
+                self.construct_clock_call_arg(),
+                CallArg::Pure(bcs::to_bytes(&date).context(current_fn!())?),
+                self.construct_hospital_id_metadata_object_call_arg(false),
+                CallArg::Pure(bcs::to_bytes(hospital_personnel_address).context(current_fn!())?),
+                self.construct_hospital_personnel_id_account_object_call_arg(true),
+                CallArg::Pure(bcs::to_bytes(&justification).context(current_fn!())?),
+                CallArg::Pure(bcs::to_bytes(&metadata).context(current_fn!())?),
+                CallArg::Pure(bcs::to_bytes(patient_address).context(current_fn!())?),
+                self.construct_patient_id_account_object_call_arg(true),
+                self.construct_proxy_cap(
+                    &iota_client,
+                    Identifier::from_str(DECMED_MODULE_SHARED).context(current_fn!())?,
+                    AccountAddress::from_str(DECMED_PACKAGE_ID).context(current_fn!())?,
+                    sender,
+                )
+                .await
+                .context(current_fn!())?,
+            ],
+        )
+        .context(current_fn!())?;
+
+        let (sponsor_account, reservation_id, gas_coins) =
+            Utils::reserve_gas(NANOS_PER_IOTA * 2, 10)
+                .await
+                .context(current_fn!())?;
+        let ref_gas_price = Utils::get_ref_gas_price(&iota_client)
+            .await
+            .context(current_fn!())?;
+
+        let tx_data = Utils::construct_sponsored_tx_data(
+            sender,
+            gas_coins,
+            pt,
+            GAS_BUDGET,
+            ref_gas_price,
+            sponsor_account,
+        );
+
+        let signer = sender_key_pair;
+        let tx = Transaction::from_data_and_signer(tx_data, vec![&signer]);
+
+        let response = Utils::execute_tx(tx, reservation_id)
+            .await
+            .context(current_fn!())?;
+
+        // Consent, role and justification are checked again on chain
+        Utils::handle_error_execute_tx(response)
+            .context(current_fn!())
+            .code(StatusCode::FORBIDDEN)?;
+
+        Ok(())
+    }
+
     pub async fn create_medical_record(
         &self,
         hospital_personnel_address: &IotaAddress,
@@ -287,6 +366,52 @@ impl MoveCall {
         Ok(role)
     }
 
+    /// At most `size` medical metadata of the patient, oldest first starting at index `cursor`
+    pub async fn get_medical_metadata(
+        &self,
+        cursor: u64,
+        patient_address: &IotaAddress,
+        size: u64,
+        sender: IotaAddress,
+    ) -> Result<Vec<MovePatientMedicalMetadata>, ProxyError> {
+        let iota_client = Utils::get_iota_client().await.context(current_fn!())?;
+        let pt = Utils::construct_pt(
+            "get_medical_metadata",
+            self.decmed_package.package_id,
+            self.decmed_package.module_proxy.clone(),
+            vec![],
+            vec![
+                self.construct_address_id_object_call_arg(false),
+                CallArg::Pure(bcs::to_bytes(&cursor).context(current_fn!())?),
+                CallArg::Pure(bcs::to_bytes(patient_address).context(current_fn!())?),
+                self.construct_patient_id_account_object_call_arg(false),
+                CallArg::Pure(bcs::to_bytes(&size).context(current_fn!())?),
+                self.construct_proxy_cap(
+                    &iota_client,
+                    Identifier::from_str(DECMED_MODULE_SHARED).context(current_fn!())?,
+                    AccountAddress::from_str(DECMED_PACKAGE_ID).context(current_fn!())?,
+                    sender,
+                )
+                .await
+                .context(current_fn!())?,
+            ],
+        )
+        .context(current_fn!())?;
+
+        let response = Utils::move_call_read_only(sender, &iota_client, pt)
+            .await
+            .context(current_fn!())?;
+
+        Utils::handle_error_move_call_read_only(response.clone())
+            .context(current_fn!())
+            .code(StatusCode::BAD_REQUEST)?;
+
+        let medical_metadata: Vec<MovePatientMedicalMetadata> =
+            Utils::parse_move_read_only_result(response, 0).context(current_fn!())?;
+
+        Ok(medical_metadata)
+    }
+
     /// ## Returns:
     /// 1: prev_index
     /// 2: next_index
@@ -415,6 +540,89 @@ impl MoveCall {
         Ok((medical_metadata, administrative_metadata))
     }
 
+    pub async fn is_access_granted(
+        &self,
+        hospital_personnel_address: &IotaAddress,
+        patient_address: &IotaAddress,
+        sender: IotaAddress,
+    ) -> Result<bool, ProxyError> {
+        let iota_client = Utils::get_iota_client().await.context(current_fn!())?;
+        let pt = Utils::construct_pt(
+            "is_access_granted",
+            self.decmed_package.package_id,
+            self.decmed_package.module_proxy.clone(),
+            vec![],
+            vec![
+                self.construct_address_id_object_call_arg(false),
+                self.construct_clock_call_arg(),
+                CallArg::Pure(bcs::to_bytes(hospital_personnel_address).context(current_fn!())?),
+                self.construct_hospital_personnel_id_account_object_call_arg(false),
+                CallArg::Pure(bcs::to_bytes(patient_address).context(current_fn!())?),
+                self.construct_proxy_cap(
+                    &iota_client,
+                    Identifier::from_str(DECMED_MODULE_SHARED).context(current_fn!())?,
+                    AccountAddress::from_str(DECMED_PACKAGE_ID).context(current_fn!())?,
+                    sender,
+                )
+                .await
+                .context(current_fn!())?,
+            ],
+        )
+        .context(current_fn!())?;
+
+        let response = Utils::move_call_read_only(sender, &iota_client, pt)
+            .await
+            .context(current_fn!())?;
+
+        Utils::handle_error_move_call_read_only(response.clone())
+            .context(current_fn!())
+            .code(StatusCode::CONFLICT)?;
+
+        Ok(true)
+    }
+
+    pub async fn is_guardian_allowed(
+        &self,
+        guardian_address: &IotaAddress,
+        patient_address: &IotaAddress,
+        right: u8,
+        sender: IotaAddress,
+    ) -> Result<bool, ProxyError> {
+        let iota_client = Utils::get_iota_client().await.context(current_fn!())?;
+        let pt = Utils::construct_pt(
+            "is_guardian_allowed",
+            self.decmed_package.package_id,
+            self.decmed_package.module_proxy.clone(),
+            vec![],
+            vec![
+                self.construct_address_id_object_call_arg(false),
+                CallArg::Pure(bcs::to_bytes(guardian_address).context(current_fn!())?),
+                CallArg::Pure(bcs::to_bytes(patient_address).context(current_fn!())?),
+                self.construct_patient_id_account_object_call_arg(false),
+                CallArg::Pure(bcs::to_bytes(&right).context(current_fn!())?),
+                self.construct_proxy_cap(
+                    &iota_client,
+                    Identifier::from_str(DECMED_MODULE_SHARED).context(current_fn!())?,
+                    AccountAddress::from_str(DECMED_PACKAGE_ID).context(current_fn!())?,
+                    sender,
+                )
+                .await
+                .context(current_fn!())?,
+            ],
+        )
+        .context(current_fn!())?;
+
+        let response = Utils::move_call_read_only(sender, &iota_client, pt)
+            .await
+            .context(current_fn!())?;
+
+        Utils::handle_error_move_call_read_only(response.clone())
+            .context(current_fn!())
+            .code(StatusCode::FORBIDDEN)?;
+
+        Ok(true)
+    }
+
     pub async fn is_patient_registered(
         &self,
         patient_address: &IotaAddress,
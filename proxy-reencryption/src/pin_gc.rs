@@ -0,0 +1,322 @@
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Context};
+use iota_types::base_types::IotaAddress;
+use r2d2::PooledConnection;
+use redis::{Client, Commands};
+
+use crate::{
+    constants::{
+        MEDICAL_KEYS_UPDATE_DUR_MAX, MEDICAL_METADATA_PAGE_SIZE, PIN_GC_GRACE_DUR, PIN_GC_INTERVAL,
+        PIN_SUPERSEDED_RETENTION_DUR,
+    },
+    current_fn,
+    proxy_error::ProxyError,
+    types::{
+        AppState, BlobStoreBackend, MedicalMetadata, PinRetentionPolicy, PinStatus, TrackedPin,
+    },
+    utils::Utils,
+};
+
+/// Redis hash of `TrackedPin`, keyed by `{backend}:{locator}`
+const PINS_KEY: &str = "pins";
+
+pub struct PinGc {}
+
+impl PinGc {
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default()
+    }
+
+    fn pin_field(backend: BlobStoreBackend, locator: &str) -> String {
+        format!("{}:{}", backend, locator)
+    }
+
+    pub fn track(
+        conn: &mut PooledConnection<Client>,
+        backend: BlobStoreBackend,
+        iota_address: String,
+        locator: String,
+    ) -> Result<(), ProxyError> {
+        let now = Self::now();
+        let pin = TrackedPin {
+            backend,
+            created_at: now,
+            iota_address,
+            locator,
+            patient_iota_address: None,
+            status: PinStatus::Uploaded,
+            superseded_at: None,
+            updated_at: now,
+        };
+
+        let _: () = conn
+            .hset(
+                PINS_KEY,
+                Self::pin_field(backend, &pin.locator),
+                Utils::serde_serialize_to_base64(&pin).context(current_fn!())?,
+            )
+            .context(current_fn!())?;
+
+        Ok(())
+    }
+
+    /**
+     * Untracked content (uploaded before pins were tracked) is left as-is.
+     */
+    pub fn set_status(
+        conn: &mut PooledConnection<Client>,
+        backend: BlobStoreBackend,
+        locator: &str,
+        patient_iota_address: &str,
+        status: PinStatus,
+    ) -> Result<(), ProxyError> {
+        let field = Self::pin_field(backend, locator);
+        let pin: Option<String> = conn.hget(PINS_KEY, &field).context(current_fn!())?;
+        let Some(pin) = pin else {
+            return Ok(());
+        };
+
+        let mut pin: TrackedPin =
+            Utils::serde_deserialize_from_base64(pin).context(current_fn!())?;
+        pin.patient_iota_address = Some(patient_iota_address.to_string());
+        pin.status = status;
+        pin.superseded_at = None;
+        pin.updated_at = Self::now();
+
+        let _: () = conn
+            .hset(
+                PINS_KEY,
+                field,
+                Utils::serde_serialize_to_base64(&pin).context(current_fn!())?,
+            )
+            .context(current_fn!())?;
+
+        Ok(())
+    }
+
+    /**
+     * Compares tracked pins with the content referenced by each patient's on-chain medical
+     * metadata:
+     * - uploaded content is orphaned once it can no longer be referenced
+     * - pending content is orphaned when its tx never landed
+     * - committed content is superseded once no record references it, and kept for
+     *   `PIN_SUPERSEDED_RETENTION_DUR`
+     * Orphaned and expired superseded content is reported or removed according to
+     * `AppState.pin_retention_policy`.
+     */
+    pub async fn reconcile(state: &AppState) -> Result<(), ProxyError> {
+        let now = Self::now();
+        let proxy_iota_address =
+            IotaAddress::from_str(&state.proxy_iota_address).context(current_fn!())?;
+
+        let pins: HashMap<String, String> = {
+            let mut conn = state.redis_pool.get().context(current_fn!())?;
+            conn.hgetall(PINS_KEY).context(current_fn!())?
+        };
+
+        // Patient -> referenced `(backend, locator)`, `None` when the chain could not be read
+        let mut referenced: HashMap<String, Option<HashSet<(BlobStoreBackend, String)>>> =
+            HashMap::new();
+        let mut findings: Vec<String> = Vec::new();
+
+        for (field, pin) in pins {
+            let mut pin: TrackedPin =
+                Utils::serde_deserialize_from_base64(pin).context(current_fn!())?;
+
+            let reason = match pin.patient_iota_address.clone() {
+                None => {
+                    if pin.updated_at + MEDICAL_KEYS_UPDATE_DUR_MAX + PIN_GC_GRACE_DUR < now {
+                        Some("orphaned, never referenced")
+                    } else {
+                        None
+                    }
+                }
+                Some(patient_iota_address) => {
+                    if !referenced.contains_key(&patient_iota_address) {
+                        let locators = Self::get_referenced_locators(
+                            state,
+                            &patient_iota_address,
+                            proxy_iota_address,
+                        )
+                        .await;
+
+                        if let Err(e) = &locators {
+                            Utils::debug_print(current_fn!(), e);
+                        }
+
+                        referenced.insert(patient_iota_address.clone(), locators.ok());
+                    }
+
+                    let Some(locators) = referenced
+                        .get(&patient_iota_address)
+                        .and_then(|l| l.as_ref())
+                    else {
+                        continue;
+                    };
+
+                    if locators.contains(&(pin.backend, pin.locator.clone())) {
+                        if pin.status != PinStatus::Committed || pin.superseded_at.is_some() {
+                            Self::set_status(
+                                &mut state.redis_pool.get().context(current_fn!())?,
+                                pin.backend,
+                                &pin.locator,
+                                &patient_iota_address,
+                                PinStatus::Committed,
+                            )
+                            .context(current_fn!())?;
+                        }
+
+                        None
+                    } else {
+                        match pin.status {
+                            PinStatus::Committed => {
+                                if pin.superseded_at.is_none() {
+                                    pin.superseded_at = Some(now);
+
+                                    let mut conn = state.redis_pool.get().context(current_fn!())?;
+                                    let _: () = conn
+                                        .hset(
+                                            PINS_KEY,
+                                            &field,
+                                            Utils::serde_serialize_to_base64(&pin)
+                                                .context(current_fn!())?,
+                                        )
+                                        .context(current_fn!())?;
+                                }
+
+                                if pin.superseded_at.unwrap_or(now) + PIN_SUPERSEDED_RETENTION_DUR
+                                    < now
+                                {
+                                    Some("superseded, retention elapsed")
+                                } else {
+                                    None
+                                }
+                            }
+                            PinStatus::Pending | PinStatus::Uploaded => {
+                                if pin.updated_at + PIN_GC_GRACE_DUR < now {
+                                    Some("orphaned, tx never landed")
+                                } else {
+                                    None
+                                }
+                            }
+                        }
+                    }
+                }
+            };
+
+            let Some(reason) = reason else {
+                continue;
+            };
+
+            findings.push(format!("{} ({})", field, reason));
+
+            if state.pin_retention_policy == PinRetentionPolicy::Remove {
+                if let Err(e) = Self::remove(state, &field, &pin).await {
+                    Utils::debug_print(current_fn!(), e);
+                }
+            }
+        }
+
+        if !findings.is_empty() {
+            Utils::debug_print(
+                current_fn!(),
+                format!(
+                    "{} unreferenced content ({:?}): {}",
+                    findings.len(),
+                    state.pin_retention_policy,
+                    findings.join(", ")
+                ),
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn remove(state: &AppState, field: &str, pin: &TrackedPin) -> Result<(), ProxyError> {
+        state
+            .blob_stores
+            .get(pin.backend)
+            .context(current_fn!())?
+            .delete(&pin.locator)
+            .await
+            .context(current_fn!())?;
+
+        let mut conn = state.redis_pool.get().context(current_fn!())?;
+        let _: () = conn.hdel(PINS_KEY, field).context(current_fn!())?;
+
+        Ok(())
+    }
+
+    /**
+     * Runs `reconcile` every `PIN_GC_INTERVAL`.
+     */
+    pub async fn run(state: Arc<AppState>) {
+        let mut interval = tokio::time::interval(Duration::from_secs(PIN_GC_INTERVAL));
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = Self::reconcile(&state).await {
+                Utils::debug_print(current_fn!(), e);
+            }
+        }
+    }
+
+    async fn get_referenced_locators(
+        state: &AppState,
+        patient_iota_address: &str,
+        proxy_iota_address: IotaAddress,
+    ) -> Result<HashSet<(BlobStoreBackend, String)>, ProxyError> {
+        let patient_iota_address = IotaAddress::from_str(patient_iota_address)
+            .map_err(|_| anyhow!("Invalid patient IOTA address"))
+            .context(current_fn!())?;
+
+        let mut locators = HashSet::new();
+        let mut cursor = 0;
+
+        // Paged so patients with many records stay under the dev-inspect limits
+        loop {
+            let medical_metadata = state
+                .move_call
+                .get_medical_metadata(
+                    cursor,
+                    &patient_iota_address,
+                    MEDICAL_METADATA_PAGE_SIZE,
+                    proxy_iota_address,
+                )
+                .await
+                .context(current_fn!())?;
+            let page_len = medical_metadata.len() as u64;
+
+            // Replaced versions stay referenced through the record's history
+            for metadata in medical_metadata {
+                let metadata: MedicalMetadata =
+                    Utils::serde_deserialize_from_base64(metadata.metadata)
+                        .context(current_fn!())?;
+
+                locators.extend(
+                    Utils::medical_metadata_versions(metadata)
+                        .into_iter()
+                        .map(|version| (version.backend, version.locator)),
+                );
+            }
+
+            if page_len < MEDICAL_METADATA_PAGE_SIZE {
+                break;
+            }
+
+            cursor += page_len;
+        }
+
+        Ok(locators)
+    }
+}
@@ -12,12 +12,21 @@ pub enum ProxyError {
         source: anyhow::Error,
         code: StatusCode,
     },
+    #[error("Integrity mismatch for {locator}: expected sha256 {expected}, got {actual}")]
+    IntegrityMismatch {
+        actual: String,
+        expected: String,
+        locator: String,
+    },
 }
 
 impl IntoResponse for ProxyError {
     fn into_response(self) -> Response {
         let (error_message, code) = match self {
             ProxyError::Anyhow { source, code } => (format!("{:?}", source), code),
+            err @ ProxyError::IntegrityMismatch { .. } => {
+                (err.to_string(), StatusCode::BAD_GATEWAY)
+            }
         };
 
         let error_response = json!({
@@ -1,4 +1,4 @@
-use std::fmt::Debug;
+use std::{collections::HashMap, fmt::Debug};
 
 use iota_json_rpc_types::{IotaObjectRef, IotaTransactionBlockEffects};
 use iota_types::{
@@ -9,8 +9,17 @@ use r2d2::Pool;
 use redis::Client;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+use zeroize::Zeroizing;
 
-use crate::move_call::MoveCall;
+use crate::{blob_store::BlobStores, move_call::MoveCall};
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum AccessRequestStatus {
+    Approved,
+    Pending,
+    Rejected,
+}
 
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum AuthRole {
@@ -19,6 +28,20 @@ pub enum AuthRole {
     Patient,
 }
 
+/// Where a medical record's encrypted content is stored, `ipfs_cluster` on older records
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Display, EnumString, Eq, Hash, PartialEq, Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum BlobStoreBackend {
+    #[default]
+    IpfsCluster,
+    Kubo,
+    LocalFs,
+    S3,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum MoveHospitalPersonnelRole {
     Admin,
@@ -26,6 +49,28 @@ pub enum MoveHospitalPersonnelRole {
     MedicalPersonnel,
 }
 
+/// What reconciliation does with content no record references, `report` only logs it
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Display, EnumString, Eq, PartialEq, Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum PinRetentionPolicy {
+    Remove,
+    #[default]
+    Report,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum PinStatus {
+    /// Referenced by a medical record on chain
+    Committed,
+    /// Referenced by a submitted tx that is not confirmed yet
+    Pending,
+    /// Uploaded but not referenced by any tx yet
+    Uploaded,
+}
+
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum ReencryptionPurposeType {
     Read,
@@ -38,18 +83,56 @@ pub struct AccessKeys {
     pub k_frag: String,
     pub data_pre_public_key: String,
     pub data_pre_secret_key_seed_capsule: String,
+    /// Medical record indexes the read access covers, `None` for every record
+    pub medical_record_scope: Option<Vec<u64>>,
     pub patient_pre_public_key: String,
     pub signer_pre_public_key: String,
 }
 
+/// Read by the hospital app from the access on chain, same format as the patient app writes
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AccessMetadata {
+    pub access_token: String,
+    /// Set on break-glass accesses, the proxy can't read the name and relays it encrypted by the
+    /// patient app, `patient_name` is empty then
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enc_patient_name: Option<AccessMetadataEncrypted>,
+    pub patient_iota_address: String,
+    pub patient_name: String,
+    pub patient_pre_public_key: Option<String>,
+}
+
+/// `AccessMetadata` encrypted for the hospital personnel PRE public key
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AccessMetadataEncrypted {
+    pub capsule: String,
+    pub enc_data: String,
+}
+
+/// Stored under `access-request:{id}` until `exp`, `id` is the SHA-256 of `code`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AccessRequest {
+    /// Signed hospital personnel code, verified again by the patient app
+    pub code: String,
+    /// Unix timestamp in seconds
+    pub created_at: u64,
+    /// Unix timestamp in seconds
+    pub exp: u64,
+    pub hospital_personnel_iota_address: String,
+    pub patient_iota_address: String,
+    pub status: AccessRequestStatus,
+}
+
 pub struct AppState {
+    pub blob_stores: BlobStores,
     pub global_admin_iota_address: String,
-    pub global_admin_iota_key_pair: String,
-    pub jwt_ecdsa_key_pair: String,
+    pub global_admin_iota_key_pair: Zeroizing<String>,
+    pub jwt_ecdsa_key_pair: Zeroizing<String>,
     pub jwt_ecdsa_pub_key: String,
     pub move_call: MoveCall,
+    pub pin_retention_policy: PinRetentionPolicy,
     pub proxy_iota_address: String,
-    pub proxy_iota_key_pair: String,
+    pub proxy_iota_key_pair: Zeroizing<String>,
     pub redis_pool: Pool<Client>,
 }
 
@@ -67,8 +150,9 @@ pub struct AuthenticateHandlerResponse {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ClientMedicalMetadata {
     pub capsule: String,
-    pub enc_data: String,
     pub enc_key_and_nonce: String,
+    #[serde(alias = "cid")]
+    pub locator: String,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -96,6 +180,43 @@ pub struct DecmedPackage {
     pub global_admin_cap_id: ObjectID,
 }
 
+/// Appended to `emergency-audit:{patient}` on every break-glass access, never trimmed
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EmergencyAuditEntry {
+    /// Unix timestamp in seconds
+    pub created_at: u64,
+    /// Unix timestamp in seconds
+    pub exp: u64,
+    pub hospital_personnel_iota_address: String,
+    pub justification: String,
+    pub patient_iota_address: String,
+}
+
+/// Stored in the `emergency-keys:{patient}` hash under the recipient address while the patient
+/// consents to break-glass access. Built by the patient app for a single recipient, the proxy
+/// only moves it to `keys:{hospital_personnel}@{patient}` and never holds a key to decrypt it.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EmergencyKeys {
+    pub data_pre_public_key: String,
+    pub data_pre_secret_key_seed_capsule: String,
+    /// Encrypted for `hospital_personnel_pre_public_key`
+    pub enc_data_pre_secret_key_seed: String,
+    /// Encrypted for `hospital_personnel_pre_public_key`
+    pub enc_patient_name: AccessMetadataEncrypted,
+    /// Recipient key the keys were built for, a recipient registering a new key needs new keys
+    pub hospital_personnel_pre_public_key: String,
+    pub k_frag: String,
+    pub patient_pre_public_key: String,
+    pub signer_pre_public_key: String,
+}
+
+/// Entry of the `emergency-recipients` hash, medical personnel patients build break-glass keys for
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EmergencyRecipient {
+    pub hospital_personnel_iota_address: String,
+    pub pre_public_key: String,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ErrorResponse {
     pub error: String,
@@ -118,7 +239,7 @@ pub struct GenerateAndRegisterProxyAddress {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct GenerateJwtHandlerResponse {
     pub public_key: String,
-    pub secret_key: String,
+    pub secret_key: Zeroizing<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -128,10 +249,38 @@ pub struct GetNonceHandlerPayload {
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct GenerateSignatureHandlerPayload {
-    pub iota_keypair: String,
+    pub iota_keypair: Zeroizing<String>,
     pub nonce: String,
 }
 
+/// Stored under `guardian-keys:{guardian}@{patient}` until the guardian is removed. Each right
+/// gets its own kfrag and the proxy keeps both, the guardian never holds a patient secret.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct GuardianKeys {
+    /// Delegated data key the grant kfrag re-encrypts for, its seed is shared with the guardian
+    pub data_pre_public_key: Option<String>,
+    /// Patient to `data_pre_public_key`, copied to the keys of every access the guardian grants
+    pub grant_k_frag: Option<String>,
+    pub guardian_pre_public_key: String,
+    pub patient_pre_public_key: String,
+    /// Patient to `guardian_pre_public_key`, re-encrypts record keys for the guardian to read
+    pub read_k_frag: Option<String>,
+    pub signer_pre_public_key: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HospitalPersonnelQrPayload {
+    /// Unix timestamp in seconds
+    pub exp: u64,
+    /// Unix timestamp in seconds
+    pub iat: u64,
+    pub iota_address: String,
+    /// Set on access requests
+    pub patient_iota_address: Option<String>,
+    /// Set on access requests
+    pub reason: Option<String>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct JwtClaims {
     pub role: AuthRole,
@@ -151,22 +300,87 @@ pub struct ReserveGasResult {
     pub sponsor_address: IotaAddress,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HandlerCreateAccessRequestPayload {
+    /// `decmed-hp` code signed by the requesting personnel, naming the patient and a reason
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HandlerCreateEmergencyAccessPayload {
+    pub hospital_personnel_iota_address: String,
+    /// Why the patient can't be asked, kept on chain and in the audit trail
+    pub justification: String,
+    pub patient_iota_address: String,
+    /// Signature of the hospital personnel over the nonce from `/nonce`
+    pub signature: String,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct HandlerCreateMedicalRecordPayload {
     pub medical_metadata: String,
     pub patient_iota_address: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct HandlerGetAccessRequestStatusQueryParams {
+    pub id: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HandlerGetAccessRequestsPayload {
+    pub patient_iota_address: String,
+    /// Signature of the patient over the nonce from `/nonce`
+    pub signature: String,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct HandlerGetAdministrativeDataQueryParams {
     pub patient_iota_address: String,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HandlerGetEmergencyRecipientsPayload {
+    pub patient_iota_address: String,
+    /// Signature of the patient over the nonce from `/nonce`
+    pub signature: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HandlerGetGuardianMedicalRecordCFragPayload {
+    pub guardian_iota_address: String,
+    pub index: u64,
+    pub patient_iota_address: String,
+    /// Signature of the guardian over the nonce from `/nonce`
+    pub signature: String,
+    /// `None` selects the current version
+    pub version: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HandlerGetMedicalRecordContentQueryParams {
+    #[serde(deserialize_with = "crate::utils::Utils::empty_string_as_none")]
+    pub index: Option<u64>,
+    pub patient_iota_address: String,
+    /// `None` selects the current version
+    #[serde(
+        default,
+        deserialize_with = "crate::utils::Utils::empty_string_as_none"
+    )]
+    pub version: Option<u64>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct HandlerGetMedicalRecordQueryParams {
     #[serde(deserialize_with = "crate::utils::Utils::empty_string_as_none")]
     pub index: Option<u64>,
     pub patient_iota_address: String,
+    /// `None` selects the current version
+    #[serde(
+        default,
+        deserialize_with = "crate::utils::Utils::empty_string_as_none"
+    )]
+    pub version: Option<u64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -176,31 +390,164 @@ pub struct HandlerGetMedicalRecordUpdateQueryParams {
     pub patient_iota_address: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct HandlerGetMedicalRecordVersionsQueryParams {
+    #[serde(deserialize_with = "crate::utils::Utils::empty_string_as_none")]
+    pub index: Option<u64>,
+    pub patient_iota_address: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HandlerGetPatientMedicalRecordContentPayload {
+    /// Set when a guardian reads a record of its ward
+    #[serde(default)]
+    pub guardian_iota_address: Option<String>,
+    pub index: u64,
+    pub patient_iota_address: String,
+    /// Signature of the patient, or the guardian, over the nonce from `/nonce`
+    pub signature: String,
+    /// `None` selects the current version
+    #[serde(default)]
+    pub version: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HandlerRespondAccessRequestPayload {
+    pub id: String,
+    pub patient_iota_address: String,
+    /// Signature of the patient over the nonce from `/nonce`
+    pub signature: String,
+    /// `Approved` once the patient granted access on chain, or `Rejected`
+    pub status: AccessRequestStatus,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HandlerRevokeEmergencyKeysPayload {
+    pub patient_iota_address: String,
+    /// Signature of the patient over the nonce from `/nonce`
+    pub signature: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HandlerRevokeGuardianKeysPayload {
+    pub guardian_iota_address: String,
+    pub patient_iota_address: String,
+    /// Signature of the patient, or the guardian stepping down, over the nonce from `/nonce`
+    pub signature: String,
+    pub signer_iota_address: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HandlerRevokeKeysPayload {
+    /// Set when a guardian revokes on behalf of the patient
+    #[serde(default)]
+    pub guardian_iota_address: Option<String>,
+    pub hospital_personnel_iota_addresses: Vec<String>,
+    pub patient_iota_address: String,
+    /// Signature of the patient, or the guardian, over the nonce from `/nonce`
+    pub signature: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HandlerStoreEmergencyKeysPayload {
+    /// By recipient IOTA address, merged with the keys already stored
+    pub keys: HashMap<String, EmergencyKeys>,
+    pub patient_iota_address: String,
+    /// Signature of the patient over the nonce from `/nonce`
+    pub signature: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HandlerStoreEmergencyRecipientPayload {
+    pub hospital_personnel_iota_address: String,
+    pub hospital_personnel_pre_public_key: String,
+    /// Signature of the hospital personnel over the nonce from `/nonce`
+    pub signature: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HandlerStoreGuardianKeysPayload {
+    pub guardian_iota_address: String,
+    pub keys: GuardianKeys,
+    pub patient_iota_address: String,
+    /// Signature of the patient over the nonce from `/nonce`
+    pub signature: String,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct HandlerStoreKeysPayload {
     pub enc_data_pre_secret_key_seed: String,
+    /// Minutes, chosen by the patient
+    pub exp_dur_read: u64,
+    /// Minutes, `None` grants no update access
+    pub exp_dur_update: Option<u64>,
+    /// Set when a guardian grants on behalf of the patient, the guardian signs the nonce
+    #[serde(default)]
+    pub guardian_iota_address: Option<String>,
     pub hospital_personnel_iota_address: String,
-    pub k_frag: String,
+    /// Administrative data is always readable, enforced on chain by the access data types
+    pub is_medical_read: bool,
+    /// `None` when a guardian grants, the grant kfrag of the guardian is used
+    #[serde(default)]
+    pub k_frag: Option<String>,
     pub data_pre_public_key: String,
     pub data_pre_secret_key_seed_capsule: String,
+    /// Ascending medical record indexes chosen by the patient, `None` for every record
+    pub medical_record_scope: Option<Vec<u64>>,
     pub patient_iota_address: String,
     pub patient_pre_public_key: String,
     pub signature: String,
-    pub signer_pre_public_key: String,
+    /// `None` when a guardian grants, see `k_frag`
+    #[serde(default)]
+    pub signer_pre_public_key: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct HandlerUpdateMedicalRecordPayload {
+    /// Index of the record under the current update access
+    pub index: u64,
     pub medical_metadata: String,
     pub patient_iota_address: String,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct MedicalMetadata {
+    #[serde(default)]
+    pub backend: BlobStoreBackend,
     pub capsule: String,
-    pub cid: String,
     pub created_at: String,
+    /// Author IOTA address, `None` on records created before it was recorded
+    #[serde(default)]
+    pub created_by: Option<String>,
     pub enc_key_and_nonce: String,
+    /// Backend specific, the CID on IPFS backends and the object name otherwise
+    #[serde(alias = "cid")]
+    pub locator: String,
+    /// Hex SHA-256 of the stored ciphertext, `None` on records created before it was recorded
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// Replaced versions, oldest first
+    #[serde(default)]
+    pub versions: Vec<MedicalMetadataVersion>,
+}
+
+/// A single version of `MedicalMetadata`, without the history
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MedicalMetadataVersion {
+    pub backend: BlobStoreBackend,
+    pub capsule: String,
+    pub created_at: String,
+    pub created_by: Option<String>,
+    pub enc_key_and_nonce: String,
+    pub locator: String,
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MedicalMetadataVersionSummary {
+    pub created_at: String,
+    pub created_by: Option<String>,
+    pub version: u64,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -230,6 +577,28 @@ where
     pub status_code: u16,
 }
 
+/// Content pinned by the proxy, tracked until no record references it anymore
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TrackedPin {
+    pub backend: BlobStoreBackend,
+    pub created_at: u64,
+    /// Uploader
+    pub iota_address: String,
+    pub locator: String,
+    pub patient_iota_address: Option<String>,
+    pub status: PinStatus,
+    /// First reconciliation that found the committed content unreferenced
+    pub superseded_at: Option<u64>,
+    pub updated_at: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UploadedContent {
+    pub backend: BlobStoreBackend,
+    pub iota_address: String,
+    pub sha256: String,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct UtilIpfsAddResponse {
     pub allocations: Vec<String>,
@@ -237,3 +606,13 @@ pub struct UtilIpfsAddResponse {
     pub name: String,
     pub size: u64,
 }
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UtilKuboAddResponse {
+    #[serde(rename = "Hash")]
+    pub hash: String,
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "Size")]
+    pub size: String,
+}
@@ -6,12 +6,14 @@ use std::{
 
 use anyhow::{anyhow, Context};
 use axum::{
-    http::StatusCode,
+    body::{Body, Bytes},
+    http::{header, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
 use bip39::Mnemonic;
 use chrono::{DateTime, Utc};
+use futures_util::{Stream, StreamExt};
 use iota_json_rpc_types::{
     DevInspectResults, IotaObjectData, IotaObjectDataFilter, IotaObjectDataOptions,
     IotaObjectResponseQuery, IotaTransactionBlockEffectsAPI,
@@ -32,44 +34,35 @@ use iota_types::{
 use jwt_simple::prelude::ES256KeyPair;
 use move_core_types::{account_address::AccountAddress, language_storage::StructTag};
 use rand::Rng;
-use reqwest::{Client, IntoUrl};
+use redis::Commands;
 use serde::{
     de::{self, DeserializeOwned},
     Deserialize, Deserializer, Serialize,
 };
 use serde_json::json;
+use sha2::{Digest, Sha256};
+use shared_crypto::intent::{Intent, IntentMessage};
+use umbral_pre::{PublicKey, SecretKey, SecretKeyFactory};
+use zeroize::Zeroizing;
 
 use base64::{engine::general_purpose::STANDARD, Engine as _};
 
 use crate::{
-    constants::{GAS_STATION_BASE_URL, IOTA_URL, IPFS_BASE_URL, IPFS_GATEWAY_BASE_URL},
+    blob_store::BlobStores,
+    constants::{
+        GAS_STATION_BASE_URL, HOSPITAL_PERSONNEL_QR_PREFIX, HOSPITAL_PERSONNEL_QR_VERSION, IOTA_URL,
+    },
     current_fn,
-    proxy_error::ProxyError,
-    types::{ExecuteTxResponse, ReserveGasResponse, SuccessResponse, UtilIpfsAddResponse},
+    proxy_error::{ProxyError, ResultExt},
+    types::{
+        ExecuteTxResponse, HospitalPersonnelQrPayload, MedicalMetadata, MedicalMetadataVersion,
+        MedicalMetadataVersionSummary, ReserveGasResponse, SuccessResponse,
+    },
 };
 
 pub struct Utils {}
 
 impl Utils {
-    pub async fn add_and_pin_to_ipfs(data: String) -> Result<String, ProxyError> {
-        let path_part = reqwest::multipart::Part::text(data);
-        let form = reqwest::multipart::Form::new().part("path", path_part);
-        let req_client = reqwest::Client::new();
-        let res = req_client
-            .post(format!("{}/add", IPFS_BASE_URL))
-            .multipart(form)
-            .send()
-            .await
-            .context(current_fn!())?;
-
-        let res = res
-            .json::<UtilIpfsAddResponse>()
-            .await
-            .context(current_fn!())?;
-
-        Ok(res.cid)
-    }
-
     pub fn build_success_response<T>(data: T, status_code: StatusCode) -> Response
     where
         T: Debug + Serialize,
@@ -108,6 +101,18 @@ impl Utils {
         Ok(CallArg::Object(cap_object_arg))
     }
 
+    /**
+     * return: `(secret_key, public_key)`
+     */
+    pub fn compute_pre_keys(seed: &[u8]) -> Result<(SecretKey, PublicKey), ProxyError> {
+        let secret_key = SecretKeyFactory::from_secure_randomness(seed)
+            .map_err(|e| anyhow!(e.to_string()).context(current_fn!()))?
+            .make_key(seed);
+        let public_key = secret_key.public_key();
+
+        Ok((secret_key, public_key))
+    }
+
     pub fn construct_es256_key_pair_from_pem(
         key_pair_pem: &str,
     ) -> Result<ES256KeyPair, ProxyError> {
@@ -200,50 +205,49 @@ impl Utils {
         Ok(bearer_token[1].to_string())
     }
 
-    pub async fn do_http_get_request<T, E, U>(
-        req_client: &Client,
-        success_status_code: StatusCode,
-        url: U,
-    ) -> Result<T, ProxyError>
-    where
-        T: DeserializeOwned + From<String>,
-        E: DeserializeOwned + Debug,
-        U: IntoUrl,
-    {
-        let res = req_client.get(url).send().await.context(current_fn!())?;
-        let res_status = res.status();
-        let content_type = res
-            .headers()
-            .get(reqwest::header::CONTENT_TYPE)
-            .and_then(|v| v.to_str().ok())
-            .ok_or(anyhow!("Failed to get content type from header").context(current_fn!()))?
-            .to_string();
-        let res_body = res.bytes().await.context(current_fn!())?;
-
-        if res_status != success_status_code {
-            let error: E = serde_json::from_slice(&res_body.to_vec()).context(current_fn!())?;
-
+    /**
+     * Checks the format and the signature by the personnel IOTA key, validity and role are up to
+     * the caller.
+     * code: `{HOSPITAL_PERSONNEL_QR_PREFIX}:{version}:{base64 payload}:{base64 signature}`
+     */
+    pub fn decode_hospital_personnel_qr(
+        code: &str,
+    ) -> Result<(IotaAddress, HospitalPersonnelQrPayload), ProxyError> {
+        let code: Vec<&str> = code.trim().split(':').collect();
+
+        if code.len() != 4
+            || code[0] != HOSPITAL_PERSONNEL_QR_PREFIX
+            || code[1] != HOSPITAL_PERSONNEL_QR_VERSION.to_string()
+        {
             return Err(ProxyError::Anyhow {
-                source: anyhow!(format!("{:#?}", error)).context(current_fn!()),
-                code: StatusCode::INTERNAL_SERVER_ERROR,
+                source: anyhow!("Invalid hospital personnel code"),
+                code: StatusCode::BAD_REQUEST,
             });
         }
 
-        match content_type.as_str() {
-            "application/json" => {
-                Ok(serde_json::from_slice(&res_body.to_vec()).context(current_fn!())?)
-            }
-            "text/plain; charset=utf-8" => Ok(T::from(
-                String::from_utf8(res_body.to_vec()).context(current_fn!())?,
-            )),
-            _ => {
-                return Err(ProxyError::Anyhow {
-                    source: anyhow!(format!("Unknown content-type: {}", content_type))
-                        .context(current_fn!()),
-                    code: StatusCode::INTERNAL_SERVER_ERROR,
-                })
-            }
-        }
+        let payload: HospitalPersonnelQrPayload = STANDARD
+            .decode(code[2])
+            .ok()
+            .and_then(|payload| serde_json::from_slice(&payload).ok())
+            .ok_or(anyhow!("Invalid hospital personnel code"))
+            .code(StatusCode::BAD_REQUEST)?;
+        let iota_address = IotaAddress::from_str(&payload.iota_address)
+            .map_err(|_| anyhow!("Invalid hospital personnel IOTA address"))
+            .code(StatusCode::BAD_REQUEST)?;
+
+        // The payload is signed as sent, before decoding
+        Self::construct_signature_from_str(code[3])
+            .map_err(|_| anyhow!("Invalid signature"))
+            .code(StatusCode::BAD_REQUEST)?
+            .verify_secure(
+                &IntentMessage::new(Intent::personal_message(), code[2].to_string()),
+                iota_address,
+                SignatureScheme::ED25519,
+            )
+            .map_err(|_| anyhow!("Failed to verify signature"))
+            .code(StatusCode::UNAUTHORIZED)?;
+
+        Ok((iota_address, payload))
     }
 
     pub fn empty_string_as_none<'de, D, T>(de: D) -> Result<Option<T>, D::Error>
@@ -286,10 +290,10 @@ impl Utils {
         Ok(ex_tx_res)
     }
 
-    pub fn generate_64_bytes_seed() -> [u8; 64] {
+    pub fn generate_64_bytes_seed() -> Zeroizing<[u8; 64]> {
         let mut rng = rand::rng();
-        let mut random_seed = [0u8; 64];
-        rng.fill(&mut random_seed);
+        let mut random_seed = Zeroizing::new([0u8; 64]);
+        rng.fill(&mut *random_seed);
 
         random_seed
     }
@@ -306,10 +310,10 @@ impl Utils {
     /**
      * Return: `(public_key, secret_key)`
      */
-    pub fn generate_jwt() -> Result<(String, String), ProxyError> {
+    pub fn generate_jwt() -> Result<(String, Zeroizing<String>), ProxyError> {
         let keypair = ES256KeyPair::generate();
 
-        let secret_key = keypair.to_pem().context(current_fn!())?;
+        let secret_key = Zeroizing::new(keypair.to_pem().context(current_fn!())?);
         let public_key = keypair.public_key().to_pem().context(current_fn!())?;
 
         Ok((public_key, secret_key))
@@ -319,19 +323,6 @@ impl Utils {
         Ok(Mnemonic::generate(size).context(current_fn!())?)
     }
 
-    pub async fn get_data_ipfs(cid: String) -> Result<String, ProxyError> {
-        let req_client = reqwest::Client::new();
-        let content = Utils::do_http_get_request::<String, String, _>(
-            &req_client,
-            StatusCode::OK,
-            format!("{}/ipfs/{}", IPFS_GATEWAY_BASE_URL, cid),
-        )
-        .await
-        .context(current_fn!())?;
-
-        Ok(content)
-    }
-
     pub async fn get_iota_client() -> Result<IotaClient, ProxyError> {
         Ok(IotaClientBuilder::default()
             .build(IOTA_URL)
@@ -417,6 +408,36 @@ impl Utils {
         Ok(())
     }
 
+    /**
+     * Return: every version of a record, oldest first and the current one last
+     */
+    pub fn medical_metadata_versions(
+        medical_metadata: MedicalMetadata,
+    ) -> Vec<MedicalMetadataVersion> {
+        let MedicalMetadata {
+            backend,
+            capsule,
+            created_at,
+            created_by,
+            enc_key_and_nonce,
+            locator,
+            sha256,
+            mut versions,
+        } = medical_metadata;
+
+        versions.push(MedicalMetadataVersion {
+            backend,
+            capsule,
+            created_at,
+            created_by,
+            enc_key_and_nonce,
+            locator,
+            sha256,
+        });
+
+        versions
+    }
+
     pub async fn move_call_read_only(
         sender: IotaAddress,
         iota_client: &IotaClient,
@@ -479,6 +500,39 @@ impl Utils {
             .context(current_fn!())?)
     }
 
+    /**
+     * `None` selects the current version
+     * Return: `(selected version, timeline of every version)`
+     */
+    pub fn select_medical_metadata_version(
+        medical_metadata: MedicalMetadata,
+        version: Option<u64>,
+    ) -> Result<(MedicalMetadataVersion, Vec<MedicalMetadataVersionSummary>), ProxyError> {
+        let mut versions = Self::medical_metadata_versions(medical_metadata);
+        let timeline = versions
+            .iter()
+            .enumerate()
+            .map(
+                |(version, medical_metadata)| MedicalMetadataVersionSummary {
+                    created_at: medical_metadata.created_at.clone(),
+                    created_by: medical_metadata.created_by.clone(),
+                    version: version as u64,
+                },
+            )
+            .collect();
+
+        let version = version.unwrap_or(versions.len() as u64 - 1) as usize;
+
+        if version >= versions.len() {
+            return Err(ProxyError::Anyhow {
+                source: anyhow!("Medical record version not found"),
+                code: StatusCode::NOT_FOUND,
+            });
+        }
+
+        Ok((versions.swap_remove(version), timeline))
+    }
+
     pub fn serde_deserialize_from_base64<T>(val: String) -> Result<T, ProxyError>
     where
         T: DeserializeOwned,
@@ -497,6 +551,125 @@ impl Utils {
         Ok(STANDARD.encode(ser_val))
     }
 
+    /**
+     * Consumes the nonce from `/nonce` once `signature` over it by `iota_address` is verified
+     */
+    pub fn verify_nonce_signature(
+        conn: &mut redis::Connection,
+        iota_address: IotaAddress,
+        signature: &str,
+    ) -> Result<(), ProxyError> {
+        let signature = Self::construct_signature_from_str(signature)
+            .map_err(|_| anyhow!("Invalid signature"))
+            .code(StatusCode::BAD_REQUEST)?;
+
+        let nonce: String = conn
+            .get(format!("nonce:{}", iota_address.to_string()))
+            .map_err(|_| anyhow!("Nonce not found"))
+            .code(StatusCode::BAD_REQUEST)?;
+
+        let _ = signature
+            .verify_secure(
+                &IntentMessage::new(Intent::personal_message(), nonce),
+                iota_address,
+                SignatureScheme::ED25519,
+            )
+            .map_err(|_| anyhow!("Failed to verify signature"))
+            .code(StatusCode::UNAUTHORIZED)?;
+
+        let _: () = conn
+            .del(format!("nonce:{}", iota_address.to_string()))
+            .map_err(|_| anyhow!("Nonce expired"))
+            .code(StatusCode::UNAUTHORIZED)?;
+
+        Ok(())
+    }
+
+    /**
+     * Streams the content of a record version from its backend, shared by the personnel and the
+     * patient content routes once they resolved `medical_metadata` from chain.
+     */
+    pub async fn build_medical_record_content_response(
+        blob_stores: &BlobStores,
+        medical_metadata: MedicalMetadataVersion,
+    ) -> Result<Response, ProxyError> {
+        let blob_store = blob_stores
+            .get(medical_metadata.backend)
+            .context(current_fn!())?;
+
+        // Fetched once and hashed while streaming, the status is already sent by the time a
+        // mismatch is found so it aborts the body instead, clients verify the hash as well
+        let content = blob_store
+            .get(&medical_metadata.locator)
+            .await
+            .context(current_fn!())?;
+
+        let res = Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "application/octet-stream")
+            .body(Body::from_stream(Self::verify_sha256_stream(
+                medical_metadata.locator,
+                medical_metadata.sha256,
+                content,
+            )))
+            .context(current_fn!())?;
+
+        Ok(res)
+    }
+
+    /**
+     * Passes `stream` through, the SHA-256 of all bytes is compared with `sha256` once the
+     * stream ends and a mismatch is yielded as the last item, aborting the response.
+     * Legacy records without a recorded hash are passed through unchecked.
+     */
+    pub fn verify_sha256_stream<S>(
+        locator: String,
+        sha256: Option<String>,
+        stream: S,
+    ) -> impl Stream<Item = Result<Bytes, ProxyError>>
+    where
+        S: Stream<Item = Result<Bytes, ProxyError>>,
+    {
+        futures_util::stream::unfold(
+            (Box::pin(stream), Sha256::new(), false),
+            move |(mut stream, mut hasher, is_done)| {
+                let locator = locator.clone();
+                let sha256 = sha256.clone();
+
+                async move {
+                    if is_done {
+                        return None;
+                    }
+
+                    match stream.next().await {
+                        Some(Ok(bytes)) => {
+                            hasher.update(&bytes);
+                            Some((Ok(bytes), (stream, hasher, false)))
+                        }
+                        Some(Err(e)) => Some((Err(e), (stream, hasher, true))),
+                        None => {
+                            let expected = sha256?;
+                            let actual = hex::encode(hasher.finalize_reset());
+
+                            if actual == expected {
+                                return None;
+                            }
+
+                            Some((
+                                Err(ProxyError::IntegrityMismatch {
+                                    actual,
+                                    expected,
+                                    locator,
+                                }),
+                                (stream, hasher, true),
+                            ))
+                        }
+                    }
+                }
+            },
+        )
+    }
+
     pub fn sys_time_to_iso(system_time: SystemTime) -> String {
         let iso: DateTime<Utc> = system_time.into();
         iso.to_rfc3339()